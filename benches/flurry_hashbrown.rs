@@ -70,6 +70,29 @@ bench_suite!(
     "insert_flurry_hashbrown",
 );
 
+macro_rules! bench_insert_pin_batched {
+    ($group:ident, $keydist:expr, $bench_id: expr) => {
+        $group.bench_function(BenchmarkId::from_parameter($bench_id), |b| {
+            let map: HashMap<_, _> = HashMap::with_capacity(SIZE as usize);
+            b.iter(|| {
+                map.pin_batched(|map| {
+                    map.clear();
+                    ($keydist).take(SIZE).for_each(|i| {
+                        map.insert(i, i);
+                    });
+                });
+                black_box(&map);
+            });
+        });
+    };
+}
+
+bench_suite!(
+    bench_insert_pin_batched,
+    insert_pin_batched_flurry_hashbrown,
+    "insert_pin_batched_flurry_hashbrown",
+);
+
 macro_rules! bench_insert_erase {
     ($group:ident, $keydist:expr, $bench_id: expr) => {
         let base: HashMap<_, _> = HashMap::with_capacity(SIZE as usize);
@@ -192,6 +215,7 @@ bench_suite!(bench_iter, iter_flurry_hashbrown, "iter_flurry_hashbrown",);
 criterion_group!(
     benches,
     insert_flurry_hashbrown,
+    insert_pin_batched_flurry_hashbrown,
     insert_erase_flurry_hashbrown,
     get_flurry_hashbrown,
     get_absent_flurry_hashbrown,
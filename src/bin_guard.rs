@@ -0,0 +1,234 @@
+//! Holding the lock of a single bin of a map across several operations.
+//!
+//! See `HashMap::lock_bin_for` for details.
+
+use crate::epoch::Guard;
+use crate::map::LockedBin;
+use crate::raw::Table;
+use crate::HashMap;
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+use std::fmt::{self, Debug, Formatter};
+use std::hash::{BuildHasher, Hash};
+
+/// A held lock of one bin of a [`HashMap`], through which the keys in that bin are read and
+/// written.
+///
+/// Reads see the values the keys had when the bin was locked, along with the writes made through
+/// the guard. Writes are buffered in the guard, and only applied to the map when it is dropped,
+/// right before the bin is unlocked.
+///
+/// See [`HashMap::lock_bin_for`] for details.
+pub struct BinGuard<'g, K, V, S = crate::DefaultHashBuilder>
+where
+    K: 'static + Sync + Send + Clone + Hash + Ord,
+    V: 'static + Sync + Send,
+    S: BuildHasher,
+{
+    map: &'g HashMap<K, V, S>,
+    table: &'g Table<K, V>,
+    /// The locked bin, which is only taken out when the guard is dropped.
+    locked: Option<LockedBin<'g, K, V>>,
+    writes: BTreeMap<K, Option<V>>,
+    guard: &'g Guard,
+}
+
+impl<'g, K, V, S> BinGuard<'g, K, V, S>
+where
+    K: 'static + Sync + Send + Clone + Hash + Ord,
+    V: 'static + Sync + Send,
+    S: BuildHasher,
+{
+    pub(crate) fn new(
+        map: &'g HashMap<K, V, S>,
+        table: &'g Table<K, V>,
+        locked: LockedBin<'g, K, V>,
+        guard: &'g Guard,
+    ) -> Self {
+        BinGuard {
+            map,
+            table,
+            locked: Some(locked),
+            writes: BTreeMap::new(),
+            guard,
+        }
+    }
+
+    /// Panics if `key` does not belong in the locked bin.
+    fn check_bin<Q>(&self, key: &Q)
+    where
+        Q: ?Sized + Hash,
+    {
+        let bini = self.table.bini(self.map.hash(key));
+        assert_eq!(
+            Some(bini),
+            self.locked.as_ref().map(|locked| locked.bini),
+            "key is not in the locked bin"
+        );
+    }
+
+    /// Returns a reference to the value corresponding to the key.
+    ///
+    /// If the key has been written through this guard, this is the written value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key does not belong in the locked bin.
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+    {
+        self.check_bin(key);
+        if let Some(write) = self.writes.get(key) {
+            return write.as_ref();
+        }
+        // no other thread can write to the bin while we hold its lock.
+        self.map.get(key, self.guard)
+    }
+
+    /// Returns `true` if the guard sees a value for the specified key.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key does not belong in the locked bin.
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+    {
+        self.get(key).is_some()
+    }
+
+    /// Maps `key` to `value` once the guard is dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key does not belong in the locked bin.
+    pub fn set(&mut self, key: K, value: V) {
+        self.check_bin(&key);
+        self.writes.insert(key, Some(value));
+    }
+
+    /// Removes `key` from the map once the guard is dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key does not belong in the locked bin.
+    pub fn remove(&mut self, key: &K) {
+        self.check_bin(key);
+        self.writes.insert(key.clone(), None);
+    }
+}
+
+impl<K, V, S> Debug for BinGuard<'_, K, V, S>
+where
+    K: 'static + Sync + Send + Clone + Hash + Ord + Debug,
+    V: 'static + Sync + Send,
+    S: BuildHasher,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BinGuard")
+            .field("bin", &self.locked.as_ref().map(|locked| locked.bini))
+            .field("written", &self.writes.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl<K, V, S> Drop for BinGuard<'_, K, V, S>
+where
+    K: 'static + Sync + Send + Clone + Hash + Ord,
+    V: 'static + Sync + Send,
+    S: BuildHasher,
+{
+    fn drop(&mut self) {
+        let locked = match self.locked.take() {
+            Some(locked) => locked,
+            None => return,
+        };
+        // if the thread panicked while holding the guard, the writes may be half-finished, so
+        // they are thrown away.
+        if self.writes.is_empty() || std::thread::panicking() {
+            self.map.release_bins(self.table, vec![locked], self.guard);
+            return;
+        }
+        let (keys, values): (Vec<K>, Vec<Option<V>>) =
+            std::mem::take(&mut self.writes).into_iter().unzip();
+        let hashes: Vec<_> = keys.iter().map(|key| self.map.hash(key)).collect();
+        let keys: Vec<&K> = keys.iter().collect();
+        self.map.write_locked(
+            self.table,
+            vec![locked],
+            &keys,
+            &hashes,
+            |_| values.into_iter().map(Some).collect(),
+            self.guard,
+        );
+    }
+}
+
+impl<K, V, S> HashMap<K, V, S>
+where
+    K: 'static + Sync + Send + Clone + Hash + Ord,
+    V: 'static + Sync + Send,
+    S: BuildHasher,
+{
+    /// Locks the bin that `key` belongs in, and returns a [`BinGuard`] through which the keys in
+    /// that bin can be read and written while the lock is held.
+    ///
+    /// This is an advanced tool for building higher-level structures on top of the map, which
+    /// need to read and write several keys that live in the same bin as a single step, with more
+    /// freedom than [`compute_many`](HashMap::compute_many) gives. Writes through the guard are
+    /// buffered, and applied together when it is dropped, before the bin is unlocked. Other
+    /// writers of keys in the bin, including [`transaction`](HashMap::transaction)s and
+    /// `compute_many`, wait until then, so they see either none or all of the writes. Readers do
+    /// not take locks, and may see some of the writes before others. If the thread panics while
+    /// it holds the guard, the buffered writes are discarded.
+    ///
+    /// Only keys that belong in the locked bin can be accessed through the guard, which can be
+    /// checked for a key ahead of time with [`same_bin`](HashMap::same_bin). Which keys share a
+    /// bin changes as the map is resized, although no resize can move the locked bin while it is
+    /// held.
+    ///
+    /// # Deadlocks
+    ///
+    /// While the guard is held, any other write to a key in the bin blocks, including writes from
+    /// the thread that holds the guard, which thus deadlocks if it writes to such a key through
+    /// the map rather than through the guard. Locking a second bin while holding a guard may also
+    /// deadlock with another thread that does the same in the opposite order, so only ever hold
+    /// one `BinGuard` at a time, and keep it for as short a time as possible, since resizes of the
+    /// map wait for it as well.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// let guard = map.guard();
+    /// map.insert(1, 10, &guard);
+    ///
+    /// let mut bin = map.lock_bin_for(&1, &guard);
+    /// let value = *bin.get(&1).unwrap();
+    /// bin.set(1, value + 1);
+    /// assert_eq!(bin.get(&1), Some(&11));
+    /// // the write is only applied once the bin is unlocked
+    /// assert_eq!(map.get(&1, &guard), Some(&10));
+    /// drop(bin);
+    /// assert_eq!(map.get(&1, &guard), Some(&11));
+    /// ```
+    pub fn lock_bin_for<'g, Q>(&'g self, key: &Q, guard: &'g Guard) -> BinGuard<'g, K, V, S>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+    {
+        self.check_guard(guard);
+        let (table, mut locked) = self.lock_bins(&[self.hash(key)], guard);
+        BinGuard::new(
+            self,
+            table,
+            locked.pop().expect("one bin was locked"),
+            guard,
+        )
+    }
+}
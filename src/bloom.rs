@@ -0,0 +1,158 @@
+//! A concurrent Bloom filter that lets a [`HashSet`](crate::HashSet) answer most negative lookups
+//! without touching its table.
+//!
+//! See [`HashSet::with_bloom`](crate::HashSet::with_bloom) for details.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+const WORD_BITS: usize = 64;
+
+/// A fixed-size Bloom filter whose bits live in atomic words, so that concurrent inserts never
+/// need to take a lock.
+///
+/// The filter works on the 64-bit hashes the owning set already computes for its elements. The
+/// bit positions for an element are derived from that single hash through double hashing, which
+/// gives the same false positive behavior as using independent hash functions.
+pub(crate) struct Bloom {
+    words: Box<[AtomicU64]>,
+    hashes: u32,
+}
+
+impl Bloom {
+    /// Creates a filter sized so that, once it holds `capacity` elements, lookups of absent
+    /// elements report a false positive with probability (roughly) `fp_rate`.
+    pub(crate) fn new(capacity: usize, fp_rate: f64) -> Self {
+        assert!(
+            fp_rate > 0.0 && fp_rate < 1.0,
+            "Bloom filter false positive rate must be in (0, 1)"
+        );
+        let capacity = capacity.max(1) as f64;
+        let ln2 = std::f64::consts::LN_2;
+
+        // the textbook optimal parameters: m = -n ln(p) / ln(2)^2 bits and k = m/n ln(2) hashes.
+        let bits = (-capacity * fp_rate.ln() / (ln2 * ln2)).ceil() as usize;
+        let nwords = bits / WORD_BITS + 1;
+        let hashes = ((nwords * WORD_BITS) as f64 / capacity * ln2).round() as u32;
+
+        Bloom {
+            words: (0..nwords).map(|_| AtomicU64::new(0)).collect(),
+            hashes: hashes.max(1),
+        }
+    }
+
+    /// Yields the word index and bit mask of each of the filter bits for `hash`.
+    fn bits(&self, hash: u64) -> impl Iterator<Item = (usize, u64)> {
+        let nbits = (self.words.len() * WORD_BITS) as u64;
+        let h1 = hash;
+        // the second hash must be odd so that it cycles through all bit positions.
+        let h2 = hash.rotate_left(32) | 1;
+        (0..u64::from(self.hashes)).map(move |i| {
+            let bit = (h1.wrapping_add(i.wrapping_mul(h2)) % nbits) as usize;
+            (bit / WORD_BITS, 1 << (bit % WORD_BITS))
+        })
+    }
+
+    /// Records an element with the given hash.
+    ///
+    /// This must happen _before_ the element is made visible in the set, so that no lookup can
+    /// find the element in the set but miss it in the filter.
+    pub(crate) fn insert(&self, hash: u64) {
+        for (word, mask) in self.bits(hash) {
+            self.words[word].fetch_or(mask, Ordering::SeqCst);
+        }
+    }
+
+    /// Returns `false` if no element with the given hash has ever been inserted since the filter
+    /// was last cleared, and `true` if one _may_ have been.
+    pub(crate) fn may_contain(&self, hash: u64) -> bool {
+        self.bits(hash)
+            .all(|(word, mask)| self.words[word].load(Ordering::SeqCst) & mask != 0)
+    }
+
+    /// Resets the filter so that it no longer reports any element as present.
+    pub(crate) fn clear(&mut self) {
+        for word in self.words.iter_mut() {
+            *word.get_mut() = 0;
+        }
+    }
+}
+
+impl Clone for Bloom {
+    fn clone(&self) -> Self {
+        Bloom {
+            words: self
+                .words
+                .iter()
+                .map(|word| AtomicU64::new(word.load(Ordering::SeqCst)))
+                .collect(),
+            hashes: self.hashes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::HashSet;
+
+    #[test]
+    fn no_false_negatives() {
+        let set = HashSet::with_bloom(10_000, 0.01);
+        let guard = set.guard();
+        for i in (0..20_000).step_by(2) {
+            set.insert(i, &guard);
+        }
+        for i in (0..20_000).step_by(2) {
+            assert!(set.contains(&i, &guard));
+        }
+
+        let false_positives = (1..20_000)
+            .step_by(2)
+            .filter(|i| {
+                let bloom = set.bloom.as_ref().unwrap();
+                bloom.may_contain(set.map.hash(i))
+            })
+            .count();
+        // the expected rate is 1%, so this leaves plenty of slack.
+        assert!(false_positives < 500, "{} false positives", false_positives);
+        for i in (1..20_000).step_by(2) {
+            assert!(!set.contains(&i, &guard));
+        }
+    }
+
+    #[test]
+    fn bloom_survives_extend_and_clone() {
+        let set = HashSet::with_bloom(100, 0.01);
+        (&set).extend(0..100);
+        let set = set.clone();
+        let guard = set.guard();
+        for i in 0..100 {
+            assert!(set.contains(&i, &guard));
+        }
+    }
+
+    #[test]
+    fn rebuild_bloom() {
+        let mut set = HashSet::with_bloom(100, 0.01);
+        {
+            let guard = set.guard();
+            for i in 0..100 {
+                set.insert(i, &guard);
+            }
+            for i in 0..90 {
+                set.remove(&i, &guard);
+            }
+        }
+
+        set.rebuild_bloom();
+
+        let bloom = set.bloom.as_ref().unwrap();
+        let stale = (0..90)
+            .filter(|i| bloom.may_contain(set.map.hash(i)))
+            .count();
+        assert!(stale < 10, "{} stale elements still in filter", stale);
+        let guard = set.guard();
+        for i in 90..100 {
+            assert!(set.contains(&i, &guard));
+        }
+    }
+}
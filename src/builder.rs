@@ -0,0 +1,230 @@
+//! Builders for configuring a map or a set before it is created.
+//!
+//! See `Builder` and `SetBuilder` for details.
+
+use crate::{HashMap, HashSet};
+use std::fmt::{self, Debug, Formatter};
+use std::marker::PhantomData;
+
+/// A builder for a [`HashMap`], as returned by [`HashMap::builder`].
+///
+/// All options are optional. A map built without setting any of them is the same as one
+/// returned by [`HashMap::new`].
+///
+/// # Examples
+///
+/// ```
+/// use flurry::HashMap;
+/// use std::collections::hash_map::RandomState;
+///
+/// let map = HashMap::builder()
+///     .capacity(100)
+///     .load_factor(0.5)
+///     .hasher(RandomState::new())
+///     .build();
+/// map.pin().insert(1, 2);
+/// ```
+pub struct Builder<K, V, S = crate::DefaultHashBuilder> {
+    capacity: usize,
+    min_capacity: usize,
+    load_factor: Option<f32>,
+    treeify: bool,
+    hasher: S,
+    _map: PhantomData<(K, V)>,
+}
+
+impl<K, V> Builder<K, V, crate::DefaultHashBuilder> {
+    pub(crate) fn new() -> Self {
+        Builder {
+            capacity: 0,
+            min_capacity: 0,
+            load_factor: None,
+            treeify: true,
+            hasher: crate::DefaultHashBuilder::default(),
+            _map: PhantomData,
+        }
+    }
+}
+
+impl<K, V, S> Builder<K, V, S> {
+    /// Sizes the map to hold at least `capacity` entries without resizing.
+    ///
+    /// Unlike `min_capacity`, this allocates the map's table right away when the map is built.
+    /// See also [`HashMap::with_capacity`].
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Makes the map use `hasher` to hash keys.
+    ///
+    /// See also [`HashMap::with_hasher`].
+    pub fn hasher<S2>(self, hasher: S2) -> Builder<K, V, S2> {
+        Builder {
+            capacity: self.capacity,
+            min_capacity: self.min_capacity,
+            load_factor: self.load_factor,
+            treeify: self.treeify,
+            hasher,
+            _map: PhantomData,
+        }
+    }
+
+    /// Sets the fraction of the map's bins that may be filled before its table is resized.
+    ///
+    /// The default load factor is 0.75. A lower load factor trades memory for shorter bins, and
+    /// a higher one the other way around. The load factor is also used to size the table for the
+    /// requested [`capacity`](Builder::capacity), and when [reserving](HashMap::reserve) room
+    /// in a table that already exists.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `load_factor` is not a positive, finite number.
+    pub fn load_factor(mut self, load_factor: f32) -> Self {
+        assert!(
+            load_factor > 0.0 && load_factor.is_finite(),
+            "the load factor must be positive and finite"
+        );
+        self.load_factor = Some(load_factor);
+        self
+    }
+
+    /// Makes the map's table hold at least `min_capacity` bins once it is allocated.
+    ///
+    /// The table is still only allocated when the map is first inserted into, unless a
+    /// [`capacity`](Builder::capacity) is also set. The number of bins is always rounded up to a
+    /// power of two.
+    pub fn min_capacity(mut self, min_capacity: usize) -> Self {
+        self.min_capacity = min_capacity;
+        self
+    }
+
+    /// Sets whether bins that grow long are turned into trees.
+    ///
+    /// By default, once more than 8 keys share a bin and the table has at least 64 bins, the bin
+    /// is turned into a balanced tree, so that lookups in it take logarithmic rather than linear
+    /// time. With treeification disabled, such bins stay linked lists, which saves the cost of
+    /// building and maintaining the trees. The table still grows as usual.
+    ///
+    /// Note that this reintroduces a DoS attack vector: if an attacker can choose keys that all
+    /// hash to the same bin, for example because the map uses a fixed [`hasher`](Builder::hasher),
+    /// every operation on those keys takes time linear in their number.
+    pub fn treeification(mut self, enabled: bool) -> Self {
+        self.treeify = enabled;
+        self
+    }
+
+    /// Creates the configured map.
+    pub fn build(self) -> HashMap<K, V, S> {
+        let mut map = HashMap::with_hasher(self.hasher);
+        map.configure(
+            self.capacity,
+            self.min_capacity,
+            self.load_factor,
+            self.treeify,
+        );
+        map
+    }
+}
+
+impl<K, V, S> Debug for Builder<K, V, S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Builder")
+            .field("capacity", &self.capacity)
+            .field("min_capacity", &self.min_capacity)
+            .field("load_factor", &self.load_factor)
+            .field("treeify", &self.treeify)
+            .finish()
+    }
+}
+
+/// A builder for a [`HashSet`], as returned by [`HashSet::builder`].
+///
+/// See [`Builder`] for what the options do.
+///
+/// # Examples
+///
+/// ```
+/// use flurry::HashSet;
+///
+/// let set = HashSet::builder().min_capacity(64).load_factor(0.5).build();
+/// set.pin().insert(1);
+/// ```
+pub struct SetBuilder<T, S = crate::DefaultHashBuilder> {
+    map: Builder<T, (), S>,
+}
+
+impl<T> SetBuilder<T, crate::DefaultHashBuilder> {
+    pub(crate) fn new() -> Self {
+        SetBuilder {
+            map: Builder::new(),
+        }
+    }
+}
+
+impl<T, S> SetBuilder<T, S> {
+    /// Sizes the set to hold at least `capacity` elements without resizing.
+    ///
+    /// See also [`Builder::capacity`].
+    pub fn capacity(self, capacity: usize) -> Self {
+        SetBuilder {
+            map: self.map.capacity(capacity),
+        }
+    }
+
+    /// Makes the set use `hasher` to hash elements.
+    ///
+    /// See also [`Builder::hasher`].
+    pub fn hasher<S2>(self, hasher: S2) -> SetBuilder<T, S2> {
+        SetBuilder {
+            map: self.map.hasher(hasher),
+        }
+    }
+
+    /// Sets the fraction of the set's bins that may be filled before its table is resized.
+    ///
+    /// See also [`Builder::load_factor`].
+    pub fn load_factor(self, load_factor: f32) -> Self {
+        SetBuilder {
+            map: self.map.load_factor(load_factor),
+        }
+    }
+
+    /// Sets whether bins that grow long are turned into trees.
+    ///
+    /// See also [`Builder::treeification`].
+    pub fn treeification(self, enabled: bool) -> Self {
+        SetBuilder {
+            map: self.map.treeification(enabled),
+        }
+    }
+
+    /// Makes the set's table hold at least `min_capacity` bins once it is allocated.
+    ///
+    /// See also [`Builder::min_capacity`].
+    pub fn min_capacity(self, min_capacity: usize) -> Self {
+        SetBuilder {
+            map: self.map.min_capacity(min_capacity),
+        }
+    }
+
+    /// Creates the configured set.
+    pub fn build(self) -> HashSet<T, S> {
+        HashSet {
+            map: self.map.build(),
+            #[cfg(feature = "bloom")]
+            bloom: None,
+        }
+    }
+}
+
+impl<T, S> Debug for SetBuilder<T, S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SetBuilder")
+            .field("capacity", &self.map.capacity)
+            .field("min_capacity", &self.map.min_capacity)
+            .field("load_factor", &self.map.load_factor)
+            .field("treeify", &self.map.treeify)
+            .finish()
+    }
+}
@@ -0,0 +1,139 @@
+//! Method names familiar from other concurrent maps.
+//!
+//! These are provided to ease migrating code over to flurry, and are opt-in: bring the traits
+//! into scope with `use flurry::compat::EntryExt;`. Each method is a thin wrapper around one of
+//! flurry's native primitives, which remain the better choice for new code.
+
+use crate::{HashMap, HashMapRef};
+use std::borrow::Borrow;
+use std::hash::{BuildHasher, Hash};
+
+/// In-place updates in the style of `dashmap`'s `alter` and `alter_all`.
+///
+/// Unlike their `dashmap` counterparts, the update functions receive the current value by
+/// reference, since flurry never hands out ownership of values that are still in the map.
+///
+/// # Examples
+///
+/// ```
+/// use flurry::compat::EntryExt;
+/// use flurry::HashMap;
+///
+/// let map = HashMap::new();
+/// map.pin().insert("a", 1);
+/// map.pin().insert("b", 2);
+///
+/// map.alter("a", |_, v| v * 10);
+/// map.alter_all(|_, v| v + 1);
+/// assert_eq!(map.pin().get("a"), Some(&11));
+/// assert_eq!(map.pin().get("b"), Some(&3));
+/// ```
+pub trait EntryExt<K, V> {
+    /// Replaces the value for `key` with the result of `f`, if `key` is present in the map.
+    ///
+    /// This is [`HashMap::compute_if_present`] with an update function that always keeps the
+    /// entry.
+    fn alter<Q, F>(&self, key: &Q, f: F)
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+        F: FnOnce(&K, &V) -> V;
+
+    /// Replaces every value in the map with the result of `f`.
+    ///
+    /// Each entry is updated atomically, but the map as a whole is not: entries that are
+    /// inserted concurrently may or may not be altered.
+    fn alter_all<F>(&self, f: F)
+    where
+        F: FnMut(&K, &V) -> V;
+}
+
+impl<K, V, S> EntryExt<K, V> for HashMap<K, V, S>
+where
+    K: 'static + Sync + Send + Clone + Hash + Ord,
+    V: 'static + Sync + Send,
+    S: BuildHasher,
+{
+    fn alter<Q, F>(&self, key: &Q, f: F)
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+        F: FnOnce(&K, &V) -> V,
+    {
+        self.pin().alter(key, f);
+    }
+
+    fn alter_all<F>(&self, f: F)
+    where
+        F: FnMut(&K, &V) -> V,
+    {
+        self.pin().alter_all(f);
+    }
+}
+
+impl<K, V, S> EntryExt<K, V> for HashMapRef<'_, K, V, S>
+where
+    K: 'static + Sync + Send + Clone + Hash + Ord,
+    V: 'static + Sync + Send,
+    S: BuildHasher,
+{
+    fn alter<Q, F>(&self, key: &Q, f: F)
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+        F: FnOnce(&K, &V) -> V,
+    {
+        self.compute_if_present(key, |k, v| Some(f(k, v)));
+    }
+
+    fn alter_all<F>(&self, mut f: F)
+    where
+        F: FnMut(&K, &V) -> V,
+    {
+        for key in self.keys() {
+            self.compute_if_present(key, |k, v| Some(f(k, v)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::EntryExt;
+    use crate::HashMap;
+
+    #[test]
+    fn alter() {
+        let map = HashMap::new();
+        map.pin().insert(1, "a".to_string());
+
+        map.alter(&1, |_, v| format!("{}{}", v, v));
+        assert_eq!(map.pin().get(&1), Some(&"aa".to_string()));
+
+        // absent keys are left alone
+        map.alter(&2, |_, _| unreachable!("2 is not in the map"));
+        assert_eq!(map.pin().get(&2), None);
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn alter_all() {
+        let map = HashMap::new();
+        for i in 0..100 {
+            map.pin().insert(i, i);
+        }
+
+        map.pin().alter_all(|k, v| k + v);
+        let guard = map.guard();
+        assert_eq!(map.len(), 100);
+        for i in 0..100 {
+            assert_eq!(map.get(&i, &guard), Some(&(2 * i)));
+        }
+    }
+
+    #[test]
+    fn alter_all_empty() {
+        let map = HashMap::<usize, usize>::new();
+        map.alter_all(|_, _| unreachable!("the map is empty"));
+        assert!(map.is_empty());
+    }
+}
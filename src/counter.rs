@@ -0,0 +1,223 @@
+//! A concurrent counter of occurrences of keys.
+//!
+//! See `Counter` for details.
+
+use crate::epoch::Guard;
+use crate::HashMap;
+use std::borrow::Borrow;
+use std::cmp::Reverse;
+use std::fmt::{self, Debug, Formatter};
+use std::hash::{BuildHasher, Hash};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// A concurrent counter that tallies how often each key occurs.
+///
+/// Every key maps to an atomic count, so once a key has been seen, adding to its count never
+/// touches the structure of the underlying map. Counting words in a text, or aggregating metrics
+/// from many threads, thus scales with the number of distinct keys rather than with the number of
+/// additions.
+///
+/// The counter is built on a [`HashMap`] from keys to their counts. It also keeps the sum of all
+/// counts, so that [`total`](Counter::total) does not have to walk the map.
+///
+/// # Examples
+///
+/// ```
+/// use flurry::Counter;
+///
+/// let words = Counter::new();
+/// let guard = words.guard();
+///
+/// for word in "the cat saw the other cat and the dog".split(' ') {
+///     words.add(word, 1, &guard);
+/// }
+/// assert_eq!(words.count(&"the", &guard), 3);
+/// assert_eq!(words.count(&"bird", &guard), 0);
+/// assert_eq!(words.total(), 9);
+/// assert_eq!(words.top_k(2, &guard), vec![("the", 3), ("cat", 2)]);
+/// ```
+pub struct Counter<K, S = crate::DefaultHashBuilder> {
+    map: HashMap<K, AtomicU64, S>,
+    total: AtomicU64,
+}
+
+impl<K> Counter<K, crate::DefaultHashBuilder> {
+    /// Creates an empty `Counter`.
+    pub fn new() -> Self {
+        Self::with_hasher(crate::DefaultHashBuilder::default())
+    }
+}
+
+impl<K> Default for Counter<K, crate::DefaultHashBuilder> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, S> Counter<K, S> {
+    /// Creates an empty `Counter` which will use `hash_builder` to hash keys.
+    pub fn with_hasher(hash_builder: S) -> Self {
+        Counter {
+            map: HashMap::with_hasher(hash_builder),
+            total: AtomicU64::new(0),
+        }
+    }
+
+    /// Pin a `Guard` for use with this counter.
+    ///
+    /// See also [`HashMap::guard`].
+    pub fn guard(&self) -> Guard {
+        self.map.guard()
+    }
+
+    /// Returns the number of distinct keys that have been counted.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if no key has been counted yet.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Returns the sum of the counts of all keys.
+    ///
+    /// While other threads are adding to the counter, this may not yet include their most recent
+    /// additions.
+    pub fn total(&self) -> u64 {
+        self.total.load(Ordering::SeqCst)
+    }
+}
+
+impl<K, S> Counter<K, S>
+where
+    K: 'static + Sync + Send + Clone + Hash + Ord,
+    S: BuildHasher,
+{
+    /// Adds `n` to the count of `key`, and returns the new count.
+    ///
+    /// A key that has not been counted before starts out at 0.
+    pub fn add(&self, key: K, n: u64, guard: &Guard) -> u64 {
+        self.total.fetch_add(n, Ordering::SeqCst);
+        if let Some(count) = self.map.get_uncounted(&key, guard) {
+            return count.fetch_add(n, Ordering::SeqCst) + n;
+        }
+        match self.map.try_insert(key, AtomicU64::new(n), guard) {
+            Ok(_) => n,
+            // another thread inserted the key first, so we add to its count instead.
+            Err(e) => e.current.fetch_add(n, Ordering::SeqCst) + n,
+        }
+    }
+
+    /// Returns the count of `key`, which is 0 if it has never been counted.
+    ///
+    /// The key may be any borrowed form of the counter's key type, but [`Hash`] and [`Ord`] on
+    /// the borrowed form *must* match those for the key type.
+    pub fn count<Q>(&self, key: &Q, guard: &Guard) -> u64
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+    {
+        self.map
+            .get(key, guard)
+            .map_or(0, |count| count.load(Ordering::SeqCst))
+    }
+
+    /// Returns the `k` keys with the highest counts along with their counts, from the highest
+    /// count to the lowest.
+    ///
+    /// Keys with equal counts are returned in arbitrary order. This walks the whole map, but
+    /// only sorts the `k` keys it returns.
+    pub fn top_k(&self, k: usize, guard: &Guard) -> Vec<(K, u64)> {
+        if k == 0 {
+            return Vec::new();
+        }
+        let mut counts: Vec<_> = self
+            .map
+            .iter(guard)
+            .map(|(key, count)| (key.clone(), count.load(Ordering::SeqCst)))
+            .collect();
+        if k < counts.len() {
+            counts.select_nth_unstable_by_key(k - 1, |&(_, count)| Reverse(count));
+            counts.truncate(k);
+        }
+        counts.sort_unstable_by_key(|&(_, count)| Reverse(count));
+        counts
+    }
+}
+
+impl<K, S> Debug for Counter<K, S>
+where
+    K: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let guard = self.guard();
+        f.debug_map()
+            .entries(
+                self.map
+                    .iter(&guard)
+                    .map(|(k, count)| (k, count.load(Ordering::SeqCst))),
+            )
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Counter;
+    use std::sync::Arc;
+
+    #[test]
+    fn add_and_count() {
+        let counter = Counter::new();
+        let guard = counter.guard();
+        assert_eq!(counter.add("a", 2, &guard), 2);
+        assert_eq!(counter.add("a", 3, &guard), 5);
+        assert_eq!(counter.add("b", 1, &guard), 1);
+        assert_eq!(counter.count(&"a", &guard), 5);
+        assert_eq!(counter.count(&"c", &guard), 0);
+        assert_eq!(counter.total(), 6);
+        assert_eq!(counter.len(), 2);
+    }
+
+    #[test]
+    fn top_k() {
+        let counter = Counter::new();
+        let guard = counter.guard();
+        for i in 0..100u64 {
+            counter.add(i, i, &guard);
+        }
+        assert_eq!(counter.top_k(3, &guard), vec![(99, 99), (98, 98), (97, 97)]);
+        assert_eq!(counter.top_k(0, &guard), vec![]);
+        assert_eq!(counter.top_k(1000, &guard).len(), 100);
+    }
+
+    #[test]
+    fn concurrent_add() {
+        const THREADS: u64 = 8;
+        const ROUNDS: u64 = 1024;
+        const KEYS: u64 = 16;
+
+        let counter = Arc::new(Counter::new());
+        let threads: Vec<_> = (0..THREADS)
+            .map(|t| {
+                let counter = counter.clone();
+                std::thread::spawn(move || {
+                    let guard = counter.guard();
+                    for i in 0..ROUNDS {
+                        counter.add((t + i) % KEYS, 1, &guard);
+                    }
+                })
+            })
+            .collect();
+        for t in threads {
+            t.join().unwrap();
+        }
+
+        let guard = counter.guard();
+        for key in 0..KEYS {
+            assert_eq!(counter.count(&key, &guard), THREADS * ROUNDS / KEYS);
+        }
+        assert_eq!(counter.total(), THREADS * ROUNDS);
+    }
+}
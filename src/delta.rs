@@ -0,0 +1,175 @@
+//! Incremental reads of the entries written to a map.
+//!
+//! See `HashMap::delta_reader` for details.
+
+use crate::epoch::Guard;
+use crate::iter::IterSince;
+use crate::HashMap;
+use std::fmt::{self, Debug, Formatter};
+
+/// A reader that yields the entries of a [`HashMap`] that were inserted or updated since it last
+/// looked.
+///
+/// See [`HashMap::delta_reader`] for details.
+pub struct DeltaReader<'m, K, V, S = crate::DefaultHashBuilder> {
+    map: &'m HashMap<K, V, S>,
+    version: u64,
+}
+
+impl<'m, K, V, S> DeltaReader<'m, K, V, S> {
+    /// An iterator visiting the key-value pairs that were inserted or updated since the last
+    /// call to `poll`, or since the reader was created if this is the first call.
+    ///
+    /// Entries that were removed are not visited, and an entry that was written several times is
+    /// visited once, with its current value.
+    ///
+    /// See also [`HashMap::iter_since`].
+    pub fn poll<'g>(&mut self, guard: &'g Guard) -> IterSince<'g, K, V>
+    where
+        'm: 'g,
+    {
+        // the version is taken before the scan, so that writes that land during the scan are
+        // seen again by the next poll rather than missed. it is the settled version rather than
+        // the current one, since a write that has already taken its version may not have made
+        // its entry visible yet, and the scan would then miss it for good.
+        let since = std::mem::replace(&mut self.version, self.map.settled_version());
+        self.map.iter_since(since, guard)
+    }
+
+    /// Returns the version of the map that the last call to [`poll`](DeltaReader::poll) moved
+    /// up to.
+    ///
+    /// This is the newest version at or below which every write had become visible when `poll`
+    /// was called. It may lag behind [`HashMap::current_version`] while there are writes in
+    /// progress.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+}
+
+impl<K, V, S> Debug for DeltaReader<'_, K, V, S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeltaReader")
+            .field("version", &self.version)
+            .finish()
+    }
+}
+
+impl<K, V, S> HashMap<K, V, S> {
+    /// Returns a reader that yields the entries written to the map since it last looked.
+    ///
+    /// Each call to [`DeltaReader::poll`] visits the entries that were inserted or updated since
+    /// the previous call, or since the reader was created for the first call, which lets a
+    /// consumer tail a map that is being written to without scanning all of it each time. The
+    /// scan itself still walks every bin of the map, but only yields the changed entries.
+    ///
+    /// The reader relies on the versions described in
+    /// [`current_version`](HashMap::current_version). A write takes its version before its entry
+    /// becomes visible, so a poll only moves the reader up to a version at or below which every
+    /// write has finished, so no write is ever missed. In exchange, entries written while other
+    /// writes are in progress, or during a poll, may be yielded by both that poll and the next.
+    /// Polls made while the map is quiescent are exact.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// let guard = map.guard();
+    /// map.insert(1, "a", &guard);
+    ///
+    /// let mut reader = map.delta_reader();
+    /// map.insert(2, "b", &guard);
+    /// assert_eq!(reader.poll(&guard).collect::<Vec<_>>(), vec![(&2, &"b")]);
+    /// assert_eq!(reader.poll(&guard).count(), 0);
+    ///
+    /// map.insert(1, "c", &guard);
+    /// assert_eq!(reader.poll(&guard).collect::<Vec<_>>(), vec![(&1, &"c")]);
+    /// ```
+    pub fn delta_reader(&self) -> DeltaReader<'_, K, V, S> {
+        DeltaReader {
+            map: self,
+            version: self.settled_version(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::HashMap;
+    use std::collections::BTreeSet;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn poll_yields_new_writes() {
+        let map = HashMap::<usize, usize>::new();
+        let guard = map.guard();
+        for i in 0..10 {
+            map.insert(i, i, &guard);
+        }
+
+        let mut reader = map.delta_reader();
+        assert_eq!(reader.poll(&guard).count(), 0);
+
+        for i in 10..100 {
+            map.insert(i, i, &guard);
+        }
+        let mut batch: Vec<_> = reader.poll(&guard).map(|(&k, &v)| (k, v)).collect();
+        batch.sort_unstable();
+        assert_eq!(batch, (10..100).map(|i| (i, i)).collect::<Vec<_>>());
+
+        // updates count as writes, removals are not visited, and old entries are not seen again
+        for i in 100..200 {
+            map.insert(i, i, &guard);
+        }
+        map.insert(5, 50, &guard);
+        map.remove(&150, &guard);
+        let mut batch: Vec<_> = reader.poll(&guard).map(|(&k, &v)| (k, v)).collect();
+        batch.sort_unstable();
+        let expected: Vec<_> = std::iter::once((5, 50))
+            .chain((100..200).filter(|&i| i != 150).map(|i| (i, i)))
+            .collect();
+        assert_eq!(batch, expected);
+        assert_eq!(reader.version(), map.current_version());
+        assert_eq!(reader.poll(&guard).count(), 0);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn polls_see_concurrent_writes() {
+        const THREADS: usize = 4;
+        const KEYS: usize = 10_000;
+
+        let map = Arc::new(HashMap::<usize, usize>::new());
+        let finished = Arc::new(AtomicUsize::new(0));
+        let writers: Vec<_> = (0..THREADS)
+            .map(|t| {
+                let map = map.clone();
+                let finished = finished.clone();
+                std::thread::spawn(move || {
+                    let guard = map.guard();
+                    for i in (t..KEYS).step_by(THREADS) {
+                        map.insert(i, i, &guard);
+                    }
+                    finished.fetch_add(1, Ordering::SeqCst);
+                })
+            })
+            .collect();
+
+        let mut seen = BTreeSet::new();
+        let mut reader = map.delta_reader();
+        let mut done = false;
+        while !done {
+            // one more poll after all writers are done picks up whatever is left.
+            done = finished.load(Ordering::SeqCst) == THREADS;
+            let guard = map.guard();
+            seen.extend(reader.poll(&guard).map(|(&k, _)| k));
+        }
+        for w in writers {
+            w.join().unwrap();
+        }
+        assert_eq!(seen, (0..KEYS).collect());
+    }
+}
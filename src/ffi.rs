@@ -0,0 +1,203 @@
+//! Raw, untyped access to the table of a map, for use from foreign code.
+//!
+//! See [`HashMap::as_raw_parts`](crate::HashMap::as_raw_parts) for details.
+
+use crate::node::BinEntry;
+use crossbeam_epoch::{self as epoch, Atomic};
+use std::ffi::c_void;
+use std::ptr;
+use std::sync::atomic::Ordering;
+
+/// The parts that make up the current table of a map, as returned by
+/// [`HashMap::as_raw_parts`](crate::HashMap::as_raw_parts).
+///
+/// None of the pointers in here carry type information, so that they can be handed to code
+/// written in other languages. The two functions know how to interpret them for the map they
+/// were taken from, and must only be called with pointers obtained from these same parts.
+///
+/// A lookup of a key with the hash `h` goes like this:
+///
+///  1. call `read_bin(bins, h & (capacity - 1))`;
+///  2. if the bin is [`RawBinState::Moved`], the table is being resized and the key may already
+///     live in the new table. Fall back to a lookup through the map itself. Do the same if it is
+///     [`RawBinState::Invalid`];
+///  3. otherwise, call `read_entry` on `first`, and then on the `next` of each entry in turn,
+///     until an entry with hash `h` and the right key is found, or `next` is null. If an entry
+///     comes back with a null `key`, it could not be read, and the lookup should fall back to
+///     the map as well.
+///
+/// The functions never unwind into foreign code: instead of panicking, they report what they
+/// could not read with [`RawBinState::Invalid`] or a null `key`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct RawTableParts {
+    /// A pointer to the first bin of the table.
+    pub bins: *const c_void,
+    /// The number of bins in the table. This is always a power of two, or zero if the map has
+    /// not allocated its table yet.
+    pub capacity: usize,
+    /// Reads the bin at `index` of the table that `bins` points to.
+    pub read_bin: unsafe extern "C" fn(bins: *const c_void, index: usize) -> RawBin,
+    /// Reads the entry that `entry` points to.
+    pub read_entry: unsafe extern "C" fn(entry: *const c_void) -> RawEntry,
+}
+
+/// What a bin of a [`RawTableParts`] table holds.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RawBinState {
+    /// The bin holds no entries.
+    Empty,
+    /// The bin holds one or more entries.
+    Entries,
+    /// The entries of the bin have been moved to a new table by a resize.
+    Moved,
+    /// The bin holds something that is not a valid bin, which should never happen.
+    Invalid,
+}
+
+/// A bin of a [`RawTableParts`] table.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct RawBin {
+    /// What the bin holds.
+    pub state: RawBinState,
+    /// The first entry in the bin, or null unless `state` is [`RawBinState::Entries`].
+    pub first: *const c_void,
+}
+
+/// An entry of a [`RawTableParts`] table.
+///
+/// If the entry could not be read, all of its pointers are null.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct RawEntry {
+    /// The hash of the entry's key.
+    pub hash: u64,
+    /// A pointer to the entry's key, a `K`.
+    pub key: *const c_void,
+    /// A pointer to the entry's current value, a `V`.
+    pub value: *const c_void,
+    /// The next entry in the same bin, or null if this is the last one.
+    pub next: *const c_void,
+}
+
+impl RawTableParts {
+    /// Returns the parts of a table with `capacity` bins starting at `bins`.
+    pub(crate) fn new<K, V>(bins: *const Atomic<BinEntry<K, V>>, capacity: usize) -> Self {
+        RawTableParts {
+            bins: bins as *const c_void,
+            capacity,
+            read_bin: read_bin::<K, V>,
+            read_entry: read_entry::<K, V>,
+        }
+    }
+}
+
+unsafe extern "C" fn read_bin<K, V>(bins: *const c_void, index: usize) -> RawBin {
+    // safety: the caller guarantees that the table is still alive, and thus so are its bins and
+    // their entries. that is also what makes using an unprotected guard okay here.
+    let guard = epoch::unprotected();
+    let bin = &*(bins as *const Atomic<BinEntry<K, V>>).add(index);
+    let bin = bin.load(Ordering::Acquire, guard);
+    let (state, first) = match bin.as_ref() {
+        // the first entry of a reserved bin is still being computed, so the bin is empty.
+        None | Some(BinEntry::Reserved(_)) => (RawBinState::Empty, ptr::null()),
+        Some(BinEntry::Moved) => (RawBinState::Moved, ptr::null()),
+        Some(entry @ BinEntry::Node(_)) => (RawBinState::Entries, entry as *const BinEntry<K, V>),
+        Some(BinEntry::Tree(tree_bin)) => (
+            RawBinState::Entries,
+            tree_bin.first.load(Ordering::SeqCst, guard).as_raw(),
+        ),
+        // the head of a bin cannot be a TreeNode directly without BinEntry::Tree.
+        Some(BinEntry::TreeNode(_)) => (RawBinState::Invalid, ptr::null()),
+    };
+    RawBin {
+        state,
+        first: first as *const c_void,
+    }
+}
+
+unsafe extern "C" fn read_entry<K, V>(entry: *const c_void) -> RawEntry {
+    // safety: as for read_bin.
+    let guard = epoch::unprotected();
+    let node = match &*(entry as *const BinEntry<K, V>) {
+        BinEntry::Node(node) => node,
+        BinEntry::TreeNode(tree_node) => &tree_node.node,
+        // entries are always Nodes or TreeNodes.
+        _ => {
+            return RawEntry {
+                hash: 0,
+                key: ptr::null(),
+                value: ptr::null(),
+                next: ptr::null(),
+            }
+        }
+    };
+    RawEntry {
+        hash: node.hash,
+        key: &node.key as *const K as *const c_void,
+        value: node.value.load(Ordering::SeqCst, guard).as_raw() as *const c_void,
+        next: node.next.load(Ordering::SeqCst, guard).as_raw() as *const c_void,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{RawBinState, RawTableParts};
+    use crate::node::BinEntry;
+    use crate::HashMap;
+    use std::ffi::c_void;
+
+    /// Looks up `key` with nothing but the raw parts, like foreign code would.
+    unsafe fn raw_get<'p>(parts: &RawTableParts, hash: u64, key: &usize) -> Option<&'p usize> {
+        if parts.capacity == 0 {
+            return None;
+        }
+        let bin = (parts.read_bin)(parts.bins, hash as usize & (parts.capacity - 1));
+        assert_ne!(bin.state, RawBinState::Moved);
+        let mut entry = bin.first;
+        while !entry.is_null() {
+            let e = (parts.read_entry)(entry);
+            if e.hash == hash && *(e.key as *const usize) == *key {
+                return Some(&*(e.value as *const usize));
+            }
+            entry = e.next;
+        }
+        None
+    }
+
+    #[test]
+    fn lookup_through_raw_parts() {
+        let map = HashMap::<usize, usize>::new();
+        let guard = map.guard();
+        let hash = |key: &usize| map.hash(key);
+
+        let parts = unsafe { map.as_raw_parts(&guard) };
+        assert_eq!(unsafe { raw_get(&parts, hash(&1), &1) }, None);
+
+        for i in 0..100 {
+            map.insert(i, i * 10, &guard);
+        }
+        let parts = unsafe { map.as_raw_parts(&guard) };
+        assert_eq!(parts.capacity, map.capacity(&guard));
+        for i in 0..200 {
+            assert_eq!(
+                unsafe { raw_get(&parts, hash(&i), &i) },
+                map.get(&i, &guard)
+            );
+        }
+    }
+
+    #[test]
+    fn unreadable_entry() {
+        // a forwarding entry is not an entry that can be read, and must not make read_entry
+        // unwind into foreign code.
+        let parts = RawTableParts::new::<usize, usize>(std::ptr::null(), 0);
+        let moved = BinEntry::<usize, usize>::Moved;
+        let entry = unsafe { (parts.read_entry)(&moved as *const BinEntry<_, _> as *const c_void) };
+        assert!(entry.key.is_null());
+        assert!(entry.value.is_null());
+        assert!(entry.next.is_null());
+    }
+}
@@ -0,0 +1,285 @@
+//! A concurrent map with a fixed number of bins that never resizes.
+//!
+//! See `FixedHashMap` for details.
+
+use crate::epoch::Guard;
+use crate::iter::Iter;
+use crate::map::TREEIFY_THRESHOLD;
+use crate::HashMap;
+use std::borrow::Borrow;
+use std::error::Error;
+use std::fmt::{self, Debug, Display, Formatter};
+use std::hash::{BuildHasher, Hash};
+
+/// A concurrent map backed by a table of exactly `N` bins that is never resized.
+///
+/// Where a [`HashMap`] grows its table as entries are added, a `FixedHashMap` allocates its table
+/// once, when it is created, and keeps it for its entire lifetime. Instead of growing, it bounds
+/// the number of entries any one bin may hold: an insert that would make a bin hold more than
+/// [`max_chain`](FixedHashMap::max_chain) entries fails with a [`BinFullError`] that hands the
+/// key and value back. This makes the map a good fit for caches and embedded uses that need a hard
+/// upper bound on the memory and the lookup cost of the map. Note that every entry is still
+/// allocated individually when it is inserted.
+///
+/// `N` must be a power of two, and the chain limit must be between 1 and 8, which keeps all bins
+/// as plain linked lists.
+///
+/// # Examples
+///
+/// ```
+/// use flurry::FixedHashMap;
+///
+/// let map: FixedHashMap<_, _, 64> = FixedHashMap::new(4);
+/// let guard = map.guard();
+///
+/// assert_eq!(map.insert(1, "a", &guard), Ok(None));
+/// assert_eq!(map.insert(1, "b", &guard), Ok(Some(&"a")));
+/// assert_eq!(map.get(&1, &guard), Some(&"b"));
+/// assert_eq!(map.capacity(), 64);
+/// ```
+pub struct FixedHashMap<K, V, const N: usize, S = crate::DefaultHashBuilder> {
+    map: HashMap<K, V, S>,
+    max_chain: usize,
+}
+
+impl<K, V, const N: usize> FixedHashMap<K, V, N, crate::DefaultHashBuilder> {
+    /// Creates an empty `FixedHashMap` with `N` bins, each of which holds at most `max_chain`
+    /// entries.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `N` is not a power of two, or if `max_chain` is not between 1 and 8.
+    pub fn new(max_chain: usize) -> Self {
+        Self::with_hasher(max_chain, crate::DefaultHashBuilder::default())
+    }
+}
+
+impl<K, V, const N: usize, S> FixedHashMap<K, V, N, S> {
+    /// Creates an empty `FixedHashMap` with `N` bins, each of which holds at most `max_chain`
+    /// entries, which will use `hash_builder` to hash keys.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `N` is not a power of two, or if `max_chain` is not between 1 and 8.
+    pub fn with_hasher(max_chain: usize, hash_builder: S) -> Self {
+        assert!(
+            (1..=TREEIFY_THRESHOLD).contains(&max_chain),
+            "the chain limit must be between 1 and {}",
+            TREEIFY_THRESHOLD
+        );
+        let mut map = HashMap::with_hasher(hash_builder);
+        map.fix_table(N);
+        FixedHashMap { map, max_chain }
+    }
+
+    /// Pin a `Guard` for use with this map.
+    ///
+    /// See also [`HashMap::guard`].
+    pub fn guard(&self) -> Guard {
+        self.map.guard()
+    }
+
+    /// Returns the number of bins in the map, which is always `N`.
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Returns the maximum number of entries a single bin may hold.
+    pub fn max_chain(&self) -> usize {
+        self.max_chain
+    }
+
+    /// Returns the number of entries in the map.
+    ///
+    /// See also [`HashMap::len`].
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if the map is empty. Otherwise returns `false`.
+    ///
+    /// See also [`HashMap::is_empty`].
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// An iterator visiting all key-value pairs in arbitrary order.
+    ///
+    /// See also [`HashMap::iter`].
+    pub fn iter<'g>(&'g self, guard: &'g Guard) -> Iter<'g, K, V> {
+        self.map.iter(guard)
+    }
+}
+
+impl<K, V, const N: usize, S> FixedHashMap<K, V, N, S>
+where
+    K: 'static + Sync + Send + Clone + Hash + Ord,
+    V: 'static + Sync + Send,
+    S: BuildHasher,
+{
+    /// Inserts a key-value pair into the map.
+    ///
+    /// If the map already holds a value for this key, the value is updated and the old value is
+    /// returned, which always succeeds. Otherwise, if the bin the key belongs in already holds
+    /// [`max_chain`](FixedHashMap::max_chain) entries, the map is left unchanged and the key and
+    /// value are handed back in a [`BinFullError`].
+    pub fn insert<'g>(
+        &'g self,
+        key: K,
+        value: V,
+        guard: &'g Guard,
+    ) -> Result<Option<&'g V>, BinFullError<K, V>> {
+        self.map
+            .insert_with_chain_limit(key, value, self.max_chain, guard)
+            .map_err(|(key, value)| BinFullError { key, value })
+    }
+
+    /// Returns a reference to the value corresponding to the key.
+    ///
+    /// See also [`HashMap::get`].
+    pub fn get<'g, Q>(&'g self, key: &Q, guard: &'g Guard) -> Option<&'g V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+    {
+        self.map.get(key, guard)
+    }
+
+    /// Returns `true` if the map contains a value for the specified key.
+    ///
+    /// See also [`HashMap::contains_key`].
+    pub fn contains_key<Q>(&self, key: &Q, guard: &Guard) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+    {
+        self.map.contains_key(key, guard)
+    }
+
+    /// Removes a key from the map, returning the value at the key if the key was previously in
+    /// the map.
+    ///
+    /// See also [`HashMap::remove`].
+    pub fn remove<'g, Q>(&'g self, key: &Q, guard: &'g Guard) -> Option<&'g V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+    {
+        self.map.remove(key, guard)
+    }
+
+    /// Clears the map, removing all key-value pairs. The table itself is kept.
+    ///
+    /// See also [`HashMap::clear`].
+    pub fn clear(&self, guard: &Guard) {
+        self.map.clear(guard)
+    }
+}
+
+impl<K, V, const N: usize, S> Debug for FixedHashMap<K, V, N, S>
+where
+    K: Debug,
+    V: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let guard = self.guard();
+        f.debug_map().entries(self.map.iter(&guard)).finish()
+    }
+}
+
+/// The error type for the [`FixedHashMap::insert`] method.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BinFullError<K, V> {
+    /// The key that [`FixedHashMap::insert`] failed to insert.
+    pub key: K,
+    /// The value that [`FixedHashMap::insert`] failed to insert.
+    pub value: V,
+}
+
+impl<K, V> Display for BinFullError<K, V>
+where
+    V: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Insert of \"{:?}\" failed as the bin for its key is full",
+            self.value
+        )
+    }
+}
+
+impl<K, V> Error for BinFullError<K, V>
+where
+    K: Debug,
+    V: Debug,
+{
+}
+
+#[cfg(test)]
+mod test {
+    use super::{BinFullError, FixedHashMap};
+    use std::hash::{BuildHasherDefault, Hasher};
+
+    #[derive(Default)]
+    struct ZeroHasher;
+
+    impl Hasher for ZeroHasher {
+        fn finish(&self) -> u64 {
+            0
+        }
+        fn write(&mut self, _: &[u8]) {}
+    }
+
+    type ZeroHashBuilder = BuildHasherDefault<ZeroHasher>;
+
+    #[test]
+    fn full_bin() {
+        let map: FixedHashMap<_, _, 16, _> =
+            FixedHashMap::with_hasher(4, ZeroHashBuilder::default());
+        let guard = map.guard();
+        for i in 0..4 {
+            assert_eq!(map.insert(i, i, &guard), Ok(None));
+        }
+        assert_eq!(
+            map.insert(4, 4, &guard),
+            Err(BinFullError { key: 4, value: 4 })
+        );
+        // updates of existing keys still go through
+        assert_eq!(map.insert(0, 10, &guard), Ok(Some(&0)));
+        assert_eq!(map.len(), 4);
+        assert_eq!(map.get(&4, &guard), None);
+
+        // removing an entry makes room for another
+        assert_eq!(map.remove(&1, &guard), Some(&1));
+        assert_eq!(map.insert(4, 4, &guard), Ok(None));
+        assert_eq!(map.get(&4, &guard), Some(&4));
+    }
+
+    #[test]
+    fn fill() {
+        let map: FixedHashMap<_, _, 16> = FixedHashMap::new(2);
+        let guard = map.guard();
+        let mut rejected = 0;
+        for i in 0..100 {
+            match map.insert(i, i, &guard) {
+                Ok(old) => assert_eq!(old, None),
+                Err(e) => {
+                    assert_eq!(e, BinFullError { key: i, value: i });
+                    rejected += 1;
+                }
+            }
+        }
+        // 16 bins with at most 2 entries each can hold at most 32 entries
+        assert!(map.len() <= 32);
+        assert_eq!(map.len() + rejected, 100);
+        assert_eq!(map.capacity(), 16);
+        assert_eq!(map.map.capacity(&guard), 16);
+    }
+
+    #[test]
+    #[should_panic]
+    fn bins_not_power_of_two() {
+        let _: FixedHashMap<usize, usize, 12> = FixedHashMap::new(2);
+    }
+}
@@ -0,0 +1,94 @@
+//! The hasher that maps use unless they are given another one.
+//!
+//! See `DefaultHashBuilder` for details.
+
+use std::collections::hash_map::RandomState;
+use std::fmt::{self, Debug, Formatter};
+use std::hash::{BuildHasher, Hasher};
+
+/// Default hasher for [`HashMap`](crate::HashMap).
+///
+/// This builds [`ahash`](https://docs.rs/ahash) hashers, which are a lot faster than those of
+/// the standard library. Every `DefaultHashBuilder` created through
+/// [`default`](DefaultHashBuilder::default) draws its keys from the operating system's random
+/// source, the same way [`std::collections::hash_map::RandomState`] does. This keeps an attacker
+/// from predicting which keys will collide in a given map, even across runs of the same program,
+/// and thus from mounting a HashDoS attack with keys that all land in the same bin.
+///
+/// Use [`with_seed`](DefaultHashBuilder::with_seed) where a map needs to place its keys the same
+/// way every time, such as in tests.
+#[derive(Clone)]
+pub struct DefaultHashBuilder(ahash::RandomState);
+
+impl DefaultHashBuilder {
+    /// Creates a builder with keys drawn from the operating system's random source.
+    pub fn new() -> Self {
+        // every std RandomState gets its own random keys, so hashing anything with it yields
+        // values that an attacker cannot guess.
+        let random = RandomState::new();
+        let mut k0 = random.build_hasher();
+        k0.write_u64(0);
+        let mut k1 = random.build_hasher();
+        k1.write_u64(1);
+        Self::with_seeds(k0.finish(), k1.finish())
+    }
+
+    /// Creates a builder whose hashers always hash the same key to the same value, derived from
+    /// `seed`.
+    ///
+    /// Maps that use builders created with the same seed lay out their entries identically. This
+    /// gives up HashDoS resistance, so do not use it for maps that hold untrusted keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::{DefaultHashBuilder, HashMap};
+    ///
+    /// let a = HashMap::with_hasher(DefaultHashBuilder::with_seed(42));
+    /// let b = HashMap::with_hasher(DefaultHashBuilder::with_seed(42));
+    /// for i in 0..100 {
+    ///     a.pin().insert(i, i);
+    ///     b.pin().insert(i, i);
+    /// }
+    /// assert_eq!(a.pin().bin_stats(), b.pin().bin_stats());
+    /// ```
+    pub fn with_seed(seed: u64) -> Self {
+        // spread the single seed over both keys, so that no two seeds give the same keys.
+        Self::with_seeds(seed, seed.rotate_left(32) ^ 0x9e37_79b9_7f4a_7c15)
+    }
+
+    /// Creates a builder whose hashers use exactly the keys `k0` and `k1`.
+    ///
+    /// Like [`with_seed`](DefaultHashBuilder::with_seed), this gives up HashDoS resistance.
+    pub fn with_seeds(k0: u64, k1: u64) -> Self {
+        DefaultHashBuilder(ahash::RandomState::with_seeds(k0, k1))
+    }
+}
+
+impl Default for DefaultHashBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<ahash::RandomState> for DefaultHashBuilder {
+    fn from(state: ahash::RandomState) -> Self {
+        DefaultHashBuilder(state)
+    }
+}
+
+impl BuildHasher for DefaultHashBuilder {
+    type Hasher = ahash::AHasher;
+
+    #[inline]
+    fn build_hasher(&self) -> Self::Hasher {
+        self.0.build_hasher()
+    }
+}
+
+impl Debug for DefaultHashBuilder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        // the keys are deliberately left out, since they are what keeps them from being guessed.
+        f.debug_struct("DefaultHashBuilder").finish()
+    }
+}
@@ -0,0 +1,70 @@
+//! A HyperLogLog sketch for estimating the number of distinct elements in a stream.
+//!
+//! See `HashMap::approx_distinct_values` for details.
+
+/// The number of hash bits used to pick a register.
+const PRECISION: u32 = 12;
+
+/// The number of registers, which makes for a standard error of about `1.04 / sqrt(REGISTERS)`,
+/// or 1.6%.
+const REGISTERS: usize = 1 << PRECISION;
+
+/// A HyperLogLog sketch over 64-bit hashes.
+///
+/// Every hash picks a register with its top bits, and the register remembers the longest run of
+/// leading zeros it has seen in the remaining bits. Long runs are rare, so the registers together
+/// tell how many distinct hashes went into the sketch, using only a byte per register no matter
+/// how many hashes that was.
+pub(crate) struct HyperLogLog {
+    registers: Box<[u8]>,
+}
+
+impl HyperLogLog {
+    pub(crate) fn new() -> Self {
+        HyperLogLog {
+            registers: vec![0; REGISTERS].into_boxed_slice(),
+        }
+    }
+
+    /// Records an element with the given hash.
+    pub(crate) fn insert(&mut self, hash: u64) {
+        // the estimate relies on the hash bits being uniform, which not every hasher guarantees,
+        // so the hash is run through the finalizer of MurmurHash3 first.
+        let mut hash = hash;
+        hash ^= hash >> 33;
+        hash = hash.wrapping_mul(0xff51_afd7_ed55_8ccd);
+        hash ^= hash >> 33;
+        hash = hash.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+        hash ^= hash >> 33;
+
+        let register = (hash >> (64 - PRECISION)) as usize;
+        // the sentinel bit caps the rank should all the remaining bits be zero.
+        let rest = (hash << PRECISION) | (1 << (PRECISION - 1));
+        let rank = rest.leading_zeros() as u8 + 1;
+        if self.registers[register] < rank {
+            self.registers[register] = rank;
+        }
+    }
+
+    /// Returns the estimated number of distinct hashes recorded so far.
+    pub(crate) fn estimate(&self) -> u64 {
+        let m = REGISTERS as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = self
+            .registers
+            .iter()
+            .map(|&rank| 2f64.powi(-i32::from(rank)))
+            .sum();
+        let raw = alpha * m * m / sum;
+
+        // for small cardinalities the raw estimate is biased, but then there are still empty
+        // registers, and linear counting over those is accurate.
+        let empty = self.registers.iter().filter(|&&rank| rank == 0).count();
+        let estimate = if raw <= 2.5 * m && empty != 0 {
+            m * (m / empty as f64).ln()
+        } else {
+            raw
+        };
+        estimate.round() as u64
+    }
+}
@@ -0,0 +1,177 @@
+//! A concurrent interner that assigns integer ids to keys.
+//!
+//! See `Interner` for details.
+
+use crate::epoch::Guard;
+use crate::HashMap;
+use std::borrow::Borrow;
+use std::fmt::{self, Debug, Formatter};
+use std::hash::{BuildHasher, Hash};
+
+/// A concurrent interner that gives every distinct key a unique, stable `u32` id.
+///
+/// Ids are handed out sequentially from 0 by a counter in the interner, and a key is inserted
+/// together with its id in a single atomic step, so even when several threads intern the same
+/// key at once, exactly one id is assigned to it, and every thread gets that id back. No two keys
+/// are ever given the same id.
+///
+/// The interner is built on a [`HashMap`] from keys to their ids, and assigns ids with
+/// [`HashMap::intern`]. Unlike with a bare map, ids can only be assigned through
+/// [`intern`](Interner::intern), so they cannot clash with ids that were made up elsewhere.
+///
+/// # Examples
+///
+/// ```
+/// use flurry::Interner;
+///
+/// let symbols = Interner::new();
+/// let guard = symbols.guard();
+/// assert_eq!(symbols.intern(String::from("foo"), &guard), 0);
+/// assert_eq!(symbols.intern(String::from("bar"), &guard), 1);
+/// assert_eq!(symbols.intern(String::from("foo"), &guard), 0);
+/// assert_eq!(symbols.id("bar", &guard), Some(1));
+/// assert_eq!(symbols.id("baz", &guard), None);
+/// ```
+pub struct Interner<K, S = crate::DefaultHashBuilder> {
+    map: HashMap<K, u32, S>,
+}
+
+impl<K> Interner<K, crate::DefaultHashBuilder> {
+    /// Creates an empty `Interner`.
+    pub fn new() -> Self {
+        Self::with_hasher(crate::DefaultHashBuilder::default())
+    }
+}
+
+impl<K> Default for Interner<K, crate::DefaultHashBuilder> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, S> Interner<K, S> {
+    /// Creates an empty `Interner` which will use `hash_builder` to hash keys.
+    pub fn with_hasher(hash_builder: S) -> Self {
+        Interner {
+            map: HashMap::with_hasher(hash_builder),
+        }
+    }
+
+    /// Pin a `Guard` for use with this interner.
+    ///
+    /// See also [`HashMap::guard`].
+    pub fn guard(&self) -> Guard {
+        self.map.guard()
+    }
+
+    /// Returns the number of keys that have been interned.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if no key has been interned yet.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+impl<K, S> Interner<K, S>
+where
+    K: 'static + Sync + Send + Clone + Hash + Ord,
+    S: BuildHasher,
+{
+    /// Returns the id of `key`, and assigns it the next unused id first if it does not have one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if all `u32::MAX` ids have been handed out.
+    ///
+    /// See also [`HashMap::intern`].
+    pub fn intern(&self, key: K, guard: &Guard) -> u32 {
+        self.map.intern(key, guard)
+    }
+
+    /// Returns the id of `key`, or `None` if it has not been interned.
+    ///
+    /// The key may be any borrowed form of the interner's key type, but [`Hash`] and [`Ord`] on
+    /// the borrowed form *must* match those for the key type.
+    pub fn id<Q>(&self, key: &Q, guard: &Guard) -> Option<u32>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+    {
+        self.map.get(key, guard).copied()
+    }
+}
+
+impl<K, S> Debug for Interner<K, S>
+where
+    K: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let guard = self.guard();
+        f.debug_map().entries(self.map.iter(&guard)).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Interner;
+    use std::sync::Arc;
+
+    #[test]
+    fn intern_and_id() {
+        let interner = Interner::new();
+        let guard = interner.guard();
+        assert!(interner.is_empty());
+        assert_eq!(interner.intern("a", &guard), 0);
+        assert_eq!(interner.intern("b", &guard), 1);
+        assert_eq!(interner.intern("a", &guard), 0);
+        assert_eq!(interner.id(&"b", &guard), Some(1));
+        assert_eq!(interner.id(&"c", &guard), None);
+        assert_eq!(interner.len(), 2);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn concurrent_intern() {
+        const THREADS: usize = 4;
+        const KEYS: usize = 500;
+
+        let interner = Arc::new(Interner::<String>::new());
+        let threads: Vec<_> = (0..THREADS)
+            .map(|t| {
+                let interner = interner.clone();
+                std::thread::spawn(move || {
+                    let guard = interner.guard();
+                    // each thread interns an overlapping range of keys, starting at a different
+                    // one
+                    (0..KEYS)
+                        .map(|i| {
+                            let key = ((i + t * KEYS / THREADS) % KEYS).to_string();
+                            (key.clone(), interner.intern(key, &guard))
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        let interned: Vec<_> = threads
+            .into_iter()
+            .flat_map(|t| t.join().unwrap())
+            .collect();
+
+        // every thread got the same id for a key as the one that ended up in the interner
+        let guard = interner.guard();
+        assert_eq!(interner.len(), KEYS);
+        for (key, id) in interned {
+            assert_eq!(interner.id(&key, &guard), Some(id));
+        }
+        // and the ids are exactly the first KEYS ones
+        let mut ids: Vec<_> = (0..KEYS)
+            .map(|i| interner.id(&i.to_string(), &guard).unwrap())
+            .collect();
+        ids.sort_unstable();
+        assert_eq!(ids, (0..KEYS as u32).collect::<Vec<_>>());
+        assert_eq!(interner.intern(String::from("new"), &guard), KEYS as u32);
+    }
+}
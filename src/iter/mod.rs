@@ -1,7 +1,13 @@
 mod traverser;
 pub(crate) use traverser::NodeIter;
 
+use crate::{HashMap, HashSet};
 use crossbeam_epoch::Guard;
+use parking_lot::Mutex;
+use std::borrow::Borrow;
+use std::fmt::{self, Debug, Formatter};
+use std::hash::{BuildHasher, Hash};
+use std::ops::RangeBounds;
 use std::sync::atomic::Ordering;
 
 /// An iterator over a map's entries.
@@ -24,6 +30,312 @@ impl<'g, K, V> Iterator for Iter<'g, K, V> {
     }
 }
 
+/// An iterator over a map's entries that also reports whether the lock of each entry's bin is
+/// held.
+///
+/// See [`HashMap::iter_with_lock_state`](crate::HashMap::iter_with_lock_state) for details.
+#[derive(Debug)]
+pub struct IterWithLockState<'g, K, V> {
+    pub(crate) node_iter: NodeIter<'g, K, V>,
+    /// The lock of the bin of the last entry, and whether it was held when the bin was reached.
+    pub(crate) bin: Option<(&'g Mutex<()>, bool)>,
+    pub(crate) guard: &'g Guard,
+}
+
+impl<'g, K, V> Iterator for IterWithLockState<'g, K, V> {
+    type Item = (&'g K, &'g V, bool);
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.node_iter.next()?;
+        let lock = self
+            .node_iter
+            .bin_lock()
+            .expect("every node is reached through the head of its bin");
+        let held = match self.bin {
+            Some((last, held)) if std::ptr::eq(last, lock) => held,
+            // parking_lot 0.10 has no way to check a lock without trying to take it. If the lock
+            // was free, we hold it until the end of this statement, and a writer that comes
+            // along in the meantime has to wait for us (or gives up, if it is nonblocking).
+            _ => lock.try_lock().is_none(),
+        };
+        self.bin = Some((lock, held));
+        let value = node.value.load(Ordering::SeqCst, self.guard);
+        // safety: flurry does not drop or move until after guard drop
+        let value = unsafe { value.deref() };
+        Some((&node.key, value, held))
+    }
+}
+
+/// An iterator over clones of a map's entries.
+///
+/// See [`HashMap::iter_owned`](crate::HashMap::iter_owned) for details.
+#[derive(Debug)]
+pub struct IterOwned<'g, K, V> {
+    pub(crate) iter: Iter<'g, K, V>,
+}
+
+impl<'g, K, V> Iterator for IterOwned<'g, K, V>
+where
+    K: Clone,
+    V: Clone,
+{
+    type Item = (K, V);
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, value) = self.iter.next()?;
+        Some((key.clone(), value.clone()))
+    }
+}
+
+/// An iterator over a map's entries that yields clones of the keys alongside references to the
+/// values.
+///
+/// See [`HashMap::iter_owned_keys`](crate::HashMap::iter_owned_keys) for details.
+#[derive(Debug)]
+pub struct IterOwnedKeys<'g, K, V> {
+    pub(crate) iter: Iter<'g, K, V>,
+}
+
+impl<'g, K, V> Iterator for IterOwnedKeys<'g, K, V>
+where
+    K: Clone,
+{
+    type Item = (K, &'g V);
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, value) = self.iter.next()?;
+        Some((key.clone(), value))
+    }
+}
+
+/// An iterator over a map's entries in chunks of a fixed size.
+///
+/// See [`HashMap::iter_chunks`](crate::HashMap::iter_chunks) for details.
+#[derive(Debug)]
+pub struct Chunks<'g, K, V> {
+    pub(crate) iter: Iter<'g, K, V>,
+    pub(crate) chunk_size: usize,
+}
+
+impl<'g, K, V> Iterator for Chunks<'g, K, V> {
+    type Item = Vec<(&'g K, &'g V)>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let chunk: Vec<_> = self.iter.by_ref().take(self.chunk_size).collect();
+        if chunk.is_empty() {
+            None
+        } else {
+            Some(chunk)
+        }
+    }
+}
+
+/// An iterator over a map's entries in a seeded pseudo-random order.
+///
+/// See [`HashMap::iter_shuffled`](crate::HashMap::iter_shuffled) for details.
+#[derive(Debug)]
+pub struct IterShuffled<'g, K, V> {
+    pub(crate) entries: std::vec::IntoIter<(&'g K, &'g V)>,
+}
+
+impl<'g, K, V> Iterator for IterShuffled<'g, K, V> {
+    type Item = (&'g K, &'g V);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entries.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+impl<'g, K, V> ExactSizeIterator for IterShuffled<'g, K, V> {}
+
+/// An iterator over a map's entries that reports malformed bins rather than visiting them.
+///
+/// See [`HashMap::iter_checked`](crate::HashMap::iter_checked) for details.
+#[derive(Debug)]
+pub struct IterChecked<'g, K, V> {
+    pub(crate) entries: std::vec::IntoIter<Result<(&'g K, &'g V), crate::BinError>>,
+}
+
+impl<'g, K, V> Iterator for IterChecked<'g, K, V> {
+    type Item = Result<(&'g K, &'g V), crate::BinError>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entries.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+impl<'g, K, V> ExactSizeIterator for IterChecked<'g, K, V> {}
+
+/// A cursor over a map's entries that hands them out in batches when asked.
+///
+/// See [`HashMap::iter_throttled`](crate::HashMap::iter_throttled) for details.
+#[derive(Debug)]
+pub struct ThrottledIter<'g, K, V> {
+    pub(crate) iter: Iter<'g, K, V>,
+    pub(crate) per_poll: usize,
+    pub(crate) done: bool,
+}
+
+impl<'g, K, V> ThrottledIter<'g, K, V> {
+    /// Returns the next batch of up to `per_poll` entries.
+    ///
+    /// A batch only holds fewer than `per_poll` entries once all entries of the map have been
+    /// handed out, after which all batches are empty.
+    pub fn next_batch(&mut self) -> Vec<(&'g K, &'g V)> {
+        let mut batch = Vec::new();
+        if self.done {
+            return batch;
+        }
+        batch.reserve(self.per_poll);
+        while batch.len() < self.per_poll {
+            match self.iter.next() {
+                Some(entry) => batch.push(entry),
+                None => {
+                    self.done = true;
+                    break;
+                }
+            }
+        }
+        batch
+    }
+
+    /// Returns `true` once all entries of the map have been handed out.
+    ///
+    /// This may only become `true` when a batch comes up short, so a map whose length is a
+    /// multiple of `per_poll` ends with an empty batch.
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+}
+
+/// An iterator over the map's entries whose keys fall within a range.
+///
+/// See [`HashMap::range`](crate::HashMap::range) for details.
+#[derive(Debug)]
+pub struct Range<'g, K, V, R> {
+    pub(crate) iter: Iter<'g, K, V>,
+    pub(crate) range: R,
+}
+
+impl<'g, K, V, R> Iterator for Range<'g, K, V, R>
+where
+    K: Ord,
+    R: RangeBounds<K>,
+{
+    type Item = (&'g K, &'g V);
+    fn next(&mut self) -> Option<Self::Item> {
+        let range = &self.range;
+        self.iter.find(|(key, _)| range.contains(*key))
+    }
+}
+
+/// An iterator over the entries of a map whose keys are also in another map.
+///
+/// See [`HashMap::intersect_keys`](crate::HashMap::intersect_keys) for details.
+#[derive(Debug)]
+pub struct IntersectKeys<'g, K, V, S, V2, S2> {
+    pub(crate) map: &'g HashMap<K, V, S>,
+    pub(crate) other: &'g HashMap<K, V2, S2>,
+    pub(crate) walk: IntersectWalk<'g, K, V, V2>,
+    pub(crate) guard: &'g Guard,
+}
+
+/// The map that an [`IntersectKeys`] walks, which is the smaller of the two.
+#[derive(Debug)]
+pub(crate) enum IntersectWalk<'g, K, V, V2> {
+    Ours(Iter<'g, K, V>),
+    Theirs(Keys<'g, K, V2>),
+}
+
+impl<'g, K, V, S, V2, S2> Iterator for IntersectKeys<'g, K, V, S, V2, S2>
+where
+    K: Hash + Ord,
+    S: BuildHasher,
+    S2: BuildHasher,
+{
+    type Item = (&'g K, &'g V);
+    fn next(&mut self) -> Option<Self::Item> {
+        let guard = self.guard;
+        match self.walk {
+            IntersectWalk::Ours(ref mut iter) => {
+                let other = self.other;
+                iter.find(|(key, _)| other.contains_key(*key, guard))
+            }
+            IntersectWalk::Theirs(ref mut keys) => loop {
+                let key = keys.next()?;
+                if let Some(entry) = self.map.get_key_value(key, guard) {
+                    return Some(entry);
+                }
+            },
+        }
+    }
+}
+
+/// An iterator over the entries of a [`LayeredMap`](crate::LayeredMap), where entries of the
+/// primary map shadow those of the parent map.
+///
+/// See [`LayeredMap::iter`](crate::LayeredMap::iter) for details.
+#[derive(Debug)]
+pub struct LayeredIter<'g, K, V, S> {
+    pub(crate) primary: &'g HashMap<K, V, S>,
+    pub(crate) primary_iter: Iter<'g, K, V>,
+    pub(crate) parent_iter: Iter<'g, K, V>,
+    pub(crate) guard: &'g Guard,
+}
+
+impl<'g, K, V, S> Iterator for LayeredIter<'g, K, V, S>
+where
+    K: Hash + Ord,
+    S: BuildHasher,
+{
+    type Item = (&'g K, &'g V);
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(entry) = self.primary_iter.next() {
+            return Some(entry);
+        }
+        let (primary, guard) = (self.primary, self.guard);
+        self.parent_iter
+            .find(|(key, _)| !primary.contains_key(*key, guard))
+    }
+}
+
+/// An iterator over a slice of keys and the values they map to, in the order of the slice.
+///
+/// See [`HashMap::get_ordered`](crate::HashMap::get_ordered) for details.
+#[derive(Debug)]
+pub struct GetOrdered<'g, Q, K, V, S> {
+    pub(crate) map: &'g HashMap<K, V, S>,
+    pub(crate) keys: std::slice::Iter<'g, Q>,
+    pub(crate) guard: &'g Guard,
+}
+
+impl<'g, Q, K, V, S> Iterator for GetOrdered<'g, Q, K, V, S>
+where
+    K: Borrow<Q> + Hash + Ord,
+    Q: Hash + Ord,
+    S: BuildHasher,
+{
+    type Item = (&'g Q, Option<&'g V>);
+    fn next(&mut self) -> Option<Self::Item> {
+        let key = self.keys.next()?;
+        Some((key, self.map.get(key, self.guard)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.keys.size_hint()
+    }
+}
+
+impl<'g, Q, K, V, S> ExactSizeIterator for GetOrdered<'g, Q, K, V, S>
+where
+    K: Borrow<Q> + Hash + Ord,
+    Q: Hash + Ord,
+    S: BuildHasher,
+{
+}
+
 /// An iterator over a map's keys.
 ///
 /// See [`HashMap::keys`](crate::HashMap::keys) for details.
@@ -60,6 +372,119 @@ impl<'g, K, V> Iterator for Values<'g, K, V> {
     }
 }
 
+/// An iterator over the distinct values of a map.
+///
+/// See [`HashMap::distinct_values_iter`](crate::HashMap::distinct_values_iter) for details.
+#[derive(Debug)]
+pub struct DistinctValues<'g, K, V> {
+    pub(crate) values: Values<'g, K, V>,
+    pub(crate) seen: std::collections::HashSet<&'g V>,
+}
+
+impl<'g, K, V> Iterator for DistinctValues<'g, K, V>
+where
+    V: Hash + Eq,
+{
+    type Item = &'g V;
+    fn next(&mut self) -> Option<Self::Item> {
+        let seen = &mut self.seen;
+        self.values.find(|value| seen.insert(*value))
+    }
+}
+
+/// An iterator over the map's entries that were written after a given version.
+///
+/// See [`HashMap::iter_since`](crate::HashMap::iter_since) for details.
+#[cfg(feature = "versioning")]
+#[derive(Debug)]
+pub struct IterSince<'g, K, V> {
+    pub(crate) node_iter: NodeIter<'g, K, V>,
+    pub(crate) version: u64,
+    pub(crate) guard: &'g Guard,
+}
+
+#[cfg(feature = "versioning")]
+impl<'g, K, V> Iterator for IterSince<'g, K, V> {
+    type Item = (&'g K, &'g V);
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let node = self.node_iter.next()?;
+            if node.version.load(Ordering::SeqCst) <= self.version {
+                continue;
+            }
+            let value = node.value.load(Ordering::SeqCst, self.guard);
+            // safety: flurry does not drop or move until after guard drop
+            let value = unsafe { value.deref() };
+            return Some((&node.key, value));
+        }
+    }
+}
+
+/// An iterator over the map's entries along with the sequence numbers they were inserted with.
+///
+/// See [`HashMap::iter_enumerated`](crate::HashMap::iter_enumerated) for details.
+#[cfg(feature = "insertion-order")]
+#[derive(Debug)]
+pub struct IterEnumerated<'g, K, V> {
+    pub(crate) node_iter: NodeIter<'g, K, V>,
+    pub(crate) guard: &'g Guard,
+}
+
+#[cfg(feature = "insertion-order")]
+impl<'g, K, V> Iterator for IterEnumerated<'g, K, V> {
+    type Item = (u64, &'g K, &'g V);
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.node_iter.next()?;
+        let value = node.value.load(Ordering::SeqCst, self.guard);
+        // safety: flurry does not drop or move until after guard drop
+        let value = unsafe { value.deref() };
+        Some((node.seq.load(Ordering::Relaxed), &node.key, value))
+    }
+}
+
+/// An iterator that removes and yields the elements of a set that match a predicate.
+///
+/// See [`HashSet::extract_if`](crate::HashSet::extract_if) for details.
+pub struct ExtractIf<'g, T, F, S = crate::DefaultHashBuilder> {
+    pub(crate) set: &'g HashSet<T, S>,
+    pub(crate) keys: Keys<'g, T, ()>,
+    pub(crate) pred: F,
+    pub(crate) guard: &'g Guard,
+}
+
+impl<'g, T, F, S> Iterator for ExtractIf<'g, T, F, S>
+where
+    T: 'static + Sync + Send + Clone + Hash + Ord,
+    S: BuildHasher,
+    F: FnMut(&T) -> bool,
+{
+    type Item = &'g T;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let value = self.keys.next()?;
+            if !(self.pred)(value) {
+                continue;
+            }
+            // another thread may have removed the element since we saw it, in which case it is
+            // not ours to yield.
+            if let Some(value) = self.set.take(value, self.guard) {
+                return Some(value);
+            }
+        }
+    }
+}
+
+impl<T, F, S> Debug for ExtractIf<'_, T, F, S>
+where
+    T: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExtractIf")
+            .field("keys", &self.keys)
+            .finish()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::HashMap;
@@ -82,6 +507,188 @@ mod tests {
         );
     }
 
+    #[test]
+    fn iter_owned() {
+        let map = HashMap::<usize, String>::new();
+
+        let guard = epoch::pin();
+        map.insert(1, "a".to_string(), &guard);
+        map.insert(2, "b".to_string(), &guard);
+
+        let entries: Vec<(usize, String)> = map.iter_owned(&guard).collect();
+        drop(guard);
+        assert_eq!(
+            entries.iter().cloned().collect::<HashSet<_>>(),
+            HashSet::from_iter(vec![(1, "a".to_string()), (2, "b".to_string())])
+        );
+
+        // the clones are unaffected by later changes to the map
+        let guard = epoch::pin();
+        map.insert(1, "c".to_string(), &guard);
+        map.remove(&2, &guard);
+        assert_eq!(entries.len(), 2);
+        assert!(entries.contains(&(1, "a".to_string())));
+        assert!(entries.contains(&(2, "b".to_string())));
+    }
+
+    #[test]
+    fn iter_owned_keys() {
+        let map = HashMap::<String, usize>::new();
+
+        let guard = epoch::pin();
+        for i in 0..10 {
+            map.insert(i.to_string(), i, &guard);
+        }
+
+        let mut keys = Vec::new();
+        let mut sum = 0;
+        for (key, value) in map.iter_owned_keys(&guard) {
+            keys.push(key);
+            sum += value;
+        }
+        assert_eq!(sum, 45);
+
+        // the keys outlive the guard and the map
+        drop(guard);
+        drop(map);
+        keys.sort_unstable();
+        assert_eq!(keys, (0..10).map(|i| i.to_string()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn iter_chunks() {
+        let map = HashMap::<usize, usize>::new();
+
+        let guard = epoch::pin();
+        for i in 0..250 {
+            map.insert(i, i * 2, &guard);
+        }
+
+        let chunks: Vec<_> = map.iter_chunks(100, &guard).collect();
+        assert_eq!(
+            chunks.iter().map(Vec::len).collect::<Vec<_>>(),
+            vec![100, 100, 50]
+        );
+        let entries: HashSet<(&usize, &usize)> = chunks.into_iter().flatten().collect();
+        assert_eq!(entries.len(), 250);
+        assert_eq!(entries, map.iter(&guard).collect());
+    }
+
+    #[test]
+    fn iter_throttled() {
+        let map = HashMap::<usize, usize>::new();
+
+        let guard = epoch::pin();
+        for i in 0..250 {
+            map.insert(i, i * 2, &guard);
+        }
+
+        let mut export = map.iter_throttled(100, &guard);
+        let mut batches = Vec::new();
+        while !export.is_done() {
+            // the consumer could wait here for as long as it likes
+            batches.push(export.next_batch());
+        }
+        assert_eq!(
+            batches.iter().map(Vec::len).collect::<Vec<_>>(),
+            vec![100, 100, 50]
+        );
+        let entries: HashSet<(&usize, &usize)> = batches.into_iter().flatten().collect();
+        assert_eq!(entries.len(), 250);
+        assert_eq!(entries, map.iter(&guard).collect::<HashSet<_>>());
+        assert!(export.next_batch().is_empty());
+    }
+
+    #[test]
+    fn range() {
+        let map = HashMap::<usize, usize>::new();
+
+        let guard = epoch::pin();
+        for i in 0..100 {
+            map.insert(i, i * 2, &guard);
+        }
+
+        let mut entries: Vec<_> = map.range(10..20, &guard).map(|(&k, &v)| (k, v)).collect();
+        entries.sort_unstable();
+        assert_eq!(entries, (10..20).map(|i| (i, i * 2)).collect::<Vec<_>>());
+        assert_eq!(map.range(95.., &guard).count(), 5);
+        assert_eq!(map.range(..=0, &guard).count(), 1);
+        assert_eq!(map.range(200..300, &guard).count(), 0);
+    }
+
+    #[test]
+    fn intersect_keys() {
+        let names = HashMap::<u32, String>::new();
+        let flags = HashMap::<u32, bool>::new();
+
+        let guard = epoch::pin();
+        for i in 0..100 {
+            names.insert(i, i.to_string(), &guard);
+        }
+        for i in (50..150).step_by(5) {
+            flags.insert(i, i % 2 == 0, &guard);
+        }
+
+        let expected: HashSet<u32> = (50..100).step_by(5).collect();
+        // walks the smaller map, which is flags
+        let joined: Vec<_> = names.intersect_keys(&flags, &guard).collect();
+        assert_eq!(joined.len(), expected.len());
+        for (key, value) in joined {
+            assert!(expected.contains(key));
+            assert_eq!(value, &key.to_string());
+        }
+        // walks the smaller map, which is now flags itself
+        let joined: HashSet<_> = flags
+            .intersect_keys(&names, &guard)
+            .map(|(k, _)| *k)
+            .collect();
+        assert_eq!(joined, expected);
+    }
+
+    #[test]
+    fn get_ordered() {
+        let map = HashMap::<u32, char>::new();
+        let guard = epoch::pin();
+        for &(key, value) in &[(2, 'b'), (4, 'd'), (5, 'e')] {
+            map.insert(key, value, &guard);
+        }
+
+        let keys = [5, 1, 2, 3, 4, 2];
+        let found: Vec<_> = map.get_ordered(&keys, &guard).collect();
+        assert_eq!(
+            found,
+            [
+                (&5, Some(&'e')),
+                (&1, None),
+                (&2, Some(&'b')),
+                (&3, None),
+                (&4, Some(&'d')),
+                (&2, Some(&'b')),
+            ]
+        );
+        assert_eq!(map.get_ordered(&keys, &guard).len(), keys.len());
+    }
+
+    #[test]
+    fn distinct_values_iter() {
+        let map = HashMap::<usize, usize>::new();
+
+        let guard = epoch::pin();
+        for i in 0..100 {
+            map.insert(i, i % 7, &guard);
+        }
+
+        let mut values: Vec<_> = map.distinct_values_iter(&guard).copied().collect();
+        values.sort_unstable();
+        assert_eq!(values, (0..7).collect::<Vec<_>>());
+        assert_eq!(
+            HashMap::<usize, usize>::new()
+                .distinct_values_iter(&guard)
+                .count(),
+            0
+        );
+    }
+
     #[test]
     fn keys() {
         let map = HashMap::<usize, usize>::new();
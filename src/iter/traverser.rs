@@ -1,6 +1,9 @@
 use crate::node::{BinEntry, Node, TreeNode};
 use crate::raw::Table;
 use crossbeam_epoch::{Guard, Shared};
+use parking_lot::Mutex;
+#[cfg(feature = "rayon")]
+use std::ops::Range;
 use std::sync::atomic::Ordering;
 
 #[derive(Debug)]
@@ -14,6 +17,9 @@ pub(crate) struct NodeIter<'g, K, V> {
     /// The last bin entry iterated over
     prev: Option<&'g Node<K, V>>,
 
+    /// The lock of the bin that `prev` is in
+    bin_lock: Option<&'g Mutex<()>>,
+
     /// Index of bin to use next
     index: usize,
 
@@ -26,11 +32,40 @@ pub(crate) struct NodeIter<'g, K, V> {
     /// Initial table size
     base_size: usize,
 
+    /// Whether to visit the bins of the initial table from the last one to the first one
+    reverse: bool,
+
     guard: &'g Guard,
 }
 
 impl<'g, K, V> NodeIter<'g, K, V> {
     pub(crate) fn new(table: Shared<'g, Table<K, V>>, guard: &'g Guard) -> Self {
+        Self::with_direction(table, false, guard)
+    }
+
+    /// Like `new`, but visits the bins of the table from the last one to the first one. Entries
+    /// within a bin are still visited in order.
+    pub(crate) fn new_rev(table: Shared<'g, Table<K, V>>, guard: &'g Guard) -> Self {
+        Self::with_direction(table, true, guard)
+    }
+
+    /// Like `new`, but only visits the entries of the bins of the table whose indices are in
+    /// `bins`. The entries of those bins are found even if they have been moved to a larger
+    /// table, so iterators over disjoint ranges visit disjoint sets of entries.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn with_range(
+        table: Shared<'g, Table<K, V>>,
+        bins: Range<usize>,
+        guard: &'g Guard,
+    ) -> Self {
+        let mut iter = Self::with_direction(table, false, guard);
+        iter.base_limit = std::cmp::min(bins.end, iter.base_size);
+        iter.base_index = bins.start;
+        iter.index = iter.top_level_bin();
+        iter
+    }
+
+    fn with_direction(table: Shared<'g, Table<K, V>>, reverse: bool, guard: &'g Guard) -> Self {
         let (table, len) = if table.is_null() {
             (None, 0)
         } else {
@@ -40,16 +75,36 @@ impl<'g, K, V> NodeIter<'g, K, V> {
             (Some(table), table.len())
         };
 
-        Self {
+        let mut iter = Self {
             table,
             stack: None,
             spare: None,
             prev: None,
+            bin_lock: None,
             base_size: len,
             base_index: 0,
             index: 0,
             base_limit: len,
+            reverse,
             guard,
+        };
+        iter.index = iter.top_level_bin();
+        iter
+    }
+
+    /// Returns the lock of the bin that the node last returned by `next` is in.
+    pub(crate) fn bin_lock(&self) -> Option<&'g Mutex<()>> {
+        self.bin_lock
+    }
+
+    /// Returns the index of the bin in the initial table that `base_index` refers to.
+    ///
+    /// `base_index` always counts up from 0, even when iterating in reverse.
+    fn top_level_bin(&self) -> usize {
+        if self.reverse && self.base_index < self.base_size {
+            self.base_size - 1 - self.base_index
+        } else {
+            self.base_index
         }
     }
 
@@ -103,7 +158,7 @@ impl<'g, K, V> NodeIter<'g, K, V> {
                 // we've gone past the last part of this top-level bin,
                 // so move to the _next_ top-level bin.
                 self.base_index += 1;
-                self.index = self.base_index;
+                self.index = self.top_level_bin();
             }
         }
     }
@@ -129,6 +184,9 @@ impl<'g, K, V> Iterator for NodeIter<'g, K, V> {
                         e = Some(&tree_node.node);
                     }
                     BinEntry::Moved => unreachable!("Nodes can only point to Nodes or TreeNodes"),
+                    BinEntry::Reserved(_) => {
+                        unreachable!("Nodes can only point to Nodes or TreeNodes")
+                    }
                     BinEntry::Tree(_) => unreachable!("Nodes can only point to Nodes or TreeNodes"),
                 }
             }
@@ -168,8 +226,10 @@ impl<'g, K, V> Iterator for NodeIter<'g, K, V> {
                     }
                     BinEntry::Node(node) => {
                         e = Some(node);
+                        self.bin_lock = Some(&node.lock);
                     }
                     BinEntry::Tree(tree_bin) => {
+                        self.bin_lock = Some(&tree_bin.lock);
                         // since we want to iterate over all entries, TreeBins
                         // are also traversed via the `next` pointers of their
                         // contained node
@@ -190,6 +250,8 @@ impl<'g, K, V> Iterator for NodeIter<'g, K, V> {
                     BinEntry::TreeNode(_) => unreachable!(
                         "The head of a bin cannot be a TreeNode directly without BinEntry::Tree"
                     ),
+                    // the bin is empty until the reservation is replaced
+                    BinEntry::Reserved(_) => {}
                 }
             }
 
@@ -199,7 +261,7 @@ impl<'g, K, V> Iterator for NodeIter<'g, K, V> {
                 self.index = i + self.base_size;
                 if self.index >= n {
                     self.base_index += 1;
-                    self.index = self.base_index;
+                    self.index = self.top_level_bin();
                 }
             }
         }
@@ -219,7 +281,6 @@ mod tests {
     use super::*;
     use crate::raw::Table;
     use crossbeam_epoch::{self as epoch, Atomic, Owned};
-    use parking_lot::Mutex;
 
     #[test]
     fn iter_new() {
@@ -244,13 +305,11 @@ mod tests {
     #[test]
     fn iter_simple() {
         let mut bins = vec![Atomic::null(); 16];
-        bins[8] = Atomic::new(BinEntry::Node(Node {
-            hash: 0,
-            key: 0usize,
-            value: Atomic::new(0usize),
-            next: Atomic::null(),
-            lock: Mutex::new(()),
-        }));
+        bins[8] = Atomic::new(BinEntry::Node(Node::<usize, usize>::new(
+            0,
+            0,
+            Atomic::new(0),
+        )));
 
         let table = Owned::new(Table::from(bins));
         let guard = epoch::pin();
@@ -271,13 +330,11 @@ mod tests {
     fn iter_fw() {
         // construct the forwarded-to table
         let mut deep_bins = vec![Atomic::null(); 16];
-        deep_bins[8] = Atomic::new(BinEntry::Node(Node {
-            hash: 0,
-            key: 0usize,
-            value: Atomic::new(0usize),
-            next: Atomic::null(),
-            lock: Mutex::new(()),
-        }));
+        deep_bins[8] = Atomic::new(BinEntry::Node(Node::<usize, usize>::new(
+            0,
+            0,
+            Atomic::new(0),
+        )));
         let guard = epoch::pin();
         let deep_table = Owned::new(Table::from(deep_bins)).into_shared(&guard);
 
@@ -0,0 +1,240 @@
+//! A map that overrides the entries of another map.
+//!
+//! See `LayeredMap` for details.
+
+use crate::epoch::Guard;
+use crate::iter::LayeredIter;
+use crate::HashMap;
+use std::borrow::Borrow;
+use std::fmt::{self, Debug, Formatter};
+use std::hash::{BuildHasher, Hash};
+
+/// A map layered over a parent [`HashMap`], whose own entries shadow those of the parent.
+///
+/// A `LayeredMap` holds a primary map of its own, and a reference to a parent map. Lookups check
+/// the primary map first, and fall back to the parent map if the key is not in the primary map.
+/// Writes only ever go to the primary map, so the parent map is never modified through a
+/// `LayeredMap`, and can be shared by any number of them. This models layers of overrides, such
+/// as configuration values that override defaults, without copying the parent map.
+///
+/// Since the parent map is not modified, removing a key from a `LayeredMap` only removes the
+/// override: if the parent map has the key, its value shows through again.
+///
+/// The primary map uses the same epoch collector as the parent map, so a single guard can be
+/// used to access both layers.
+///
+/// # Examples
+///
+/// ```
+/// use flurry::{HashMap, LayeredMap};
+///
+/// let defaults = HashMap::new();
+/// defaults.pin().insert("color", "blue");
+/// defaults.pin().insert("size", "medium");
+///
+/// let config = LayeredMap::new(&defaults);
+/// let guard = config.guard();
+/// config.insert("size", "large", &guard);
+///
+/// assert_eq!(config.get("color", &guard), Some(&"blue"));
+/// assert_eq!(config.get("size", &guard), Some(&"large"));
+/// assert_eq!(defaults.pin().get("size"), Some(&"medium"));
+///
+/// config.remove("size", &guard);
+/// assert_eq!(config.get("size", &guard), Some(&"medium"));
+/// ```
+pub struct LayeredMap<'p, K, V, S = crate::DefaultHashBuilder> {
+    primary: HashMap<K, V, S>,
+    parent: &'p HashMap<K, V, S>,
+}
+
+impl<'p, K, V> LayeredMap<'p, K, V, crate::DefaultHashBuilder> {
+    /// Creates a `LayeredMap` with an empty primary map over `parent`.
+    pub fn new(parent: &'p HashMap<K, V, crate::DefaultHashBuilder>) -> Self {
+        Self::with_hasher(parent, crate::DefaultHashBuilder::default())
+    }
+}
+
+impl<'p, K, V, S> LayeredMap<'p, K, V, S> {
+    /// Creates a `LayeredMap` with an empty primary map over `parent`, where the primary map will
+    /// use `hash_builder` to hash keys.
+    pub fn with_hasher(parent: &'p HashMap<K, V, S>, hash_builder: S) -> Self {
+        LayeredMap {
+            primary: HashMap::with_hasher(hash_builder).with_collector(parent.collector().clone()),
+            parent,
+        }
+    }
+
+    /// Pin a `Guard` for use with this map.
+    ///
+    /// See also [`HashMap::guard`].
+    pub fn guard(&self) -> Guard {
+        self.primary.guard()
+    }
+
+    /// Returns the primary map, which holds the entries that override those of the parent map.
+    pub fn primary(&self) -> &HashMap<K, V, S> {
+        &self.primary
+    }
+
+    /// Returns the parent map.
+    pub fn parent(&self) -> &'p HashMap<K, V, S> {
+        self.parent
+    }
+}
+
+impl<'p, K, V, S> LayeredMap<'p, K, V, S>
+where
+    K: Hash + Ord,
+    S: BuildHasher,
+{
+    /// Returns a reference to the value corresponding to the key in the primary map, or in the
+    /// parent map if the primary map does not have the key.
+    ///
+    /// See also [`HashMap::get`].
+    pub fn get<'g, Q>(&'g self, key: &Q, guard: &'g Guard) -> Option<&'g V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+    {
+        self.primary
+            .get(key, guard)
+            .or_else(|| self.parent.get(key, guard))
+    }
+
+    /// Returns `true` if either the primary map or the parent map contains a value for the
+    /// specified key.
+    ///
+    /// See also [`HashMap::contains_key`].
+    pub fn contains_key<Q>(&self, key: &Q, guard: &Guard) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+    {
+        self.primary.contains_key(key, guard) || self.parent.contains_key(key, guard)
+    }
+
+    /// An iterator visiting all key-value pairs of both layers in arbitrary order.
+    ///
+    /// Every key is yielded once: with its value in the primary map if it has one there, and
+    /// with its value in the parent map otherwise. The entries of the primary map are yielded
+    /// first.
+    pub fn iter<'g>(&'g self, guard: &'g Guard) -> LayeredIter<'g, K, V, S> {
+        LayeredIter {
+            primary: &self.primary,
+            primary_iter: self.primary.iter(guard),
+            parent_iter: self.parent.iter(guard),
+            guard,
+        }
+    }
+}
+
+impl<'p, K, V, S> LayeredMap<'p, K, V, S>
+where
+    K: 'static + Sync + Send + Clone + Hash + Ord,
+    V: 'static + Sync + Send,
+    S: BuildHasher,
+{
+    /// Inserts a key-value pair into the primary map, which overrides any value the parent map
+    /// has for the key.
+    ///
+    /// Returns the value the primary map previously had for the key, if any. The parent map is
+    /// left unchanged.
+    ///
+    /// See also [`HashMap::insert`].
+    pub fn insert<'g>(&'g self, key: K, value: V, guard: &'g Guard) -> Option<&'g V> {
+        self.primary.insert(key, value, guard)
+    }
+
+    /// Removes a key from the primary map, returning the value the primary map had for it, if
+    /// any.
+    ///
+    /// The parent map is left unchanged, so if it has a value for the key, that value is visible
+    /// through the `LayeredMap` again.
+    ///
+    /// See also [`HashMap::remove`].
+    pub fn remove<'g, Q>(&'g self, key: &Q, guard: &'g Guard) -> Option<&'g V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+    {
+        self.primary.remove(key, guard)
+    }
+}
+
+impl<K, V, S> Debug for LayeredMap<'_, K, V, S>
+where
+    K: Debug + Hash + Ord,
+    V: Debug,
+    S: BuildHasher,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let guard = self.guard();
+        f.debug_map().entries(self.iter(&guard)).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::LayeredMap;
+    use crate::HashMap;
+
+    #[test]
+    fn overrides_shadow_parent() {
+        let parent = HashMap::<usize, usize>::new();
+        {
+            let guard = parent.guard();
+            for i in 0..10 {
+                parent.insert(i, i, &guard);
+            }
+        }
+
+        let layered = LayeredMap::new(&parent);
+        let guard = layered.guard();
+        for i in 5..15 {
+            assert_eq!(layered.insert(i, i * 10, &guard), None);
+        }
+
+        // misses in the primary map fall through to the parent map
+        for i in 0..5 {
+            assert_eq!(layered.get(&i, &guard), Some(&i));
+        }
+        for i in 5..15 {
+            assert_eq!(layered.get(&i, &guard), Some(&(i * 10)));
+        }
+        assert!(!layered.contains_key(&15, &guard));
+        assert_eq!(layered.get(&15, &guard), None);
+
+        // every key is yielded once, with the value of the topmost layer that has it
+        let mut entries: Vec<_> = layered.iter(&guard).map(|(&k, &v)| (k, v)).collect();
+        entries.sort_unstable();
+        let expected: Vec<_> = (0..5)
+            .map(|i| (i, i))
+            .chain((5..15).map(|i| (i, i * 10)))
+            .collect();
+        assert_eq!(entries, expected);
+
+        // removing an override uncovers the parent value, and the parent is never written
+        assert_eq!(layered.remove(&7, &guard), Some(&70));
+        assert_eq!(layered.get(&7, &guard), Some(&7));
+        assert_eq!(layered.remove(&2, &guard), None);
+        assert_eq!(layered.get(&2, &guard), Some(&2));
+        assert_eq!(parent.len(), 10);
+        assert_eq!(layered.primary().len(), 9);
+    }
+
+    #[test]
+    fn parent_with_custom_collector() {
+        let parent =
+            HashMap::<usize, usize>::new().with_collector(crossbeam_epoch::Collector::new());
+        parent.pin().insert(1, 1);
+
+        // a guard of the layered map must be usable with both layers
+        let layered = LayeredMap::new(&parent);
+        let guard = layered.guard();
+        assert_eq!(layered.insert(2, 2, &guard), None);
+        assert_eq!(layered.get(&1, &guard), Some(&1));
+        assert_eq!(layered.get(&2, &guard), Some(&2));
+        assert_eq!(layered.iter(&guard).count(), 2);
+    }
+}
@@ -0,0 +1,172 @@
+//! A map that is created on first access, for use in `static`s.
+//!
+//! See `LazyHashMap` for details.
+
+use crate::HashMap;
+use std::cell::UnsafeCell;
+use std::fmt::{self, Debug, Formatter};
+use std::mem::MaybeUninit;
+use std::ops::Deref;
+use std::sync::Once;
+
+/// A [`HashMap`] that is created the first time it is accessed.
+///
+/// [`HashMap::new`] cannot be called in a `const` context, so a map cannot be stored directly
+/// in a `static`. A `LazyHashMap` can: [`LazyHashMap::new`] is a `const fn`, and the map itself
+/// is created, with a default hasher, the first time the `LazyHashMap` is dereferenced. If
+/// several threads race for that first access, exactly one of them creates the map, and the
+/// others wait for it to finish, so all threads always share the same map.
+///
+/// Once created, the map is accessed through [`Deref`], and so offers the full [`HashMap`] API.
+///
+/// # Examples
+///
+/// ```
+/// use flurry::LazyHashMap;
+///
+/// static CACHE: LazyHashMap<u64, String> = LazyHashMap::new();
+///
+/// fn lookup(id: u64) -> String {
+///     let guard = CACHE.guard();
+///     match CACHE.try_insert(id, format!("user-{}", id), &guard) {
+///         Ok(name) => name.clone(),
+///         Err(e) => e.current.clone(),
+///     }
+/// }
+///
+/// assert_eq!(lookup(7), "user-7");
+/// assert_eq!(lookup(7), "user-7");
+/// assert_eq!(CACHE.len(), 1);
+/// ```
+pub struct LazyHashMap<K, V, S = crate::DefaultHashBuilder> {
+    once: Once,
+    map: UnsafeCell<MaybeUninit<HashMap<K, V, S>>>,
+}
+
+// safety: the map is only ever written once, inside `once`, and only read after `once` has
+// completed, so sharing a `LazyHashMap` is no different from sharing the map itself. the map
+// may be created by any thread that shares the `LazyHashMap`, which is why it must be `Send`.
+unsafe impl<K, V, S> Sync for LazyHashMap<K, V, S> where HashMap<K, V, S>: Send + Sync {}
+
+impl<K, V, S> LazyHashMap<K, V, S> {
+    /// Creates a `LazyHashMap` whose map has not been created yet.
+    pub const fn new() -> Self {
+        LazyHashMap {
+            once: Once::new(),
+            map: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+
+    /// Returns `true` if the map has been created.
+    pub fn is_initialized(this: &Self) -> bool {
+        this.once.is_completed()
+    }
+}
+
+impl<K, V, S> LazyHashMap<K, V, S>
+where
+    S: Default,
+{
+    /// Returns the map, and creates it first if that has not happened yet.
+    ///
+    /// This is what dereferencing a `LazyHashMap` does. It is an associated function rather than
+    /// a method so that it does not shadow methods of the map, such as [`HashMap::get`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if creating the map panicked on an earlier access.
+    pub fn force(this: &Self) -> &HashMap<K, V, S> {
+        this.once.call_once(|| {
+            let map = HashMap::with_hasher(S::default());
+            // safety: call_once runs this at most once, and no reference to the map exists
+            // before it has completed.
+            unsafe { (*this.map.get()).as_mut_ptr().write(map) };
+        });
+        // safety: once has completed, so the map has been written, and is never written again.
+        unsafe { &*(*this.map.get()).as_ptr() }
+    }
+}
+
+impl<K, V, S> Default for LazyHashMap<K, V, S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V, S> Deref for LazyHashMap<K, V, S>
+where
+    S: Default,
+{
+    type Target = HashMap<K, V, S>;
+
+    fn deref(&self) -> &Self::Target {
+        Self::force(self)
+    }
+}
+
+impl<K, V, S> Debug for LazyHashMap<K, V, S>
+where
+    HashMap<K, V, S>: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if Self::is_initialized(self) {
+            // safety: once has completed, so the map has been written.
+            let map = unsafe { &*(*self.map.get()).as_ptr() };
+            f.debug_tuple("LazyHashMap").field(map).finish()
+        } else {
+            f.write_str("LazyHashMap(<uninit>)")
+        }
+    }
+}
+
+impl<K, V, S> Drop for LazyHashMap<K, V, S> {
+    fn drop(&mut self) {
+        if self.once.is_completed() {
+            // safety: once has completed, so the map has been written, and we have &mut self, so
+            // there cannot be any outstanding references to it.
+            unsafe { std::ptr::drop_in_place((*self.map.get()).as_mut_ptr()) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::LazyHashMap;
+    use crate::HashMap;
+
+    static MAP: LazyHashMap<usize, usize> = LazyHashMap::new();
+
+    #[test]
+    fn shared_static() {
+        const THREADS: usize = 8;
+
+        let threads: Vec<_> = (0..THREADS)
+            .map(|t| {
+                std::thread::spawn(move || {
+                    let map: &HashMap<usize, usize> = &MAP;
+                    map.pin().insert(t, t);
+                    map as *const HashMap<usize, usize> as usize
+                })
+            })
+            .collect();
+        let maps: Vec<_> = threads.into_iter().map(|t| t.join().unwrap()).collect();
+
+        assert!(LazyHashMap::is_initialized(&MAP));
+        assert!(maps.iter().all(|&map| map == &*MAP as *const _ as usize));
+        let guard = MAP.guard();
+        assert_eq!(MAP.len(), THREADS);
+        for t in 0..THREADS {
+            assert_eq!(MAP.get(&t, &guard), Some(&t));
+        }
+    }
+
+    #[test]
+    fn drop_initialized() {
+        let lazy = LazyHashMap::<usize, Box<usize>>::new();
+        assert!(!LazyHashMap::is_initialized(&lazy));
+        assert_eq!(format!("{:?}", lazy), "LazyHashMap(<uninit>)");
+        lazy.pin().insert(1, Box::new(1));
+        assert!(LazyHashMap::is_initialized(&lazy));
+        assert_eq!(format!("{:?}", lazy), "LazyHashMap({1: 1})");
+    }
+}
@@ -92,13 +92,10 @@
 //! However, some other types of nodes exist: `BinEntry::TreeNode`s are arranged in balanced trees
 //! instead of linear lists. Bins of type `BinEntry::Tree` hold the roots of sets of `BinEntry::TreeNode`s.
 //! Some nodes are of type `BinEntry::Moved`; these "forwarding nodes" are placed at the
-//! heads of bins during resizing. The Java version also has other special node types, but these
-//! have not yet been implemented in this port. These special nodes are all either uncommon or
-//! transient.
+//! heads of bins during resizing. Nodes of type `BinEntry::Reserved` are placeholders that hold
+//! an empty bin while the value for its first entry is computed. These special nodes are all
+//! either uncommon or transient.
 //!
-/*
-//! TODO: TreeNodes, ReservationNodes
-*/
 //! The table is lazily initialized to a power-of-two size upon the first insertion.  Each bin in
 //! the table normally contains a list of nodes (most often, the list has only zero or one
 //! `BinEntry`). Table accesses require atomic reads, writes, and CASes.
@@ -245,12 +242,24 @@
 use crossbeam_epoch::Guard;
 use std::ops::Deref;
 
+mod bin_guard;
+mod builder;
+mod counter;
+mod fixed;
+mod hasher;
+mod hll;
+mod interner;
+mod layered;
+mod lazy;
 mod map;
 mod map_ref;
+mod memo;
 mod node;
+mod normalized;
 mod raw;
 mod set;
 mod set_ref;
+mod txn;
 
 #[cfg(feature = "rayon")]
 mod rayon_impls;
@@ -258,16 +267,55 @@ mod rayon_impls;
 #[cfg(feature = "serde")]
 mod serde_impls;
 
+#[cfg(feature = "bloom")]
+mod bloom;
+
+#[cfg(feature = "ttl")]
+mod ttl;
+
+#[cfg(feature = "versioning")]
+mod delta;
+
+#[cfg(feature = "metrics")]
+mod metrics;
+
+#[cfg(feature = "node-pool")]
+mod pool;
+
 /// Iterator types.
 pub mod iter;
 
-pub use map::{HashMap, TryInsertError};
+pub mod compat;
+
+pub mod traits;
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+pub use bin_guard::BinGuard;
+pub use builder::{Builder, SetBuilder};
+pub use counter::Counter;
+#[cfg(feature = "versioning")]
+pub use delta::DeltaReader;
+pub use fixed::{BinFullError, FixedHashMap};
+pub use hasher::DefaultHashBuilder;
+pub use interner::Interner;
+pub use layered::LayeredMap;
+pub use lazy::LazyHashMap;
+pub use map::{
+    diff, swap, BinError, BinStats, Compute, GetTrace, HashMap, LockStrategy, MapDiff, MapOp,
+    ReplaceIf, TryInsertError, ValueRef, WouldBlock,
+};
 pub use map_ref::HashMapRef;
-pub use set::HashSet;
+pub use memo::MemoMap;
+#[cfg(feature = "metrics")]
+pub use metrics::MapStats;
+pub use normalized::{Lowercase, NormalizedMap, Normalizer};
+pub use set::{HashSet, MapView};
 pub use set_ref::HashSetRef;
-
-/// Default hasher for [`HashMap`].
-pub type DefaultHashBuilder = ahash::RandomState;
+#[cfg(feature = "ttl")]
+pub use ttl::TtlMap;
+pub use txn::TxnView;
 
 /// Types needed to safely access shared data concurrently.
 pub mod epoch {
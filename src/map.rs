@@ -1,13 +1,28 @@
+use crate::builder::Builder;
+#[cfg(feature = "ffi")]
+use crate::ffi::RawTableParts;
 use crate::iter::*;
+#[cfg(feature = "metrics")]
+use crate::metrics::{MapStats, Metrics};
 use crate::node::*;
+#[cfg(feature = "node-pool")]
+use crate::pool::NodePool;
 use crate::raw::*;
 use crossbeam_epoch::{self as epoch, Atomic, Guard, Owned, Shared};
+use parking_lot::Mutex;
 use std::borrow::Borrow;
+use std::convert::TryInto;
 use std::error::Error;
 use std::fmt::{self, Debug, Display, Formatter};
 use std::hash::{BuildHasher, Hash, Hasher};
 use std::iter::FromIterator;
-use std::sync::atomic::{AtomicIsize, Ordering};
+use std::marker::PhantomData;
+use std::ops::{Deref, RangeBounds};
+use std::panic::{self, AssertUnwindSafe};
+#[cfg(any(feature = "versioning", feature = "insertion-order"))]
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::{AtomicIsize, AtomicU32, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 
 const ISIZE_BITS: usize = core::mem::size_of::<isize>() * 8;
 
@@ -27,7 +42,7 @@ const DEFAULT_CAPACITY: usize = 16;
 /// nodes. The value must be greater than 2, and should be at least 8 to mesh
 /// with assumptions in tree removal about conversion back to plain bins upon
 /// shrinkage.
-const TREEIFY_THRESHOLD: usize = 8;
+pub(crate) const TREEIFY_THRESHOLD: usize = 8;
 
 /// The bin count threshold for untreeifying a (split) bin during a resize
 /// operation. Should be less than TREEIFY_THRESHOLD, and at most 6 to mesh with
@@ -71,6 +86,17 @@ macro_rules! load_factor {
     };
 }
 
+/// Adds to one of the counters of the map's metrics, if they are enabled.
+macro_rules! record {
+    ($map: expr, $counter: ident) => {
+        record!($map, $counter, 1)
+    };
+    ($map: expr, $counter: ident, $n: expr) => {
+        #[cfg(feature = "metrics")]
+        $map.metrics.$counter.fetch_add($n, Ordering::Relaxed);
+    };
+}
+
 /// A concurrent hash table.
 ///
 /// Flurry uses [`Guards`] to control the lifetime of the resources that get stored and
@@ -101,6 +127,59 @@ pub struct HashMap<K, V, S = crate::DefaultHashBuilder> {
     /// next element count value upon which to resize the table.
     size_ctl: AtomicIsize,
 
+    /// The number of entries that a nonblocking write found the table should grow to hold, but
+    /// left for the next write that may block to grow it for, or 0 if there is no such growth.
+    pending_growth: AtomicUsize,
+
+    /// The fraction of the bins that may be filled before the table is resized, or `None` for
+    /// the default of ¾.
+    load_factor: Option<f32>,
+
+    /// Whether long bins are turned into trees, as set by
+    /// [`Builder::treeification`](crate::Builder::treeification).
+    treeify: bool,
+
+    /// How many times a thread tries a held bin lock before it parks, as set by
+    /// [`HashMap::set_lock_strategy`]. `usize::MAX` means that it never parks.
+    lock_spins: AtomicUsize,
+
+    /// The id that [`HashMap::intern`] assigns to the next key it inserts.
+    next_id: AtomicU32,
+
+    /// The version of the most recent insert or update. Every write stamps the entry it touches
+    /// with the next version.
+    #[cfg(feature = "versioning")]
+    version: AtomicU64,
+
+    /// The number of writes that have been stamped with a version, but may not have been made
+    /// visible yet. See [`PendingWrite`].
+    #[cfg(feature = "versioning")]
+    pending_writes: AtomicUsize,
+
+    /// A version at or below which all writes are known to be visible.
+    #[cfg(feature = "versioning")]
+    settled_version: AtomicU64,
+
+    /// The sequence number of the most recently inserted key.
+    #[cfg(feature = "insertion-order")]
+    seq: AtomicU64,
+
+    /// Counters of the operations performed on the map.
+    #[cfg(feature = "metrics")]
+    metrics: Metrics,
+
+    /// Allocations of removed nodes, which are reused for inserted nodes.
+    ///
+    /// Nodes are only returned to the pool once they are reclaimed, which may happen after the
+    /// map itself has been dropped, so the pool is shared with the deferred reclamation.
+    #[cfg(feature = "node-pool")]
+    node_pool: std::sync::Arc<NodePool<K, V>>,
+
+    /// Called with every entry that is removed from the map once the entry is reclaimed, as set
+    /// by [`HashMap::set_drop_observer`].
+    #[cfg(feature = "drop-observer")]
+    drop_observer: parking_lot::RwLock<Option<DropObserver<K, V>>>,
+
     /// Collector that all `Guard` references used for operations on this map must be tied to. It
     /// is important that they all assocate with the _same_ `Collector`, otherwise you end up with
     /// unsoundness as described in https://github.com/jonhoo/flurry/issues/46. Specifically, a
@@ -148,13 +227,35 @@ pub struct HashMap<K, V, S = crate::DefaultHashBuilder> {
     /// https://github.com/crossbeam-rs/crossbeam/blob/ebecb82c740a1b3d9d10f235387848f7e3fa9c68/crossbeam-skiplist/src/base.rs#L308-L319
     collector: epoch::Collector,
 
+    /// A guard that the map keeps pinned so that the epoch of `collector` cannot advance far
+    /// enough for any garbage to be reclaimed until [`HashMap::collect`] is called.
+    #[cfg(feature = "manual-gc")]
+    held_pin: Mutex<Option<HeldPin>>,
+
     build_hasher: S,
 }
 
+/// A pinned guard that is only ever touched while holding the map's `held_pin` lock.
+#[cfg(feature = "manual-gc")]
+struct HeldPin(#[allow(dead_code)] Guard);
+
+// safety: a `Guard` is not `Send` because pinning and unpinning update its participant's
+// bookkeeping without synchronization. the guard in a `HeldPin` is never used to access the
+// map, and it is only ever created and dropped while holding the lock that protects it, so those
+// updates can never race.
+#[cfg(feature = "manual-gc")]
+unsafe impl Send for HeldPin {}
+
+/// A function that is called with every entry that is removed from a map once it is reclaimed.
+#[cfg(feature = "drop-observer")]
+type DropObserver<K, V> = std::sync::Arc<dyn Fn(&K, &V) + Send + Sync>;
+
 #[derive(Eq, PartialEq, Clone, Debug)]
 enum PutResult<'a, T> {
     Inserted {
         new: &'a T,
+        /// The number of entries in the map right after this one was added.
+        len: usize,
     },
     Replaced {
         old: &'a T,
@@ -178,13 +279,71 @@ impl<'a, T> PutResult<'a, T> {
     #[allow(dead_code)]
     fn after(&self) -> Option<&'a T> {
         match *self {
-            PutResult::Inserted { new } => Some(new),
+            PutResult::Inserted { new, .. } => Some(new),
             PutResult::Replaced { new, .. } => Some(new),
             PutResult::Exists { .. } => None,
         }
     }
 }
 
+/// The conditions under which `put_with_limits` hands an entry back instead of inserting it.
+#[derive(Clone, Copy, Debug)]
+struct PutLimits {
+    /// The most entries a linear bin may hold after the insert.
+    max_chain: usize,
+    /// Whether the insert may wait for the lock of its bin. If not, the entry is handed back
+    /// whenever that lock is held by another thread.
+    blocking: bool,
+}
+
+impl PutLimits {
+    const NONE: Self = PutLimits {
+        max_chain: usize::MAX,
+        blocking: true,
+    };
+}
+
+/// A write that has been stamped with a version, but may not have been made visible yet.
+///
+/// The version of the map that [`DeltaReader`](crate::DeltaReader) moves up to cannot pass the
+/// version of such a write until this is dropped, so it has to be held until the written entry
+/// or value has been stored where readers can see it.
+#[must_use = "the write counts as visible as soon as this is dropped"]
+struct PendingWrite<'m, K, V, S> {
+    map: &'m HashMap<K, V, S>,
+}
+
+impl<K, V, S> Drop for PendingWrite<'_, K, V, S> {
+    fn drop(&mut self) {
+        self.map.finish_write();
+    }
+}
+
+/// Hints to the CPU that the memory of `data` will soon be read.
+///
+/// On x86_64, this issues a prefetch instruction, which never blocks. Elsewhere, it does nothing.
+#[inline(always)]
+fn prefetch<T>(data: &T) {
+    #[cfg(target_arch = "x86_64")]
+    // safety: prefetching only hints at the cache, and never faults, whatever the address. all
+    // x86_64 CPUs support SSE, which the instruction belongs to.
+    unsafe {
+        use core::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+        _mm_prefetch::<_MM_HINT_T0>((data as *const T).cast::<i8>());
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    let _ = data;
+}
+
+/// A bin that [`HashMap::compute_many`] holds the lock of.
+pub(crate) struct LockedBin<'g, K, V> {
+    pub(crate) bini: usize,
+    /// The entry the bin held when it was locked. This is either its head, or the reservation
+    /// that was placed in the bin because it was empty.
+    bin: Shared<'g, BinEntry<K, V>>,
+    _lock: parking_lot::MutexGuard<'g, ()>,
+}
+
 /// The error type for the [`HashMap::try_insert`] method.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct TryInsertError<'a, V> {
@@ -217,6 +376,217 @@ where
     }
 }
 
+/// A bin of a [`HashMap`] that failed a structural sanity check, as reported by
+/// [`HashMap::iter_checked`] and [`HashMap::compact`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BinError {
+    /// The index of the bin in the map's table.
+    pub bin: usize,
+    /// What is wrong with the bin.
+    pub reason: &'static str,
+}
+
+impl Display for BinError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "bin {} is malformed: {}", self.bin, self.reason)
+    }
+}
+
+impl Error for BinError {}
+
+/// The error type for the nonblocking operations of [`HashMap`], such as
+/// [`HashMap::try_insert_nonblocking`], when the lock of the bin they need is held by another
+/// thread.
+///
+/// Holds whatever the operation would have moved into the map, so that the caller can retry it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct WouldBlock<T = ()>(pub T);
+
+impl<T> Display for WouldBlock<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "operation would block on a bin lock held by another thread"
+        )
+    }
+}
+
+impl<T> Error for WouldBlock<T>
+where
+    T: Debug,
+{
+    #[inline]
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
+
+/// How a thread waits for the lock of a bin that another thread holds.
+///
+/// See [`HashMap::set_lock_strategy`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LockStrategy {
+    /// Keep trying the lock until it is released.
+    ///
+    /// This gets the lock soonest once it is released, which helps throughput when bins are only
+    /// held briefly, but burns CPU for as long as the wait lasts.
+    Spin,
+    /// Put the thread to sleep until the lock is released.
+    ///
+    /// This is what a map does unless told otherwise.
+    Park,
+    /// Try the lock `spins` more times, and then put the thread to sleep until it is released.
+    SpinThenPark {
+        /// The number of times to try the lock before parking.
+        spins: usize,
+    },
+}
+
+/// The outcome of a [`HashMap::compute_full`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Compute<'a, V> {
+    /// The key was not present, and an entry with the new value was inserted.
+    Inserted(&'a V),
+    /// The key was present, and its value was replaced.
+    Updated {
+        /// The value the key was mapped to before.
+        old: &'a V,
+        /// The value the key is mapped to now.
+        new: &'a V,
+    },
+    /// The key was present, and its entry was removed. Holds the removed value.
+    Removed(&'a V),
+    /// The key was not present, and no entry was inserted.
+    NoOp,
+}
+
+/// The outcome of a [`HashMap::replace_if_with`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ReplaceIf<'a, V> {
+    /// The predicate held for the key's value, which was replaced. Holds the old value.
+    Replaced(&'a V),
+    /// The predicate did not hold for the key's value, which was left in place. Holds that value.
+    Rejected(&'a V),
+    /// The key was not present.
+    Absent,
+}
+
+/// A single write operation, as applied by [`HashMap::apply_batch`].
+pub enum MapOp<K, V> {
+    /// Maps the key to the value, like [`HashMap::insert`].
+    Insert(K, V),
+    /// Removes the key, like [`HashMap::remove`].
+    Remove(K),
+    /// Computes a new value for the key from its current value, like
+    /// [`HashMap::compute_if_present`]. The function is not called if the key is absent, and the
+    /// entry is removed if it returns `None`.
+    #[allow(clippy::type_complexity)]
+    Update(K, Box<dyn FnOnce(&K, &V) -> Option<V>>),
+}
+
+impl<K, V> Debug for MapOp<K, V>
+where
+    K: Debug,
+    V: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            MapOp::Insert(key, value) => f.debug_tuple("Insert").field(key).field(value).finish(),
+            MapOp::Remove(key) => f.debug_tuple("Remove").field(key).finish(),
+            MapOp::Update(key, _) => f.debug_tuple("Update").field(key).finish(),
+        }
+    }
+}
+
+/// A reference to a value in a [`HashMap`] that carries its own epoch guard.
+///
+/// As long as the `ValueRef` lives, the current thread stays pinned, so the value it refers to
+/// cannot be reclaimed, even if it is concurrently removed from the map. Keep in mind that this
+/// also prevents the collection of any other garbage generated by the map.
+///
+/// Returned by [`HashMap::get_pinned`].
+pub struct ValueRef<'map, K, V> {
+    guard: Guard,
+    value: *const V,
+    _map: PhantomData<&'map (K, V)>,
+}
+
+impl<K, V> ValueRef<'_, K, V> {
+    /// Returns the guard that keeps the referenced value alive.
+    pub fn guard(&self) -> &Guard {
+        &self.guard
+    }
+}
+
+impl<K, V> Deref for ValueRef<'_, K, V> {
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        // safety: the value was read while holding `self.guard`, which has been pinned ever
+        // since. the value will thus not be dropped until `self` (and its guard) is dropped. the
+        // map itself cannot be dropped either, since we borrow it for 'map.
+        unsafe { &*self.value }
+    }
+}
+
+impl<K, V> Debug for ValueRef<'_, K, V>
+where
+    V: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ValueRef").field(&**self).finish()
+    }
+}
+
+/// A snapshot of how the entries of a [`HashMap`] are distributed over its bins.
+///
+/// Returned by [`HashMap::bin_stats`]. As with all operations on a concurrent map, the snapshot
+/// may not reflect concurrent modifications.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BinStats {
+    /// The number of entries in each bin of the map's table, indexed by bin.
+    pub bin_lengths: Vec<usize>,
+    /// The number of bins whose entries are stored in a tree rather than in a linked list.
+    pub tree_bins: usize,
+}
+
+impl BinStats {
+    /// Returns the number of bins in the map's table.
+    pub fn bins(&self) -> usize {
+        self.bin_lengths.len()
+    }
+
+    /// Returns the number of bins that hold at least one entry.
+    pub fn occupied(&self) -> usize {
+        self.bin_lengths.iter().filter(|&&len| len != 0).count()
+    }
+
+    /// Returns the number of entries in the fullest bin.
+    pub fn longest(&self) -> usize {
+        self.bin_lengths.iter().copied().max().unwrap_or(0)
+    }
+
+    /// Returns a histogram of bin lengths, where element `i` is the number of bins that hold
+    /// exactly `i` entries.
+    pub fn histogram(&self) -> Vec<usize> {
+        let mut histogram = vec![0; self.longest() + 1];
+        for &len in &self.bin_lengths {
+            histogram[len] += 1;
+        }
+        histogram
+    }
+}
+
+/// How a lookup through [`HashMap::get_traced`] found its way to the key's bin.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GetTrace {
+    /// The number of bins the lookup looked at, one in each table it visited.
+    pub bins_traversed: usize,
+    /// Whether the key's bin had been moved to the next table of an ongoing resize, so that the
+    /// lookup had to continue there.
+    pub followed_forward: bool,
+}
+
 // ===
 // the following methods only see Ks and Vs if there have been inserts.
 // modifications to the map are all guarded by thread-safety bounds (Send + Sync + 'static).
@@ -263,6 +633,53 @@ impl<K, V> HashMap<K, V, crate::DefaultHashBuilder> {
     pub fn with_capacity(capacity: usize) -> Self {
         Self::with_capacity_and_hasher(capacity, crate::DefaultHashBuilder::default())
     }
+
+    /// Returns a [`Builder`] that configures a new `HashMap`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map: HashMap<&str, i32> = HashMap::builder().capacity(10).load_factor(0.5).build();
+    /// assert_eq!(map.pin().capacity(), 32);
+    /// ```
+    pub fn builder() -> Builder<K, V> {
+        Builder::new()
+    }
+}
+
+impl<K, V> HashMap<K, V, crate::DefaultHashBuilder>
+where
+    K: 'static + Sync + Send + Clone + Hash + Ord,
+    V: 'static + Sync + Send + Clone,
+{
+    /// Creates a map that maps each of `keys` to the value at the same position in `values`.
+    ///
+    /// This is convenient when keys and values arrive as separate columns. The map is sized to
+    /// hold all the entries up front. If a key appears more than once, the map holds the value
+    /// of its last occurrence.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `keys` and `values` have different lengths.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::from_slices(&[1, 2, 3], &["a", "b", "c"]);
+    /// assert_eq!(map.pin().get(&2), Some(&"b"));
+    /// ```
+    pub fn from_slices(keys: &[K], values: &[V]) -> Self {
+        assert_eq!(
+            keys.len(),
+            values.len(),
+            "from_slices was given a different number of keys and values"
+        );
+        keys.iter().cloned().zip(values.iter().cloned()).collect()
+    }
 }
 
 impl<K, V, S> Default for HashMap<K, V, S>
@@ -299,8 +716,29 @@ impl<K, V, S> HashMap<K, V, S> {
             transfer_index: AtomicIsize::new(0),
             count: AtomicIsize::new(0),
             size_ctl: AtomicIsize::new(0),
+            pending_growth: AtomicUsize::new(0),
+            load_factor: None,
+            treeify: true,
+            lock_spins: AtomicUsize::new(0),
+            next_id: AtomicU32::new(0),
+            #[cfg(feature = "versioning")]
+            version: AtomicU64::new(0),
+            #[cfg(feature = "versioning")]
+            pending_writes: AtomicUsize::new(0),
+            #[cfg(feature = "versioning")]
+            settled_version: AtomicU64::new(0),
+            #[cfg(feature = "insertion-order")]
+            seq: AtomicU64::new(0),
+            #[cfg(feature = "metrics")]
+            metrics: Metrics::default(),
+            #[cfg(feature = "node-pool")]
+            node_pool: std::sync::Arc::new(NodePool::new()),
+            #[cfg(feature = "drop-observer")]
+            drop_observer: parking_lot::RwLock::new(None),
             build_hasher: hash_builder,
             collector: epoch::default_collector().clone(),
+            #[cfg(feature = "manual-gc")]
+            held_pin: Mutex::new(Some(HeldPin(epoch::default_collector().register().pin()))),
         }
     }
 
@@ -361,6 +799,25 @@ impl<K, V, S> HashMap<K, V, S> {
     }
     */
 
+    /// Associates a custom [`epoch::Collector`] with this map.
+    ///
+    /// This is the crate-internal counterpart to the `with_collector` above, for maps that are
+    /// meant to be accessed with the guards of another map, such as the primary map of a
+    /// [`LayeredMap`](crate::LayeredMap).
+    pub(crate) fn with_collector(mut self, collector: epoch::Collector) -> Self {
+        #[cfg(feature = "manual-gc")]
+        {
+            *self.held_pin.get_mut() = Some(HeldPin(collector.register().pin()));
+        }
+        self.collector = collector;
+        self
+    }
+
+    /// Returns the [`epoch::Collector`] associated with this map.
+    pub(crate) fn collector(&self) -> &epoch::Collector {
+        &self.collector
+    }
+
     /// Pin a `Guard` for use with this map.
     ///
     /// Keep in mind that for as long as you hold onto this `Guard`, you are preventing the
@@ -369,1238 +826,5969 @@ impl<K, V, S> HashMap<K, V, S> {
         self.collector.register().pin()
     }
 
-    #[inline]
-    fn check_guard(&self, guard: &Guard) {
-        // guard.collector() may be `None` if it is unprotected
-        if let Some(c) = guard.collector() {
-            assert_eq!(c, &self.collector);
-        }
+    /// Sets how threads wait for the lock of a bin that another thread holds.
+    ///
+    /// Writers lock the bin of the key they write to, so threads that write to keys in the same
+    /// bin at the same time wait for each other. [`LockStrategy::Spin`] gets the lock soonest
+    /// once it is released, which favors throughput when bins are only held briefly.
+    /// [`LockStrategy::Park`], the default, frees up the CPU while waiting, which favors
+    /// efficiency when bins are held for long, for example by slow closures passed to
+    /// [`compute_if_present`](HashMap::compute_if_present). [`LockStrategy::SpinThenPark`] does
+    /// a bit of both.
+    ///
+    /// The strategy only affects how long a write takes to get hold of its bin, and never what it
+    /// does once it has. It takes effect for waits that start after the call.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::{HashMap, LockStrategy};
+    ///
+    /// let map = HashMap::new();
+    /// map.set_lock_strategy(LockStrategy::SpinThenPark { spins: 100 });
+    /// map.pin().insert(1, "a");
+    /// ```
+    pub fn set_lock_strategy(&self, strategy: LockStrategy) {
+        let spins = match strategy {
+            LockStrategy::Spin => usize::MAX,
+            LockStrategy::Park => 0,
+            LockStrategy::SpinThenPark { spins } => spins.min(usize::MAX - 1),
+        };
+        self.lock_spins.store(spins, Ordering::Relaxed);
     }
 
-    /// Returns the number of entries in the map.
+    /// Reclaims the memory of the entries and values that have been removed from the map.
+    ///
+    /// With the `manual-gc` feature, the map keeps a guard of its collector pinned at all times,
+    /// which keeps the epoch from advancing far enough for any garbage to be reclaimed, no matter
+    /// how often other guards are pinned or flushed. The removed entries and values of the map,
+    /// and their destructors, are thus only ever run during a call to this method. This is useful
+    /// for tests that need to observe exactly when destructors run.
+    ///
+    /// Keep in mind that the map uses the global collector, so while the guard is pinned, the
+    /// garbage of _everything_ else that uses that collector is held back as well.
+    ///
+    /// This unpins the map's guard, advances the epoch as far as it can, runs the destructors of
+    /// all garbage that no guard can still see, and then pins the guard again. Garbage that a
+    /// guard held elsewhere may still see, or that is still in the local garbage of a thread that
+    /// holds a guard, is left for a later call.
     ///
     /// # Examples
     ///
     /// ```
     /// use flurry::HashMap;
+    /// use std::sync::Arc;
     ///
+    /// let value = Arc::new(0);
     /// let map = HashMap::new();
+    /// map.pin().insert(1, value.clone());
+    /// map.pin().remove(&1);
+    /// assert_eq!(Arc::strong_count(&value), 2);
     ///
-    /// map.pin().insert(1, "a");
-    /// map.pin().insert(2, "b");
-    /// assert!(map.pin().len() == 2);
+    /// map.collect();
+    /// assert_eq!(Arc::strong_count(&value), 1);
     /// ```
-    pub fn len(&self) -> usize {
-        let n = self.count.load(Ordering::Relaxed);
-        if n < 0 {
-            0
-        } else {
-            n as usize
+    #[cfg(feature = "manual-gc")]
+    pub fn collect(&self) {
+        // the global epoch has to advance twice past the epoch that garbage was deferred in before
+        // it can be reclaimed, and every flush only reclaims a handful of bags of garbage.
+        const ROUNDS: usize = 64;
+
+        let mut held_pin = self.held_pin.lock();
+        *held_pin = None;
+        for _ in 0..ROUNDS {
+            self.guard().flush();
         }
+        *held_pin = Some(HeldPin(self.guard()));
     }
 
-    /// Returns `true` if the map is empty. Otherwise returns `false`.
+    /// Makes the map call `observer` with every entry that is removed from it, once the entry is
+    /// actually reclaimed.
+    ///
+    /// A removed entry is not freed right away, but only once no thread can still be reading it,
+    /// that is, once every guard that was pinned when it was removed has been dropped. The
+    /// observer is called at that point, from whichever thread happens to reclaim the entry,
+    /// which helps verify that removed entries are eventually freed, and find guards that are
+    /// held for too long and keep them from being freed. Entries that are still in the map when
+    /// it is dropped, and values that are replaced by newer ones, are not reported. Setting an
+    /// observer replaces the previous one, which is still called for entries that were removed
+    /// before.
+    ///
+    /// This is meant for debugging: while an observer is set, every removal clones its key for
+    /// the observer.
     ///
     /// # Examples
     ///
     /// ```
     /// use flurry::HashMap;
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use std::sync::Arc;
     ///
+    /// let reclaimed = Arc::new(AtomicUsize::new(0));
     /// let map = HashMap::new();
-    /// assert!(map.pin().is_empty());
-    /// map.pin().insert("a", 1);
-    /// assert!(!map.pin().is_empty());
+    /// let counter = reclaimed.clone();
+    /// map.set_drop_observer(move |_: &usize, _: &usize| {
+    ///     counter.fetch_add(1, Ordering::SeqCst);
+    /// });
+    ///
+    /// let guard = map.guard();
+    /// map.insert(1, 1, &guard);
+    /// map.remove(&1, &guard);
+    /// // our guard can still see the entry, so it cannot have been reclaimed yet
+    /// assert_eq!(reclaimed.load(Ordering::SeqCst), 0);
     /// ```
-    pub fn is_empty(&self) -> bool {
-        self.len() == 0
+    #[cfg(feature = "drop-observer")]
+    pub fn set_drop_observer<F>(&self, observer: F)
+    where
+        F: Fn(&K, &V) + Send + Sync + 'static,
+    {
+        *self.drop_observer.write() = Some(std::sync::Arc::new(observer));
     }
 
-    #[cfg(test)]
-    /// Returns the capacity of the map.
-    fn capacity(&self, guard: &Guard) -> usize {
-        self.check_guard(guard);
-        let table = self.table.load(Ordering::Relaxed, &guard);
+    /// Returns the map's current table, which is null if the map has not been inserted into yet.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn current_table<'g>(&self, guard: &'g Guard) -> Shared<'g, Table<K, V>> {
+        self.table.load(Ordering::SeqCst, guard)
+    }
 
-        if table.is_null() {
-            0
-        } else {
-            // Safety: we loaded `table` under the `guard`,
-            // so it must still be valid here
-            unsafe { table.deref() }.len()
+    #[inline]
+    pub(crate) fn check_guard(&self, guard: &Guard) {
+        // guard.collector() may be `None` if it is unprotected
+        if let Some(c) = guard.collector() {
+            assert_eq!(c, &self.collector);
         }
     }
 
-    /// Returns the stamp bits for resizing a table of size n.
-    /// Must be negative when shifted left by `RESIZE_STAMP_SHIFT`.
-    fn resize_stamp(n: usize) -> isize {
-        n.leading_zeros() as isize | (1_isize << (RESIZE_STAMP_BITS - 1))
+    /// Takes the lock that the table initialization takes, after waiting for any initialization
+    /// or resize of the table to finish, and returns the value `size_ctl` held before.
+    ///
+    /// The lock is released by storing a non-negative value into `size_ctl`.
+    fn lock_table(&self) -> isize {
+        loop {
+            let sc = self.size_ctl.load(Ordering::SeqCst);
+            if sc < 0 {
+                // the table is being initialized or resized; wait for that to finish
+                std::thread::yield_now();
+                continue;
+            }
+            if self
+                .size_ctl
+                .compare_exchange(sc, -1, Ordering::SeqCst, Ordering::SeqCst)
+                .is_ok()
+            {
+                return sc;
+            }
+        }
     }
 
-    /// An iterator visiting all key-value pairs in arbitrary order.
-    ///
-    /// The iterator element type is `(&'g K, &'g V)`.
-    pub fn iter<'g>(&'g self, guard: &'g Guard) -> Iter<'g, K, V> {
-        self.check_guard(guard);
-        let table = self.table.load(Ordering::SeqCst, guard);
-        let node_iter = NodeIter::new(table, guard);
-        Iter { node_iter, guard }
+    /// Takes the lock of a bin, waiting for it as set by [`HashMap::set_lock_strategy`] if it is
+    /// held.
+    fn wait_for_bin<'l>(&self, lock: &'l Mutex<()>) -> parking_lot::MutexGuard<'l, ()> {
+        if let Some(held) = lock.try_lock() {
+            return held;
+        }
+        record!(self, lock_waits);
+        self.lock_contended(lock)
     }
 
-    /// An iterator visiting all keys in arbitrary order.
+    /// Takes the lock of a bin that was just found held, spinning and then parking as set by
+    /// [`HashMap::set_lock_strategy`].
+    fn lock_contended<'l>(&self, lock: &'l Mutex<()>) -> parking_lot::MutexGuard<'l, ()> {
+        let spins = self.lock_spins.load(Ordering::Relaxed);
+        let mut tries = 0;
+        loop {
+            if let Some(held) = lock.try_lock() {
+                return held;
+            }
+            if spins != usize::MAX && tries >= spins {
+                return lock.lock();
+            }
+            tries += 1;
+            std::hint::spin_loop();
+        }
+    }
+
+    /// Takes the lock of a bin, or returns `None` if it is held and `blocking` is `false`.
     ///
-    /// The iterator element type is `&'g K`.
-    pub fn keys<'g>(&'g self, guard: &'g Guard) -> Keys<'g, K, V> {
-        self.check_guard(guard);
-        let table = self.table.load(Ordering::SeqCst, guard);
-        let node_iter = NodeIter::new(table, guard);
-        Keys { node_iter }
+    /// Increments `waits` if the lock was held and we waited for it.
+    fn lock_bin<'l>(
+        &self,
+        lock: &'l Mutex<()>,
+        blocking: bool,
+        waits: &mut u32,
+    ) -> Option<parking_lot::MutexGuard<'l, ()>> {
+        if let Some(held) = lock.try_lock() {
+            return Some(held);
+        }
+        if !blocking {
+            return None;
+        }
+        *waits += 1;
+        record!(self, lock_waits);
+        Some(self.lock_contended(lock))
     }
 
-    /// An iterator visiting all values in arbitrary order.
+    /// Stamps `node` with the next version of the map, and, if this is the first time `node` is
+    /// stamped, with the next sequence number.
     ///
-    /// The iterator element type is `&'g V`.
-    pub fn values<'g>(&'g self, guard: &'g Guard) -> Values<'g, K, V> {
-        self.check_guard(guard);
-        let table = self.table.load(Ordering::SeqCst, guard);
-        let node_iter = NodeIter::new(table, guard);
-        Values { node_iter, guard }
+    /// This must be called while `node` is either not yet shared, or its bin lock is held, so
+    /// that the stamps cannot get lost when the node is copied during a resize.
+    ///
+    /// The returned [`PendingWrite`] must be held until the write is visible to readers.
+    #[inline]
+    fn stamp(&self, node: &Node<K, V>) -> PendingWrite<'_, K, V, S> {
+        let pending = self.begin_write();
+        #[cfg(feature = "versioning")]
+        node.version.store(
+            self.version.fetch_add(1, Ordering::SeqCst) + 1,
+            Ordering::SeqCst,
+        );
+        #[cfg(feature = "insertion-order")]
+        if node.seq.load(Ordering::Relaxed) == 0 {
+            node.seq.store(
+                self.seq.fetch_add(1, Ordering::Relaxed) + 1,
+                Ordering::Relaxed,
+            );
+        }
+        #[cfg(not(any(feature = "versioning", feature = "insertion-order")))]
+        let _ = node;
+        pending
     }
 
-    fn init_table<'g>(&'g self, guard: &'g Guard) -> Shared<'g, Table<K, V>> {
-        loop {
-            let table = self.table.load(Ordering::SeqCst, guard);
-            // safety: we loaded the table while epoch was pinned. table won't be deallocated until
-            // next epoch at the earliest.
-            if !table.is_null() && !unsafe { table.deref() }.is_empty() {
-                break table;
-            }
-            // try to allocate the table
-            let mut sc = self.size_ctl.load(Ordering::SeqCst);
-            if sc < 0 {
-                // we lost the initialization race; just spin
-                std::thread::yield_now();
-                continue;
-            }
-
-            if self.size_ctl.compare_and_swap(sc, -1, Ordering::SeqCst) == sc {
-                // we get to do it!
-                let mut table = self.table.load(Ordering::SeqCst, guard);
+    /// Registers a write that is about to stamp one or more nodes, and that is finished once the
+    /// returned [`PendingWrite`] is dropped.
+    ///
+    /// [`stamp`](HashMap::stamp) does this by itself, so this is only needed by writes that
+    /// stamp several nodes before they make any of them visible.
+    #[inline]
+    fn begin_write(&self) -> PendingWrite<'_, K, V, S> {
+        // this must happen before the write takes its version. see settled_version.
+        #[cfg(feature = "versioning")]
+        self.pending_writes.fetch_add(1, Ordering::SeqCst);
+        PendingWrite { map: self }
+    }
 
-                // safety: we loaded the table while epoch was pinned. table won't be deallocated
-                // until next epoch at the earliest.
-                if table.is_null() || unsafe { table.deref() }.is_empty() {
-                    let n = if sc > 0 {
-                        sc as usize
-                    } else {
-                        DEFAULT_CAPACITY
-                    };
-                    let new_table = Owned::new(Table::new(n));
-                    table = new_table.into_shared(guard);
-                    self.table.store(table, Ordering::SeqCst);
-                    sc = load_factor!(n as isize)
-                }
-                self.size_ctl.store(sc, Ordering::SeqCst);
-                break table;
-            }
+    #[inline]
+    fn finish_write(&self) {
+        #[cfg(feature = "versioning")]
+        if self.pending_writes.fetch_sub(1, Ordering::SeqCst) == 1 {
+            // this may have been the last write in progress, so try to move the settled
+            // version up while we know that it is worthwhile.
+            self.settled_version();
         }
     }
 
-    /// Presize the table to accommodate the given number of elements.
-    fn presize(&mut self, size: usize) {
-        // NOTE: this is a stripped-down version of try_presize for use only when we _know_ that
-        // the table is new, and that therefore we won't have to help out with transfers or deal
-        // with contending initializations.
-
-        // safety: we are creating this map, so no other thread can access it,
-        // while we are initializing it.
-        let guard = unsafe { epoch::unprotected() };
-
-        let requested_capacity = if size >= MAXIMUM_CAPACITY / 2 {
-            MAXIMUM_CAPACITY
+    /// Returns a version at or below which every write to the map is visible.
+    ///
+    /// Unlike [`current_version`](HashMap::current_version), no write that is stamped with the
+    /// returned version or a lower one can still be in progress, so a scan that starts after
+    /// this returns sees all of them. The settled version only moves up at times when no write
+    /// is in progress at all, so under a steady stream of writes it may lag behind.
+    #[cfg(feature = "versioning")]
+    pub(crate) fn settled_version(&self) -> u64 {
+        let version = self.version.load(Ordering::SeqCst);
+        // every write registers itself in `pending_writes` before it takes its version. so if
+        // there are no pending writes after we read `version`, every write that took a version
+        // up to `version` has finished, and made its changes visible.
+        if self.pending_writes.load(Ordering::SeqCst) == 0 {
+            std::cmp::max(
+                self.settled_version.fetch_max(version, Ordering::SeqCst),
+                version,
+            )
         } else {
-            // round the requested_capacity to the next power of to from 1.5 * size + 1
-            // TODO: find out if this is neccessary
-            let size = size + (size >> 1) + 1;
-
-            std::cmp::min(MAXIMUM_CAPACITY, size.next_power_of_two())
-        } as usize;
-
-        // sanity check that the map has indeed not been set up already
-        assert_eq!(self.size_ctl.load(Ordering::SeqCst), 0);
-        assert!(self.table.load(Ordering::SeqCst, &guard).is_null());
-
-        // the table has not yet been initialized, so we can just create it
-        // with as many bins as were requested
-
-        // create a table with `new_capacity` empty bins
-        let new_table = Owned::new(Table::new(requested_capacity)).into_shared(guard);
+            self.settled_version.load(Ordering::SeqCst)
+        }
+    }
 
-        // store the new table to `self.table`
-        self.table.store(new_table, Ordering::SeqCst);
+    /// Returns the number of nodes in the list that starts at `node`.
+    fn chain_len<'g>(mut node: &'g Node<K, V>, guard: &'g Guard) -> usize {
+        let mut len = 1;
+        loop {
+            let next = node.next.load(Ordering::SeqCst, guard);
+            // safety: the next pointer of a node is only ever swapped to a node that stays valid
+            // at least until the guard is dropped.
+            node = match unsafe { next.as_ref() } {
+                None => return len,
+                Some(BinEntry::Node(next)) => next,
+                Some(BinEntry::TreeNode(next)) => &next.node,
+                Some(_) => unreachable!("Nodes can only point to Nodes or TreeNodes"),
+            };
+            len += 1;
+        }
+    }
 
-        // resize the table once it is 75% full
-        let new_load_to_resize_at = load_factor!(requested_capacity as isize);
+    /// Moves `entry` onto the heap, to become a new node of the map.
+    ///
+    /// With the `node-pool` feature, this reuses the allocation of a node that was removed
+    /// earlier if there is one.
+    #[inline]
+    fn alloc_node(&self, entry: BinEntry<K, V>) -> Owned<BinEntry<K, V>> {
+        #[cfg(feature = "node-pool")]
+        return self.node_pool.alloc(entry);
+        #[cfg(not(feature = "node-pool"))]
+        Owned::new(entry)
+    }
 
-        // store the next load at which the table should resize to it's size_ctl field
-        // and thus release the initialization "lock"
-        self.size_ctl.store(new_load_to_resize_at, Ordering::SeqCst);
+    /// Marks a node that was removed from the map as garbage.
+    ///
+    /// With the `node-pool` feature, the node's allocation is kept for reuse by
+    /// [`alloc_node`](HashMap::alloc_node) once the node is reclaimed.
+    ///
+    /// # Safety
+    ///
+    /// The same rules as for [`Guard::defer_destroy`] apply.
+    #[inline]
+    unsafe fn retire_node(&self, node: Shared<'_, BinEntry<K, V>>, guard: &Guard) {
+        #[cfg(feature = "node-pool")]
+        self.node_pool.retire(node, guard);
+        #[cfg(not(feature = "node-pool"))]
+        guard.defer_destroy(node);
     }
-}
 
-// ===
-// the following methods require Clone and Ord, since they ultimately call
-// `transfer`, which needs to be able to clone keys and work with tree bins.
-// however, they do _not_ need to require thread-safety bounds (Send + Sync +
-// 'static) since if the bounds do not hold, the map is empty, so no keys or
-// values will be transfered anyway.
-// ===
+    /// Marks the value of an entry that was removed from the map as garbage.
+    ///
+    /// With the `drop-observer` feature, the map's drop observer, if it has one, is called with
+    /// the entry when the value is reclaimed. It is given a clone of `key`, since the node that
+    /// holds the key may be reclaimed first.
+    ///
+    /// # Safety
+    ///
+    /// The same rules as for [`Guard::defer_destroy`] apply to `value`.
+    #[inline]
+    unsafe fn retire_value(&self, key: &K, value: Shared<'_, V>, guard: &Guard)
+    where
+        K: Clone,
+    {
+        #[cfg(feature = "drop-observer")]
+        if let Some(observer) = self.drop_observer.read().clone() {
+            let key = key.clone();
+            guard.defer_unchecked(move || {
+                let value = value.into_owned();
+                observer(&key, &value);
+            });
+            return;
+        }
+        #[cfg(not(feature = "drop-observer"))]
+        let _ = key;
+        guard.defer_destroy(value);
+    }
 
-impl<K, V, S> HashMap<K, V, S>
-where
-    K: Clone + Ord,
-{
-    /// Tries to presize table to accommodate the given number of elements.
-    fn try_presize(&self, size: usize, guard: &Guard) {
-        let requested_capacity = if size >= MAXIMUM_CAPACITY / 2 {
-            MAXIMUM_CAPACITY
+    /// Returns the number of entries in the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    ///
+    /// map.pin().insert(1, "a");
+    /// map.pin().insert(2, "b");
+    /// assert!(map.pin().len() == 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        let n = self.count.load(Ordering::Relaxed);
+        if n < 0 {
+            0
         } else {
-            // round the requested_capacity to the next power of to from 1.5 * size + 1
-            // TODO: find out if this is neccessary
-            let size = size + (size >> 1) + 1;
+            n as usize
+        }
+    }
+
+    /// Returns `true` if the map is empty. Otherwise returns `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// assert!(map.pin().is_empty());
+    /// map.pin().insert("a", 1);
+    /// assert!(!map.pin().is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 
-            std::cmp::min(MAXIMUM_CAPACITY, size.next_power_of_two())
-        } as isize;
+    /// Blocks until the map holds at least `target` entries, or until `timeout` has elapsed.
+    /// Returns `true` if the map reached `target` entries.
+    ///
+    /// This is handy in concurrent tests that must wait for producer threads to fill the map. The
+    /// map does not notify waiters when it changes, so this polls [`len`](HashMap::len), sleeping
+    /// for exponentially longer in between, up to a millisecond. The target may thus be reached
+    /// up to a millisecond before this returns, and the map may have shrunk again by then. A
+    /// timeout so long that its deadline cannot be represented, such as [`Duration::MAX`], waits
+    /// for as long as it takes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    ///
+    /// let map = Arc::new(HashMap::new());
+    /// let producer = {
+    ///     let map = map.clone();
+    ///     std::thread::spawn(move || {
+    ///         for i in 0..100 {
+    ///             map.pin().insert(i, i);
+    ///         }
+    ///     })
+    /// };
+    /// assert!(map.wait_until_len(100, Duration::from_secs(10)));
+    /// producer.join().unwrap();
+    /// ```
+    pub fn wait_until_len(&self, target: usize, timeout: Duration) -> bool {
+        const MAX_BACKOFF: Duration = Duration::from_millis(1);
 
+        if self.len() >= target {
+            return true;
+        }
+        // a timeout too long to be represented as a deadline is as good as no timeout at all.
+        let deadline = Instant::now().checked_add(timeout);
+        let mut backoff = Duration::from_micros(1);
         loop {
-            let size_ctl = self.size_ctl.load(Ordering::SeqCst);
-            if size_ctl < 0 {
-                break;
+            let now = Instant::now();
+            let nap = match deadline {
+                Some(deadline) if now >= deadline => return false,
+                Some(deadline) => std::cmp::min(backoff, deadline - now),
+                None => backoff,
+            };
+            std::thread::sleep(nap);
+            backoff = std::cmp::min(backoff * 2, MAX_BACKOFF);
+            if self.len() >= target {
+                return true;
             }
+        }
+    }
 
-            let table = self.table.load(Ordering::SeqCst, &guard);
-
-            // The current capacity == the number of bins in the current table
-            let current_capactity = if table.is_null() {
-                0
-            } else {
-                unsafe { table.deref() }.len()
-            };
+    /// Returns the capacity of the map, that is, the number of bins in its table.
+    ///
+    /// The map resizes once it holds three quarters as many entries as it has bins, or as set by
+    /// [`Builder::load_factor`](crate::Builder::load_factor). A map that has not yet been
+    /// inserted into has a capacity of 0.
+    ///
+    /// The capacity is always a power of two, and every resize doubles it: a resize moves the
+    /// entries of each bin into exactly two bins of the new table, which is what lets several
+    /// threads move bins concurrently, and readers find entries in either table meanwhile. There
+    /// is thus no way to grow the map more gently. To make it use less memory for the same number
+    /// of entries, give it a higher load factor instead, which trades memory for longer bins.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::<usize, usize>::new();
+    /// assert_eq!(map.pin().capacity(), 0);
+    /// map.pin().insert(1, 1);
+    /// assert!(map.pin().capacity() >= 1);
+    /// ```
+    pub fn capacity(&self, guard: &Guard) -> usize {
+        self.check_guard(guard);
+        let table = self.table.load(Ordering::Relaxed, &guard);
 
-            if current_capactity == 0 {
-                // the table has not yet been initialized, so we can just create it
-                // with as many bins as were requested
+        if table.is_null() {
+            0
+        } else {
+            // Safety: we loaded `table` under the `guard`,
+            // so it must still be valid here
+            unsafe { table.deref() }.len()
+        }
+    }
 
-                // since the map is uninitialized, size_ctl describes the initial capacity
-                let initial_capacity = size_ctl;
+    /// Returns the size in bytes of the map's array of bins.
+    ///
+    /// This is just the [`capacity`](HashMap::capacity) times the size of a pointer to a bin, and
+    /// leaves out the memory of the entries themselves, and of whatever their keys and values
+    /// point to. It is a cheap way to track how the structure of the map grows as it is resized.
+    /// While a resize is in progress, the new array is not included.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::<usize, usize>::new();
+    /// assert_eq!(map.pin().table_bytes(), 0);
+    /// map.pin().insert(1, 1);
+    /// assert_eq!(
+    ///     map.pin().table_bytes(),
+    ///     map.pin().capacity() * std::mem::size_of::<usize>()
+    /// );
+    /// ```
+    pub fn table_bytes(&self, guard: &Guard) -> usize {
+        self.capacity(guard) * std::mem::size_of::<Atomic<BinEntry<K, V>>>()
+    }
 
-                // the new capacity is either the requested capacity or the initial capacity (size_ctl)
-                let new_capacity = requested_capacity.max(initial_capacity) as usize;
+    /// Returns the fraction of the map's capacity that is in use, that is, [`len`](HashMap::len)
+    /// divided by [`capacity`](HashMap::capacity).
+    ///
+    /// The map resizes once its load reaches its load factor, which is 0.75 unless it was
+    /// configured through [`Builder::load_factor`](crate::Builder::load_factor). A map that has
+    /// not yet been inserted into has a load of 0.
+    ///
+    /// Since the length and the capacity are read separately, the load may be off while other
+    /// threads are modifying or resizing the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::<usize, usize>::new();
+    /// assert_eq!(map.pin().load(), 0.0);
+    /// map.pin().insert(1, 1);
+    /// assert_eq!(map.pin().load(), 1.0 / map.pin().capacity() as f64);
+    /// ```
+    pub fn load(&self, guard: &Guard) -> f64 {
+        let capacity = self.capacity(guard);
+        if capacity == 0 {
+            0.0
+        } else {
+            self.len() as f64 / capacity as f64
+        }
+    }
 
-                // try to aquire the initialization "lock" to indicate that we are initializing the table.
-                if self
-                    .size_ctl
-                    .compare_and_swap(size_ctl, -1, Ordering::SeqCst)
-                    != size_ctl
-                {
-                    // somebody else is already initializing the table (or has already finished).
-                    continue;
-                }
+    /// Returns a reference to the map's [`BuildHasher`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::{DefaultHashBuilder, HashMap};
+    ///
+    /// let map: HashMap<usize, usize> = HashMap::default();
+    /// let hasher: &DefaultHashBuilder = map.hasher();
+    /// ```
+    pub fn hasher(&self) -> &S {
+        &self.build_hasher
+    }
 
-                // we got the initialization `lock`; Make sure the table is still unitialized
-                // (or is the same table with 0 bins we read earlier, althought that should not be the case)
-                if self.table.load(Ordering::SeqCst, guard) != table {
-                    // NOTE: this could probably be `!self.table.load(...).is_null()`
-                    // if we decide that tables can never have 0 bins.
+    /// Returns the untyped parts that make up the map's current table, so that foreign code can
+    /// look up entries without going through Rust generics.
+    ///
+    /// See [`RawTableParts`](crate::ffi::RawTableParts) for how to perform a lookup with the
+    /// returned parts. The hash of a key is computed by feeding the key to a hasher built with
+    /// the map's [`hasher`](HashMap::hasher).
+    ///
+    /// # Safety
+    ///
+    /// The returned parts contain raw pointers into the map that are not tracked by the borrow
+    /// checker. The caller must uphold all of the following for as long as it uses them:
+    ///
+    ///  - `guard` must stay pinned, and the map must not be dropped. Once `guard` is dropped, the
+    ///    table, and any entry or value read through the parts, may be freed at any time.
+    ///  - The parts only ever describe the table that was current when this method was called.
+    ///    A concurrent resize may move entries out of the table at any time, and bins that have
+    ///    been moved no longer give access to their entries. An entry that is not found may thus
+    ///    still be in the map.
+    ///  - Entries, keys, and values must only be read, never written, and values must be read
+    ///    as the `V` of this map, and keys as its `K`.
+    ///  - The function pointers in the parts must only be called with pointers that were obtained
+    ///    from these same parts.
+    ///
+    /// This is only available with the `ffi` feature.
+    #[cfg(feature = "ffi")]
+    pub unsafe fn as_raw_parts(&self, guard: &Guard) -> RawTableParts {
+        self.check_guard(guard);
+        let table = self.table.load(Ordering::SeqCst, guard);
+        // safety: we loaded the table while epoch was pinned. table won't be deallocated until
+        // next epoch at the earliest.
+        match table.as_ref() {
+            Some(t) => RawTableParts::new(t.bins_ptr(), t.len()),
+            None => RawTableParts::new::<K, V>(std::ptr::null(), 0),
+        }
+    }
 
-                    // the table is already initialized; Write the `size_ctl` value it had back to it's
-                    // `size_ctl` field to release the initialization "lock"
-                    self.size_ctl.store(size_ctl, Ordering::SeqCst);
-                    continue;
-                }
-
-                // create a table with `new_capacity` empty bins
-                let new_table = Owned::new(Table::new(new_capacity)).into_shared(guard);
-
-                // store the new table to `self.table`
-                let old_table = self.table.swap(new_table, Ordering::SeqCst, &guard);
+    /// Returns the stamp bits for resizing a table of size n.
+    /// Must be negative when shifted left by `RESIZE_STAMP_SHIFT`.
+    fn resize_stamp(n: usize) -> isize {
+        n.leading_zeros() as isize | (1_isize << (RESIZE_STAMP_BITS - 1))
+    }
 
-                // old_table should be `null`, since we don't ever initialize a table with 0 bins
-                // and this branch only happens if table has not yet been initialized or it's length is 0.
-                assert!(old_table.is_null());
+    /// An iterator visiting all key-value pairs in arbitrary order.
+    ///
+    /// The iterator element type is `(&'g K, &'g V)`.
+    ///
+    /// The iterator is weakly consistent, and never fails because of concurrent modifications,
+    /// including a concurrent [`clear`](HashMap::clear) or resize. It yields each key at most
+    /// once, and only entries that were in the map at some point while it was iterating. Entries
+    /// inserted or removed after the iterator was created may or may not be yielded.
+    ///
+    /// Every entry and value that the iterator yields is protected by `guard`, so they remain
+    /// valid even if another thread removes them from the map, until the guard is dropped.
+    pub fn iter<'g>(&'g self, guard: &'g Guard) -> Iter<'g, K, V> {
+        self.check_guard(guard);
+        let table = self.table.load(Ordering::SeqCst, guard);
+        let node_iter = NodeIter::new(table, guard);
+        Iter { node_iter, guard }
+    }
 
-                // TODO: if we allow tables with 0 bins. `defer_destroy` `old_table` if it's not `null`:
-                // if !old_table.is_null() {
-                //     // TODO: safety argument, for why this is okay
-                //     unsafe { guard.defer_destroy(old_table) }
-                // }
+    /// An iterator visiting clones of all key-value pairs in arbitrary order.
+    ///
+    /// Unlike [`iter`](HashMap::iter), which yields references that are only valid for as long
+    /// as `guard` is held, this clones each entry as it is yielded, so the items can be kept
+    /// around after the guard is dropped. The guard must still outlive the iterator itself.
+    ///
+    /// The iterator element type is `(K, V)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map: HashMap<_, _> = (0..3).map(|i| (i, i * 2)).collect();
+    /// let mut entries: Vec<(i32, i32)> = map.iter_owned(&map.guard()).collect();
+    /// entries.sort_unstable();
+    /// assert_eq!(entries, vec![(0, 0), (1, 2), (2, 4)]);
+    /// ```
+    pub fn iter_owned<'g>(&'g self, guard: &'g Guard) -> IterOwned<'g, K, V>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        IterOwned {
+            iter: self.iter(guard),
+        }
+    }
 
-                // resize the table once it is 75% full
-                let new_load_to_resize_at = load_factor!(new_capacity as isize);
+    /// An iterator visiting all key-value pairs in arbitrary order, yielding clones of the keys
+    /// alongside references to the values.
+    ///
+    /// This sits between [`iter`](HashMap::iter) and [`iter_owned`](HashMap::iter_owned): the
+    /// keys can be kept around after the guard is dropped, while the values, which are only
+    /// valid for as long as `guard` is held, need not be cloned.
+    ///
+    /// The iterator element type is `(K, &'g V)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map: HashMap<_, _> = (0..3).map(|i| (i.to_string(), i)).collect();
+    /// let guard = map.guard();
+    /// let (mut keys, values): (Vec<String>, Vec<&i32>) = map.iter_owned_keys(&guard).unzip();
+    /// keys.sort_unstable();
+    /// assert_eq!(keys, vec!["0", "1", "2"]);
+    /// assert_eq!(values.into_iter().sum::<i32>(), 3);
+    /// ```
+    pub fn iter_owned_keys<'g>(&'g self, guard: &'g Guard) -> IterOwnedKeys<'g, K, V>
+    where
+        K: Clone,
+    {
+        IterOwnedKeys {
+            iter: self.iter(guard),
+        }
+    }
 
-                // store the next load at which the table should resize to it's size_ctl field
-                // and thus release the initialization "lock"
-                self.size_ctl.store(new_load_to_resize_at, Ordering::SeqCst);
-            } else if requested_capacity <= size_ctl || current_capactity >= MAXIMUM_CAPACITY {
-                // Either the `requested_capacity` was smaller than or equal to the load we would resize at (size_ctl)
-                // and we don't need to resize, since our load factor will still be acceptable if we don't
+    /// An iterator visiting all key-value pairs in arbitrary order, along with whether the lock of
+    /// the bin each entry is in is currently held by some thread.
+    ///
+    /// The iterator element type is `(&'g K, &'g V, bool)`.
+    ///
+    /// This is a diagnostic tool for finding out which entries are involved in lock contention.
+    /// It never waits for a lock: the lock of each bin is probed with a `try_lock` when the
+    /// iterator reaches the bin, and released again right away if it was free. The probe does
+    /// take the lock for that instant, though: a writer that gets to the bin at the same time
+    /// briefly waits for it, and a nonblocking write such as
+    /// [`try_insert_nonblocking`](HashMap::try_insert_nonblocking) fails with
+    /// [`WouldBlock`](crate::WouldBlock). All entries of a bin report the result of that one
+    /// probe. Since other threads take and release locks all the time, the result is only a
+    /// snapshot, and may be stale by the time it is looked at. Otherwise, this behaves like
+    /// [`iter`](HashMap::iter).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// let guard = map.guard();
+    /// map.insert(1, "a", &guard);
+    ///
+    /// let entries: Vec<_> = map.iter_with_lock_state(&guard).collect();
+    /// assert_eq!(entries, [(&1, &"a", false)]);
+    /// ```
+    pub fn iter_with_lock_state<'g>(&'g self, guard: &'g Guard) -> IterWithLockState<'g, K, V> {
+        self.check_guard(guard);
+        let table = self.table.load(Ordering::SeqCst, guard);
+        IterWithLockState {
+            node_iter: NodeIter::new(table, guard),
+            bin: None,
+            guard,
+        }
+    }
 
-                // Or it was larger than the `MAXIMUM_CAPACITY` of the map and we refuse
-                // to resize to an invalid capacity
-                break;
-            } else if table == self.table.load(Ordering::SeqCst, &guard) {
-                // The table is initialized, try to resize it to the requested capacity
+    /// An iterator visiting all key-value pairs, walking the map's bins from the last one to the
+    /// first one.
+    ///
+    /// The entries within each bin are still visited in the same order as by
+    /// [`iter`](HashMap::iter). As long as the map is not modified, this yields the same entries
+    /// as `iter`, in a different but deterministic order, which can help with reproducing bugs
+    /// that depend on the order in which entries are visited.
+    ///
+    /// The iterator element type is `(&'g K, &'g V)`.
+    pub fn iter_rev<'g>(&'g self, guard: &'g Guard) -> Iter<'g, K, V> {
+        self.check_guard(guard);
+        let table = self.table.load(Ordering::SeqCst, guard);
+        let node_iter = NodeIter::new_rev(table, guard);
+        Iter { node_iter, guard }
+    }
 
-                let rs: isize = Self::resize_stamp(current_capactity) << RESIZE_STAMP_SHIFT;
-                // TODO: see #29: `rs` is postive even though `resize_stamp` says:
-                // "Must be negative when shifted left by RESIZE_STAMP_SHIFT"
-                // and since our size_control field needs to be negative
-                // to indicate a resize this needs to be addressed
+    /// An iterator visiting all key-value pairs in a pseudo-random order determined by `seed`.
+    ///
+    /// The order of [`iter`](HashMap::iter) follows the bins the keys hash to, which downstream
+    /// processing may not want to correlate with, for example when sampling entries. This instead
+    /// collects references to all entries up front, and then yields them in a random permutation.
+    /// As long as the map is not modified, the same seed always yields the same order, which
+    /// makes the order reproducible in tests.
+    ///
+    /// The iterator element type is `(&'g K, &'g V)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map: HashMap<_, _> = (0..10).map(|i| (i, i)).collect();
+    /// let guard = map.guard();
+    /// let order: Vec<_> = map.iter_shuffled(42, &guard).collect();
+    /// assert_eq!(order.len(), 10);
+    /// assert!(map.iter_shuffled(42, &guard).eq(order));
+    /// ```
+    pub fn iter_shuffled<'g>(&'g self, seed: u64, guard: &'g Guard) -> IterShuffled<'g, K, V> {
+        let mut entries: Vec<_> = self.iter(guard).collect();
+        // a Fisher-Yates shuffle, drawing from a splitmix64 generator.
+        let mut state = seed;
+        for i in (1..entries.len()).rev() {
+            state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^= z >> 31;
+            entries.swap(i, (z % (i as u64 + 1)) as usize);
+        }
+        IterShuffled {
+            entries: entries.into_iter(),
+        }
+    }
 
-                if self
-                    .size_ctl
-                    .compare_and_swap(size_ctl, rs + 2, Ordering::SeqCst)
-                    == size_ctl
-                {
-                    // someone else already started to resize the table
-                    // TODO: can we `self.help_transfer`?
-                    self.transfer(table, Shared::null(), &guard);
-                }
-            }
+    /// An iterator visiting all key-value pairs in arbitrary order, `chunk_size` pairs at a
+    /// time.
+    ///
+    /// Every chunk but the last holds exactly `chunk_size` pairs. All references stay valid for
+    /// as long as `guard` is held, so chunks can be collected and processed in batches.
+    ///
+    /// The iterator element type is `Vec<(&'g K, &'g V)>`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map: HashMap<_, _> = (0..5).map(|i| (i, i)).collect();
+    /// let guard = map.guard();
+    /// let sizes: Vec<_> = map.iter_chunks(2, &guard).map(|chunk| chunk.len()).collect();
+    /// assert_eq!(sizes, vec![2, 2, 1]);
+    /// ```
+    pub fn iter_chunks<'g>(&'g self, chunk_size: usize, guard: &'g Guard) -> Chunks<'g, K, V> {
+        assert!(chunk_size != 0, "chunk size must be non-zero");
+        Chunks {
+            iter: self.iter(guard),
+            chunk_size,
         }
     }
 
-    // NOTE: transfer requires that K and V are Send + Sync if it will actually transfer anything.
-    // If K/V aren't Send + Sync, the map must be empty, and therefore calling tansfer is fine.
-    #[inline(never)]
-    fn transfer<'g>(
+    /// Returns a cursor over all key-value pairs of the map, which hands out up to `per_poll`
+    /// pairs each time the caller asks for more.
+    ///
+    /// Unlike [`iter_chunks`](HashMap::iter_chunks), the cursor is not an [`Iterator`]: the
+    /// consumer calls [`next_batch`](ThrottledIter::next_batch) whenever it is ready for more
+    /// entries, such as from the `poll` method of a stream, and so decides how fast the map is
+    /// walked. Nothing is read from the map in between batches. As with any iterator over the
+    /// map, `guard` stays pinned for as long as the cursor is in use.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `per_poll` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map: HashMap<_, _> = (0..5).map(|i| (i, i)).collect();
+    /// let guard = map.guard();
+    /// let mut export = map.iter_throttled(2, &guard);
+    /// assert_eq!(export.next_batch().len(), 2);
+    /// assert_eq!(export.next_batch().len(), 2);
+    /// assert_eq!(export.next_batch().len(), 1);
+    /// assert!(export.is_done());
+    /// assert!(export.next_batch().is_empty());
+    /// ```
+    pub fn iter_throttled<'g>(
         &'g self,
-        table: Shared<'g, Table<K, V>>,
-        mut next_table: Shared<'g, Table<K, V>>,
+        per_poll: usize,
         guard: &'g Guard,
-    ) {
-        // safety: table was read while `guard` was held. the code that drops table only drops it
-        // after it is no longer reachable, and any outstanding references are no longer active.
-        // this references is still active (marked by the guard), so the target of the references
-        // won't be dropped while the guard remains active.
-        let n = unsafe { table.deref() }.len();
-        let ncpu = num_cpus();
+    ) -> ThrottledIter<'g, K, V> {
+        assert!(per_poll != 0, "batch size must be non-zero");
+        ThrottledIter {
+            iter: self.iter(guard),
+            per_poll,
+            done: false,
+        }
+    }
 
-        let stride = if ncpu > 1 { (n >> 3) / ncpu } else { n };
-        let stride = std::cmp::max(stride as isize, MIN_TRANSFER_STRIDE);
+    /// Calls `f` on every key-value pair of the map, repinning `guard` after every `every` pairs.
+    ///
+    /// Iterating over a large map with [`iter`](HashMap::iter) keeps the guard pinned for the
+    /// whole scan, which keeps all garbage that any thread produces in the meantime from being
+    /// freed. This instead repins the guard (and flushes its garbage) at the first bin boundary
+    /// after every `every` pairs, so memory can be reclaimed while the scan is under way.
+    ///
+    /// Repinning ends the guarantee that anything read from the map so far stays alive, which is
+    /// why this is not an [`Iterator`]: the references handed to `f` only live for that one call,
+    /// and the guard is borrowed mutably for the whole scan, so no reference that was obtained
+    /// through it can be held across a repin. Since the table itself may be freed once the guard
+    /// is repinned, the scan picks up where it left off in whatever table the map holds at that
+    /// point. It does so by visiting the entries in the bit-reversed order of their hashes, which
+    /// is the same regardless of the size of the table, so each entry that is in the map for the
+    /// entire scan is visited exactly once even if the map is resized in between. Like with
+    /// `iter`, entries that are inserted or removed during the scan may or may not be visited.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `every` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map: HashMap<_, _> = (0..1000).map(|i| (i, i)).collect();
+    /// let mut guard = map.guard();
+    /// let mut sum = 0;
+    /// map.for_each_repinning(100, &mut guard, |_, v| sum += v);
+    /// assert_eq!(sum, (0..1000).sum::<i32>());
+    /// ```
+    pub fn for_each_repinning<F>(&self, every: usize, guard: &mut Guard, mut f: F)
+    where
+        F: FnMut(&K, &V),
+    {
+        assert!(every != 0, "repin interval must be non-zero");
+        self.check_guard(guard);
 
-        if next_table.is_null() {
-            // we are initiating a resize
-            let table = Owned::new(Table::new(n << 1));
-            let now_garbage = self.next_table.swap(table, Ordering::SeqCst, guard);
-            assert!(now_garbage.is_null());
-            self.transfer_index.store(n as isize, Ordering::SeqCst);
-            next_table = self.next_table.load(Ordering::Relaxed, guard);
+        // all entries whose bit-reversed hash is below `progress` have been visited. a bin at
+        // position `pos` in bit-reversed order of a table with 2^bits bins holds exactly the
+        // entries whose bit-reversed hash starts with the bits of `pos`.
+        let mut progress = 0u64;
+        let mut since_repin = 0;
+        'tables: loop {
+            let table = self.table.load(Ordering::SeqCst, guard);
+            // safety: we loaded the table while epoch was pinned. table won't be deallocated
+            // until next epoch at the earliest.
+            let t = match unsafe { table.as_ref() } {
+                Some(t) if !t.is_empty() => t,
+                _ => return,
+            };
+            let shift = 64 - t.len().trailing_zeros();
+            let mut pos = progress.checked_shr(shift).unwrap_or(0);
+            loop {
+                let bini = pos.reverse_bits().checked_shr(shift).unwrap_or(0) as usize;
+                since_repin += Self::visit_bin_from(t, bini, progress, &mut f, guard);
+
+                let end = (u128::from(pos) + 1) << shift;
+                if end > u128::from(u64::MAX) {
+                    // that was the last bin
+                    return;
+                }
+                progress = end as u64;
+                pos += 1;
+
+                if since_repin >= every {
+                    since_repin = 0;
+                    guard.repin();
+                    guard.flush();
+                    continue 'tables;
+                }
+            }
         }
+    }
 
-        // safety: same argument as for table above
-        let next_n = unsafe { next_table.deref() }.len();
+    /// Calls `f` on the entries of bin `bini` of `t` whose bit-reversed hash is at least
+    /// `progress`, following the bin into the next table if it has been moved. Returns the
+    /// number of entries visited.
+    fn visit_bin_from<F>(
+        t: &Table<K, V>,
+        bini: usize,
+        progress: u64,
+        f: &mut F,
+        guard: &Guard,
+    ) -> usize
+    where
+        F: FnMut(&K, &V),
+    {
+        let mut visit = |node: &Node<K, V>| {
+            if node.hash.reverse_bits() < progress {
+                return 0;
+            }
+            // safety: flurry does not drop or move until after guard drop
+            let value = unsafe { node.value.load(Ordering::SeqCst, guard).deref() };
+            f(&node.key, value);
+            1
+        };
 
-        let mut advance = true;
-        let mut finishing = false;
-        let mut i = 0;
-        let mut bound = 0;
-        loop {
-            // try to claim a range of bins for us to transfer
-            while advance {
-                i -= 1;
-                if i >= bound || finishing {
-                    advance = false;
-                    break;
+        let mut visited = 0;
+        // safety: the bin was read under our guard, so it has not been dropped.
+        match unsafe { t.bin(bini, guard).as_ref() } {
+            None | Some(BinEntry::Reserved(_)) => {}
+            Some(BinEntry::Moved) => {
+                // the entries of this bin are spread over the bins of the next table whose index
+                // has the same low bits.
+                // safety: same argument as for following Moved in Table::find
+                let next = unsafe { t.next_table(guard).deref() };
+                let mut i = bini;
+                while i < next.len() {
+                    visited += Self::visit_bin_from(next, i, progress, f, guard);
+                    i += t.len();
                 }
-
-                let next_index = self.transfer_index.load(Ordering::SeqCst);
-                if next_index <= 0 {
-                    i = -1;
-                    advance = false;
-                    break;
+            }
+            Some(BinEntry::Node(node)) => {
+                let mut node = node;
+                loop {
+                    visited += visit(node);
+                    // safety: as above.
+                    match unsafe { node.next.load(Ordering::SeqCst, guard).as_ref() } {
+                        Some(next) => {
+                            node = next
+                                .as_node()
+                                .expect("entry following Node should always be a Node")
+                        }
+                        None => break,
+                    }
                 }
-
-                let next_bound = if next_index > stride {
-                    next_index - stride
-                } else {
-                    0
-                };
-                if self
-                    .transfer_index
-                    .compare_and_swap(next_index, next_bound, Ordering::SeqCst)
-                    == next_index
-                {
-                    bound = next_bound;
-                    i = next_index;
-                    advance = false;
-                    break;
+            }
+            Some(BinEntry::Tree(tree_bin)) => {
+                let mut p = tree_bin.first.load(Ordering::SeqCst, guard);
+                while !p.is_null() {
+                    // safety: the TreeBin was read under our guard, at which point the tree
+                    // was valid. Structurally, TreeNodes always point to TreeNodes, so this is
+                    // sound.
+                    let tree_node = unsafe { TreeNode::get_tree_node(p) };
+                    visited += visit(&tree_node.node);
+                    p = tree_node.node.next.load(Ordering::SeqCst, guard);
                 }
             }
+            Some(BinEntry::TreeNode(_)) => unreachable!(
+                "The head of a bin cannot be a TreeNode directly without BinEntry::Tree"
+            ),
+        }
+        visited
+    }
 
-            if i < 0 || i as usize >= n || i as usize + n >= next_n {
-                // the resize has finished
+    /// An iterator visiting all keys in arbitrary order.
+    ///
+    /// The iterator element type is `&'g K`.
+    pub fn keys<'g>(&'g self, guard: &'g Guard) -> Keys<'g, K, V> {
+        self.check_guard(guard);
+        let table = self.table.load(Ordering::SeqCst, guard);
+        let node_iter = NodeIter::new(table, guard);
+        Keys { node_iter }
+    }
 
-                if finishing {
-                    // this branch is only taken for one thread partaking in the resize!
-                    self.next_table.store(Shared::null(), Ordering::SeqCst);
-                    let now_garbage = self.table.swap(next_table, Ordering::SeqCst, guard);
-                    // safety: need to guarantee that now_garbage is no longer reachable. more
-                    // specifically, no thread that executes _after_ this line can ever get a
-                    // reference to now_garbage.
-                    //
-                    // first, we need to argue that there is no _other_ way to get to now_garbage.
-                    //
-                    //  - it is _not_ accessible through self.table any more
-                    //  - it is _not_ accessible through self.next_table any more
-                    //  - what about forwarding nodes (BinEntry::Moved)?
-                    //    the only BinEntry::Moved that point to now_garbage, are the ones in
-                    //    _previous_ tables. to get to those previous tables, one must ultimately
-                    //    have arrived through self.table (because that's where all operations
-                    //    start their search). since self.table has now changed, only "old" threads
-                    //    can still be accessing them. no new thread can get to past tables, and
-                    //    therefore they also cannot get to ::Moved that point to now_garbage, so
-                    //    we're fine.
-                    //
-                    // this means that no _future_ thread (i.e., in a later epoch where the value
-                    // may be freed) can get a reference to now_garbage.
-                    //
-                    // next, let's talk about threads with _existing_ references to now_garbage.
-                    // such a thread must have gotten that reference before the call to swap.
-                    // because of this, that thread must be pinned to an epoch <= the epoch of our
-                    // guard (since our guard is pinning the epoch). since the garbage is placed in
-                    // our epoch, it won't be freed until the _next_ epoch, at which point, that
-                    // thread must have dropped its guard, and with it, any reference to the value.
-                    unsafe { guard.defer_destroy(now_garbage) };
-                    self.size_ctl
-                        .store(((n as isize) << 1) - ((n as isize) >> 1), Ordering::SeqCst);
-                    return;
-                }
+    /// An iterator visiting all values in arbitrary order.
+    ///
+    /// The iterator element type is `&'g V`.
+    pub fn values<'g>(&'g self, guard: &'g Guard) -> Values<'g, K, V> {
+        self.check_guard(guard);
+        let table = self.table.load(Ordering::SeqCst, guard);
+        let node_iter = NodeIter::new(table, guard);
+        Values { node_iter, guard }
+    }
 
-                let sc = self.size_ctl.load(Ordering::SeqCst);
-                if self.size_ctl.compare_and_swap(sc, sc - 1, Ordering::SeqCst) == sc {
-                    if (sc - 2) != Self::resize_stamp(n) << RESIZE_STAMP_SHIFT {
-                        return;
-                    }
+    /// An iterator visiting every distinct value of the map once, in arbitrary order.
+    ///
+    /// Values that are stored under several keys are only yielded the first time they are seen.
+    /// To that end, the iterator remembers every value it has yielded, and so uses memory that is
+    /// linear in the number of distinct values.
+    ///
+    /// The iterator element type is `&'g V`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// let mref = map.pin();
+    /// mref.insert("alice", "admin");
+    /// mref.insert("bob", "user");
+    /// mref.insert("carol", "user");
+    ///
+    /// let mut roles: Vec<_> = mref.distinct_values_iter().collect();
+    /// roles.sort();
+    /// assert_eq!(roles, vec![&"admin", &"user"]);
+    /// ```
+    pub fn distinct_values_iter<'g>(&'g self, guard: &'g Guard) -> DistinctValues<'g, K, V>
+    where
+        V: Hash + Eq,
+    {
+        DistinctValues {
+            values: self.values(guard),
+            seen: std::collections::HashSet::new(),
+        }
+    }
 
-                    // we are the chosen thread to finish the resize!
-                    finishing = true;
+    /// Walks all the entries of the map, and hints to the CPU to bring their keys and values into
+    /// its cache.
+    ///
+    /// This is a tool for predictable read latency: calling it right before a burst of lookups
+    /// means that those lookups find what they need in the cache, rather than each waiting for
+    /// main memory. It takes time linear in the size of the map, and of course only helps if the
+    /// map fits in the cache to begin with.
+    ///
+    /// The hints are only issued on x86_64. On other architectures, this only walks the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map: HashMap<_, _> = (0..100).map(|i| (i, i)).collect();
+    /// let guard = map.guard();
+    /// map.prefetch_all(&guard);
+    /// assert_eq!(map.get(&42, &guard), Some(&42));
+    /// ```
+    pub fn prefetch_all(&self, guard: &Guard) {
+        for (key, value) in self.iter(guard) {
+            prefetch(key);
+            prefetch(value);
+        }
+    }
 
-                    // ???
-                    advance = true;
+    /// Returns a snapshot of the counters of operations performed on the map.
+    ///
+    /// The counters are updated with relaxed atomic operations as the map is used, so a snapshot
+    /// taken while other threads are modifying the map may not reflect their most recent
+    /// operations, and the individual counts may be from slightly different points in time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// let guard = map.guard();
+    /// map.insert(1, "a", &guard);
+    /// map.get(&1, &guard);
+    /// map.get(&2, &guard);
+    ///
+    /// let stats = map.stats();
+    /// assert_eq!(stats.inserts, 1);
+    /// assert_eq!(stats.hits, 1);
+    /// assert_eq!(stats.misses, 1);
+    /// ```
+    #[cfg(feature = "metrics")]
+    pub fn stats(&self) -> MapStats {
+        self.metrics.snapshot()
+    }
 
-                    // NOTE: the java code says "recheck before commit" here
-                    i = n as isize;
-                }
+    /// Returns the current version of the map.
+    ///
+    /// Every insert or update of an entry stamps that entry with a new version, which is higher
+    /// than any version handed out before. The returned version can later be passed to
+    /// [`iter_since`](HashMap::iter_since) to visit the entries that were written since.
+    ///
+    /// Versions are assigned as writes happen, so a write that is concurrent with this call may
+    /// be stamped with a version at or below the returned one and only become visible
+    /// afterwards. Markers that are taken while the map is quiescent are exact.
+    #[cfg(feature = "versioning")]
+    pub fn current_version(&self) -> u64 {
+        self.version.load(Ordering::SeqCst)
+    }
 
-                continue;
-            }
-            let i = i as usize;
+    /// An iterator visiting all key-value pairs that were inserted or updated after the map was
+    /// at `version`, in arbitrary order.
+    ///
+    /// Entries that were removed are not visited. The iterator element type is `(&'g K, &'g V)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// let guard = map.guard();
+    /// map.insert(1, "a", &guard);
+    /// let version = map.current_version();
+    ///
+    /// map.insert(2, "b", &guard);
+    /// let changed: Vec<_> = map.iter_since(version, &guard).collect();
+    /// assert_eq!(changed, vec![(&2, &"b")]);
+    /// ```
+    #[cfg(feature = "versioning")]
+    pub fn iter_since<'g>(&'g self, version: u64, guard: &'g Guard) -> IterSince<'g, K, V> {
+        self.check_guard(guard);
+        let table = self.table.load(Ordering::SeqCst, guard);
+        let node_iter = NodeIter::new(table, guard);
+        IterSince {
+            node_iter,
+            version,
+            guard,
+        }
+    }
 
-            // safety: these were read while `guard` was held. the code that drops these, only
-            // drops them after a) they are no longer reachable, and b) any outstanding references
-            // are no longer active. these references are still active (marked by the guard), so
-            // the target of these references won't be dropped while the guard remains active.
-            let table = unsafe { table.deref() };
+    /// An iterator visiting all key-value pairs in arbitrary order, along with the sequence
+    /// number each entry was inserted with.
+    ///
+    /// The iterator element type is `(u64, &'g K, &'g V)`.
+    ///
+    /// With the `insertion-order` feature, every key that is inserted into the map is given the
+    /// next of a sequence of numbers that starts at 1. The number stays with the entry when its
+    /// value is updated, and when the map is resized, so it identifies the entry across any
+    /// number of iterations, no matter the order in which they visit entries. A key that is
+    /// removed and inserted again is given a new number. Sorting by the numbers puts the entries
+    /// in the order their keys were inserted in, with the caveat that concurrent inserts into
+    /// different bins may take their numbers in a different order than they become visible.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// let guard = map.guard();
+    /// map.insert("b", 1, &guard);
+    /// map.insert("a", 2, &guard);
+    /// map.insert("b", 3, &guard);
+    ///
+    /// let mut entries: Vec<_> = map.iter_enumerated(&guard).collect();
+    /// entries.sort_unstable();
+    /// assert_eq!(entries, vec![(1, &"b", &3), (2, &"a", &2)]);
+    /// ```
+    #[cfg(feature = "insertion-order")]
+    pub fn iter_enumerated<'g>(&'g self, guard: &'g Guard) -> IterEnumerated<'g, K, V> {
+        self.check_guard(guard);
+        let table = self.table.load(Ordering::SeqCst, guard);
+        let node_iter = NodeIter::new(table, guard);
+        IterEnumerated { node_iter, guard }
+    }
 
-            let bin = table.bin(i as usize, guard);
-            if bin.is_null() {
-                advance = table
-                    .cas_bin(i, Shared::null(), table.get_moved(next_table, guard), guard)
-                    .is_ok();
-                continue;
-            }
-            // safety: as for table above
-            let next_table = unsafe { next_table.deref() };
+    /// Returns the `n` most recently inserted entries of the map, newest first.
+    ///
+    /// Entries are ordered by the sequence numbers described in
+    /// [`iter_enumerated`](HashMap::iter_enumerated), so an entry whose value was updated keeps
+    /// its place, and a key that was removed and inserted again counts as new. This takes a
+    /// single scan over the map, and only keeps the `n` newest entries it has seen so far.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// let guard = map.guard();
+    /// for i in 0..10 {
+    ///     map.insert(i, i * 10, &guard);
+    /// }
+    ///
+    /// assert_eq!(map.recent(2, &guard), vec![(&9, &90), (&8, &80)]);
+    /// ```
+    #[cfg(feature = "insertion-order")]
+    pub fn recent<'g>(&'g self, n: usize, guard: &'g Guard) -> Vec<(&'g K, &'g V)> {
+        use std::cmp::Reverse;
+        use std::collections::BinaryHeap;
 
-            // safety: bin is a valid pointer.
-            //
-            // there are three cases when a bin pointer is invalidated:
-            //
-            //  1. if the table was resized, bin is a move entry, and the resize has completed. in
-            //     that case, the table (and all its heads) will be dropped in the next epoch
-            //     following that.
-            //  2. if the table is being resized, bin may be swapped with a move entry. the old bin
-            //     will then be dropped in the following epoch after that happens.
-            //  3. when elements are inserted into or removed from the map, bin may be changed into
-            //     or from a TreeBin from or into a regular, linear bin. the old bin will also be
-            //     dropped in the following epoch if that happens.
-            //
-            // in all cases, we held the guard when we got the reference to the bin. if any such
-            // swap happened, it must have happened _after_ we read. since we did the read while
-            // pinning the epoch, the drop must happen in the _next_ epoch (i.e., the one that we
-            // are holding up by holding on to our guard).
-            match *unsafe { bin.deref() } {
-                BinEntry::Moved => {
-                    // already processed
-                    advance = true;
+        self.check_guard(guard);
+        if n == 0 {
+            return Vec::new();
+        }
+        // a min-heap of the newest sequence numbers seen so far, where the oldest is the first
+        // to go. the entries themselves sit in `slots`, so that they need not be comparable.
+        let capacity = n.min(self.len());
+        let mut newest = BinaryHeap::with_capacity(capacity);
+        let mut slots = Vec::with_capacity(capacity);
+        for (seq, key, value) in self.iter_enumerated(guard) {
+            if newest.len() < n {
+                newest.push(Reverse((seq, slots.len())));
+                slots.push((key, value));
+            } else if let Some(&Reverse((oldest, slot))) = newest.peek() {
+                if seq > oldest {
+                    newest.pop();
+                    newest.push(Reverse((seq, slot)));
+                    slots[slot] = (key, value);
                 }
-                BinEntry::Node(ref head) => {
-                    // bin is non-empty, need to link into it, so we must take the lock
-                    let head_lock = head.lock.lock();
-
-                    // need to check that this is _still_ the head
-                    let current_head = table.bin(i, guard);
-                    if current_head.as_raw() != bin.as_raw() {
-                        // nope -- try again from the start
-                        continue;
-                    }
+            }
+        }
+        newest
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse((_, slot))| slots[slot])
+            .collect()
+    }
 
-                    // yes, it is still the head, so we can now "own" the bin
-                    // note that there can still be readers in the bin!
+    /// Returns the `top_n` most frequently looked up keys of the map along with how often they
+    /// were looked up, from the most to the least frequent.
+    ///
+    /// Every lookup that finds a key, such as through [`get`](HashMap::get) or
+    /// [`contains_key`](HashMap::contains_key), bumps an access count that is kept with the
+    /// entry. Lookups that other operations make internally, such as
+    /// [`get_or_try_insert_with`](HashMap::get_or_try_insert_with), are not counted. The count
+    /// is bumped with a relaxed atomic add, which is cheap, but may still cause contention on
+    /// keys that are looked up from many threads at once. An entry's count starts
+    /// over when it is removed and inserted again, and may miss lookups that happen while its bin
+    /// is being moved by a resize. Counts wrap around on overflow.
+    ///
+    /// Keys with equal counts are returned in arbitrary order. This walks the whole map, but only
+    /// sorts the `top_n` keys it returns.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// let guard = map.guard();
+    /// map.insert(1, "a", &guard);
+    /// map.insert(2, "b", &guard);
+    /// for _ in 0..3 {
+    ///     map.get(&2, &guard);
+    /// }
+    /// map.get(&1, &guard);
+    ///
+    /// assert_eq!(map.hot_keys(1, &guard), vec![(&2, 3)]);
+    /// ```
+    #[cfg(feature = "hot-keys")]
+    pub fn hot_keys<'g>(&'g self, top_n: usize, guard: &'g Guard) -> Vec<(&'g K, u32)> {
+        self.check_guard(guard);
+        if top_n == 0 {
+            return Vec::new();
+        }
+        let table = self.table.load(Ordering::SeqCst, guard);
+        let mut counts: Vec<_> = NodeIter::new(table, guard)
+            .map(|node| (&node.key, node.accesses.load(Ordering::Relaxed)))
+            .collect();
+        if top_n < counts.len() {
+            counts.select_nth_unstable_by_key(top_n - 1, |&(_, count)| std::cmp::Reverse(count));
+            counts.truncate(top_n);
+        }
+        counts.sort_unstable_by_key(|&(_, count)| std::cmp::Reverse(count));
+        counts
+    }
 
-                    // TODO: ReservationNode
+    /// Buckets references to all the map's entries by a group derived from each entry.
+    ///
+    /// This scans the map once, calling `key_fn` with every key-value pair, and collects the
+    /// entries into a [`std::collections::HashMap`] that maps each group to the entries that
+    /// fall into it. The references remain valid for the lifetime of the guard.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// for i in 0..6 {
+    ///     map.pin().insert(i, i);
+    /// }
+    ///
+    /// let guard = map.guard();
+    /// let groups = map.group_by(|_, v| v % 2 == 0, &guard);
+    /// assert_eq!(groups[&true].len(), 3);
+    /// assert_eq!(groups[&false].len(), 3);
+    /// ```
+    pub fn group_by<'g, G, F>(
+        &'g self,
+        key_fn: F,
+        guard: &'g Guard,
+    ) -> std::collections::HashMap<G, Vec<(&'g K, &'g V)>>
+    where
+        G: Hash + Eq,
+        F: Fn(&K, &V) -> G,
+    {
+        let mut groups = std::collections::HashMap::new();
+        for (key, value) in self.iter(guard) {
+            groups
+                .entry(key_fn(key, value))
+                .or_insert_with(Vec::new)
+                .push((key, value));
+        }
+        groups
+    }
 
-                    let mut run_bit = head.hash & n as u64;
-                    let mut last_run = bin;
-                    let mut p = bin;
-                    loop {
-                        // safety: p is a valid pointer.
-                        //
-                        // p is only dropped in the next epoch following when its bin is replaced
-                        // with a move node (see safety comment near table.store_bin below). we
-                        // read the bin, and got to p, so its bin has not yet been swapped with a
-                        // move node. and, we have the epoch pinned, so the next epoch cannot have
-                        // arrived yet. therefore, it will be dropped in a future epoch, and is
-                        // safe to use now.
-                        let node = unsafe { p.deref() }.as_node().unwrap();
-                        let next = node.next.load(Ordering::SeqCst, guard);
+    /// An iterator visiting all key-value pairs whose keys fall within `range`, in arbitrary
+    /// order.
+    ///
+    /// The map keeps no ordering of its keys, so this checks every entry of the map against
+    /// `range`, and takes time linear in the size of the map no matter how few entries it
+    /// yields.
+    ///
+    /// The iterator element type is `(&'g K, &'g V)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map: HashMap<_, _> = (0..100).map(|i| (i, i)).collect();
+    /// let guard = map.guard();
+    /// let mut keys: Vec<_> = map.range(10..13, &guard).map(|(k, _)| *k).collect();
+    /// keys.sort_unstable();
+    /// assert_eq!(keys, vec![10, 11, 12]);
+    /// ```
+    pub fn range<'g, R>(&'g self, range: R, guard: &'g Guard) -> Range<'g, K, V, R>
+    where
+        K: Ord,
+        R: RangeBounds<K>,
+    {
+        Range {
+            iter: self.iter(guard),
+            range,
+        }
+    }
 
-                        let b = node.hash & n as u64;
-                        if b != run_bit {
-                            run_bit = b;
-                            last_run = p;
-                        }
+    fn init_table<'g>(&'g self, guard: &'g Guard) -> Shared<'g, Table<K, V>> {
+        loop {
+            let table = self.table.load(Ordering::SeqCst, guard);
+            // safety: we loaded the table while epoch was pinned. table won't be deallocated until
+            // next epoch at the earliest.
+            if !table.is_null() && !unsafe { table.deref() }.is_empty() {
+                break table;
+            }
+            // try to allocate the table
+            let mut sc = self.size_ctl.load(Ordering::SeqCst);
+            if sc < 0 {
+                // we lost the initialization race; just spin
+                std::thread::yield_now();
+                continue;
+            }
 
-                        if next.is_null() {
-                            break;
-                        }
-                        p = next;
-                    }
+            if self.size_ctl.compare_and_swap(sc, -1, Ordering::SeqCst) == sc {
+                // we get to do it!
+                let mut table = self.table.load(Ordering::SeqCst, guard);
 
-                    let mut low_bin = Shared::null();
-                    let mut high_bin = Shared::null();
-                    if run_bit == 0 {
-                        // last run is all in the low bin
-                        low_bin = last_run;
+                // safety: we loaded the table while epoch was pinned. table won't be deallocated
+                // until next epoch at the earliest.
+                if table.is_null() || unsafe { table.deref() }.is_empty() {
+                    let n = if sc > 0 {
+                        sc as usize
                     } else {
-                        // last run is all in the high bin
-                        high_bin = last_run;
-                    }
+                        DEFAULT_CAPACITY
+                    };
+                    let new_table = Owned::new(Table::new(n));
+                    table = new_table.into_shared(guard);
+                    self.table.store(table, Ordering::SeqCst);
+                    sc = self.resize_threshold(n)
+                }
+                self.size_ctl.store(sc, Ordering::SeqCst);
+                break table;
+            }
+        }
+    }
 
-                    p = bin;
-                    while p != last_run {
-                        // safety: p is a valid pointer.
-                        //
-                        // p is only dropped in the next epoch following when its bin is replaced
-                        // with a move node (see safety comment near table.store_bin below). we
-                        // read the bin, and got to p, so its bin has not yet been swapped with a
-                        // move node. and, we have the epoch pinned, so the next epoch cannot have
-                        // arrived yet. therefore, it will be dropped in a future epoch, and is
-                        // safe to use now.
-                        let node = unsafe { p.deref() }.as_node().unwrap();
+    /// Presize the table to accommodate the given number of elements.
+    fn presize(&mut self, size: usize) {
+        // NOTE: this is a stripped-down version of try_presize for use only when we _know_ that
+        // the table is new, and that therefore we won't have to help out with transfers or deal
+        // with contending initializations.
 
-                        let link = if node.hash & n as u64 == 0 {
-                            // to the low bin!
-                            &mut low_bin
-                        } else {
-                            // to the high bin!
-                            &mut high_bin
-                        };
+        // safety: we are creating this map, so no other thread can access it,
+        // while we are initializing it.
+        let guard = unsafe { epoch::unprotected() };
 
-                        *link = Owned::new(BinEntry::Node(Node::with_next(
-                            node.hash,
-                            node.key.clone(),
-                            node.value.clone(),
-                            Atomic::from(*link),
-                        )))
-                        .into_shared(guard);
+        // sanity check that the map has indeed not been set up already. size_ctl may hold the
+        // initial capacity that the table should at least have.
+        let initial_capacity = self.size_ctl.load(Ordering::SeqCst);
+        assert!(initial_capacity >= 0);
+        assert!(self.table.load(Ordering::SeqCst, &guard).is_null());
 
-                        p = node.next.load(Ordering::SeqCst, guard);
-                    }
+        let requested_capacity = self.table_size_for(size).max(initial_capacity as usize);
 
-                    next_table.store_bin(i, low_bin);
-                    next_table.store_bin(i + n, high_bin);
-                    table.store_bin(
-                        i,
-                        table.get_moved(Shared::from(next_table as *const _), guard),
-                    );
+        // the table has not yet been initialized, so we can just create it
+        // with as many bins as were requested
 
-                    // everything up to last_run in the _old_ bin linked list is now garbage.
-                    // those nodes have all been re-allocated in the new bin linked list.
-                    p = bin;
-                    while p != last_run {
-                        // safety:
-                        //
-                        // we need to argue that there is no longer a way to access p. the only way
-                        // to get to p is through table[i]. since table[i] has been replaced by a
-                        // BinEntry::Moved, p is no longer accessible.
-                        //
-                        // any existing reference to p must have been taken before table.store_bin.
-                        // at that time we had the epoch pinned, so any threads that have such a
-                        // reference must be before or at our epoch. since the p isn't destroyed
-                        // until the next epoch, those old references are fine since they are tied
-                        // to those old threads' pins of the old epoch.
-                        let next = unsafe { p.deref() }
-                            .as_node()
-                            .unwrap()
-                            .next
-                            .load(Ordering::SeqCst, guard);
-                        unsafe { guard.defer_destroy(p) };
-                        p = next;
-                    }
+        // create a table with `new_capacity` empty bins
+        let new_table = Owned::new(Table::new(requested_capacity)).into_shared(guard);
 
-                    advance = true;
+        // store the new table to `self.table`
+        self.table.store(new_table, Ordering::SeqCst);
 
-                    drop(head_lock);
+        // resize the table once it is filled up to the load factor
+        let new_load_to_resize_at = self.resize_threshold(requested_capacity);
+
+        // store the next load at which the table should resize to it's size_ctl field
+        // and thus release the initialization "lock"
+        self.size_ctl.store(new_load_to_resize_at, Ordering::SeqCst);
+    }
+
+    /// Sets up a new map as configured by a [`Builder`](crate::Builder).
+    pub(crate) fn configure(
+        &mut self,
+        capacity: usize,
+        min_capacity: usize,
+        load_factor: Option<f32>,
+        treeify: bool,
+    ) {
+        self.load_factor = load_factor;
+        self.treeify = treeify;
+        if min_capacity > 0 {
+            // the table is still null, so size_ctl holds the initial table size.
+            let initial_capacity =
+                std::cmp::min(MAXIMUM_CAPACITY, min_capacity.next_power_of_two());
+            self.size_ctl
+                .store(initial_capacity as isize, Ordering::SeqCst);
+        }
+        if capacity > 0 {
+            self.presize(capacity);
+        }
+    }
+
+    /// Returns the number of bins a table needs to hold `size` entries without resizing.
+    fn table_size_for(&self, size: usize) -> usize {
+        if size >= MAXIMUM_CAPACITY / 2 {
+            return MAXIMUM_CAPACITY;
+        }
+        let size = match self.load_factor {
+            // round the requested_capacity to the next power of to from 1.5 * size + 1
+            // TODO: find out if this is neccessary
+            None => size + (size >> 1) + 1,
+            // like in the Java implementation, a custom load factor sizes the table to
+            // 1 + size / load factor bins.
+            Some(load_factor) => {
+                let size = 1.0 + size as f64 / load_factor as f64;
+                if size >= MAXIMUM_CAPACITY as f64 {
+                    return MAXIMUM_CAPACITY;
                 }
-                BinEntry::Tree(ref tree_bin) => {
-                    let bin_lock = tree_bin.lock.lock();
+                size as usize
+            }
+        };
+        std::cmp::min(MAXIMUM_CAPACITY, size.next_power_of_two())
+    }
 
-                    // need to check that this is _still_ the correct bin
-                    let current_head = table.bin(i, guard);
-                    if current_head != bin {
-                        // nope -- try again from the start
-                        continue;
-                    }
+    /// Returns the number of entries at which a table with `n` bins should be resized.
+    fn resize_threshold(&self, n: usize) -> isize {
+        match self.load_factor {
+            None => load_factor!(n as isize),
+            // the cast saturates, so huge load factors effectively disable resizing.
+            Some(load_factor) => ((n as f64 * load_factor as f64) as isize).max(1),
+        }
+    }
 
-                    let mut low = Shared::null();
-                    let mut low_tail = Shared::null();
-                    let mut high = Shared::null();
-                    let mut high_tail = Shared::null();
-                    let mut low_count = 0;
-                    let mut high_count = 0;
-                    let mut e = tree_bin.first.load(Ordering::Relaxed, guard);
-                    while !e.is_null() {
-                        // safety: the TreeBin was read under our guard, at
-                        // which point the tree structure was valid. Since our
-                        // guard pins the current epoch, the TreeNodes remain
-                        // valid for at least as long as we hold onto the guard.
-                        // Structurally, TreeNodes always point to TreeNodes, so this is sound.
-                        let tree_node = unsafe { TreeNode::get_tree_node(e) };
-                        let hash = tree_node.node.hash;
-                        let new_node = TreeNode::new(
-                            hash,
-                            tree_node.node.key.clone(),
-                            tree_node.node.value.clone(),
-                            Atomic::null(),
-                            Atomic::null(),
-                        );
-                        let run_bit = hash & n as u64;
-                        if run_bit == 0 {
-                            new_node.prev.store(low_tail, Ordering::Relaxed);
-                            let new_node =
-                                Owned::new(BinEntry::TreeNode(new_node)).into_shared(guard);
-                            if low_tail.is_null() {
-                                // this is the first element inserted into the low bin
-                                low = new_node;
-                            } else {
-                                // safety: `low_tail` was just created by us and not shared.
-                                // Structurally, TreeNodes always point to TreeNodes, so this is sound.
-                                unsafe { TreeNode::get_tree_node(low_tail) }
-                                    .node
-                                    .next
-                                    .store(new_node, Ordering::Relaxed);
-                            }
-                            low_tail = new_node;
-                            low_count += 1;
-                        } else {
-                            new_node.prev.store(high_tail, Ordering::Relaxed);
-                            let new_node =
-                                Owned::new(BinEntry::TreeNode(new_node)).into_shared(guard);
-                            if high_tail.is_null() {
-                                // this is the first element inserted into the high bin
-                                high = new_node;
-                            } else {
-                                // safety: `high_tail` was just created by us and not shared.
-                                // Structurally, TreeNodes always point to TreeNodes, so this is sound.
-                                unsafe { TreeNode::get_tree_node(high_tail) }
-                                    .node
-                                    .next
-                                    .store(new_node, Ordering::Relaxed);
-                            }
-                            high_tail = new_node;
-                            high_count += 1;
-                        }
-                        e = tree_node.node.next.load(Ordering::Relaxed, guard);
-                    }
+    /// Gives the new map a table of exactly `bins` bins that is never resized.
+    pub(crate) fn fix_table(&mut self, bins: usize) {
+        assert!(
+            bins.is_power_of_two() && bins <= MAXIMUM_CAPACITY,
+            "the number of bins must be a power of two no larger than {}",
+            MAXIMUM_CAPACITY
+        );
 
-                    let mut reused_bin = false;
-                    let low_bin = if low_count <= UNTREEIFY_THRESHOLD {
-                        // use a regular bin instead of a tree bin since the
-                        // bin is too small. since the tree nodes are
-                        // already behind shared references, we have to
-                        // clean them up manually.
-                        let low_linear = Self::untreeify(low, guard);
-                        // safety: we have just created `low` and its `next`
-                        // nodes and have never shared them
-                        unsafe { TreeBin::drop_tree_nodes(low, false, guard) };
-                        low_linear
-                    } else if high_count != 0 {
-                        // the new bin will also be a tree bin. if both the high
-                        // bin and the low bin are non-empty, we have to
-                        // allocate a new TreeBin.
-                        Owned::new(BinEntry::Tree(TreeBin::new(
-                            // safety: we have just created `low` and its `next`
-                            // nodes and have never shared them
-                            unsafe { low.into_owned() },
-                            guard,
-                        )))
-                        .into_shared(guard)
-                    } else {
-                        // if not, we can re-use the old bin here, since it will
-                        // be swapped for a Moved entry while we are still
-                        // behind the bin lock.
-                        reused_bin = true;
-                        // since we also don't use the created low nodes here,
-                        // we need to clean them up.
-                        // safety: we have just created `low` and its `next`
-                        // nodes and have never shared them
-                        unsafe { TreeBin::drop_tree_nodes(low, false, guard) };
-                        bin
-                    };
-                    let high_bin = if high_count <= UNTREEIFY_THRESHOLD {
-                        let high_linear = Self::untreeify(high, guard);
-                        // safety: we have just created `high` and its `next`
-                        // nodes and have never shared them
-                        unsafe { TreeBin::drop_tree_nodes(high, false, guard) };
-                        high_linear
-                    } else if low_count != 0 {
-                        Owned::new(BinEntry::Tree(TreeBin::new(
-                            // safety: we have just created `high` and its `next`
-                            // nodes and have never shared them
-                            unsafe { high.into_owned() },
-                            guard,
-                        )))
-                        .into_shared(guard)
-                    } else {
-                        reused_bin = true;
-                        // since we also don't use the created low nodes here,
-                        // we need to clean them up.
-                        // safety: we have just created `high` and its `next`
-                        // nodes and have never shared them
-                        unsafe { TreeBin::drop_tree_nodes(high, false, guard) };
-                        bin
-                    };
+        // safety: we are creating this map, so no other thread can access it,
+        // while we are initializing it.
+        let guard = unsafe { epoch::unprotected() };
 
-                    next_table.store_bin(i, low_bin);
-                    next_table.store_bin(i + n, high_bin);
-                    table.store_bin(
-                        i,
-                        table.get_moved(Shared::from(next_table as *const _), guard),
-                    );
+        // sanity check that the map has indeed not been set up already
+        assert_eq!(self.size_ctl.load(Ordering::SeqCst), 0);
+        assert!(self.table.load(Ordering::SeqCst, guard).is_null());
 
-                    // if we did not re-use the old bin, it is now garbage,
-                    // since all of its nodes have been reallocated. However,
-                    // we always re-use the stored values, so we can't drop those.
-                    if !reused_bin {
-                        // safety: the entry for this bin in the old table was
-                        // swapped for a Moved entry, so no thread can obtain a
-                        // new reference to `bin` from there. we only defer
-                        // dropping the old bin if it was not used in
-                        // `next_table` so there is no other reference to it
-                        // anyone could obtain.
-                        unsafe { TreeBin::defer_drop_without_values(bin, guard) };
-                    }
+        let new_table = Owned::new(Table::new(bins)).into_shared(guard);
+        self.table.store(new_table, Ordering::SeqCst);
 
-                    advance = true;
-                    drop(bin_lock);
+        // a resize is only ever started once the number of entries reaches size_ctl, and
+        // `try_presize` never grows a table whose size_ctl is already above the threshold it
+        // asks for, so this disables resizing altogether.
+        self.size_ctl.store(isize::MAX, Ordering::SeqCst);
+    }
+}
+
+// ===
+// the following methods require Clone and Ord, since they ultimately call
+// `transfer`, which needs to be able to clone keys and work with tree bins.
+// however, they do _not_ need to require thread-safety bounds (Send + Sync +
+// 'static) since if the bounds do not hold, the map is empty, so no keys or
+// values will be transfered anyway.
+// ===
+
+impl<K, V, S> HashMap<K, V, S>
+where
+    K: Clone + Ord,
+{
+    /// Tries to presize table to accommodate the given number of elements.
+    fn try_presize(&self, size: usize, guard: &Guard) {
+        let requested_capacity = self.table_size_for(size);
+        let threshold = match self.load_factor {
+            // NOTE: like in the Java implementation, we keep resizing until the _capacity_ we
+            // asked for fits below the resize threshold, which may allocate up to twice as many
+            // bins as are needed to hold `size` entries.
+            None => requested_capacity as isize,
+            // with a custom load factor, grow until the table resizes no earlier than a table
+            // with the requested capacity would, so that the load factor applies whether or not
+            // the table already exists.
+            Some(_) => self.resize_threshold(requested_capacity),
+        };
+        self.try_grow_table(requested_capacity as isize, threshold, guard);
+    }
+
+    /// Grows the table until its resize threshold is at least `threshold`.
+    ///
+    /// If the table is not yet initialized, it is created with `requested_capacity` bins.
+    fn try_grow_table(&self, requested_capacity: isize, threshold: isize, guard: &Guard) {
+        loop {
+            let size_ctl = self.size_ctl.load(Ordering::SeqCst);
+            if size_ctl < 0 {
+                break;
+            }
+
+            let table = self.table.load(Ordering::SeqCst, &guard);
+
+            // The current capacity == the number of bins in the current table
+            let current_capactity = if table.is_null() {
+                0
+            } else {
+                unsafe { table.deref() }.len()
+            };
+
+            if current_capactity == 0 {
+                // the table has not yet been initialized, so we can just create it
+                // with as many bins as were requested
+
+                // since the map is uninitialized, size_ctl describes the initial capacity
+                let initial_capacity = size_ctl;
+
+                // the new capacity is either the requested capacity or the initial capacity (size_ctl)
+                let new_capacity = requested_capacity.max(initial_capacity) as usize;
+
+                // try to aquire the initialization "lock" to indicate that we are initializing the table.
+                if self
+                    .size_ctl
+                    .compare_and_swap(size_ctl, -1, Ordering::SeqCst)
+                    != size_ctl
+                {
+                    // somebody else is already initializing the table (or has already finished).
+                    continue;
+                }
+
+                // we got the initialization `lock`; Make sure the table is still unitialized
+                // (or is the same table with 0 bins we read earlier, althought that should not be the case)
+                if self.table.load(Ordering::SeqCst, guard) != table {
+                    // NOTE: this could probably be `!self.table.load(...).is_null()`
+                    // if we decide that tables can never have 0 bins.
+
+                    // the table is already initialized; Write the `size_ctl` value it had back to it's
+                    // `size_ctl` field to release the initialization "lock"
+                    self.size_ctl.store(size_ctl, Ordering::SeqCst);
+                    continue;
+                }
+
+                // create a table with `new_capacity` empty bins
+                let new_table = Owned::new(Table::new(new_capacity)).into_shared(guard);
+
+                // store the new table to `self.table`
+                let old_table = self.table.swap(new_table, Ordering::SeqCst, &guard);
+
+                // old_table should be `null`, since we don't ever initialize a table with 0 bins
+                // and this branch only happens if table has not yet been initialized or it's length is 0.
+                assert!(old_table.is_null());
+
+                // TODO: if we allow tables with 0 bins. `defer_destroy` `old_table` if it's not `null`:
+                // if !old_table.is_null() {
+                //     // TODO: safety argument, for why this is okay
+                //     unsafe { guard.defer_destroy(old_table) }
+                // }
+
+                // resize the table once it is filled up to the load factor
+                let new_load_to_resize_at = self.resize_threshold(new_capacity);
+
+                // store the next load at which the table should resize to it's size_ctl field
+                // and thus release the initialization "lock"
+                self.size_ctl.store(new_load_to_resize_at, Ordering::SeqCst);
+            } else if threshold <= size_ctl || current_capactity >= MAXIMUM_CAPACITY {
+                // Either the `threshold` was smaller than or equal to the load we would resize at (size_ctl)
+                // and we don't need to resize, since our load factor will still be acceptable if we don't
+
+                // Or it was larger than the `MAXIMUM_CAPACITY` of the map and we refuse
+                // to resize to an invalid capacity
+                break;
+            } else if table == self.table.load(Ordering::SeqCst, guard) {
+                // The table is initialized, try to resize it to the requested capacity
+
+                let rs: isize = Self::resize_stamp(current_capactity) << RESIZE_STAMP_SHIFT;
+                // TODO: see #29: `rs` is postive even though `resize_stamp` says:
+                // "Must be negative when shifted left by RESIZE_STAMP_SHIFT"
+                // and since our size_control field needs to be negative
+                // to indicate a resize this needs to be addressed
+
+                if self
+                    .size_ctl
+                    .compare_and_swap(size_ctl, rs + 2, Ordering::SeqCst)
+                    == size_ctl
+                {
+                    // someone else already started to resize the table
+                    // TODO: can we `self.help_transfer`?
+                    self.transfer(table, Shared::null(), guard);
                 }
-                BinEntry::TreeNode(_) => unreachable!(
-                    "The head of a bin cannot be a TreeNode directly without BinEntry::Tree"
-                ),
             }
         }
     }
 
-    fn help_transfer<'g>(
+    /// Moves bins from `table` to `next_table` until there are none left to claim, and starts the
+    /// resize first if `next_table` is null.
+    ///
+    /// With the `tracing` feature, this runs in a `resize` span that records the capacities of
+    /// the two tables, whether this thread started the resize or is helping out, and how long
+    /// it took. Each range of bins the thread claims is reported as an event within the span.
+    fn transfer<'g>(
         &'g self,
         table: Shared<'g, Table<K, V>>,
+        next_table: Shared<'g, Table<K, V>>,
         guard: &'g Guard,
-    ) -> Shared<'g, Table<K, V>> {
-        if table.is_null() {
-            return table;
-        }
-
-        // safety: table is only dropped on the next epoch change after it is swapped to null.
-        // we read it as not null, so it must not be dropped until a subsequent epoch. since we
-        // held `guard` at the time, we know that the current epoch continues to persist, and that
-        // our reference is therefore valid.
-        let next_table = unsafe { table.deref() }.next_table(guard);
-        if next_table.is_null() {
-            return table;
-        }
+    ) {
+        #[cfg(feature = "tracing")]
+        let (span, started) = {
+            // safety: see transfer_bins
+            let n = unsafe { table.deref() }.len();
+            let span = tracing::debug_span!(
+                "resize",
+                capacity = n,
+                new_capacity = n << 1,
+                initiated = next_table.is_null(),
+                duration_us = tracing::field::Empty,
+            );
+            (span, std::time::Instant::now())
+        };
+        #[cfg(feature = "tracing")]
+        let entered = span.enter();
 
-        // safety: same as above
-        let rs = Self::resize_stamp(unsafe { table.deref() }.len()) << RESIZE_STAMP_SHIFT;
+        self.transfer_bins(table, next_table, guard);
 
-        while next_table == self.next_table.load(Ordering::SeqCst, guard)
-            && table == self.table.load(Ordering::SeqCst, guard)
+        #[cfg(feature = "tracing")]
         {
-            let sc = self.size_ctl.load(Ordering::SeqCst);
-            if sc >= 0
-                || sc == rs + MAX_RESIZERS
-                || sc == rs + 1
-                || self.transfer_index.load(Ordering::SeqCst) <= 0
-            {
-                break;
-            }
-
-            if self.size_ctl.compare_and_swap(sc, sc + 1, Ordering::SeqCst) == sc {
-                self.transfer(table, next_table, guard);
-                break;
-            }
+            drop(entered);
+            span.record("duration_us", started.elapsed().as_micros() as u64);
         }
-        next_table
     }
 
-    fn add_count(&self, n: isize, resize_hint: Option<usize>, guard: &Guard) {
-        // TODO: implement the Java CounterCell business here
+    // NOTE: transfer requires that K and V are Send + Sync if it will actually transfer anything.
+    // If K/V aren't Send + Sync, the map must be empty, and therefore calling tansfer is fine.
+    #[inline(never)]
+    fn transfer_bins<'g>(
+        &'g self,
+        table: Shared<'g, Table<K, V>>,
+        mut next_table: Shared<'g, Table<K, V>>,
+        guard: &'g Guard,
+    ) {
+        // safety: table was read while `guard` was held. the code that drops table only drops it
+        // after it is no longer reachable, and any outstanding references are no longer active.
+        // this references is still active (marked by the guard), so the target of the references
+        // won't be dropped while the guard remains active.
+        let n = unsafe { table.deref() }.len();
+        let ncpu = num_cpus();
 
-        use std::cmp;
-        let mut count = match n.cmp(&0) {
-            cmp::Ordering::Greater => self.count.fetch_add(n, Ordering::SeqCst) + n,
-            cmp::Ordering::Less => self.count.fetch_sub(n.abs(), Ordering::SeqCst) - n,
-            cmp::Ordering::Equal => self.count.load(Ordering::SeqCst),
-        };
+        let stride = if ncpu > 1 { (n >> 3) / ncpu } else { n };
+        let stride = std::cmp::max(stride as isize, MIN_TRANSFER_STRIDE);
 
-        // if resize_hint is None, it means the caller does not want us to consider a resize.
-        // if it is Some(n), the caller saw n entries in a bin
-        if resize_hint.is_none() {
-            return;
+        if next_table.is_null() {
+            // we are initiating a resize
+            record!(self, resizes);
+            let table = Owned::new(Table::new(n << 1));
+            let now_garbage = self.next_table.swap(table, Ordering::SeqCst, guard);
+            assert!(now_garbage.is_null());
+            self.transfer_index.store(n as isize, Ordering::SeqCst);
+            next_table = self.next_table.load(Ordering::Relaxed, guard);
+        } else {
+            // we are helping out with a resize that another thread started
+            record!(self, resize_helps);
         }
 
-        // TODO: use the resize hint
-        let _saw_bin_length = resize_hint.unwrap();
+        // safety: same argument as for table above
+        let next_n = unsafe { next_table.deref() }.len();
 
+        let mut advance = true;
+        let mut finishing = false;
+        let mut i = 0;
+        let mut bound = 0;
         loop {
-            let sc = self.size_ctl.load(Ordering::SeqCst);
-            if (count as isize) < sc {
-                // we're not at the next resize point yet
-                break;
-            }
+            // try to claim a range of bins for us to transfer
+            while advance {
+                i -= 1;
+                if i >= bound || finishing {
+                    advance = false;
+                    break;
+                }
 
-            let table = self.table.load(Ordering::SeqCst, guard);
-            if table.is_null() {
-                // table will be initalized by another thread anyway
-                break;
-            }
+                let next_index = self.transfer_index.load(Ordering::SeqCst);
+                if next_index <= 0 {
+                    i = -1;
+                    advance = false;
+                    break;
+                }
 
-            // safety: table is only dropped on the next epoch change after it is swapped to null.
-            // we read it as not null, so it must not be dropped until a subsequent epoch. since we
-            // hold a Guard, we know that the current epoch will persist, and that our reference
-            // will therefore remain valid.
-            let n = unsafe { table.deref() }.len();
-            if n >= MAXIMUM_CAPACITY {
+                let next_bound = if next_index > stride {
+                    next_index - stride
+                } else {
+                    0
+                };
+                if self
+                    .transfer_index
+                    .compare_and_swap(next_index, next_bound, Ordering::SeqCst)
+                    == next_index
+                {
+                    #[cfg(feature = "tracing")]
+                    tracing::trace!(
+                        first_bin = next_bound,
+                        last_bin = next_index - 1,
+                        "claimed bins"
+                    );
+                    bound = next_bound;
+                    i = next_index;
+                    advance = false;
+                    break;
+                }
+            }
+
+            if i < 0 || i as usize >= n || i as usize + n >= next_n {
+                // the resize has finished
+
+                if finishing {
+                    // this branch is only taken for one thread partaking in the resize!
+                    self.next_table.store(Shared::null(), Ordering::SeqCst);
+                    let now_garbage = self.table.swap(next_table, Ordering::SeqCst, guard);
+                    // safety: need to guarantee that now_garbage is no longer reachable. more
+                    // specifically, no thread that executes _after_ this line can ever get a
+                    // reference to now_garbage.
+                    //
+                    // first, we need to argue that there is no _other_ way to get to now_garbage.
+                    //
+                    //  - it is _not_ accessible through self.table any more
+                    //  - it is _not_ accessible through self.next_table any more
+                    //  - what about forwarding nodes (BinEntry::Moved)?
+                    //    the only BinEntry::Moved that point to now_garbage, are the ones in
+                    //    _previous_ tables. to get to those previous tables, one must ultimately
+                    //    have arrived through self.table (because that's where all operations
+                    //    start their search). since self.table has now changed, only "old" threads
+                    //    can still be accessing them. no new thread can get to past tables, and
+                    //    therefore they also cannot get to ::Moved that point to now_garbage, so
+                    //    we're fine.
+                    //
+                    // this means that no _future_ thread (i.e., in a later epoch where the value
+                    // may be freed) can get a reference to now_garbage.
+                    //
+                    // next, let's talk about threads with _existing_ references to now_garbage.
+                    // such a thread must have gotten that reference before the call to swap.
+                    // because of this, that thread must be pinned to an epoch <= the epoch of our
+                    // guard (since our guard is pinning the epoch). since the garbage is placed in
+                    // our epoch, it won't be freed until the _next_ epoch, at which point, that
+                    // thread must have dropped its guard, and with it, any reference to the value.
+                    unsafe { guard.defer_destroy(now_garbage) };
+                    self.size_ctl
+                        .store(self.resize_threshold(n << 1), Ordering::SeqCst);
+                    return;
+                }
+
+                let sc = self.size_ctl.load(Ordering::SeqCst);
+                if self.size_ctl.compare_and_swap(sc, sc - 1, Ordering::SeqCst) == sc {
+                    if (sc - 2) != Self::resize_stamp(n) << RESIZE_STAMP_SHIFT {
+                        return;
+                    }
+
+                    // we are the chosen thread to finish the resize!
+                    finishing = true;
+
+                    // ???
+                    advance = true;
+
+                    // NOTE: the java code says "recheck before commit" here
+                    i = n as isize;
+                }
+
+                continue;
+            }
+            let i = i as usize;
+
+            // safety: these were read while `guard` was held. the code that drops these, only
+            // drops them after a) they are no longer reachable, and b) any outstanding references
+            // are no longer active. these references are still active (marked by the guard), so
+            // the target of these references won't be dropped while the guard remains active.
+            let table = unsafe { table.deref() };
+
+            let bin = table.bin(i as usize, guard);
+            if bin.is_null() {
+                advance = table
+                    .cas_bin(i, Shared::null(), table.get_moved(next_table, guard), guard)
+                    .is_ok();
+                continue;
+            }
+            // safety: as for table above
+            let next_table = unsafe { next_table.deref() };
+
+            // safety: bin is a valid pointer.
+            //
+            // there are three cases when a bin pointer is invalidated:
+            //
+            //  1. if the table was resized, bin is a move entry, and the resize has completed. in
+            //     that case, the table (and all its heads) will be dropped in the next epoch
+            //     following that.
+            //  2. if the table is being resized, bin may be swapped with a move entry. the old bin
+            //     will then be dropped in the following epoch after that happens.
+            //  3. when elements are inserted into or removed from the map, bin may be changed into
+            //     or from a TreeBin from or into a regular, linear bin. the old bin will also be
+            //     dropped in the following epoch if that happens.
+            //
+            // in all cases, we held the guard when we got the reference to the bin. if any such
+            // swap happened, it must have happened _after_ we read. since we did the read while
+            // pinning the epoch, the drop must happen in the _next_ epoch (i.e., the one that we
+            // are holding up by holding on to our guard).
+            match *unsafe { bin.deref() } {
+                BinEntry::Moved => {
+                    // already processed
+                    advance = true;
+                }
+                BinEntry::Reserved(ref lock) => {
+                    // another thread is computing the first entry of this bin. wait until it is
+                    // done, and then look at the bin again.
+                    drop(self.wait_for_bin(lock));
+                    continue;
+                }
+                BinEntry::Node(ref head) => {
+                    // bin is non-empty, need to link into it, so we must take the lock
+                    let head_lock = self.wait_for_bin(&head.lock);
+
+                    // need to check that this is _still_ the head
+                    let current_head = table.bin(i, guard);
+                    if current_head.as_raw() != bin.as_raw() {
+                        // nope -- try again from the start
+                        continue;
+                    }
+
+                    // yes, it is still the head, so we can now "own" the bin
+                    // note that there can still be readers in the bin!
+
+                    let mut run_bit = head.hash & n as u64;
+                    let mut last_run = bin;
+                    let mut p = bin;
+                    loop {
+                        // safety: p is a valid pointer.
+                        //
+                        // p is only dropped in the next epoch following when its bin is replaced
+                        // with a move node (see safety comment near table.store_bin below). we
+                        // read the bin, and got to p, so its bin has not yet been swapped with a
+                        // move node. and, we have the epoch pinned, so the next epoch cannot have
+                        // arrived yet. therefore, it will be dropped in a future epoch, and is
+                        // safe to use now.
+                        let node = unsafe { p.deref() }.as_node().unwrap();
+                        let next = node.next.load(Ordering::SeqCst, guard);
+
+                        let b = node.hash & n as u64;
+                        if b != run_bit {
+                            run_bit = b;
+                            last_run = p;
+                        }
+
+                        if next.is_null() {
+                            break;
+                        }
+                        p = next;
+                    }
+
+                    let mut low_bin = Shared::null();
+                    let mut high_bin = Shared::null();
+                    if run_bit == 0 {
+                        // last run is all in the low bin
+                        low_bin = last_run;
+                    } else {
+                        // last run is all in the high bin
+                        high_bin = last_run;
+                    }
+
+                    p = bin;
+                    while p != last_run {
+                        // safety: p is a valid pointer.
+                        //
+                        // p is only dropped in the next epoch following when its bin is replaced
+                        // with a move node (see safety comment near table.store_bin below). we
+                        // read the bin, and got to p, so its bin has not yet been swapped with a
+                        // move node. and, we have the epoch pinned, so the next epoch cannot have
+                        // arrived yet. therefore, it will be dropped in a future epoch, and is
+                        // safe to use now.
+                        let node = unsafe { p.deref() }.as_node().unwrap();
+
+                        let link = if node.hash & n as u64 == 0 {
+                            // to the low bin!
+                            &mut low_bin
+                        } else {
+                            // to the high bin!
+                            &mut high_bin
+                        };
+
+                        let new_node = Node::with_next(
+                            node.hash,
+                            node.key.clone(),
+                            node.value.clone(),
+                            Atomic::from(*link),
+                        );
+                        new_node.copy_metadata(node);
+                        *link = Owned::new(BinEntry::Node(new_node)).into_shared(guard);
+
+                        p = node.next.load(Ordering::SeqCst, guard);
+                    }
+
+                    next_table.store_bin(i, low_bin);
+                    next_table.store_bin(i + n, high_bin);
+                    table.store_bin(
+                        i,
+                        table.get_moved(Shared::from(next_table as *const _), guard),
+                    );
+
+                    // everything up to last_run in the _old_ bin linked list is now garbage.
+                    // those nodes have all been re-allocated in the new bin linked list.
+                    p = bin;
+                    while p != last_run {
+                        // safety:
+                        //
+                        // we need to argue that there is no longer a way to access p. the only way
+                        // to get to p is through table[i]. since table[i] has been replaced by a
+                        // BinEntry::Moved, p is no longer accessible.
+                        //
+                        // any existing reference to p must have been taken before table.store_bin.
+                        // at that time we had the epoch pinned, so any threads that have such a
+                        // reference must be before or at our epoch. since the p isn't destroyed
+                        // until the next epoch, those old references are fine since they are tied
+                        // to those old threads' pins of the old epoch.
+                        let next = unsafe { p.deref() }
+                            .as_node()
+                            .unwrap()
+                            .next
+                            .load(Ordering::SeqCst, guard);
+                        unsafe { guard.defer_destroy(p) };
+                        p = next;
+                    }
+
+                    advance = true;
+
+                    drop(head_lock);
+                }
+                BinEntry::Tree(ref tree_bin) => {
+                    let bin_lock = self.wait_for_bin(&tree_bin.lock);
+
+                    // need to check that this is _still_ the correct bin
+                    let current_head = table.bin(i, guard);
+                    if current_head != bin {
+                        // nope -- try again from the start
+                        continue;
+                    }
+
+                    let mut low = Shared::null();
+                    let mut low_tail = Shared::null();
+                    let mut high = Shared::null();
+                    let mut high_tail = Shared::null();
+                    let mut low_count = 0;
+                    let mut high_count = 0;
+                    let mut e = tree_bin.first.load(Ordering::Relaxed, guard);
+                    while !e.is_null() {
+                        // safety: the TreeBin was read under our guard, at
+                        // which point the tree structure was valid. Since our
+                        // guard pins the current epoch, the TreeNodes remain
+                        // valid for at least as long as we hold onto the guard.
+                        // Structurally, TreeNodes always point to TreeNodes, so this is sound.
+                        let tree_node = unsafe { TreeNode::get_tree_node(e) };
+                        let hash = tree_node.node.hash;
+                        let new_node = TreeNode::new(
+                            hash,
+                            tree_node.node.key.clone(),
+                            tree_node.node.value.clone(),
+                            Atomic::null(),
+                            Atomic::null(),
+                        );
+                        new_node.node.copy_metadata(&tree_node.node);
+                        let run_bit = hash & n as u64;
+                        if run_bit == 0 {
+                            new_node.prev.store(low_tail, Ordering::Relaxed);
+                            let new_node =
+                                Owned::new(BinEntry::TreeNode(new_node)).into_shared(guard);
+                            if low_tail.is_null() {
+                                // this is the first element inserted into the low bin
+                                low = new_node;
+                            } else {
+                                // safety: `low_tail` was just created by us and not shared.
+                                // Structurally, TreeNodes always point to TreeNodes, so this is sound.
+                                unsafe { TreeNode::get_tree_node(low_tail) }
+                                    .node
+                                    .next
+                                    .store(new_node, Ordering::Relaxed);
+                            }
+                            low_tail = new_node;
+                            low_count += 1;
+                        } else {
+                            new_node.prev.store(high_tail, Ordering::Relaxed);
+                            let new_node =
+                                Owned::new(BinEntry::TreeNode(new_node)).into_shared(guard);
+                            if high_tail.is_null() {
+                                // this is the first element inserted into the high bin
+                                high = new_node;
+                            } else {
+                                // safety: `high_tail` was just created by us and not shared.
+                                // Structurally, TreeNodes always point to TreeNodes, so this is sound.
+                                unsafe { TreeNode::get_tree_node(high_tail) }
+                                    .node
+                                    .next
+                                    .store(new_node, Ordering::Relaxed);
+                            }
+                            high_tail = new_node;
+                            high_count += 1;
+                        }
+                        e = tree_node.node.next.load(Ordering::Relaxed, guard);
+                    }
+
+                    let mut reused_bin = false;
+                    let low_bin = if low_count <= UNTREEIFY_THRESHOLD {
+                        // use a regular bin instead of a tree bin since the
+                        // bin is too small. since the tree nodes are
+                        // already behind shared references, we have to
+                        // clean them up manually.
+                        let low_linear = Self::untreeify(low, guard);
+                        // safety: we have just created `low` and its `next`
+                        // nodes and have never shared them
+                        unsafe { TreeBin::drop_tree_nodes(low, false, guard) };
+                        low_linear
+                    } else if high_count != 0 {
+                        // the new bin will also be a tree bin. if both the high
+                        // bin and the low bin are non-empty, we have to
+                        // allocate a new TreeBin.
+                        Owned::new(BinEntry::Tree(TreeBin::new(
+                            // safety: we have just created `low` and its `next`
+                            // nodes and have never shared them
+                            unsafe { low.into_owned() },
+                            guard,
+                        )))
+                        .into_shared(guard)
+                    } else {
+                        // if not, we can re-use the old bin here, since it will
+                        // be swapped for a Moved entry while we are still
+                        // behind the bin lock.
+                        reused_bin = true;
+                        // since we also don't use the created low nodes here,
+                        // we need to clean them up.
+                        // safety: we have just created `low` and its `next`
+                        // nodes and have never shared them
+                        unsafe { TreeBin::drop_tree_nodes(low, false, guard) };
+                        bin
+                    };
+                    let high_bin = if high_count <= UNTREEIFY_THRESHOLD {
+                        let high_linear = Self::untreeify(high, guard);
+                        // safety: we have just created `high` and its `next`
+                        // nodes and have never shared them
+                        unsafe { TreeBin::drop_tree_nodes(high, false, guard) };
+                        high_linear
+                    } else if low_count != 0 {
+                        Owned::new(BinEntry::Tree(TreeBin::new(
+                            // safety: we have just created `high` and its `next`
+                            // nodes and have never shared them
+                            unsafe { high.into_owned() },
+                            guard,
+                        )))
+                        .into_shared(guard)
+                    } else {
+                        reused_bin = true;
+                        // since we also don't use the created low nodes here,
+                        // we need to clean them up.
+                        // safety: we have just created `high` and its `next`
+                        // nodes and have never shared them
+                        unsafe { TreeBin::drop_tree_nodes(high, false, guard) };
+                        bin
+                    };
+
+                    next_table.store_bin(i, low_bin);
+                    next_table.store_bin(i + n, high_bin);
+                    table.store_bin(
+                        i,
+                        table.get_moved(Shared::from(next_table as *const _), guard),
+                    );
+
+                    // if we did not re-use the old bin, it is now garbage,
+                    // since all of its nodes have been reallocated. However,
+                    // we always re-use the stored values, so we can't drop those.
+                    if !reused_bin {
+                        // safety: the entry for this bin in the old table was
+                        // swapped for a Moved entry, so no thread can obtain a
+                        // new reference to `bin` from there. we only defer
+                        // dropping the old bin if it was not used in
+                        // `next_table` so there is no other reference to it
+                        // anyone could obtain.
+                        unsafe { TreeBin::defer_drop_without_values(bin, guard) };
+                    }
+
+                    advance = true;
+                    drop(bin_lock);
+                }
+                BinEntry::TreeNode(_) => unreachable!(
+                    "The head of a bin cannot be a TreeNode directly without BinEntry::Tree"
+                ),
+            }
+        }
+    }
+
+    fn help_transfer<'g>(
+        &'g self,
+        table: Shared<'g, Table<K, V>>,
+        guard: &'g Guard,
+    ) -> Shared<'g, Table<K, V>> {
+        if table.is_null() {
+            return table;
+        }
+
+        // safety: table is only dropped on the next epoch change after it is swapped to null.
+        // we read it as not null, so it must not be dropped until a subsequent epoch. since we
+        // held `guard` at the time, we know that the current epoch continues to persist, and that
+        // our reference is therefore valid.
+        let next_table = unsafe { table.deref() }.next_table(guard);
+        if next_table.is_null() {
+            return table;
+        }
+
+        // safety: same as above
+        let rs = Self::resize_stamp(unsafe { table.deref() }.len()) << RESIZE_STAMP_SHIFT;
+
+        while next_table == self.next_table.load(Ordering::SeqCst, guard)
+            && table == self.table.load(Ordering::SeqCst, guard)
+        {
+            let sc = self.size_ctl.load(Ordering::SeqCst);
+            if sc >= 0
+                || sc == rs + MAX_RESIZERS
+                || sc == rs + 1
+                || self.transfer_index.load(Ordering::SeqCst) <= 0
+            {
+                break;
+            }
+
+            if self.size_ctl.compare_and_swap(sc, sc + 1, Ordering::SeqCst) == sc {
+                self.transfer(table, next_table, guard);
+                break;
+            }
+        }
+        next_table
+    }
+
+    /// Adds `n` to the count of entries in the map, resizing it if that is warranted, and returns
+    /// the count right after `n` was added.
+    fn add_count(&self, n: isize, resize_hint: Option<usize>, guard: &Guard) -> isize {
+        // TODO: implement the Java CounterCell business here
+
+        use std::cmp;
+        let mut count = match n.cmp(&0) {
+            cmp::Ordering::Greater => {
+                record!(self, inserts, n as u64);
+                self.count.fetch_add(n, Ordering::SeqCst) + n
+            }
+            cmp::Ordering::Less => {
+                record!(self, removals, n.unsigned_abs() as u64);
+                self.count.fetch_sub(n.abs(), Ordering::SeqCst) - n
+            }
+            cmp::Ordering::Equal => self.count.load(Ordering::SeqCst),
+        };
+        let updated = count;
+
+        // if resize_hint is None, it means the caller does not want us to consider a resize.
+        // if it is Some(n), the caller saw n entries in a bin
+        if resize_hint.is_none() {
+            return updated;
+        }
+
+        // TODO: use the resize hint
+        let _saw_bin_length = resize_hint.unwrap();
+
+        self.grow_if_pending(guard);
+
+        loop {
+            let sc = self.size_ctl.load(Ordering::SeqCst);
+            if (count as isize) < sc {
+                // we're not at the next resize point yet
+                break;
+            }
+
+            let table = self.table.load(Ordering::SeqCst, guard);
+            if table.is_null() {
+                // table will be initalized by another thread anyway
+                break;
+            }
+
+            // safety: table is only dropped on the next epoch change after it is swapped to null.
+            // we read it as not null, so it must not be dropped until a subsequent epoch. since we
+            // hold a Guard, we know that the current epoch will persist, and that our reference
+            // will therefore remain valid.
+            let n = unsafe { table.deref() }.len();
+            if n >= MAXIMUM_CAPACITY {
                 // can't resize any more anyway
                 break;
             }
 
-            let rs = Self::resize_stamp(n) << RESIZE_STAMP_SHIFT;
-            if sc < 0 {
-                // ongoing resize! can we join the resize transfer?
-                if sc == rs + MAX_RESIZERS || sc == rs + 1 {
-                    break;
+            let rs = Self::resize_stamp(n) << RESIZE_STAMP_SHIFT;
+            if sc < 0 {
+                // ongoing resize! can we join the resize transfer?
+                if sc == rs + MAX_RESIZERS || sc == rs + 1 {
+                    break;
+                }
+                let nt = self.next_table.load(Ordering::SeqCst, guard);
+                if nt.is_null() {
+                    break;
+                }
+                if self.transfer_index.load(Ordering::SeqCst) <= 0 {
+                    break;
+                }
+
+                // try to join!
+                if self.size_ctl.compare_and_swap(sc, sc + 1, Ordering::SeqCst) == sc {
+                    self.transfer(table, nt, guard);
+                }
+            } else if self.size_ctl.compare_and_swap(sc, rs + 2, Ordering::SeqCst) == sc {
+                // a resize is needed, but has not yet started
+                // TODO: figure out why this is rs + 2, not just rs
+                // NOTE: this also applies to `try_presize`
+                self.transfer(table, Shared::null(), guard);
+            }
+
+            // another resize may be needed!
+            count = self.count.load(Ordering::SeqCst);
+        }
+        updated
+    }
+
+    /// Records that the table should grow to hold `size` entries, for a write that may not block
+    /// and so cannot resize the table itself. The next write that may block grows the table.
+    fn defer_growth(&self, size: usize) {
+        self.pending_growth.fetch_max(size, Ordering::SeqCst);
+    }
+
+    /// Grows the table as a nonblocking write asked for through `defer_growth`, if any.
+    fn grow_if_pending(&self, guard: &Guard) {
+        if self.pending_growth.load(Ordering::Relaxed) == 0 {
+            return;
+        }
+        let size = self.pending_growth.swap(0, Ordering::SeqCst);
+        if size != 0 {
+            self.try_presize(size, guard);
+        }
+    }
+
+    /// Adds a nonblocking insert to the count of entries in the map like `add_count`, and leaves
+    /// the resize that the insert may call for to the next write that may block.
+    fn add_count_nonblocking(&self, n: isize, guard: &Guard) -> isize {
+        let count = self.add_count(n, None, guard);
+        let sc = self.size_ctl.load(Ordering::SeqCst);
+        if sc >= 0 && count >= sc && !self.table.load(Ordering::SeqCst, guard).is_null() {
+            self.defer_growth(count as usize);
+        }
+        count
+    }
+
+    /// Tries to reserve capacity for at least `additional` more elements to be inserted in the
+    /// `HashMap`.
+    ///
+    /// The collection may reserve more space to avoid frequent reallocations.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map: HashMap<&str, i32> = HashMap::new();
+    ///
+    /// map.pin().reserve(10);
+    /// ```
+    ///
+    /// # Notes
+    ///
+    /// Reserving does not panic in flurry. If the new size is invalid, no
+    /// reallocation takes place.
+    pub fn reserve(&self, additional: usize, guard: &Guard) {
+        self.check_guard(guard);
+        let absolute = self.len() + additional;
+        self.try_presize(absolute, guard);
+    }
+
+    /// Reserves capacity for the size the map is projected to reach if it keeps growing by
+    /// `entries_per_interval` entries per interval for another `intervals_ahead` intervals.
+    ///
+    /// This is a planning tool for maps that grow at a predictable rate, such as during streaming
+    /// ingestion: rather than resizing again and again as entries trickle in, the map grows to
+    /// hold `len + entries_per_interval * intervals_ahead` entries up front, just like with
+    /// [`reserve`](HashMap::reserve). If the map can already hold that many entries, this does
+    /// nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map: HashMap<usize, usize> = HashMap::new();
+    /// // expect 100 entries per second for the next minute
+    /// map.pin().reserve_for_rate(100, 60);
+    /// assert!(map.pin().capacity() >= 6000);
+    /// ```
+    pub fn reserve_for_rate(
+        &self,
+        entries_per_interval: usize,
+        intervals_ahead: usize,
+        guard: &Guard,
+    ) {
+        self.check_guard(guard);
+        let projected = self
+            .len()
+            .saturating_add(entries_per_interval.saturating_mul(intervals_ahead));
+        self.try_presize(projected, guard);
+    }
+
+    /// Tries to reserve capacity for exactly `additional` more elements to be inserted in the
+    /// `HashMap`.
+    ///
+    /// Unlike [`reserve`](HashMap::reserve), which leaves room for further growth and may end up
+    /// with up to twice the number of bins actually needed, this grows the table only to the
+    /// smallest capacity that will hold `len + additional` entries at the map's load factor. This
+    /// minimizes memory use for maps that are not expected to grow any further. Since the
+    /// capacity is always a power of two, the table may still hold somewhat more than that.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map: HashMap<&str, i32> = HashMap::new();
+    ///
+    /// map.pin().reserve_exact(11);
+    /// assert_eq!(map.pin().capacity(), 16);
+    /// ```
+    ///
+    /// # Notes
+    ///
+    /// Reserving does not panic in flurry. If the new size is invalid, no
+    /// reallocation takes place.
+    pub fn reserve_exact(&self, additional: usize, guard: &Guard) {
+        self.check_guard(guard);
+        let absolute = self.len() + additional;
+        if absolute == 0 {
+            return;
+        }
+
+        // the map resizes once it holds as many entries as its threshold, so we need a
+        // threshold of at least one more than the number of entries we want to hold.
+        let threshold = absolute.saturating_add(1);
+        let mut requested_capacity = std::cmp::min(MAXIMUM_CAPACITY, threshold.next_power_of_two());
+        while requested_capacity < MAXIMUM_CAPACITY
+            && self.resize_threshold(requested_capacity) < threshold as isize
+        {
+            requested_capacity <<= 1;
+        }
+        self.try_grow_table(requested_capacity as isize, threshold as isize, guard);
+    }
+
+    /// Returns `true` if a resize of the map is in progress.
+    ///
+    /// A resize counts as in progress from the moment it starts until its new table has replaced
+    /// the old one, that is, until [`capacity`](HashMap::capacity) reports the new size. Of
+    /// course, a resize may start or finish right after this returns.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// map.pin().insert(1, 1);
+    /// assert!(!map.pin().is_resizing());
+    /// ```
+    pub fn is_resizing(&self, guard: &Guard) -> bool {
+        self.check_guard(guard);
+        // the thread that finishes a resize clears next_table _before_ it swaps in the new table,
+        // but size_ctl only stops encoding the resize once the new table is in place.
+        self.size_ctl.load(Ordering::SeqCst) < -1
+            || !self.next_table.load(Ordering::SeqCst, guard).is_null()
+    }
+
+    /// Waits until the resize that is in progress, if any, has completed.
+    ///
+    /// Rather than just waiting, the calling thread helps move bins to the new table for as long
+    /// as there are bins left that no other thread has claimed yet. Once this returns, the map's
+    /// capacity stays the same until the next resize starts, which makes this useful before a
+    /// burst of operations that should all see the same table.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// for i in 0..100 {
+    ///     map.pin().insert(i, i);
+    /// }
+    /// map.pin().await_resize();
+    /// assert!(!map.pin().is_resizing());
+    /// ```
+    pub fn await_resize(&self, guard: &Guard) {
+        while self.is_resizing(guard) {
+            // help out if we can, and otherwise give the other resizers a chance to finish.
+            let table = self.table.load(Ordering::SeqCst, guard);
+            self.help_transfer(table, guard);
+            std::thread::yield_now();
+        }
+    }
+
+    /// Calls `f` on every key-value pair of the map, after waiting for any resize that is in
+    /// progress to complete.
+    ///
+    /// This first [awaits](HashMap::await_resize) the current resize, if any, so that the scan
+    /// starts out on a table that has no bins that were forwarded to a new one. A resize may
+    /// still start while the scan is under way, in which case the scan follows the forwarded
+    /// bins just like [`iter`](HashMap::iter) does, so each entry that is in the map for the
+    /// entire scan is still visited exactly once, and once the scan is done, this helps complete
+    /// that resize as well. Like with `iter`, entries that are inserted or removed during the
+    /// scan may or may not be visited.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map: HashMap<_, _> = (0..100).map(|i| (i, i)).collect();
+    /// let mut sum = 0;
+    /// map.pin().stable_for_each(|_, v| sum += v);
+    /// assert_eq!(sum, (0..100).sum::<i32>());
+    /// ```
+    pub fn stable_for_each<F>(&self, mut f: F, guard: &Guard)
+    where
+        F: FnMut(&K, &V),
+    {
+        self.await_resize(guard);
+        for (key, value) in self.iter(guard) {
+            f(key, value);
+        }
+        self.await_resize(guard);
+    }
+
+    /// Allocates the map's table now, so that the first write to the map does not have to.
+    ///
+    /// A map that is created with a capacity only allocates its table on the first insert. For a
+    /// large capacity, that allocation, and initializing every bin of the table, can make the
+    /// first insert much slower than the ones that follow. Latency-sensitive services can call
+    /// this at startup to pay that cost up front instead. If the table has already been
+    /// allocated, this does nothing, so it is safe to call while other threads use the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::with_capacity(1 << 16);
+    /// map.pin().prefault();
+    /// assert!(map.pin().capacity() >= 1 << 16);
+    /// map.pin().insert(1, "a");
+    /// ```
+    pub fn prefault(&self, guard: &Guard) {
+        self.check_guard(guard);
+        if self.table.load(Ordering::SeqCst, guard).is_null() {
+            self.init_table(guard);
+        }
+    }
+
+    /// Collects the heads of all the bins in the current table.
+    ///
+    /// If a resize is in progress, this first helps it complete, so none of the returned heads
+    /// are `BinEntry::Moved`. The returned heads may of course be replaced by concurrent
+    /// modifications at any time, but remain valid to dereference for the lifetime of the guard.
+    fn bin_heads<'g>(&'g self, guard: &'g Guard) -> Vec<Shared<'g, BinEntry<K, V>>> {
+        let mut heads = Vec::new();
+        'settle: loop {
+            heads.clear();
+            let table = self.table.load(Ordering::SeqCst, guard);
+            if table.is_null() {
+                return heads;
+            }
+            // safety: we loaded the table while holding the guard, so it will not be dropped
+            // until after the guard is dropped.
+            let t = unsafe { table.deref() };
+            for i in 0..t.len() {
+                let bin = t.bin(i, guard);
+                // safety: bins read under the guard stay valid for the guard's lifetime. see the
+                // safety argument in put() for details.
+                if let Some(&BinEntry::Moved) = unsafe { bin.as_ref() } {
+                    // a resize is in progress. help out if we can, and then give the other
+                    // resizers a chance to finish before we look again.
+                    self.help_transfer(table, guard);
+                    std::thread::yield_now();
+                    continue 'settle;
+                }
+                heads.push(bin);
+            }
+            return heads;
+        }
+    }
+
+    /// Returns a snapshot of how the map's entries are distributed over its bins.
+    ///
+    /// This is a diagnostic tool, and is useful for judging the quality of a hasher for a given
+    /// set of keys. It walks every bin of the map's table, and so takes time linear in the
+    /// capacity of the map. If a resize is in progress, this helps to finish it first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// for i in 0..10 {
+    ///     map.pin().insert(i, i);
+    /// }
+    ///
+    /// let stats = map.pin().bin_stats();
+    /// assert_eq!(stats.bin_lengths.iter().sum::<usize>(), 10);
+    /// assert_eq!(stats.tree_bins, 0);
+    /// ```
+    pub fn bin_stats(&self, guard: &Guard) -> BinStats {
+        self.check_guard(guard);
+        let mut stats = BinStats {
+            bin_lengths: Vec::new(),
+            tree_bins: 0,
+        };
+        for bin in self.bin_heads(guard) {
+            // safety: bin_heads guarantees that the heads are valid for the guard's lifetime.
+            if let Some(BinEntry::Tree(_)) = unsafe { bin.as_ref() } {
+                stats.tree_bins += 1;
+            }
+            stats.bin_lengths.push(Self::bin_len(bin, guard));
+        }
+        stats
+    }
+
+    /// Returns the number of entries in the bin with head `bin`, as returned by `bin_heads`.
+    fn bin_len<'g>(bin: Shared<'g, BinEntry<K, V>>, guard: &'g Guard) -> usize {
+        // safety: bin_heads guarantees that the heads are valid for the guard's lifetime.
+        match unsafe { bin.as_ref() } {
+            None => 0,
+            Some(BinEntry::Node(node)) => Self::chain_len(node, guard),
+            Some(BinEntry::Tree(tree_bin)) => {
+                let first = tree_bin.first.load(Ordering::SeqCst, guard);
+                // safety: the first node of a tree bin lives at least as long as the bin.
+                match unsafe { first.as_ref() } {
+                    Some(BinEntry::TreeNode(tree_node)) => Self::chain_len(&tree_node.node, guard),
+                    Some(_) => unreachable!("the first entry of a tree bin is a TreeNode"),
+                    None => 0,
+                }
+            }
+            Some(BinEntry::TreeNode(_)) => unreachable!("TreeNode cannot be the head of a bin"),
+            Some(BinEntry::Reserved(_)) => 0,
+            Some(BinEntry::Moved) => unreachable!("bin_heads never returns Moved"),
+        }
+    }
+
+    /// Returns the indices of the `n` fullest bins of the map, paired with the number of entries
+    /// in them, fullest first.
+    ///
+    /// Bins with equally many entries are ordered by index, and empty bins are never returned,
+    /// so the result may hold fewer than `n` bins. Logging this every so often is a cheap way to
+    /// notice a hash distribution that is getting worse before lookups slow down noticeably. Like
+    /// [`bin_stats`](HashMap::bin_stats), this walks every bin of the map's table, and helps to
+    /// finish a resize that is in progress first, but it only keeps `n` bins around while doing
+    /// so.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// let guard = map.guard();
+    /// for i in 0..10 {
+    ///     map.insert(i, i, &guard);
+    /// }
+    ///
+    /// let worst = map.worst_bins(3, &guard);
+    /// assert!(!worst.is_empty() && worst.len() <= 3);
+    /// assert!(worst.windows(2).all(|w| w[0].1 >= w[1].1));
+    /// ```
+    pub fn worst_bins(&self, n: usize, guard: &Guard) -> Vec<(usize, usize)> {
+        use std::cmp::Reverse;
+        use std::collections::BinaryHeap;
+
+        self.check_guard(guard);
+        if n == 0 {
+            return Vec::new();
+        }
+        let bins = self.bin_heads(guard);
+        // a min-heap of the fullest bins seen so far, where the bin on top is the first to go.
+        let mut worst = BinaryHeap::with_capacity(n.min(bins.len()) + 1);
+        for (bini, bin) in bins.into_iter().enumerate() {
+            let len = Self::bin_len(bin, guard);
+            if len == 0 {
+                continue;
+            }
+            worst.push(Reverse((len, Reverse(bini))));
+            if worst.len() > n {
+                worst.pop();
+            }
+        }
+        worst
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse((len, Reverse(bini)))| (bini, len))
+            .collect()
+    }
+
+    /// Returns the average number of entries in the bins of the map that are not empty.
+    ///
+    /// This sums up the quality of the hasher for the keys in the map in a single number: it
+    /// stays close to 1 when the keys are spread out evenly over the bins, and grows as they
+    /// cluster in fewer bins. It is cheaper to keep track of than the full distribution that
+    /// [`bin_stats`](HashMap::bin_stats) returns, but just like `bin_stats`, it walks every bin
+    /// of the map's table, and helps to finish a resize that is in progress first. An empty map
+    /// has an average chain length of 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// assert_eq!(map.pin().avg_chain_length(), 0.0);
+    /// map.pin().insert(1, 1);
+    /// assert_eq!(map.pin().avg_chain_length(), 1.0);
+    /// ```
+    pub fn avg_chain_length(&self, guard: &Guard) -> f64 {
+        self.check_guard(guard);
+        let (mut entries, mut occupied) = (0, 0);
+        for bin in self.bin_heads(guard) {
+            let len = Self::bin_len(bin, guard);
+            if len != 0 {
+                entries += len;
+                occupied += 1;
+            }
+        }
+        if occupied == 0 {
+            0.0
+        } else {
+            entries as f64 / occupied as f64
+        }
+    }
+
+    /// Returns a textual description of the map's bins, for debugging and for snapshot tests of
+    /// how entries are laid out.
+    ///
+    /// There is one line for every bin of the table, in index order, that holds the bin's index,
+    /// the number of nodes in it, and the keys of those nodes, in the order they are chained in.
+    /// Bins that have been turned into trees are marked as such. Given the same hasher and the
+    /// same sequence of writes, the result is always the same. Like
+    /// [`bin_stats`](HashMap::bin_stats), this helps to finish a resize that is in progress
+    /// first.
+    ///
+    /// This is only available with the `debug-dump` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "debug-dump")] {
+    /// use flurry::HashMap;
+    /// use std::hash::BuildHasherDefault;
+    /// use std::collections::hash_map::DefaultHasher;
+    ///
+    /// let map = HashMap::<u32, u32, BuildHasherDefault<DefaultHasher>>::default();
+    /// map.pin().insert(1, 1);
+    ///
+    /// let dump = map.pin().dump_structure();
+    /// assert_eq!(dump.lines().count(), map.pin().capacity());
+    /// assert_eq!(dump.lines().filter(|line| line.ends_with("1 [1]")).count(), 1);
+    /// # }
+    /// ```
+    #[cfg(feature = "debug-dump")]
+    pub fn dump_structure(&self, guard: &Guard) -> String
+    where
+        K: Debug,
+    {
+        use std::fmt::Write;
+
+        self.check_guard(guard);
+        let mut dump = String::new();
+        for (bini, bin) in self.bin_heads(guard).into_iter().enumerate() {
+            // safety: bin_heads guarantees that the heads are valid for the guard's lifetime.
+            let (mut next, tree) = match unsafe { bin.as_ref() } {
+                Some(BinEntry::Node(_)) => (bin, false),
+                Some(BinEntry::Tree(tree_bin)) => {
+                    (tree_bin.first.load(Ordering::SeqCst, guard), true)
+                }
+                Some(BinEntry::TreeNode(_)) => unreachable!("TreeNode cannot be the head of a bin"),
+                Some(BinEntry::Moved) => unreachable!("bin_heads never returns Moved"),
+                None | Some(BinEntry::Reserved(_)) => (Shared::null(), false),
+            };
+            let mut keys = Vec::new();
+            // safety: the nodes of a bin are only ever unlinked by swapping next pointers, and
+            // stay valid at least until the guard is dropped.
+            while let Some(entry) = unsafe { next.as_ref() } {
+                let node = match entry {
+                    BinEntry::Node(node) => node,
+                    BinEntry::TreeNode(tree_node) => &tree_node.node,
+                    _ => unreachable!("Nodes can only point to Nodes or TreeNodes"),
+                };
+                keys.push(format!("{:?}", node.key));
+                next = node.next.load(Ordering::SeqCst, guard);
+            }
+            let _ = writeln!(
+                dump,
+                "{}: {}{} [{}]",
+                bini,
+                if tree { "tree " } else { "" },
+                keys.len(),
+                keys.join(", ")
+            );
+        }
+        dump
+    }
+
+    /// An iterator visiting all key-value pairs in arbitrary order, that checks the structure of
+    /// every bin before it visits its entries.
+    ///
+    /// This is a debugging aid for tracking down corruption of the map's internals. Where
+    /// [`iter`](HashMap::iter) would panic or loop forever on a malformed bin, this yields a
+    /// [`BinError`] for the bin instead, skips its entries, and carries on with the next bin. A
+    /// bin is malformed if its entries link back to themselves, if it holds an entry whose hash
+    /// belongs in another bin, or if it mixes up the entries of linked and tree bins. The
+    /// entries are collected up front, which takes time linear in the capacity of the map, and,
+    /// like [`bin_stats`](HashMap::bin_stats), this helps to finish a resize that is in progress
+    /// first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// map.pin().insert(1, "a");
+    /// map.pin().insert(2, "b");
+    ///
+    /// let guard = map.guard();
+    /// let entries: Result<Vec<_>, _> = map.iter_checked(&guard).collect();
+    /// assert_eq!(entries.unwrap().len(), 2);
+    /// ```
+    pub fn iter_checked<'g>(&'g self, guard: &'g Guard) -> IterChecked<'g, K, V> {
+        self.check_guard(guard);
+        let heads = self.bin_heads(guard);
+        let mask = heads.len().wrapping_sub(1) as u64;
+        let mut entries = Vec::new();
+        for (bini, bin) in heads.into_iter().enumerate() {
+            // safety: bin_heads guarantees that the heads are valid for the guard's lifetime.
+            let (first, tree) = match unsafe { bin.as_ref() } {
+                Some(BinEntry::Node(_)) => (bin, false),
+                Some(BinEntry::Tree(tree_bin)) => {
+                    (tree_bin.first.load(Ordering::SeqCst, guard), true)
+                }
+                Some(BinEntry::TreeNode(_)) => {
+                    entries.push(Err(BinError {
+                        bin: bini,
+                        reason: "the head of the bin is a tree node",
+                    }));
+                    continue;
+                }
+                Some(BinEntry::Moved) => unreachable!("bin_heads never returns Moved"),
+                None | Some(BinEntry::Reserved(_)) => continue,
+            };
+            let checked = entries.len();
+            // the entries at `slow` advance at half the pace of those at `p`, so if the bin
+            // has a cycle, `p` eventually catches up with `slow` inside of it.
+            let (mut p, mut slow) = (first, first);
+            let mut advance_slow = false;
+            let error = loop {
+                // safety: the nodes of a bin are only ever unlinked by swapping next pointers,
+                // and stay valid at least until the guard is dropped.
+                let node = match (unsafe { p.as_ref() }, tree) {
+                    (None, _) => break None,
+                    (Some(BinEntry::Node(node)), false) => node,
+                    (Some(BinEntry::TreeNode(tree_node)), true) => &tree_node.node,
+                    (Some(_), false) => {
+                        break Some("a linked bin holds an entry that is not a node")
+                    }
+                    (Some(_), true) => {
+                        break Some("a tree bin holds an entry that is not a tree node")
+                    }
+                };
+                if node.hash & mask != bini as u64 {
+                    break Some("the bin holds an entry that hashes to another bin");
+                }
+                let value = node.value.load(Ordering::SeqCst, guard);
+                // safety: as for the nodes above.
+                entries.push(Ok((&node.key, unsafe { value.deref() })));
+
+                p = node.next.load(Ordering::SeqCst, guard);
+                if advance_slow {
+                    // safety: `slow` trails `p`, so it points to a node we have already visited.
+                    slow = match unsafe { slow.deref() } {
+                        BinEntry::Node(node) => node.next.load(Ordering::SeqCst, guard),
+                        BinEntry::TreeNode(tree_node) => {
+                            tree_node.node.next.load(Ordering::SeqCst, guard)
+                        }
+                        _ => unreachable!("only nodes are visited"),
+                    };
+                }
+                advance_slow = !advance_slow;
+                if !p.is_null() && p == slow {
+                    break Some("the entries of the bin link back to each other");
+                }
+            };
+            if let Some(reason) = error {
+                entries.truncate(checked);
+                entries.push(Err(BinError { bin: bini, reason }));
+            }
+        }
+        IterChecked {
+            entries: entries.into_iter(),
+        }
+    }
+
+    /// Reports, for every bin of the map's table, the bin's index and whether its lock is
+    /// currently held by some thread.
+    ///
+    /// This is a diagnostic tool for finding hot bins in the face of lock contention. It never
+    /// waits for a lock: each lock is probed with a `try_lock`, and a lock that is found free is
+    /// released again right away, so a writer may at most find it taken for that instant. Unlike
+    /// [`bin_stats`](HashMap::bin_stats), it does not help with a resize that is in progress, as
+    /// that would mean waiting for bin locks; bins that have already been moved to a new table
+    /// are reported as unlocked. Since other threads take and release locks all the time, the
+    /// result is only a snapshot, and may be stale by the time it is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// map.pin().insert(1, "a");
+    ///
+    /// let contention = map.pin().bin_lock_contention();
+    /// assert_eq!(contention.len(), map.pin().capacity());
+    /// assert!(contention.iter().all(|&(_, locked)| !locked));
+    /// ```
+    #[cfg(feature = "metrics")]
+    pub fn bin_lock_contention(&self, guard: &Guard) -> Vec<(usize, bool)> {
+        self.check_guard(guard);
+        let table = self.table.load(Ordering::SeqCst, guard);
+        // safety: we loaded the table while epoch was pinned. table won't be deallocated until
+        // next epoch at the earliest.
+        let table = match unsafe { table.as_ref() } {
+            Some(table) => table,
+            None => return Vec::new(),
+        };
+        // parking_lot 0.10 has no way to check a lock without trying to take it.
+        let is_held = |lock: &Mutex<()>| lock.try_lock().is_none();
+        (0..table.len())
+            .map(|i| {
+                // safety: we loaded the bin while epoch was pinned, so it won't be deallocated
+                // until next epoch at the earliest.
+                let locked = match unsafe { table.bin(i, guard).as_ref() } {
+                    None | Some(BinEntry::Moved) => false,
+                    Some(BinEntry::Node(node)) => is_held(&node.lock),
+                    Some(BinEntry::Tree(tree_bin)) => is_held(&tree_bin.lock),
+                    Some(BinEntry::Reserved(lock)) => is_held(lock),
+                    Some(BinEntry::TreeNode(_)) => {
+                        unreachable!("TreeNode cannot be the head of a bin")
+                    }
+                };
+                (i, locked)
+            })
+            .collect()
+    }
+
+    /// Counts the entries in the map, and resets the map's size counter to that count.
+    ///
+    /// The size counter that backs [`len`](HashMap::len) is updated as entries are inserted and
+    /// removed, and so should never disagree with the actual number of entries. Should it drift
+    /// anyway, this walks every bin of the map's table to find the true number of entries, stores
+    /// it in the counter, and returns it. Like [`bin_stats`](HashMap::bin_stats), this takes time
+    /// linear in the capacity of the map, and helps to finish any resize that is in progress.
+    ///
+    /// This does not block other threads from modifying the map. Entries that are inserted or
+    /// removed while the bins are walked may or may not be counted, and the counter can be left
+    /// off by as many such entries, so this is best called while no other thread is writing to
+    /// the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// for i in 0..10 {
+    ///     map.pin().insert(i, i);
+    /// }
+    /// assert_eq!(map.pin().recount(), 10);
+    /// assert_eq!(map.len(), 10);
+    /// ```
+    pub fn recount(&self, guard: &Guard) -> usize {
+        let n = self.bin_stats(guard).bin_lengths.iter().sum();
+        self.count.store(n as isize, Ordering::SeqCst);
+        n
+    }
+}
+
+// ===
+// the following methods never introduce new items (so they do not need the thread-safety bounds),
+// but they _do_ perform lookups, which require hashing and equality.
+// ===
+
+impl<K, V, S> HashMap<K, V, S>
+where
+    K: Hash + Ord,
+    S: BuildHasher,
+{
+    #[inline]
+    pub(crate) fn hash<Q: ?Sized + Hash>(&self, key: &Q) -> u64 {
+        let mut h = self.build_hasher.build_hasher();
+        key.hash(&mut h);
+        h.finish()
+    }
+
+    fn get_node<'g, Q>(&'g self, key: &Q, guard: &'g Guard) -> Option<&'g Node<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+    {
+        self.get_node_hashed(self.hash(key), key, guard)
+    }
+
+    fn get_node_hashed<'g, Q>(&'g self, h: u64, key: &Q, guard: &'g Guard) -> Option<&'g Node<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        let table = self.table.load(Ordering::SeqCst, guard);
+        if table.is_null() {
+            return None;
+        }
+
+        // safety: we loaded the table while epoch was pinned. table won't be deallocated until
+        // next epoch at the earliest.
+        let table = unsafe { table.deref() };
+        if table.is_empty() {
+            return None;
+        }
+
+        let bini = table.bini(h);
+        let bin = table.bin(bini, guard);
+        if bin.is_null() {
+            return None;
+        }
+
+        // safety: bin is a valid pointer.
+        //
+        // there are three cases when a bin pointer is invalidated:
+        //
+        //  1. if the table was resized, bin is a move entry, and the resize has completed. in
+        //     that case, the table (and all its heads) will be dropped in the next epoch
+        //     following that.
+        //  2. if the table is being resized, bin may be swapped with a move entry. the old bin
+        //     will then be dropped in the following epoch after that happens.
+        //  3. when elements are inserted into or removed from the map, bin may be changed into
+        //     or from a TreeBin from or into a regular, linear bin. the old bin will also be
+        //     dropped in the following epoch if that happens.
+        //
+        // in all cases, we held the guard when we got the reference to the bin. if any such
+        // swap happened, it must have happened _after_ we read. since we did the read while
+        // pinning the epoch, the drop must happen in the _next_ epoch (i.e., the one that we
+        // are holding up by holding on to our guard).
+        let node = table.find(unsafe { bin.deref() }, h, key, guard);
+        if node.is_null() {
+            return None;
+        }
+        // safety: we read the bin while pinning the epoch. a bin will never be dropped until the
+        // next epoch after it is removed. since it wasn't removed, and the epoch was pinned, that
+        // cannot be until after we drop our guard.
+        let node = unsafe { node.deref() };
+        let node = match node {
+            BinEntry::Node(ref n) => n,
+            BinEntry::TreeNode(ref tn) => &tn.node,
+            _ => panic!("`Table::find` should always return a Node"),
+        };
+        Some(node)
+    }
+
+    /// Counts a lookup made through one of the public lookup methods, which found `node`.
+    ///
+    /// Lookups that other operations make internally through `get_node` are not counted, so
+    /// that they do not inflate the hit and miss counts of the `metrics` feature or the access
+    /// counts of the `hot-keys` feature.
+    #[inline]
+    fn count_lookup(&self, node: Option<&Node<K, V>>) {
+        #[cfg(feature = "hot-keys")]
+        if let Some(node) = node {
+            node.accesses.fetch_add(1, Ordering::Relaxed);
+        }
+        if node.is_some() {
+            record!(self, hits);
+        } else {
+            record!(self, misses);
+        }
+    }
+
+    /// Returns a reference to the value corresponding to the key, like [`get`](HashMap::get),
+    /// but without counting the lookup, for operations that look up keys internally.
+    pub(crate) fn get_uncounted<'g, Q>(&'g self, key: &Q, guard: &'g Guard) -> Option<&'g V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+    {
+        let node = self.get_node(key, guard)?;
+
+        let v = node.value.load(Ordering::SeqCst, guard);
+        assert!(!v.is_null());
+        // safety: see get
+        unsafe { v.as_ref() }
+    }
+
+    /// Returns `true` if the map contains a value for the specified key.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`Hash`] and [`Ord`] on the borrowed form *must* match those for
+    /// the key type.
+    ///
+    /// [`Ord`]: std::cmp::Ord
+    /// [`Hash`]: std::hash::Hash
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// let mref = map.pin();
+    /// mref.insert(1, "a");
+    /// assert_eq!(mref.contains_key(&1), true);
+    /// assert_eq!(mref.contains_key(&2), false);
+    /// ```
+    pub fn contains_key<Q>(&self, key: &Q, guard: &Guard) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+    {
+        self.check_guard(guard);
+        self.get(key, &guard).is_some()
+    }
+
+    /// Returns a reference to the value corresponding to the key.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`Hash`] and [`Ord`] on the borrowed form *must* match those for
+    /// the key type.
+    ///
+    /// [`Ord`]: std::cmp::Ord
+    /// [`Hash`]: std::hash::Hash
+    ///
+    /// To obtain a `Guard`, use [`HashMap::guard`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// let mref = map.pin();
+    /// mref.insert(1, "a");
+    /// assert_eq!(mref.get(&1), Some(&"a"));
+    /// assert_eq!(mref.get(&2), None);
+    /// ```
+    #[inline]
+    pub fn get<'g, Q>(&'g self, key: &Q, guard: &'g Guard) -> Option<&'g V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+    {
+        self.check_guard(guard);
+        let node = self.get_node(key, guard);
+        self.count_lookup(node);
+        let node = node?;
+
+        let v = node.value.load(Ordering::SeqCst, guard);
+        assert!(!v.is_null());
+        // safety: the lifetime of the reference is bound to the guard
+        // supplied which means that the memory will not be modified
+        // until at least after the guard goes out of scope
+        unsafe { v.as_ref() }
+    }
+
+    /// Looks up each of `keys`, and returns an iterator over the keys paired with the values they
+    /// map to, in the order of `keys`.
+    ///
+    /// Keys that are not in the map are paired with `None`. The lookups happen lazily, one per
+    /// call to `next`, so the values may come from different points in time if the map is
+    /// modified concurrently.
+    ///
+    /// The keys may be any borrowed form of the map's key type, but
+    /// [`Hash`] and [`Ord`] on the borrowed form *must* match those for
+    /// the key type.
+    ///
+    /// [`Ord`]: std::cmp::Ord
+    /// [`Hash`]: std::hash::Hash
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// let guard = map.guard();
+    /// map.insert(1, "a", &guard);
+    /// map.insert(3, "c", &guard);
+    ///
+    /// let found: Vec<_> = map.get_ordered(&[3, 2, 1], &guard).collect();
+    /// assert_eq!(found, [(&3, Some(&"c")), (&2, None), (&1, Some(&"a"))]);
+    /// ```
+    pub fn get_ordered<'g, Q>(
+        &'g self,
+        keys: &'g [Q],
+        guard: &'g Guard,
+    ) -> GetOrdered<'g, Q, K, V, S>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Ord,
+    {
+        self.check_guard(guard);
+        GetOrdered {
+            map: self,
+            keys: keys.iter(),
+            guard,
+        }
+    }
+
+    /// Returns a reference to the value corresponding to the key, given the key's hash.
+    ///
+    /// This works like [`get`](HashMap::get), except that the key is not hashed. Instead, `hash`
+    /// must be the hash that the map's own [`hasher`](HashMap::hasher) produces for `key`. This
+    /// saves hashing the key again when its hash is already known, such as when the same key is
+    /// looked up in several maps that share a hasher.
+    ///
+    /// Passing any other hash does not cause undefined behavior, but the lookup will almost
+    /// certainly look in the wrong bin, and so miss a key that is in the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    /// use std::hash::{BuildHasher, Hash, Hasher};
+    ///
+    /// let map = HashMap::new();
+    /// let mref = map.pin();
+    /// mref.insert(1, "a");
+    ///
+    /// let mut hasher = mref.hasher().build_hasher();
+    /// 1.hash(&mut hasher);
+    /// let hash = hasher.finish();
+    /// assert_eq!(mref.get_prehashed(hash, &1), Some(&"a"));
+    /// ```
+    pub fn get_prehashed<'g, Q>(&'g self, hash: u64, key: &Q, guard: &'g Guard) -> Option<&'g V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.check_guard(guard);
+        let node = self.get_node_hashed(hash, key, guard);
+        self.count_lookup(node);
+        let node = node?;
+
+        let v = node.value.load(Ordering::SeqCst, guard);
+        assert!(!v.is_null());
+        // safety: see get
+        unsafe { v.as_ref() }
+    }
+
+    /// Returns the key-value pair corresponding to `key`.
+    ///
+    /// Returns `None` if this map contains no mapping for `key`.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`Hash`] and [`Ord`] on the borrowed form *must* match those for
+    /// the key type.
+    ///
+    /// [`Ord`]: std::cmp::Ord
+    /// [`Hash`]: std::hash::Hash
+    #[inline]
+    pub fn get_key_value<'g, Q>(&'g self, key: &Q, guard: &'g Guard) -> Option<(&'g K, &'g V)>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+    {
+        self.check_guard(guard);
+        let node = self.get_node(key, guard);
+        self.count_lookup(node);
+        let node = node?;
+
+        let v = node.value.load(Ordering::SeqCst, guard);
+        assert!(!v.is_null());
+        // safety: the lifetime of the reference is bound to the guard
+        // supplied which means that the memory will not be modified
+        // until at least after the guard goes out of scope
+        unsafe { v.as_ref() }.map(|v| (&node.key, v))
+    }
+
+    /// Returns `true` if `key` currently maps to the very same value as `value`, as opposed to
+    /// an equal one.
+    ///
+    /// Every insert or update stores a new value, so this tells whether the entry that `value`
+    /// was read from has been removed or had its value replaced since, which lets optimistic
+    /// algorithms detect concurrent writes to a key without comparing values.
+    ///
+    /// `value` should have been read from this map under `guard`, or under a guard that has
+    /// stayed pinned since: only then is it certain that its memory has not been freed and reused
+    /// for a newer value of the key, which would make this return `true` for a replaced value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// let guard = map.guard();
+    /// map.insert(1, "a", &guard);
+    ///
+    /// let value = map.get(&1, &guard).unwrap();
+    /// assert!(map.is_still_present(&1, value, &guard));
+    /// map.insert(1, "a", &guard);
+    /// assert!(!map.is_still_present(&1, value, &guard));
+    /// ```
+    pub fn is_still_present<Q>(&self, key: &Q, value: &V, guard: &Guard) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+    {
+        matches!(self.get(key, guard), Some(current) if std::ptr::eq(current, value))
+    }
+
+    /// Returns a handle to the value corresponding to the key that keeps the current thread
+    /// pinned for as long as it lives.
+    ///
+    /// Unlike [`get`](HashMap::get), this does not require the caller to manage a [`Guard`]:
+    /// the returned [`ValueRef`] owns one, and dereferences to the value. This makes it possible
+    /// to hold on to a value beyond a single expression without cloning it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// map.pin().insert(1, String::from("a"));
+    ///
+    /// let value = map.get_pinned(&1).unwrap();
+    /// map.pin().remove(&1);
+    /// // the value is still accessible through the handle
+    /// assert_eq!(*value, "a");
+    /// assert!(map.get_pinned(&2).is_none());
+    /// ```
+    pub fn get_pinned<Q>(&self, key: &Q) -> Option<ValueRef<'_, K, V>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+    {
+        let guard = self.guard();
+        let value: *const V = self.get(key, &guard)?;
+        Some(ValueRef {
+            guard,
+            value,
+            _map: PhantomData,
+        })
+    }
+
+    /// Returns a clone of the value corresponding to the key.
+    ///
+    /// The returned value is owned by the caller, and so is not tied to the lifetime of `guard`.
+    /// This is most useful for maps whose values are [`Arc`]s: the clone is then just a new
+    /// reference-counted handle to the same value, which stays usable after the guard is
+    /// dropped and even after the entry is removed from the map.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`Hash`] and [`Ord`] on the borrowed form *must* match those for
+    /// the key type.
+    ///
+    /// [`Ord`]: std::cmp::Ord
+    /// [`Hash`]: std::hash::Hash
+    /// [`Arc`]: std::sync::Arc
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    /// use std::sync::Arc;
+    ///
+    /// let map = HashMap::new();
+    /// map.pin().insert("config", Arc::new(vec![1, 2, 3]));
+    ///
+    /// let config = map.get_cloned("config", &map.guard()).unwrap();
+    /// map.pin().remove("config");
+    /// assert_eq!(*config, vec![1, 2, 3]);
+    /// ```
+    pub fn get_cloned<Q>(&self, key: &Q, guard: &Guard) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+        V: Clone,
+    {
+        self.get(key, guard).cloned()
+    }
+
+    /// Returns the number of entries in the key's bin that have to be visited to find the key,
+    /// counting the entry for the key itself, or `None` if the key is not in the map.
+    ///
+    /// This is useful for finding keys that share their bin with many other keys, and are thus
+    /// slower to look up. The count is the position of the key in its bin's list of entries. For
+    /// bins that have many entries and have been turned into trees, lookups descend the tree
+    /// instead, and so usually visit fewer entries than that.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// let guard = map.guard();
+    /// map.insert(1, "a", &guard);
+    /// assert_eq!(map.probe_length(&1, &guard), Some(1));
+    /// assert_eq!(map.probe_length(&2, &guard), None);
+    /// ```
+    pub fn probe_length<Q>(&self, key: &Q, guard: &Guard) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+    {
+        self.check_guard(guard);
+        let h = self.hash(key);
+        let mut table = self.table.load(Ordering::SeqCst, guard);
+        loop {
+            // safety: we loaded the table while epoch was pinned. table won't be deallocated until
+            // next epoch at the earliest. tables we find through Moved entries stay valid for at
+            // least as long as the table we found them in.
+            let t = unsafe { table.as_ref() }?;
+            if t.is_empty() {
+                return None;
+            }
+
+            let bin = t.bin(t.bini(h), guard);
+            // safety: bins are only dropped in the epoch after they were removed, and we read the
+            // bin while pinning the epoch.
+            let mut entry = match unsafe { bin.as_ref() }? {
+                BinEntry::Moved => {
+                    table = t.next_table(guard);
+                    continue;
+                }
+                BinEntry::Reserved(_) => return None,
+                entry @ BinEntry::Node(_) => Shared::from(entry as *const _),
+                BinEntry::Tree(ref tree_bin) => tree_bin.first.load(Ordering::SeqCst, guard),
+                BinEntry::TreeNode(_) => unreachable!(
+                    "The head of a bin cannot be a TreeNode directly without BinEntry::Tree"
+                ),
+            };
+
+            let mut steps = 0;
+            // safety: the entries of a bin are protected by the guard just like the bin itself.
+            while let Some(e) = unsafe { entry.as_ref() } {
+                let node = match e {
+                    BinEntry::Node(ref node) => node,
+                    BinEntry::TreeNode(ref tree_node) => &tree_node.node,
+                    _ => unreachable!("Nodes can only point to Nodes or TreeNodes"),
+                };
+                steps += 1;
+                if node.hash == h && node.key.borrow() == key {
+                    return Some(steps);
+                }
+                entry = node.next.load(Ordering::SeqCst, guard);
+            }
+            return None;
+        }
+    }
+
+    /// Looks up the value corresponding to the key like [`get`](HashMap::get), and reports how
+    /// the lookup went.
+    ///
+    /// While the map is being resized, the bins that have already been moved to the new table are
+    /// replaced with forwarding entries in the old one, and lookups that land on them continue in
+    /// the new table. The returned [`GetTrace`] tells whether that happened, and how many bins the
+    /// lookup looked at in total. This helps attribute the latency of individual slow lookups.
+    /// Lookups never help with a resize themselves, so they are never slowed down by moving bins.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// let guard = map.guard();
+    /// map.insert(1, "a", &guard);
+    /// let (value, trace) = map.get_traced(&1, &guard);
+    /// assert_eq!(value, Some(&"a"));
+    /// assert_eq!(trace.bins_traversed, 1);
+    /// assert!(!trace.followed_forward);
+    /// ```
+    pub fn get_traced<'g, Q>(&'g self, key: &Q, guard: &'g Guard) -> (Option<&'g V>, GetTrace)
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+    {
+        self.check_guard(guard);
+        let h = self.hash(key);
+        let mut trace = GetTrace::default();
+        let mut table = self.table.load(Ordering::SeqCst, guard);
+        loop {
+            // safety: we loaded the table while epoch was pinned. table won't be deallocated until
+            // next epoch at the earliest. tables we find through Moved entries stay valid for at
+            // least as long as the table we found them in.
+            let t = match unsafe { table.as_ref() } {
+                Some(t) if !t.is_empty() => t,
+                _ => return (None, trace),
+            };
+
+            trace.bins_traversed += 1;
+            let bin = t.bin(t.bini(h), guard);
+            // safety: bins are only dropped in the epoch after they were removed, and we read the
+            // bin while pinning the epoch.
+            let node = match unsafe { bin.as_ref() } {
+                None => return (None, trace),
+                Some(BinEntry::Moved) => {
+                    trace.followed_forward = true;
+                    table = t.next_table(guard);
+                    continue;
+                }
+                Some(entry) => t.find(entry, h, key, guard),
+            };
+            // safety: find returns nodes of the bin, which are protected by the guard just like
+            // the bin itself.
+            let value = unsafe { node.as_ref() }.map(|node| {
+                let node = match node {
+                    BinEntry::Node(ref n) => n,
+                    BinEntry::TreeNode(ref tn) => &tn.node,
+                    _ => panic!("`Table::find` should always return a Node"),
+                };
+                // safety: the value of a node is only dropped after the node is removed, which
+                // happens in a later epoch than the one we have pinned.
+                unsafe { node.value.load(Ordering::SeqCst, guard).deref() }
+            });
+            return (value, trace);
+        }
+    }
+
+    /// Returns the index of the bin that `key` maps to in the map's current table.
+    ///
+    /// This is the key's hash, as computed by the map's hasher, masked by the
+    /// [`capacity`](HashMap::capacity) of the map. The index changes when the map is resized. If
+    /// the map has not allocated its table yet, this returns 0.
+    ///
+    /// The key may be any borrowed form of the map's key type, and maps to the same bin as the
+    /// owned key as long as [`Hash`] on the borrowed form matches that for the key type.
+    ///
+    /// [`Hash`]: std::hash::Hash
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// let guard = map.guard();
+    /// map.insert(String::from("key"), 1, &guard);
+    /// let bin = map.bin_index("key", &guard);
+    /// assert!(bin < map.capacity(&guard));
+    /// assert_eq!(bin, map.bin_index(&String::from("key"), &guard));
+    /// ```
+    pub fn bin_index<Q>(&self, key: &Q, guard: &Guard) -> usize
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash,
+    {
+        self.check_guard(guard);
+        let table = self.table.load(Ordering::SeqCst, guard);
+        // safety: we loaded the table while epoch was pinned. table won't be deallocated until
+        // next epoch at the earliest.
+        match unsafe { table.as_ref() } {
+            Some(t) if !t.is_empty() => t.bini(self.hash(key)),
+            _ => 0,
+        }
+    }
+
+    /// Returns `true` if `a` and `b` map to the same bin in the map's current table.
+    ///
+    /// This is the same as comparing their [`bin_index`](HashMap::bin_index), and so may change
+    /// whenever the map is resized.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// let guard = map.guard();
+    /// map.insert(1, "a", &guard);
+    /// assert!(map.same_bin(&1, &1, &guard));
+    /// ```
+    pub fn same_bin<Q>(&self, a: &Q, b: &Q, guard: &Guard) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash,
+    {
+        self.bin_index(a, guard) == self.bin_index(b, guard)
+    }
+
+    /// Folds `f` over every entry in the bin that `key` maps to, starting from `init`, while
+    /// holding the lock of that bin.
+    ///
+    /// No other thread can modify the bin while `f` runs, so unlike when iterating over the map,
+    /// `f` sees the entries of the bin as they were at a single point in time. This is useful
+    /// when related keys are deliberately placed in the same bin, for example by hashing only a
+    /// prefix they share, and are updated together with
+    /// [`compute_many`](HashMap::compute_many). The bin may also hold unrelated keys that happen
+    /// to map to it, and it does not matter whether `key` itself is in the map. If the map has not
+    /// allocated its table yet, or the bin is empty, `init` is returned as is.
+    ///
+    /// Since writers to the bin are blocked while it runs, `f` should be short and simple, and
+    /// must not attempt to update this map.
+    ///
+    /// The key may be any borrowed form of the map's key type, but [`Hash`] on the borrowed form
+    /// *must* match that for the key type.
+    ///
+    /// [`Hash`]: std::hash::Hash
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// let guard = map.guard();
+    /// map.insert(1, 10, &guard);
+    /// let keys = map.reduce_bin(&1, Vec::new(), |mut keys, k, _| {
+    ///     keys.push(*k);
+    ///     keys
+    /// }, &guard);
+    /// assert_eq!(keys, [1]);
+    /// ```
+    pub fn reduce_bin<Q, F, R>(&self, key: &Q, init: R, mut f: F, guard: &Guard) -> R
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash,
+        F: FnMut(R, &K, &V) -> R,
+    {
+        self.check_guard(guard);
+        let hash = self.hash(key);
+        let mut table = self.table.load(Ordering::SeqCst, guard);
+        loop {
+            // safety: we loaded the table while epoch was pinned. table won't be deallocated
+            // until next epoch at the earliest.
+            let t = match unsafe { table.as_ref() } {
+                Some(t) if !t.is_empty() => t,
+                _ => return init,
+            };
+            let bini = t.bini(hash);
+            let bin = t.bin(bini, guard);
+            // safety: we loaded the bin while epoch was pinned, so it won't be deallocated until
+            // next epoch at the earliest.
+            let lock = match unsafe { bin.as_ref() } {
+                None => return init,
+                Some(BinEntry::Moved) => {
+                    // the bin has been moved to the next table of an ongoing resize.
+                    table = t.next_table(guard);
+                    continue;
+                }
+                Some(BinEntry::Reserved(lock)) => {
+                    // another thread is computing the first entry of this bin. wait until it is
+                    // done, and then look at the bin again.
+                    drop(self.wait_for_bin(lock));
+                    continue;
+                }
+                Some(BinEntry::Node(head)) => &head.lock,
+                Some(BinEntry::Tree(tree_bin)) => &tree_bin.lock,
+                Some(BinEntry::TreeNode(_)) => {
+                    unreachable!("TreeNode cannot be the head of a bin")
+                }
+            };
+            let _held = self.wait_for_bin(lock);
+            if t.bin(bini, guard) != bin {
+                // the bin was changed while we were waiting for its lock.
+                continue;
+            }
+            // safety: as above.
+            let mut p = match unsafe { bin.deref() } {
+                BinEntry::Tree(tree_bin) => tree_bin.first.load(Ordering::SeqCst, guard),
+                _ => bin,
+            };
+            let mut acc = init;
+            // safety: we hold the lock of the bin, so none of its nodes can be removed, and all of
+            // them were reachable from the bin after we pinned the epoch, so they will not be
+            // dropped until after our guard is dropped.
+            while let Some(entry) = unsafe { p.as_ref() } {
+                let node = match entry {
+                    BinEntry::Node(node) => node,
+                    BinEntry::TreeNode(tree_node) => &tree_node.node,
+                    _ => unreachable!("Nodes can only point to Nodes or TreeNodes"),
+                };
+                // safety: as above for the value of the node.
+                let value = unsafe { node.value.load(Ordering::SeqCst, guard).deref() };
+                acc = f(acc, &node.key, value);
+                p = node.next.load(Ordering::SeqCst, guard);
+            }
+            return acc;
+        }
+    }
+
+    /// Returns `true` if inserting a new key into the bin that `key` maps to would make the map
+    /// turn that bin into a tree.
+    ///
+    /// Keys whose hashes collide share a bin, and the entries of a bin are kept in a list until
+    /// there are more than `TREEIFY_THRESHOLD` (8) of them, at which point the bin is turned into
+    /// a balanced tree to keep lookups fast (or, while the map is still small, the map is resized
+    /// instead). Bins should almost never get that long with a good hasher, so this is an early
+    /// warning that the hasher is doing a poor job for the keys in the map. It returns `false` for
+    /// bins that already are trees, since those have no further to degrade, and for maps built
+    /// with [`treeification`](crate::Builder::treeification) disabled.
+    ///
+    /// Whether `key` itself is in the map does not matter, and the result may be stale by the
+    /// time it is returned if other threads are modifying the bin.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// let guard = map.guard();
+    /// map.insert(1, "a", &guard);
+    /// assert!(!map.would_treeify(&1, &guard));
+    /// ```
+    pub fn would_treeify<Q>(&self, key: &Q, guard: &Guard) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash,
+    {
+        self.check_guard(guard);
+        if !self.treeify {
+            return false;
+        }
+        let hash = self.hash(key);
+        let mut table = self.table.load(Ordering::SeqCst, guard);
+        loop {
+            // safety: we loaded the table while epoch was pinned. table won't be deallocated
+            // until next epoch at the earliest.
+            let t = match unsafe { table.as_ref() } {
+                Some(t) if !t.is_empty() => t,
+                _ => return false,
+            };
+            // safety: we loaded the bin while epoch was pinned, so it won't be deallocated until
+            // next epoch at the earliest.
+            return match unsafe { t.bin(t.bini(hash), guard).as_ref() } {
+                Some(BinEntry::Node(node)) => Self::chain_len(node, guard) >= TREEIFY_THRESHOLD,
+                Some(BinEntry::Moved) => {
+                    // the bin has been moved to the next table of an ongoing resize.
+                    table = t.next_table(guard);
+                    continue;
+                }
+                Some(BinEntry::TreeNode(_)) => {
+                    unreachable!("TreeNode cannot be the head of a bin")
+                }
+                None | Some(BinEntry::Tree(_)) | Some(BinEntry::Reserved(_)) => false,
+            };
+        }
+    }
+
+    /// Returns a fingerprint of the map's contents, for cheaply detecting whether the map has
+    /// changed between two points in time.
+    ///
+    /// The fingerprint combines the hashes of all entries, each hashed as a `(key, value)` pair
+    /// with the map's hasher, in a way that does not depend on the order of the entries, so it
+    /// only changes when the contents do. Removing an entry that was inserted restores the
+    /// fingerprint from before the insert.
+    ///
+    /// Since the fingerprint uses the map's hasher, fingerprints are only comparable between maps
+    /// whose hashers hash alike, such as fingerprints of the same map taken at different times.
+    /// Two different contents yield the same fingerprint with a probability of about 2^-64 if the
+    /// hasher spreads its hashes evenly, so a matching fingerprint means the map has almost
+    /// certainly not changed. However, an adversary who can predict the hasher's output can
+    /// construct contents with matching fingerprints, so use a hasher with a random seed, like
+    /// the default one, if that matters.
+    ///
+    /// This walks all entries of the map, and so takes time linear in its size. Like
+    /// [`iter`](HashMap::iter), it does not see the map at a single point in time if it is
+    /// modified concurrently.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// let guard = map.guard();
+    /// map.insert(1, "a", &guard);
+    /// let before = map.content_hash(&guard);
+    ///
+    /// map.insert(1, "b", &guard);
+    /// assert_ne!(map.content_hash(&guard), before);
+    /// map.insert(1, "a", &guard);
+    /// assert_eq!(map.content_hash(&guard), before);
+    /// ```
+    pub fn content_hash(&self, guard: &Guard) -> u64
+    where
+        V: Hash,
+    {
+        self.check_guard(guard);
+        // summing the entry hashes makes the fingerprint independent of the order of the
+        // entries, and lets removals undo inserts exactly.
+        self.iter(guard)
+            .map(|entry| self.hash(&entry))
+            .fold(0, u64::wrapping_add)
+    }
+
+    /// Returns an estimate of the number of distinct values in the map.
+    ///
+    /// Unlike [`distinct_values_iter`](HashMap::distinct_values_iter), which remembers every
+    /// value it has seen, this feeds the hashes of the values into a [HyperLogLog] sketch, and so
+    /// needs only a few kilobytes of memory no matter how large the map is. The price is that the
+    /// count is approximate: the estimate has a standard error of about 1.6%, and is off by more
+    /// than 5% only very rarely. Values whose hashes collide are counted once.
+    ///
+    /// This walks all entries of the map, and so takes time linear in its size. Like
+    /// [`iter`](HashMap::iter), it does not see the map at a single point in time if it is
+    /// modified concurrently.
+    ///
+    /// [HyperLogLog]: https://en.wikipedia.org/wiki/HyperLogLog
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// let guard = map.guard();
+    /// for i in 0..1000 {
+    ///     map.insert(i, i % 10, &guard);
+    /// }
+    /// let estimate = map.approx_distinct_values(&guard);
+    /// assert!((9..=11).contains(&estimate));
+    /// ```
+    pub fn approx_distinct_values(&self, guard: &Guard) -> u64
+    where
+        V: Hash,
+    {
+        self.check_guard(guard);
+        let mut sketch = crate::hll::HyperLogLog::new();
+        for value in self.values(guard) {
+            sketch.insert(self.hash(value));
+        }
+        sketch.estimate()
+    }
+
+    /// Checks the integrity of the chains of entries in every bin of the map.
+    ///
+    /// Removing an entry from the map always unlinks it from its bin right away, so there are
+    /// never any dead entries left behind in the bins for this method to clean up, and it does
+    /// not modify the map. It does however walk all bins, and returns a [`BinError`] for the
+    /// first bin it finds that holds an entry whose hash does not belong to it. Bins that are
+    /// being moved to a new table by a concurrent resize are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// let guard = map.guard();
+    /// for i in 0..100 {
+    ///     map.insert(i, i, &guard);
+    /// }
+    /// for i in 0..90 {
+    ///     map.remove(&i, &guard);
+    /// }
+    /// assert_eq!(map.compact(&guard), Ok(()));
+    /// assert_eq!(map.len(), 10);
+    /// ```
+    pub fn compact(&self, guard: &Guard) -> Result<(), BinError> {
+        self.check_guard(guard);
+        let table = self.table.load(Ordering::SeqCst, guard);
+        // safety: we loaded the table while epoch was pinned. table won't be deallocated until
+        // next epoch at the earliest.
+        let t = match unsafe { table.as_ref() } {
+            Some(t) => t,
+            None => return Ok(()),
+        };
+
+        for i in 0..t.len() {
+            let bin = t.bin(i, guard);
+            // safety: bins are only dropped in the epoch after they were removed, and we read the
+            // bin while pinning the epoch.
+            let mut entry = match unsafe { bin.as_ref() } {
+                None | Some(BinEntry::Moved) | Some(BinEntry::Reserved(_)) => continue,
+                Some(entry @ BinEntry::Node(_)) => Shared::from(entry as *const _),
+                Some(BinEntry::Tree(ref tree_bin)) => tree_bin.first.load(Ordering::SeqCst, guard),
+                Some(BinEntry::TreeNode(_)) => unreachable!(
+                    "The head of a bin cannot be a TreeNode directly without BinEntry::Tree"
+                ),
+            };
+
+            // safety: the entries of a bin are protected by the guard just like the bin itself.
+            while let Some(e) = unsafe { entry.as_ref() } {
+                let node = match e {
+                    BinEntry::Node(ref node) => node,
+                    BinEntry::TreeNode(ref tree_node) => &tree_node.node,
+                    _ => unreachable!("Nodes can only point to Nodes or TreeNodes"),
+                };
+                if t.bini(node.hash) != i {
+                    return Err(BinError {
+                        bin: i,
+                        reason: "the bin holds an entry that hashes to another bin",
+                    });
+                }
+                entry = node.next.load(Ordering::SeqCst, guard);
+            }
+        }
+        Ok(())
+    }
+
+    /// Turns tree bins that have shrunk to at most 6 entries back into linked lists, and returns
+    /// how many bins it converted.
+    ///
+    /// A bin that grows long is turned into a tree to keep lookups fast even with many colliding
+    /// keys. Removals only turn a tree back into a list once it has become very small, so after
+    /// heavy deletion a tree may hold just a handful of entries, and still pay for the memory and
+    /// lookup overhead of the tree. This walks all bins of the map, and converts such trees back,
+    /// just like a resize does when it splits a tree into two small halves.
+    ///
+    /// Bins that are being moved to a new table by a concurrent resize are skipped, since the
+    /// resize converts small trees itself.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// let guard = map.guard();
+    /// map.insert(1, "a", &guard);
+    /// // there are no trees to convert
+    /// assert_eq!(map.maybe_untreeify(&guard), 0);
+    /// ```
+    pub fn maybe_untreeify(&self, guard: &Guard) -> usize
+    where
+        K: Clone,
+    {
+        self.check_guard(guard);
+        let table = self.table.load(Ordering::SeqCst, guard);
+        // safety: we loaded the table while epoch was pinned. table won't be deallocated until
+        // next epoch at the earliest.
+        let t = match unsafe { table.as_ref() } {
+            Some(t) => t,
+            None => return 0,
+        };
+
+        let mut converted = 0;
+        for i in 0..t.len() {
+            let bin = t.bin(i, guard);
+            // safety: bins are only dropped in the epoch after they were removed, and we read the
+            // bin while pinning the epoch.
+            let tree_bin = match unsafe { bin.as_ref() } {
+                Some(BinEntry::Tree(ref tree_bin)) => tree_bin,
+                _ => continue,
+            };
+
+            let bin_lock = self.wait_for_bin(&tree_bin.lock);
+            // need to check that this is _still_ the head
+            if t.bin(i, guard) != bin {
+                // the bin was changed or moved in the meantime, so leave it be
+                continue;
+            }
+            let first = tree_bin.first.load(Ordering::SeqCst, guard);
+            // safety: the first node of a tree bin lives at least as long as the bin.
+            let len = match unsafe { first.as_ref() } {
+                Some(BinEntry::TreeNode(tree_node)) => Self::chain_len(&tree_node.node, guard),
+                Some(_) => unreachable!("the first entry of a tree bin is a TreeNode"),
+                None => 0,
+            };
+            if len > UNTREEIFY_THRESHOLD {
+                continue;
+            }
+
+            let linear_bin = Self::untreeify(first, guard);
+            t.store_bin(i, linear_bin);
+            drop(bin_lock);
+            // the old bin is now garbage, but its values are not, since they get re-used in the
+            // linear bin.
+            // safety: we hold the bin lock, and replaced the bin in the table, so no thread that
+            // pins the epoch after this point can get to the old bin.
+            unsafe { TreeBin::defer_drop_without_values(bin, guard) };
+            converted += 1;
+        }
+        converted
+    }
+
+    /// An iterator visiting the key-value pairs of `self` whose keys are also in `other`, in
+    /// arbitrary order.
+    ///
+    /// This is an inner join of the two maps on their keys, and `other` may hold values of a
+    /// different type. Whichever of the two maps is smaller at the time of the call is walked,
+    /// and every key of it is looked up in the larger map. The entries are always yielded from
+    /// `self`.
+    ///
+    /// The iterator element type is `(&'g K, &'g V)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let names: HashMap<_, _> = vec![(1, "one"), (2, "two")].into_iter().collect();
+    /// let even: HashMap<_, _> = vec![(2, true), (4, true)].into_iter().collect();
+    ///
+    /// let guard = names.guard();
+    /// let joined: Vec<_> = names.intersect_keys(&even, &guard).collect();
+    /// assert_eq!(joined, vec![(&2, &"two")]);
+    /// ```
+    pub fn intersect_keys<'g, V2, S2>(
+        &'g self,
+        other: &'g HashMap<K, V2, S2>,
+        guard: &'g Guard,
+    ) -> IntersectKeys<'g, K, V, S, V2, S2>
+    where
+        S2: BuildHasher,
+    {
+        self.check_guard(guard);
+        other.check_guard(guard);
+        let walk = if self.len() <= other.len() {
+            IntersectWalk::Ours(self.iter(guard))
+        } else {
+            IntersectWalk::Theirs(other.keys(guard))
+        };
+        IntersectKeys {
+            map: self,
+            other,
+            walk,
+            guard,
+        }
+    }
+
+    pub(crate) fn guarded_eq(&self, other: &Self, our_guard: &Guard, their_guard: &Guard) -> bool
+    where
+        V: PartialEq,
+    {
+        self.eq_by(other, |a, b| a == b, our_guard, their_guard)
+    }
+
+    /// Returns `true` if `self` and `other` contain the same keys, and `eq` returns `true` for
+    /// the values each map holds for every one of those keys.
+    ///
+    /// This is useful for comparing maps whose values contain fields that should not take part
+    /// in the comparison, such as timestamps. The comparison stops at the first key that is
+    /// missing from `other` or whose values do not compare equal, and does not look at the
+    /// entries at all if the two maps are of different lengths.
+    ///
+    /// `guard` is used to access `self`, and `other_guard` to access `other`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let a = HashMap::new();
+    /// let b = HashMap::new();
+    /// a.pin().insert("x", (1, "monday"));
+    /// b.pin().insert("x", (1, "tuesday"));
+    ///
+    /// assert_ne!(a, b);
+    /// assert!(a.eq_by(&b, |v1, v2| v1.0 == v2.0, &a.guard(), &b.guard()));
+    /// ```
+    pub fn eq_by<F>(&self, other: &Self, eq: F, guard: &Guard, other_guard: &Guard) -> bool
+    where
+        F: Fn(&V, &V) -> bool,
+    {
+        self.check_guard(guard);
+        other.check_guard(other_guard);
+        if self.len() != other.len() {
+            return false;
+        }
+
+        self.iter(guard)
+            .all(|(key, value)| other.get(key, other_guard).map_or(false, |v| eq(value, v)))
+    }
+}
+
+// ===
+// the following methods only ever _remove_ items, but never introduce them, so they do not need
+// the thread-safety bounds.
+// ===
+
+impl<K, V, S> HashMap<K, V, S>
+where
+    K: Clone + Ord,
+{
+    /// Clears the map, removing all key-value pairs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    ///
+    /// map.pin().insert(1, "a");
+    /// map.pin().clear();
+    /// assert!(map.pin().is_empty());
+    /// ```
+    pub fn clear(&self, guard: &Guard) {
+        // Negative number of deletions
+        let mut delta = 0;
+        let mut idx = 0usize;
+
+        let mut table = self.table.load(Ordering::SeqCst, guard);
+        // Safety: self.table is a valid pointer because we checked it above.
+        while !table.is_null() && idx < unsafe { table.deref() }.len() {
+            let tab = unsafe { table.deref() };
+            let raw_node = tab.bin(idx, guard);
+            if raw_node.is_null() {
+                idx += 1;
+                continue;
+            }
+            // Safety: node is a valid pointer because we checked
+            // it in the above if stmt.
+            match unsafe { raw_node.deref() } {
+                BinEntry::Moved => {
+                    table = self.help_transfer(table, guard);
+                    // start from the first bin again in the new table
+                    idx = 0;
+                }
+                BinEntry::Reserved(ref lock) => {
+                    // another thread is computing the first entry of this bin. wait until it is
+                    // done, and then look at the bin again.
+                    drop(self.wait_for_bin(lock));
+                    continue;
+                }
+                BinEntry::Node(ref node) => {
+                    let head_lock = self.wait_for_bin(&node.lock);
+                    // need to check that this is _still_ the head
+                    let current_head = tab.bin(idx, guard);
+                    if current_head != raw_node {
+                        // nope -- try the bin again
+                        continue;
+                    }
+                    // we now own the bin
+                    // unlink it from the map to prevent others from entering it
+                    // NOTE: The Java code stores the null bin _after_ the loop, and thus also has
+                    // to hold the lock until that point. However, after the store happens new
+                    // threads and threads waiting on the lock will read the new bin, so we can
+                    // drop the lock early and do the counting and garbage collection outside the
+                    // critical section.
+                    tab.store_bin(idx, Shared::null());
+                    drop(head_lock);
+                    // next, walk the nodes of the bin and free the nodes and their values as we go
+                    // note that we do not free the head node yet, since we're holding the lock it contains
+                    let mut p = node.next.load(Ordering::SeqCst, guard);
+                    while !p.is_null() {
+                        delta -= 1;
+                        p = {
+                            // safety: we loaded p under guard, and guard is still pinned, so p has not been dropped.
+                            let node = unsafe { p.deref() }
+                                .as_node()
+                                .expect("entry following Node should always be a Node");
+                            let next = node.next.load(Ordering::SeqCst, guard);
+                            let value = node.value.load(Ordering::SeqCst, guard);
+
+                            // free the node's value
+                            // safety: any thread that sees this p's value must have read the bin before we stored null
+                            // into it above. it must also have pinned the epoch before that time. therefore, the
+                            // defer_destroy below won't be executed until that thread's guard is dropped, at which
+                            // point it holds no outstanding references to the value anyway.
+                            unsafe { self.retire_value(&node.key, value, guard) };
+                            // NOTE: do not use the reference in `node` after this point!
+                            // free the bin entry itself
+                            // safety: same argument as for value above.
+                            unsafe { self.retire_node(p, guard) };
+                            next
+                        };
+                    }
+                    // finally, we can drop the head node and its value
+                    let value = node.value.load(Ordering::SeqCst, guard);
+                    // safety: same as the argument for being allowed to free the nodes beyond the head above
+                    unsafe { self.retire_value(&node.key, value, guard) };
+                    // NOTE: do not use the reference in `node` after this point!
+                    unsafe { self.retire_node(raw_node, guard) };
+                    delta -= 1;
+                    idx += 1;
+                }
+                BinEntry::Tree(ref tree_bin) => {
+                    let bin_lock = self.wait_for_bin(&tree_bin.lock);
+                    // need to check that this is _still_ the correct bin
+                    let current_head = tab.bin(idx, guard);
+                    if current_head != raw_node {
+                        // nope -- try the bin again
+                        continue;
+                    }
+                    // we now own the bin
+                    // unlink it from the map to prevent others from entering it
+                    // NOTE: The Java code stores the null bin _after_ the loop, and thus also has
+                    // to hold the lock until that point. However, after the store happens new
+                    // threads and threads waiting on the lock will read the new bin, so we can
+                    // drop the lock early and do the counting and garbage collection outside the
+                    // critical section.
+                    tab.store_bin(idx, Shared::null());
+                    drop(bin_lock);
+                    // next, walk the nodes of the bin and free their values as we go
+                    let mut p = tree_bin.first.load(Ordering::SeqCst, guard);
+                    while !p.is_null() {
+                        delta -= 1;
+                        p = {
+                            // safety: the TreeBin was read under our guard, at
+                            // which point the tree was valid. Since our guard
+                            // pins the current epoch, the TreeNodes remain
+                            // valid for at least as long as we hold onto the
+                            // guard.
+                            // Structurally, TreeNodes always point to TreeNodes, so this is sound.
+                            let tree_node = unsafe { TreeNode::get_tree_node(p) };
+                            let value = tree_node.node.value.load(Ordering::SeqCst, guard);
+                            // safety: same as in the BinEntry::Node case above
+                            unsafe { self.retire_value(&tree_node.node.key, value, guard) };
+                            // NOTE: we do not drop the TreeNodes here, since
+                            // they will be dropped together with the containing
+                            // TreeBin (`tree_bin`) below
+                            tree_node.node.next.load(Ordering::SeqCst, guard)
+                        };
+                    }
+                    // safety: same as in the BinEntry::Node case above
+                    unsafe { TreeBin::defer_drop_without_values(raw_node, guard) };
+                    idx += 1;
+                }
+                BinEntry::TreeNode(_) => unreachable!(
+                    "The head of a bin cannot be a TreeNode directly without BinEntry::Tree"
+                ),
+            };
+        }
+
+        if delta != 0 {
+            self.add_count(delta, None, guard);
+        }
+    }
+
+    /// Clears the map if `pred` returns `true` for the map's current length, and returns whether
+    /// it did.
+    ///
+    /// This is meant for resetting a map once it has grown too large. The length is read right
+    /// before the map is cleared, but the two do not happen atomically with respect to other
+    /// writers: entries that are inserted after the length was read but before their bin was
+    /// cleared are removed as well, and so the map may hold a few more entries than `pred` saw
+    /// when it is cleared.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// let mref = map.pin();
+    /// mref.insert(1, "a");
+    ///
+    /// assert!(!mref.clear_if(|len| len > 1));
+    /// assert_eq!(mref.len(), 1);
+    ///
+    /// mref.insert(2, "b");
+    /// assert!(mref.clear_if(|len| len > 1));
+    /// assert!(mref.is_empty());
+    /// ```
+    pub fn clear_if<P>(&self, pred: P, guard: &Guard) -> bool
+    where
+        P: FnOnce(usize) -> bool,
+    {
+        self.check_guard(guard);
+        if !pred(self.len()) {
+            return false;
+        }
+        self.clear(guard);
+        true
+    }
+}
+
+// ===
+// the following methods _do_ introduce items into the map, and so must require that the keys and
+// values are thread safe, and can be garbage collected at a later time.
+// ===
+
+impl<K, V, S> HashMap<K, V, S>
+where
+    K: 'static + Sync + Send + Clone + Hash + Ord,
+    V: 'static + Sync + Send,
+    S: BuildHasher,
+{
+    /// Inserts a key-value pair into the map.
+    ///
+    /// If the map did not have this key present, [`None`] is returned.
+    ///
+    /// If the map did have this key present, the value is updated, and the old
+    /// value is returned. The key is left unchanged. See the [std-collections
+    /// documentation] for more.
+    ///
+    /// [`None`]: std::option::Option::None
+    /// [std-collections documentation]: https://doc.rust-lang.org/std/collections/index.html#insert-and-complex-keys
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// assert_eq!(map.pin().insert(37, "a"), None);
+    /// assert_eq!(map.pin().is_empty(), false);
+    ///
+    /// // you can also re-use a map pin like so:
+    /// let mref = map.pin();
+    ///
+    /// mref.insert(37, "b");
+    /// assert_eq!(mref.insert(37, "c"), Some(&"b"));
+    /// assert_eq!(mref.get(&37), Some(&"c"));
+    /// ```
+    pub fn insert<'g>(&'g self, key: K, value: V, guard: &'g Guard) -> Option<&'g V> {
+        self.check_guard(guard);
+        self.put(key, value, false, guard).before()
+    }
+
+    /// Inserts a key-value pair into the map, given the key's hash.
+    ///
+    /// This works like [`insert`](HashMap::insert), except that the key is not hashed. Instead,
+    /// `hash` must be the hash that the map's own [`hasher`](HashMap::hasher) produces for `key`.
+    /// This saves hashing the key again when its hash is already known, such as when the same key
+    /// is inserted into several maps that share a hasher.
+    ///
+    /// The map stores the hash along with the key, and relies on it from then on. Passing any
+    /// other hash does not cause undefined behavior, but it corrupts the map: the key will likely
+    /// not be found by later lookups, and may end up in the map more than once.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    /// use std::hash::{BuildHasher, Hash, Hasher};
+    ///
+    /// let map = HashMap::new();
+    /// let mref = map.pin();
+    ///
+    /// let mut hasher = mref.hasher().build_hasher();
+    /// 37.hash(&mut hasher);
+    /// let hash = hasher.finish();
+    /// assert_eq!(mref.insert_prehashed(hash, 37, "a"), None);
+    /// assert_eq!(mref.insert_prehashed(hash, 37, "b"), Some(&"a"));
+    /// assert_eq!(mref.get(&37), Some(&"b"));
+    /// ```
+    pub fn insert_prehashed<'g>(
+        &'g self,
+        hash: u64,
+        key: K,
+        value: V,
+        guard: &'g Guard,
+    ) -> Option<&'g V> {
+        self.check_guard(guard);
+        self.put_hashed(hash, key, value, false, guard).before()
+    }
+
+    /// Inserts a key-value pair into the map, and returns the old value along with the number of
+    /// entries in the map right after the insert.
+    ///
+    /// This works like [`insert`](HashMap::insert). If the key was not yet present, the length
+    /// is the value that the map's entry count was updated to by this very insert, so unlike a
+    /// separate call to [`len`](HashMap::len), it cannot include inserts or removals that other
+    /// threads made after this one. If the key was present, the insert does not change the entry
+    /// count, and the length is read from it right after the value was replaced.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// let mref = map.pin();
+    ///
+    /// assert_eq!(mref.insert_and_len(37, "a"), (None, 1));
+    /// assert_eq!(mref.insert_and_len(42, "b"), (None, 2));
+    /// assert_eq!(mref.insert_and_len(37, "c"), (Some(&"a"), 2));
+    /// ```
+    pub fn insert_and_len<'g>(
+        &'g self,
+        key: K,
+        value: V,
+        guard: &'g Guard,
+    ) -> (Option<&'g V>, usize) {
+        self.check_guard(guard);
+        match self.put(key, value, false, guard) {
+            PutResult::Inserted { len, .. } => (None, len),
+            PutResult::Replaced { old, .. } => (Some(old), self.len()),
+            PutResult::Exists { .. } => {
+                unreachable!("replacing cannot result in PutResult::Exists")
+            }
+        }
+    }
+
+    /// Inserts a key-value pair into the map unless the key already exists.
+    ///
+    /// If the map does not contain the key, the key-value pair is inserted
+    /// and this method returns `Ok`.
+    ///
+    /// If the map does contain the key, the map is left unchanged and this
+    /// method returns `Err`.
+    ///
+    /// [std-collections documentation]: https://doc.rust-lang.org/std/collections/index.html#insert-and-complex-keys
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::{HashMap, TryInsertError};
+    ///
+    /// let map = HashMap::new();
+    /// let mref = map.pin();
+    ///
+    /// mref.insert(37, "a");
+    /// assert_eq!(
+    ///     mref.try_insert(37, "b"),
+    ///     Err(TryInsertError { current: &"a", not_inserted: &"b"})
+    /// );
+    /// assert_eq!(mref.try_insert(42, "c"), Ok(&"c"));
+    /// assert_eq!(mref.get(&37), Some(&"a"));
+    /// assert_eq!(mref.get(&42), Some(&"c"));
+    /// ```
+    #[inline]
+    pub fn try_insert<'g>(
+        &'g self,
+        key: K,
+        value: V,
+        guard: &'g Guard,
+    ) -> Result<&'g V, TryInsertError<'g, V>> {
+        match self.put(key, value, true, guard) {
+            PutResult::Exists {
+                current,
+                not_inserted,
+            } => Err(TryInsertError {
+                current,
+                not_inserted: *not_inserted,
+            }),
+            PutResult::Inserted { new, .. } => Ok(new),
+            PutResult::Replaced { .. } => {
+                unreachable!("no_replacement cannot result in PutResult::Replaced")
+            }
+        }
+    }
+
+    /// Returns a reference to the value corresponding to the key, and inserts the value that
+    /// `make` produces first if the key is not present.
+    ///
+    /// `make` is only called if the key is not present, and at most once. If it fails, nothing is
+    /// inserted, and its error is returned. The check for the key and the insert happen while the
+    /// key's bin is locked, so if several threads race to initialize the same key, only one of
+    /// them calls `make` at a time, and once one has succeeded, the others return its value
+    /// without calling `make` at all. This makes it suitable for lazily initializing values that
+    /// may fail to be created, such as file handles. Other writes to the same bin are blocked
+    /// while `make` runs, so it should be short, and must not attempt to update this map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// let guard = map.guard();
+    ///
+    /// assert_eq!(map.get_or_try_insert_with(1, || "x".parse::<u32>(), &guard).ok(), None);
+    /// assert_eq!(map.get(&1, &guard), None);
+    /// assert_eq!(map.get_or_try_insert_with(1, || "7".parse::<u32>(), &guard), Ok(&7));
+    /// assert_eq!(map.get_or_try_insert_with(1, || "8".parse::<u32>(), &guard), Ok(&7));
+    /// ```
+    pub fn get_or_try_insert_with<'g, F, E>(
+        &'g self,
+        key: K,
+        make: F,
+        guard: &'g Guard,
+    ) -> Result<&'g V, E>
+    where
+        F: FnOnce() -> Result<V, E>,
+    {
+        self.check_guard(guard);
+        if let Some(value) = self.get_uncounted(&key, guard) {
+            return Ok(value);
+        }
+        let mut error = None;
+        let current = self.compute_locked(
+            &[&key],
+            |current| match current[0] {
+                Some(_) => vec![None],
+                None => match make() {
+                    Ok(value) => vec![Some(Some(value))],
+                    Err(e) => {
+                        error = Some(e);
+                        vec![None]
+                    }
+                },
+            },
+            guard,
+        );
+        match error {
+            Some(e) => Err(e),
+            None => Ok(current[0].expect("the key is present unless make failed")),
+        }
+    }
+
+    /// Inserts a key-value pair into the map, and reports how contended the insert was.
+    ///
+    /// This behaves like [`insert`](HashMap::insert), and returns the old value in the same way,
+    /// alongside the number of times the insert had to retry: because another thread changed the
+    /// key's bin before the insert could, or because the insert had to wait for another thread
+    /// to release the lock of the bin. Consistently high retry counts point to hot bins, which
+    /// may call for a better hasher, a larger map, or spreading hot keys out.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// let guard = map.guard();
+    /// // without other threads, there is no contention
+    /// assert_eq!(map.insert_counted(37, "a", &guard), (None, 0));
+    /// assert_eq!(map.insert_counted(37, "b", &guard), (Some(&"a"), 0));
+    /// ```
+    pub fn insert_counted<'g>(
+        &'g self,
+        key: K,
+        value: V,
+        guard: &'g Guard,
+    ) -> (Option<&'g V>, u32) {
+        self.check_guard(guard);
+        let mut retries = 0;
+        let hash = self.hash(&key);
+        let result = match self.put_with_limits(
+            hash,
+            key,
+            value,
+            false,
+            PutLimits::NONE,
+            &mut retries,
+            guard,
+        ) {
+            Ok(result) => result,
+            Err(_) => unreachable!("no bin can hold usize::MAX entries"),
+        };
+        (result.before(), retries)
+    }
+
+    /// Inserts a key-value pair into the map, unless that would mean waiting for another thread.
+    ///
+    /// This behaves like [`insert`](HashMap::insert), except that if the lock of the key's bin is
+    /// held by another thread, it returns [`WouldBlock`] with the key and value right away
+    /// instead of waiting for the lock. This lets callers with tight latency bounds back off and
+    /// retry on their own schedule.
+    ///
+    /// To never wait on the locks of other bins either, a nonblocking insert does not help with
+    /// an ongoing resize, and does not start one. If the map has grown large enough to resize,
+    /// the next write that may block, such as an [`insert`](HashMap::insert) or a
+    /// [`remove`](HashMap::remove), resizes it instead. A bin that has grown long is only turned
+    /// into a tree if its lock is free right away, and is otherwise left to the next insert into
+    /// it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// let guard = map.guard();
+    /// assert_eq!(map.try_insert_nonblocking(37, "a", &guard), Ok(None));
+    /// assert_eq!(map.try_insert_nonblocking(37, "b", &guard), Ok(Some(&"a")));
+    /// ```
+    pub fn try_insert_nonblocking<'g>(
+        &'g self,
+        key: K,
+        value: V,
+        guard: &'g Guard,
+    ) -> Result<Option<&'g V>, WouldBlock<(K, V)>> {
+        self.check_guard(guard);
+        let limits = PutLimits {
+            max_chain: usize::MAX,
+            blocking: false,
+        };
+        self.put_with_limits(self.hash(&key), key, value, false, limits, &mut 0, guard)
+            .map(|result| result.before())
+            .map_err(WouldBlock)
+    }
+
+    /// Inserts a key-value pair into the map as long as doing so does not grow the map beyond
+    /// `max_len` entries.
+    ///
+    /// If the key is already present, its value is updated and the old value is returned, just
+    /// like with [`insert`](HashMap::insert), regardless of the current length of the map. If the
+    /// key is not present and the map already holds `max_len` or more entries, the map is left
+    /// unchanged and the key and value are handed back in `Err`.
+    ///
+    /// The length check and the insertion are not performed atomically with respect to other
+    /// writers. A thread only inserts once it has observed a length below `max_len`, so the bound
+    /// may be overshot by at most one entry per thread concurrently calling `insert_bounded`. If
+    /// the map is only ever grown through `insert_bounded`, and by at most `n` threads at a time,
+    /// it will thus never hold more than `max_len + n` entries.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// let mref = map.pin();
+    ///
+    /// assert_eq!(mref.insert_bounded(1, "a", 2), Ok(None));
+    /// assert_eq!(mref.insert_bounded(2, "b", 2), Ok(None));
+    /// assert_eq!(mref.insert_bounded(3, "c", 2), Err((3, "c")));
+    /// // existing keys can still be updated
+    /// assert_eq!(mref.insert_bounded(1, "d", 2), Ok(Some(&"a")));
+    /// assert_eq!(mref.len(), 2);
+    /// ```
+    pub fn insert_bounded<'g>(
+        &'g self,
+        key: K,
+        value: V,
+        max_len: usize,
+        guard: &'g Guard,
+    ) -> Result<Option<&'g V>, (K, V)> {
+        self.check_guard(guard);
+        if self.len() >= max_len && self.get_node(&key, guard).is_none() {
+            return Err((key, value));
+        }
+        Ok(self.put(key, value, false, guard).before())
+    }
+
+    fn put<'g>(
+        &'g self,
+        key: K,
+        value: V,
+        no_replacement: bool,
+        guard: &'g Guard,
+    ) -> PutResult<'g, V> {
+        self.put_hashed(self.hash(&key), key, value, no_replacement, guard)
+    }
+
+    fn put_hashed<'g>(
+        &'g self,
+        hash: u64,
+        key: K,
+        value: V,
+        no_replacement: bool,
+        guard: &'g Guard,
+    ) -> PutResult<'g, V> {
+        let limits = PutLimits::NONE;
+        match self.put_with_limits(hash, key, value, no_replacement, limits, &mut 0, guard) {
+            Ok(result) => result,
+            Err(_) => unreachable!("no bin can hold usize::MAX entries"),
+        }
+    }
+
+    /// Like `insert`, except that the key and value are handed back in `Err` if inserting them
+    /// would make a linear bin hold more than `max_chain` entries.
+    ///
+    /// Tree bins are not subject to the limit, so `max_chain` should be at most
+    /// `TREEIFY_THRESHOLD` for it to be upheld everywhere.
+    pub(crate) fn insert_with_chain_limit<'g>(
+        &'g self,
+        key: K,
+        value: V,
+        max_chain: usize,
+        guard: &'g Guard,
+    ) -> Result<Option<&'g V>, (K, V)> {
+        self.check_guard(guard);
+        let limits = PutLimits {
+            max_chain,
+            blocking: true,
+        };
+        self.put_with_limits(self.hash(&key), key, value, false, limits, &mut 0, guard)
+            .map(|result| result.before())
+    }
+
+    /// Inserts the entry unless that would violate `limits`, in which case it is handed back.
+    ///
+    /// Adds the number of times the insert had to start over or to wait for the lock of its bin
+    /// to `retries`.
+    #[allow(clippy::too_many_arguments)]
+    fn put_with_limits<'g>(
+        &'g self,
+        hash: u64,
+        mut key: K,
+        value: V,
+        no_replacement: bool,
+        limits: PutLimits,
+        retries: &mut u32,
+        guard: &'g Guard,
+    ) -> Result<PutResult<'g, V>, (K, V)> {
+        let mut table = self.table.load(Ordering::SeqCst, guard);
+        let mut bin_count;
+        let value = Owned::new(value).into_shared(guard);
+        let mut old_val = None;
+        let mut attempted = false;
+        loop {
+            // safety: see argument below for !is_null case
+            if table.is_null() || unsafe { table.deref() }.is_empty() {
+                table = self.init_table(guard);
+                continue;
+            }
+            if attempted {
+                *retries += 1;
+            }
+            attempted = true;
+
+            // safety: table is a valid pointer.
+            //
+            // we are in one of three cases:
+            //
+            //  1. if table is the one we read before the loop, then we read it while holding the
+            //     guard, so it won't be dropped until after we drop that guard b/c the drop logic
+            //     only queues a drop for the next epoch after removing the table.
+            //
+            //  2. if table is read by init_table, then either we did a load, and the argument is
+            //     as for point 1. or, we allocated a table, in which case the earliest it can be
+            //     deallocated is in the next epoch. we are holding up the epoch by holding the
+            //     guard, so this deref is safe.
+            //
+            //  3. if table is set by a Moved node (below) through help_transfer, it will _either_
+            //     keep using `table` (which is fine by 1. and 2.), or use the `next_table` raw
+            //     pointer from inside the Moved. to see that if a Moved(t) is _read_, then t must
+            //     still be valid, see the safety comment on Table.next_table.
+            let t = unsafe { table.deref() };
+
+            let bini = t.bini(hash);
+            let mut bin = t.bin(bini, guard);
+            if bin.is_null() {
+                // fast path -- bin is empty so stick us at the front
+                let node = self.alloc_node(BinEntry::Node(Node::new(hash, key, value)));
+                let pending = self.stamp(node.as_node().unwrap());
+                let swapped = t.cas_bin(bini, bin, node, guard);
+                drop(pending);
+                match swapped {
+                    Ok(_old_null_ptr) => {
+                        let len = if limits.blocking {
+                            self.add_count(1, Some(0), guard)
+                        } else {
+                            self.add_count_nonblocking(1, guard)
+                        };
+                        guard.flush();
+                        // safety: we have not moved the node's value since we placed it into
+                        // its `Atomic` in the very beginning of the method, so the ref is still
+                        // valid. since the value is not currently marked as garbage, we know it
+                        // will not collected until at least one epoch passes, and since `value`
+                        // was produced under a guard the pins the current epoch, the returned
+                        // reference will remain valid for the guard's lifetime.
+                        return Ok(PutResult::Inserted {
+                            new: unsafe { value.deref() },
+                            len: len.max(0) as usize,
+                        });
+                    }
+                    Err(changed) => {
+                        // another thread beat us to the bin
+                        *retries += 1;
+                        assert!(!changed.current.is_null());
+                        bin = changed.current;
+                        if let BinEntry::Node(node) = *changed.new.into_box() {
+                            key = node.key;
+                        } else {
+                            unreachable!("we declared node and it is a BinEntry::Node");
+                        }
+                    }
+                }
+            }
+
+            // slow path -- bin is non-empty
+
+            // safety: bin is a valid pointer.
+            //
+            // there are three cases when a bin pointer is invalidated:
+            //
+            //  1. if the table was resized, bin is a move entry, and the resize has completed. in
+            //     that case, the table (and all its heads) will be dropped in the next epoch
+            //     following that.
+            //  2. if the table is being resized, bin may be swapped with a move entry. the old bin
+            //     will then be dropped in the following epoch after that happens.
+            //  3. when elements are inserted into or removed from the map, bin may be changed into
+            //     or from a TreeBin from or into a regular, linear bin. the old bin will also be
+            //     dropped in the following epoch if that happens.
+            //
+            // in all cases, we held the guard when we got the reference to the bin. if any such
+            // swap happened, it must have happened _after_ we read. since we did the read while
+            // pinning the epoch, the drop must happen in the _next_ epoch (i.e., the one that we
+            // are holding up by holding on to our guard).
+            match *unsafe { bin.deref() } {
+                BinEntry::Moved if !limits.blocking => {
+                    // helping with the resize would mean taking the locks of other bins, so we
+                    // just follow the bin to the next table instead.
+                    table = t.next_table(guard);
+                    continue;
+                }
+                BinEntry::Moved => {
+                    table = self.help_transfer(table, guard);
+                    continue;
+                }
+                BinEntry::Reserved(ref lock) => {
+                    // another thread is computing the first entry of this bin. wait until it is
+                    // done, and then look at the bin again.
+                    if self.lock_bin(lock, limits.blocking, retries).is_none() {
+                        // safety: we own value and did not share it
+                        let value = *unsafe { value.into_owned().into_box() };
+                        return Err((key, value));
+                    }
+                    continue;
+                }
+                BinEntry::Node(ref head)
+                    if no_replacement && head.hash == hash && head.key == key =>
+                {
+                    // fast path if replacement is disallowed and first bin matches
+                    let v = head.value.load(Ordering::SeqCst, guard);
+                    // safety (for v): since the value is present now, and we've held a guard from
+                    // the beginning of the search, the value cannot be dropped until the next
+                    // epoch, which won't arrive until after we drop our guard.
+                    // safety (for value): since we never inserted the value in the tree, `value`
+                    // is the last remaining pointer to the initial value.
+                    return Ok(PutResult::Exists {
+                        current: unsafe { v.deref() },
+                        not_inserted: unsafe { value.into_owned().into_box() },
+                    });
+                }
+                BinEntry::Node(ref head) => {
+                    // bin is non-empty, need to link into it, so we must take the lock
+                    let head_lock = match self.lock_bin(&head.lock, limits.blocking, retries) {
+                        Some(head_lock) => head_lock,
+                        None => {
+                            // safety: we own value and did not share it
+                            let value = *unsafe { value.into_owned().into_box() };
+                            return Err((key, value));
+                        }
+                    };
+
+                    // need to check that this is _still_ the head
+                    let current_head = t.bin(bini, guard);
+                    if current_head != bin {
+                        // nope -- try again from the start
+                        continue;
+                    }
+
+                    // yes, it is still the head, so we can now "own" the bin
+                    // note that there can still be readers in the bin!
+
+                    bin_count = 1;
+                    let mut p = bin;
+
+                    old_val = loop {
+                        // safety: we read the bin while pinning the epoch. a bin will never be
+                        // dropped until the next epoch after it is removed. since it wasn't
+                        // removed, and the epoch was pinned, that cannot be until after we drop
+                        // our guard.
+                        let n = unsafe { p.deref() }.as_node().unwrap();
+                        if n.hash == hash && n.key == key {
+                            // the key already exists in the map!
+                            let current_value = n.value.load(Ordering::SeqCst, guard);
+
+                            // safety: since the value is present now, and we've held a guard from
+                            // the beginning of the search, the value cannot be dropped until the
+                            // next epoch, which won't arrive until after we drop our guard.
+                            let current_value = unsafe { current_value.deref() };
+
+                            if no_replacement {
+                                // the key is not absent, so don't update because of
+                                // `no_replacement`, we don't use the new value, so we need to clean
+                                // it up and return it back to the caller
+                                // safety: we own value and did not share it
+                                return Ok(PutResult::Exists {
+                                    current: current_value,
+                                    not_inserted: unsafe { value.into_owned().into_box() },
+                                });
+                            } else {
+                                // update the value in the existing node
+                                let _pending = self.stamp(n);
+                                let now_garbage = n.value.swap(value, Ordering::SeqCst, guard);
+                                // NOTE: now_garbage == current_value
+
+                                // safety: need to guarantee that now_garbage is no longer
+                                // reachable. more specifically, no thread that executes _after_
+                                // this line can ever get a reference to now_garbage.
+                                //
+                                // here are the possible cases:
+                                //
+                                //  - another thread already has a reference to now_garbage.
+                                //    they must have read it before the call to swap.
+                                //    because of this, that thread must be pinned to an epoch <=
+                                //    the epoch of our guard. since the garbage is placed in our
+                                //    epoch, it won't be freed until the _next_ epoch, at which
+                                //    point, that thread must have dropped its guard, and with it,
+                                //    any reference to the value.
+                                //  - another thread is about to get a reference to this value.
+                                //    they execute _after_ the swap, and therefore do _not_ get a
+                                //    reference to now_garbage (they get value instead). there are
+                                //    no other ways to get to a value except through its Node's
+                                //    `value` field (which is what we swapped), so freeing
+                                //    now_garbage is fine.
+                                unsafe { guard.defer_destroy(now_garbage) };
+                            }
+                            break Some(current_value);
+                        }
+
+                        // TODO: This Ordering can probably be relaxed due to the Mutex
+                        let next = n.next.load(Ordering::SeqCst, guard);
+                        if next.is_null() {
+                            if bin_count >= limits.max_chain {
+                                // the bin is full, so we hand the entry back instead.
+                                drop(head_lock);
+                                // safety: we own value and did not share it
+                                let value = *unsafe { value.into_owned().into_box() };
+                                return Err((key, value));
+                            }
+
+                            // we're at the end of the bin -- stick the node here!
+                            let node = self.alloc_node(BinEntry::Node(Node::new(hash, key, value)));
+                            let _pending = self.stamp(node.as_node().unwrap());
+                            n.next.store(node, Ordering::SeqCst);
+                            break None;
+                        }
+                        p = next;
+
+                        bin_count += 1;
+                    };
+                    drop(head_lock);
+                }
+                // NOTE: BinEntry::Tree(ref tree_bin) if no_replacement && head.hash == h && &head.key == key
+                // cannot occur as in the Java code, TreeBins have a special, indicator hash value
+                BinEntry::Tree(ref tree_bin) => {
+                    // bin is non-empty, need to link into it, so we must take the lock
+                    let head_lock = match self.lock_bin(&tree_bin.lock, limits.blocking, retries) {
+                        Some(head_lock) => head_lock,
+                        None => {
+                            // safety: we own value and did not share it
+                            let value = *unsafe { value.into_owned().into_box() };
+                            return Err((key, value));
+                        }
+                    };
+
+                    // need to check that this is _still_ the correct bin
+                    let current_head = t.bin(bini, guard);
+                    if current_head != bin {
+                        // nope -- try again from the start
+                        continue;
+                    }
+
+                    // yes, it is still the head, so we can now "own" the bin
+                    // note that there can still be readers in the bin!
+
+                    // we don't actually count bins, just set this low enough
+                    // that we don't try to treeify the bin later
+                    bin_count = 2;
+                    let p = tree_bin.find_or_put_tree_val(hash, key, value, guard);
+                    if p.is_null() {
+                        // no TreeNode was returned, so the key did not previously exist in the
+                        // TreeBin. This means it was successfully put there by the call above
+                        // and we are done.
+                        // the new entry is now the first node of the tree bin
+                        // safety: we hold the bin lock, so the new node is still in the bin.
+                        #[cfg(any(feature = "versioning", feature = "insertion-order"))]
+                        let _ = self.stamp(
+                            &unsafe {
+                                TreeNode::get_tree_node(
+                                    tree_bin.first.load(Ordering::SeqCst, guard),
+                                )
+                            }
+                            .node,
+                        );
+                        break;
+                    }
+                    // safety: the TreeBin was read under our guard, at
+                    // which point the tree structure was valid. Since our
+                    // guard pins the current epoch, the TreeNodes remain
+                    // valid for at least as long as we hold onto the guard.
+                    // Structurally, TreeNodes always point to TreeNodes, so this is sound.
+                    let tree_node = unsafe { TreeNode::get_tree_node(p) };
+                    old_val = {
+                        let current_value = tree_node.node.value.load(Ordering::SeqCst, guard);
+                        // safety: since the value is present now, and we've held a guard from
+                        // the beginning of the search, the value cannot be dropped until the
+                        // next epoch, which won't arrive until after we drop our guard.
+                        let current_value = unsafe { current_value.deref() };
+                        if no_replacement {
+                            // the key is not absent, so don't update because of
+                            // `no_replacement`, we don't use the new value, so we need to clean
+                            // it up and return it back to the caller
+                            // safety: we own value and did not share it
+                            return Ok(PutResult::Exists {
+                                current: current_value,
+                                not_inserted: unsafe { value.into_owned().into_box() },
+                            });
+                        } else {
+                            let _pending = self.stamp(&tree_node.node);
+                            let now_garbage =
+                                tree_node.node.value.swap(value, Ordering::SeqCst, guard);
+                            // NOTE: now_garbage == current_value
+
+                            // safety: need to guarantee that now_garbage is no longer
+                            // reachable. more specifically, no thread that executes _after_
+                            // this line can ever get a reference to now_garbage.
+                            //
+                            // here are the possible cases:
+                            //
+                            //  - another thread already has a reference to now_garbage.
+                            //    they must have read it before the call to swap.
+                            //    because of this, that thread must be pinned to an epoch <=
+                            //    the epoch of our guard. since the garbage is placed in our
+                            //    epoch, it won't be freed until the _next_ epoch, at which
+                            //    point, that thread must have dropped its guard, and with it,
+                            //    any reference to the value.
+                            //  - another thread is about to get a reference to this value.
+                            //    they execute _after_ the swap, and therefore do _not_ get a
+                            //    reference to now_garbage (they get `value` instead). there are
+                            //    no other ways to get to a value except through its Node's
+                            //    `value` field (which is what we swapped), so freeing
+                            //    now_garbage is fine.
+                            unsafe { guard.defer_destroy(now_garbage) };
+                        }
+                        Some(current_value)
+                    };
+                    drop(head_lock);
+                }
+                BinEntry::TreeNode(_) => unreachable!(
+                    "The head of a bin cannot be a TreeNode directly without BinEntry::Tree"
+                ),
+            }
+            // NOTE: the Java code checks `bin_count` here because they also
+            // reach this point if the bin changed while obtaining the lock.
+            // However, our code doesn't (it uses continue) and `bin_count`
+            // _cannot_ be 0 at this point.
+            debug_assert_ne!(bin_count, 0);
+            if bin_count >= TREEIFY_THRESHOLD {
+                self.try_treeify_bin(t, bini, limits.blocking, guard);
+            }
+            if let Some(old_val) = old_val {
+                return Ok(PutResult::Replaced {
+                    old: old_val,
+                    // safety: we have not moved the node's value since we placed it into its
+                    // `Atomic` in the very beginning of the method, so the ref is still valid.
+                    // since the value is not currently marked as garbage, we know it will not
+                    // collected until at least one epoch passes, and since `value` was produced
+                    // under a guard the pins the current epoch, the returned reference will remain
+                    // valid for the guard's lifetime.
+                    new: unsafe { value.deref() },
+                });
+            }
+            break;
+        }
+        // increment count, since we only get here if we did not return an old (updated) value
+        debug_assert!(old_val.is_none());
+        let len = if limits.blocking {
+            self.add_count(1, Some(bin_count), guard)
+        } else {
+            self.add_count_nonblocking(1, guard)
+        };
+        guard.flush();
+        Ok(PutResult::Inserted {
+            // safety: we have not moved the node's value since we placed it into its
+            // `Atomic` in the very beginning of the method, so the ref is still valid.
+            // since the value is not currently marked as garbage, we know it will not
+            // collected until at least one epoch passes, and since `value` was produced
+            // under a guard the pins the current epoch, the returned reference will remain
+            // valid for the guard's lifetime.
+            new: unsafe { value.deref() },
+            len: len.max(0) as usize,
+        })
+    }
+
+    fn put_all<I: Iterator<Item = (K, V)>>(&self, iter: I, guard: &Guard) {
+        for (key, value) in iter {
+            self.put(key, value, false, guard);
+        }
+    }
+
+    /// If the value for the specified `key` is present, attempts to
+    /// compute a new mapping given the key and its current mapped value.
+    ///
+    /// The new mapping is computed by the `remapping_function`, which may
+    /// return `None` to signalize that the mapping should be removed.
+    /// The entire method invocation is performed atomically.
+    /// The supplied function is invoked exactly once per invocation of
+    /// this method if the key is present, else not at all.  Some
+    /// attempted update operations on this map by other threads may be
+    /// blocked while computation is in progress, so the computation
+    /// should be short and simple.
+    ///
+    /// Returns the new value associated with the specified `key`, or `None`
+    /// if no value for the specified `key` is present.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`Hash`] and [`Ord`] on the borrowed form *must* match those for
+    /// the key type.
+    ///
+    /// [`Ord`]: std::cmp::Ord
+    /// [`Hash`]: std::hash::Hash
+    pub fn compute_if_present<'g, Q, F>(
+        &'g self,
+        key: &Q,
+        remapping_function: F,
+        guard: &'g Guard,
+    ) -> Option<&'g V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+        F: FnOnce(&K, &V) -> Option<V>,
+    {
+        self.check_guard(guard);
+        match self.try_compute_if_present(key, remapping_function, true, guard) {
+            Ok(new_val) => new_val,
+            Err(_) => unreachable!("a blocking computation always takes the bin lock"),
+        }
+    }
+
+    /// Computes a new value for `key` like [`compute_if_present`](HashMap::compute_if_present),
+    /// unless that would mean waiting for another thread.
+    ///
+    /// If the lock of the key's bin is held by another thread, this returns [`WouldBlock`] with
+    /// `remapping_function` right away, without having called it. Like
+    /// [`try_insert_nonblocking`](HashMap::try_insert_nonblocking), it does not help with an
+    /// ongoing resize.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// let guard = map.guard();
+    /// map.insert(1, 1, &guard);
+    /// let new = map.try_compute_if_present_nonblocking(&1, |_, v| Some(v + 1), &guard);
+    /// assert_eq!(new.ok(), Some(Some(&2)));
+    /// ```
+    pub fn try_compute_if_present_nonblocking<'g, Q, F>(
+        &'g self,
+        key: &Q,
+        remapping_function: F,
+        guard: &'g Guard,
+    ) -> Result<Option<&'g V>, WouldBlock<F>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+        F: FnOnce(&K, &V) -> Option<V>,
+    {
+        self.check_guard(guard);
+        self.try_compute_if_present(key, remapping_function, false, guard)
+            .map_err(WouldBlock)
+    }
+
+    /// Like `compute_if_present`, except that if `blocking` is `false`, `remapping_function` is
+    /// handed back in `Err` rather than waiting for the lock of the key's bin when another
+    /// thread holds it.
+    fn try_compute_if_present<'g, Q, F>(
+        &'g self,
+        key: &Q,
+        remapping_function: F,
+        blocking: bool,
+        guard: &'g Guard,
+    ) -> Result<Option<&'g V>, F>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+        F: FnOnce(&K, &V) -> Option<V>,
+    {
+        let hash = self.hash(&key);
+
+        let mut table = self.table.load(Ordering::SeqCst, guard);
+        let mut new_val = None;
+        let mut removed_node = false;
+        let mut bin_count;
+        loop {
+            // safety: see argument below for !is_null case
+            if table.is_null() || unsafe { table.deref() }.is_empty() {
+                table = self.init_table(guard);
+                continue;
+            }
+
+            // safety: table is a valid pointer.
+            //
+            // we are in one of three cases:
+            //
+            //  1. if table is the one we read before the loop, then we read it while holding the
+            //     guard, so it won't be dropped until after we drop that guard b/c the drop logic
+            //     only queues a drop for the next epoch after removing the table.
+            //
+            //  2. if table is read by init_table, then either we did a load, and the argument is
+            //     as for point 1. or, we allocated a table, in which case the earliest it can be
+            //     deallocated is in the next epoch. we are holding up the epoch by holding the
+            //     guard, so this deref is safe.
+            //
+            //  3. if table is set by a Moved node (below) through help_transfer, it will _either_
+            //     keep using `table` (which is fine by 1. and 2.), or use the `next_table` raw
+            //     pointer from inside the Moved. to see that if a Moved(t) is _read_, then t must
+            //     still be valid, see the safety comment on Table.next_table.
+            let t = unsafe { table.deref() };
+
+            let bini = t.bini(hash);
+            let bin = t.bin(bini, guard);
+            if bin.is_null() {
+                // fast path -- bin is empty so key is not present
+                return Ok(None);
+            }
+
+            // slow path -- bin is non-empty
+            // safety: bin is a valid pointer.
+            //
+            // there are three cases when a bin pointer is invalidated:
+            //
+            //  1. if the table was resized, bin is a move entry, and the resize has completed. in
+            //     that case, the table (and all its heads) will be dropped in the next epoch
+            //     following that.
+            //  2. if the table is being resized, bin may be swapped with a move entry. the old bin
+            //     will then be dropped in the following epoch after that happens.
+            //  3. when elements are inserted into or removed from the map, bin may be changed into
+            //     or from a TreeBin from or into a regular, linear bin. the old bin will also be
+            //     dropped in the following epoch if that happens.
+            //
+            // in all cases, we held the guard when we got the reference to the bin. if any such
+            // swap happened, it must have happened _after_ we read. since we did the read while
+            // pinning the epoch, the drop must happen in the _next_ epoch (i.e., the one that we
+            // are holding up by holding on to our guard).
+            match *unsafe { bin.deref() } {
+                BinEntry::Moved if !blocking => {
+                    // helping with the resize would mean taking the locks of other bins, so we
+                    // just follow the bin to the next table instead.
+                    table = t.next_table(guard);
+                    continue;
                 }
-                let nt = self.next_table.load(Ordering::SeqCst, guard);
-                if nt.is_null() {
-                    break;
+                BinEntry::Moved => {
+                    table = self.help_transfer(table, guard);
+                    continue;
                 }
-                if self.transfer_index.load(Ordering::SeqCst) <= 0 {
-                    break;
+                BinEntry::Reserved(ref lock) => {
+                    // another thread is computing the first entry of this bin. wait until it is
+                    // done, and then look at the bin again.
+                    match self.lock_bin(lock, blocking, &mut 0) {
+                        Some(reservation_lock) => drop(reservation_lock),
+                        None => return Err(remapping_function),
+                    }
+                    continue;
                 }
+                BinEntry::Node(ref head) => {
+                    // bin is non-empty, need to link into it, so we must take the lock
+                    let head_lock = match self.lock_bin(&head.lock, blocking, &mut 0) {
+                        Some(head_lock) => head_lock,
+                        None => return Err(remapping_function),
+                    };
 
-                // try to join!
-                if self.size_ctl.compare_and_swap(sc, sc + 1, Ordering::SeqCst) == sc {
-                    self.transfer(table, nt, guard);
+                    // need to check that this is _still_ the head
+                    let current_head = t.bin(bini, guard);
+                    if current_head != bin {
+                        // nope -- try again from the start
+                        continue;
+                    }
+
+                    // yes, it is still the head, so we can now "own" the bin
+                    // note that there can still be readers in the bin!
+
+                    bin_count = 1;
+                    let mut p = bin;
+                    let mut pred: Shared<'_, BinEntry<K, V>> = Shared::null();
+
+                    new_val = loop {
+                        // safety: we read the bin while pinning the epoch. a bin will never be
+                        // dropped until the next epoch after it is removed. since it wasn't
+                        // removed, and the epoch was pinned, that cannot be until after we drop
+                        // our guard.
+                        let n = unsafe { p.deref() }.as_node().unwrap();
+                        // TODO: This Ordering can probably be relaxed due to the Mutex
+                        let next = n.next.load(Ordering::SeqCst, guard);
+                        if n.hash == hash && n.key.borrow() == key {
+                            // the key already exists in the map!
+                            let current_value = n.value.load(Ordering::SeqCst, guard);
+
+                            // safety: since the value is present now, and we've held a guard from
+                            // the beginning of the search, the value cannot be dropped until the
+                            // next epoch, which won't arrive until after we drop our guard.
+                            let new_value =
+                                remapping_function(&n.key, unsafe { current_value.deref() });
+
+                            if let Some(value) = new_value {
+                                let value = Owned::new(value).into_shared(guard);
+                                let _pending = self.stamp(n);
+                                let now_garbage = n.value.swap(value, Ordering::SeqCst, guard);
+                                // NOTE: now_garbage == current_value
+
+                                // safety: need to guarantee that now_garbage is no longer
+                                // reachable. more specifically, no thread that executes _after_
+                                // this line can ever get a reference to now_garbage.
+                                //
+                                // here are the possible cases:
+                                //
+                                //  - another thread already has a reference to now_garbage.
+                                //    they must have read it before the call to swap.
+                                //    because of this, that thread must be pinned to an epoch <=
+                                //    the epoch of our guard. since the garbage is placed in our
+                                //    epoch, it won't be freed until the _next_ epoch, at which
+                                //    point, that thread must have dropped its guard, and with it,
+                                //    any reference to the value.
+                                //  - another thread is about to get a reference to this value.
+                                //    they execute _after_ the swap, and therefore do _not_ get a
+                                //    reference to now_garbage (they get value instead). there are
+                                //    no other ways to get to a value except through its Node's
+                                //    `value` field (which is what we swapped), so freeing
+                                //    now_garbage is fine.
+                                unsafe { guard.defer_destroy(now_garbage) };
+
+                                // safety: since the value is present now, and we've held a guard from
+                                // the beginning of the search, the value cannot be dropped until the
+                                // next epoch, which won't arrive until after we drop our guard.
+                                break Some(unsafe { value.deref() });
+                            } else {
+                                removed_node = true;
+                                // remove the BinEntry containing the removed key value pair from the bucket
+                                if !pred.is_null() {
+                                    // either by changing the pointer of the previous BinEntry, if present
+                                    // safety: see remove
+                                    unsafe { pred.deref() }
+                                        .as_node()
+                                        .unwrap()
+                                        .next
+                                        .store(next, Ordering::SeqCst);
+                                } else {
+                                    // or by setting the next node as the first BinEntry if there is no previous entry
+                                    t.store_bin(bini, next);
+                                }
+
+                                // in either case, mark the BinEntry as garbage, since it was just removed
+                                // safety: need to guarantee that the old value is no longer
+                                // reachable. more specifically, no thread that executes _after_
+                                // this line can ever get a reference to val.
+                                //
+                                // here are the possible cases:
+                                //
+                                //  - another thread already has a reference to the old value.
+                                //    they must have read it before the call to store_bin.
+                                //    because of this, that thread must be pinned to an epoch <=
+                                //    the epoch of our guard. since the garbage is placed in our
+                                //    epoch, it won't be freed until the _next_ epoch, at which
+                                //    point, that thread must have dropped its guard, and with it,
+                                //    any reference to the value.
+                                //  - another thread is about to get a reference to this value.
+                                //    they execute _after_ the store_bin, and therefore do _not_ get a
+                                //    reference to the old value. there are no other ways to get to a
+                                //    value except through its Node's `value` field (which is now gone
+                                //    together with the node), so freeing the old value is fine.
+                                unsafe { self.retire_value(&n.key, current_value, guard) };
+                                unsafe { guard.defer_destroy(p) };
+                                break None;
+                            }
+                        }
+
+                        pred = p;
+                        if next.is_null() {
+                            // we're at the end of the bin
+                            break None;
+                        }
+                        p = next;
+
+                        bin_count += 1;
+                    };
+                    drop(head_lock);
+                }
+                BinEntry::Tree(ref tree_bin) => {
+                    // bin is non-empty, need to link into it, so we must take the lock
+                    let bin_lock = match self.lock_bin(&tree_bin.lock, blocking, &mut 0) {
+                        Some(bin_lock) => bin_lock,
+                        None => return Err(remapping_function),
+                    };
+
+                    // need to check that this is _still_ the head
+                    let current_head = t.bin(bini, guard);
+                    if current_head != bin {
+                        // nope -- try again from the start
+                        continue;
+                    }
+
+                    // yes, it is still the head, so we can now "own" the bin
+                    // note that there can still be readers in the bin!
+                    bin_count = 2;
+                    let root = tree_bin.root.load(Ordering::SeqCst, guard);
+                    if root.is_null() {
+                        // TODO: Is this even reachable?
+                        // The Java code has `NULL` checks for this, but in theory it should not be possible to
+                        // encounter a tree that has no root when we have its lock. It should always have at
+                        // least `UNTREEIFY_THRESHOLD` nodes. If it is indeed impossible we should replace
+                        // this with an assertion instead.
+                        break;
+                    }
+                    new_val = {
+                        let p = TreeNode::find_tree_node(root, hash, key, guard);
+                        if p.is_null() {
+                            // the given key is not present in the map
+                            None
+                        } else {
+                            // a node for the given key exists, so we try to update it
+                            // safety: the TreeBin was read under our guard,
+                            // at which point the tree structure was valid.
+                            // Since our guard pins the current epoch, the
+                            // TreeNodes and `p` in particular remain valid
+                            // for at least as long as we hold onto the
+                            // guard.
+                            // Structurally, TreeNodes always point to TreeNodes, so this is sound.
+                            let n = &unsafe { TreeNode::get_tree_node(p) }.node;
+                            let current_value = n.value.load(Ordering::SeqCst, guard);
+
+                            // safety: since the value is present now, and we've held a guard from
+                            // the beginning of the search, the value cannot be dropped until the
+                            // next epoch, which won't arrive until after we drop our guard.
+                            let new_value =
+                                remapping_function(&n.key, unsafe { current_value.deref() });
+
+                            if let Some(value) = new_value {
+                                let value = Owned::new(value).into_shared(guard);
+                                let _pending = self.stamp(n);
+                                let now_garbage = n.value.swap(value, Ordering::SeqCst, guard);
+                                // NOTE: now_garbage == current_value
+
+                                // safety: need to guarantee that now_garbage is no longer
+                                // reachable. more specifically, no thread that executes _after_
+                                // this line can ever get a reference to now_garbage.
+                                //
+                                // here are the possible cases:
+                                //
+                                //  - another thread already has a reference to now_garbage.
+                                //    they must have read it before the call to swap.
+                                //    because of this, that thread must be pinned to an epoch <=
+                                //    the epoch of our guard. since the garbage is placed in our
+                                //    epoch, it won't be freed until the _next_ epoch, at which
+                                //    point, that thread must have dropped its guard, and with it,
+                                //    any reference to the value.
+                                //  - another thread is about to get a reference to this value.
+                                //    they execute _after_ the swap, and therefore do _not_ get a
+                                //    reference to now_garbage (they get value instead). there are
+                                //    no other ways to get to a value except through its Node's
+                                //    `value` field (which is what we swapped), so freeing
+                                //    now_garbage is fine.
+                                unsafe { guard.defer_destroy(now_garbage) };
+                                // safety: since the value is present now, and we've held a guard from
+                                // the beginning of the search, the value cannot be dropped until the
+                                // next epoch, which won't arrive until after we drop our guard.
+                                Some(unsafe { value.deref() })
+                            } else {
+                                removed_node = true;
+                                // remove the BinEntry::TreeNode containing the removed key value pair from the bucket
+                                // also drop the old value stored in the tree node, as it was removed from the map
+                                // safety: `p` is either marked for garbage collection in `remove_tree_node`
+                                // directly, or we will `need_to_untreeify`. In the latter case, we `defer_destroy`
+                                // `p` below, after storing the linear bin. Its value is unreachable either way
+                                // once `p` is, so we retire it below as well. Thus, everything is always marked
+                                // for garbage collection _after_ it becomes unaccessible by other threads.
+                                let need_to_untreeify =
+                                    unsafe { tree_bin.remove_tree_node(p, false, guard) };
+                                if need_to_untreeify {
+                                    let linear_bin = Self::untreeify(
+                                        tree_bin.first.load(Ordering::SeqCst, guard),
+                                        guard,
+                                    );
+                                    t.store_bin(bini, linear_bin);
+                                    // the old bin is now garbage, but its values are not,
+                                    // since they are re-used in the linear bin.
+                                    // safety: in the same way as for `now_garbage` above, any existing
+                                    // references to `bin` must have been obtained before storing the
+                                    // linear bin. These references were obtained while pinning an epoch
+                                    // <= our epoch and have to be dropped before the epoch can advance
+                                    // past the destruction of the old bin. After the store, threads will
+                                    // always see the linear bin, so the cannot obtain new references either.
+                                    //
+                                    // The same holds for `p` and its value, which does not get dropped together
+                                    // with `bin` here since `remove_tree_node` indicated that the bin needs to
+                                    // be untreeified.
+                                    unsafe {
+                                        TreeBin::defer_drop_without_values(bin, guard);
+                                        guard.defer_destroy(p);
+                                    }
+                                }
+                                // safety: see above
+                                unsafe { self.retire_value(&n.key, current_value, guard) };
+                                None
+                            }
+                        }
+                    };
+                    drop(bin_lock);
                 }
-            } else if self.size_ctl.compare_and_swap(sc, rs + 2, Ordering::SeqCst) == sc {
-                // a resize is needed, but has not yet started
-                // TODO: figure out why this is rs + 2, not just rs
-                // NOTE: this also applies to `try_presize`
-                self.transfer(table, Shared::null(), guard);
+                BinEntry::TreeNode(_) => unreachable!(
+                    "The head of a bin cannot be a TreeNode directly without BinEntry::Tree"
+                ),
             }
-
-            // another resize may be needed!
-            count = self.count.load(Ordering::SeqCst);
+            // NOTE: the Java code checks `bin_count` here because they also
+            // reach this point if the bin changed while obtaining the lock.
+            // However, our code doesn't (it uses continue) and making this
+            // break conditional confuses the compiler about how many times we
+            // use the `remapping_function`.
+            debug_assert_ne!(bin_count, 0);
+            break;
+        }
+        if removed_node {
+            // decrement count
+            self.add_count(-1, if blocking { Some(bin_count) } else { None }, guard);
         }
+        guard.flush();
+        Ok(new_val)
     }
 
-    /// Tries to reserve capacity for at least `additional` more elements to be inserted in the
-    /// `HashMap`.
+    /// Atomically computes a new mapping for `key` from its current mapping, if any.
     ///
-    /// The collection may reserve more space to avoid frequent reallocations.
+    /// `f` is given the stored key and value if `key` is present, and `None` otherwise. If it
+    /// returns `Some(value)`, `value` is inserted for `key` or replaces the current value. If it
+    /// returns `None`, the current entry for `key` (if any) is removed. The returned [`Compute`]
+    /// reports which of these happened.
     ///
-    /// # Examples
+    /// The entire method invocation is performed atomically, and the supplied function is
+    /// invoked exactly once. Some attempted update operations on this map by other threads may
+    /// be blocked while computation is in progress, so the computation should be short and
+    /// simple, and must not attempt to update this map.
     ///
-    /// ```
-    /// use flurry::HashMap;
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`Hash`] and [`Ord`] on the borrowed form *must* match those for
+    /// the key type. It is only turned into an owned key if a new entry is inserted.
     ///
-    /// let map: HashMap<&str, i32> = HashMap::new();
+    /// [`Ord`]: std::cmp::Ord
+    /// [`Hash`]: std::hash::Hash
+    ///
+    /// # Examples
     ///
-    /// map.pin().reserve(10);
     /// ```
+    /// use flurry::{Compute, HashMap};
     ///
-    /// # Notes
+    /// let map = HashMap::new();
+    /// let guard = map.guard();
+    /// let increment = |entry: Option<(&_, &u32)>| Some(entry.map_or(1, |(_, v)| v + 1));
     ///
-    /// Reserving does not panic in flurry. If the new size is invalid, no
-    /// reallocation takes place.
-    pub fn reserve(&self, additional: usize, guard: &Guard) {
+    /// assert_eq!(map.compute_full("hits", increment, &guard), Compute::Inserted(&1));
+    /// assert_eq!(
+    ///     map.compute_full("hits", increment, &guard),
+    ///     Compute::Updated { old: &1, new: &2 }
+    /// );
+    /// assert_eq!(map.compute_full("hits", |_| None, &guard), Compute::Removed(&2));
+    /// assert_eq!(map.compute_full("hits", |_| None, &guard), Compute::NoOp);
+    /// ```
+    pub fn compute_full<'g, Q, F>(&'g self, key: &Q, f: F, guard: &'g Guard) -> Compute<'g, V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord + ToOwned<Owned = K>,
+        F: FnOnce(Option<(&K, &V)>) -> Option<V>,
+    {
         self.check_guard(guard);
-        let absolute = self.len() + additional;
-        self.try_presize(absolute, guard);
+        match self.try_compute_full(key, f, true, guard) {
+            Ok(outcome) => outcome,
+            Err(_) => unreachable!("a blocking computation always takes the bin lock"),
+        }
     }
-}
-
-// ===
-// the following methods never introduce new items (so they do not need the thread-safety bounds),
-// but they _do_ perform lookups, which require hashing and equality.
-// ===
 
-impl<K, V, S> HashMap<K, V, S>
-where
-    K: Hash + Ord,
-    S: BuildHasher,
-{
-    #[inline]
-    fn hash<Q: ?Sized + Hash>(&self, key: &Q) -> u64 {
-        let mut h = self.build_hasher.build_hasher();
-        key.hash(&mut h);
-        h.finish()
+    /// Computes a new mapping for `key` like [`compute_full`](HashMap::compute_full), unless that
+    /// would mean waiting for another thread.
+    ///
+    /// If the lock of the key's bin is held by another thread, this returns [`WouldBlock`] with
+    /// `f` right away, without having called it. Like
+    /// [`try_insert_nonblocking`](HashMap::try_insert_nonblocking), it does not help with an
+    /// ongoing resize, and leaves resizing the table to the next write that may block.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::{Compute, HashMap};
+    ///
+    /// let map = HashMap::new();
+    /// let guard = map.guard();
+    /// let outcome = map.try_compute_full_nonblocking("hits", |_| Some(1), &guard);
+    /// assert_eq!(outcome.ok(), Some(Compute::Inserted(&1)));
+    /// ```
+    pub fn try_compute_full_nonblocking<'g, Q, F>(
+        &'g self,
+        key: &Q,
+        f: F,
+        guard: &'g Guard,
+    ) -> Result<Compute<'g, V>, WouldBlock<F>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord + ToOwned<Owned = K>,
+        F: FnOnce(Option<(&K, &V)>) -> Option<V>,
+    {
+        self.check_guard(guard);
+        self.try_compute_full(key, f, false, guard)
+            .map_err(WouldBlock)
     }
 
-    fn get_node<'g, Q>(&'g self, key: &Q, guard: &'g Guard) -> Option<&'g Node<K, V>>
+    /// Like `compute_full`, except that if `blocking` is `false`, `f` is handed back in `Err`
+    /// rather than waiting for the lock of the key's bin when another thread holds it.
+    fn try_compute_full<'g, Q, F>(
+        &'g self,
+        key: &Q,
+        f: F,
+        blocking: bool,
+        guard: &'g Guard,
+    ) -> Result<Compute<'g, V>, F>
     where
         K: Borrow<Q>,
-        Q: ?Sized + Hash + Ord,
+        Q: ?Sized + Hash + Ord + ToOwned<Owned = K>,
+        F: FnOnce(Option<(&K, &V)>) -> Option<V>,
     {
-        let table = self.table.load(Ordering::SeqCst, guard);
-        if table.is_null() {
-            return None;
-        }
+        let hash = self.hash(key);
 
-        // safety: we loaded the table while epoch was pinned. table won't be deallocated until
-        // next epoch at the earliest.
-        let table = unsafe { table.deref() };
-        if table.is_empty() {
-            return None;
-        }
+        let mut table = self.table.load(Ordering::SeqCst, guard);
+        let outcome;
+        let mut bin_count;
+        loop {
+            // safety: see argument below for !is_null case
+            if table.is_null() || unsafe { table.deref() }.is_empty() {
+                table = self.init_table(guard);
+                continue;
+            }
 
-        let h = self.hash(key);
-        let bini = table.bini(h);
-        let bin = table.bin(bini, guard);
-        if bin.is_null() {
-            return None;
+            // safety: table is a valid pointer. see compute_if_present.
+            let t = unsafe { table.deref() };
+
+            let bini = t.bini(hash);
+            let bin = t.bin(bini, guard);
+            if bin.is_null() {
+                // the key is not present, but `f` may want to insert it. we reserve the bin so
+                // that no other thread can fill it while `f` runs.
+                let reservation = Owned::new(BinEntry::Reserved(Mutex::new(()))).into_shared(guard);
+                // safety: we just allocated the reservation, and only free it after replacing it.
+                let reservation_lock =
+                    if let BinEntry::Reserved(ref lock) = unsafe { reservation.deref() } {
+                        lock.lock()
+                    } else {
+                        unreachable!("we declared reservation and it is a BinEntry::Reserved");
+                    };
+                if t.cas_bin(bini, bin, reservation, guard).is_err() {
+                    // someone else got to the bin first -- try again from the start
+                    drop(reservation_lock);
+                    // safety: the reservation never made it into the table, so we own it.
+                    drop(unsafe { reservation.into_owned() });
+                    continue;
+                }
+
+                // if `f` panics, we must not leave the reservation behind, or writers would keep
+                // waiting for it to be replaced.
+                let new_value = match panic::catch_unwind(AssertUnwindSafe(|| f(None))) {
+                    Ok(new_value) => new_value,
+                    Err(payload) => {
+                        t.store_bin(bini, Shared::null());
+                        drop(reservation_lock);
+                        // safety: the reservation is no longer reachable for threads that load
+                        // the bin after the store above. threads that read it before must be
+                        // pinned to an epoch <= ours, so it is not freed until they are done.
+                        unsafe { guard.defer_destroy(reservation) };
+                        panic::resume_unwind(payload);
+                    }
+                };
+                let outcome = match new_value {
+                    Some(value) => {
+                        let value = Owned::new(value).into_shared(guard);
+                        let node =
+                            Owned::new(BinEntry::Node(Node::new(hash, key.to_owned(), value)));
+                        let _pending = self.stamp(node.as_node().unwrap());
+                        t.store_bin(bini, node);
+                        // safety: we have not moved the node's value since we placed it into its
+                        // `Atomic`, and it will not be collected until after our guard is dropped.
+                        Compute::Inserted(unsafe { value.deref() })
+                    }
+                    None => {
+                        t.store_bin(bini, Shared::null());
+                        Compute::NoOp
+                    }
+                };
+                drop(reservation_lock);
+                // safety: as for the panicking case above.
+                unsafe { guard.defer_destroy(reservation) };
+
+                if let Compute::Inserted(_) = outcome {
+                    if blocking {
+                        self.add_count(1, Some(0), guard);
+                    } else {
+                        self.add_count_nonblocking(1, guard);
+                    }
+                }
+                guard.flush();
+                return Ok(outcome);
+            }
+
+            // slow path -- bin is non-empty
+            // safety: bin is a valid pointer. see compute_if_present.
+            match *unsafe { bin.deref() } {
+                BinEntry::Moved if !blocking => {
+                    // helping with the resize would mean taking the locks of other bins, so we
+                    // just follow the bin to the next table instead.
+                    table = t.next_table(guard);
+                    continue;
+                }
+                BinEntry::Moved => {
+                    table = self.help_transfer(table, guard);
+                    continue;
+                }
+                BinEntry::Reserved(ref lock) => {
+                    // another thread is computing the first entry of this bin. wait until it is
+                    // done, and then look at the bin again.
+                    match self.lock_bin(lock, blocking, &mut 0) {
+                        Some(reservation_lock) => drop(reservation_lock),
+                        None => return Err(f),
+                    }
+                    continue;
+                }
+                BinEntry::Node(ref head) => {
+                    // bin is non-empty, need to link into it, so we must take the lock
+                    let head_lock = match self.lock_bin(&head.lock, blocking, &mut 0) {
+                        Some(head_lock) => head_lock,
+                        None => return Err(f),
+                    };
+
+                    // need to check that this is _still_ the head
+                    let current_head = t.bin(bini, guard);
+                    if current_head != bin {
+                        // nope -- try again from the start
+                        continue;
+                    }
+
+                    // yes, it is still the head, so we can now "own" the bin
+                    // note that there can still be readers in the bin!
+                    bin_count = 1;
+                    let mut p = bin;
+                    let mut pred: Shared<'_, BinEntry<K, V>> = Shared::null();
+
+                    outcome = loop {
+                        // safety: we read the bin while pinning the epoch. a bin will never be
+                        // dropped until the next epoch after it is removed. since it wasn't
+                        // removed, and the epoch was pinned, that cannot be until after we drop
+                        // our guard.
+                        let n = unsafe { p.deref() }.as_node().unwrap();
+                        let next = n.next.load(Ordering::SeqCst, guard);
+                        if n.hash == hash && n.key.borrow() == key {
+                            // the key already exists in the map!
+                            let current_value = n.value.load(Ordering::SeqCst, guard);
+                            // safety: since the value is present now, and we've held a guard from
+                            // the beginning of the search, the value cannot be dropped until the
+                            // next epoch, which won't arrive until after we drop our guard.
+                            let old = unsafe { current_value.deref() };
+
+                            if let Some(value) = f(Some((&n.key, old))) {
+                                let value = Owned::new(value).into_shared(guard);
+                                let _pending = self.stamp(n);
+                                let now_garbage = n.value.swap(value, Ordering::SeqCst, guard);
+                                // safety: now_garbage == current_value, which is no longer
+                                // reachable for threads that come after the swap. see
+                                // compute_if_present for the full argument.
+                                unsafe { guard.defer_destroy(now_garbage) };
+                                // safety: as for `old` above.
+                                break Compute::Updated {
+                                    old,
+                                    new: unsafe { value.deref() },
+                                };
+                            }
+
+                            // remove the BinEntry containing the removed key value pair from the bucket
+                            if !pred.is_null() {
+                                // safety: see remove
+                                unsafe { pred.deref() }
+                                    .as_node()
+                                    .unwrap()
+                                    .next
+                                    .store(next, Ordering::SeqCst);
+                            } else {
+                                t.store_bin(bini, next);
+                            }
+                            // safety: the node and its value are no longer reachable for threads
+                            // that come after the unlink. see compute_if_present for the full
+                            // argument.
+                            unsafe {
+                                self.retire_value(&n.key, current_value, guard);
+                                guard.defer_destroy(p);
+                            }
+                            break Compute::Removed(old);
+                        }
+
+                        pred = p;
+                        if next.is_null() {
+                            // we're at the end of the bin, so the key is not present
+                            break match f(None) {
+                                Some(value) => {
+                                    let value = Owned::new(value).into_shared(guard);
+                                    let node = Owned::new(BinEntry::Node(Node::new(
+                                        hash,
+                                        key.to_owned(),
+                                        value,
+                                    )));
+                                    let _pending = self.stamp(node.as_node().unwrap());
+                                    n.next.store(node, Ordering::SeqCst);
+                                    // safety: we have not moved the node's value since we placed
+                                    // it into its `Atomic`, and it will not be collected until
+                                    // after our guard is dropped.
+                                    Compute::Inserted(unsafe { value.deref() })
+                                }
+                                None => Compute::NoOp,
+                            };
+                        }
+                        p = next;
+
+                        bin_count += 1;
+                    };
+                    drop(head_lock);
+                }
+                BinEntry::Tree(ref tree_bin) => {
+                    // bin is non-empty, need to link into it, so we must take the lock
+                    let bin_lock = match self.lock_bin(&tree_bin.lock, blocking, &mut 0) {
+                        Some(bin_lock) => bin_lock,
+                        None => return Err(f),
+                    };
+
+                    // need to check that this is _still_ the head
+                    let current_head = t.bin(bini, guard);
+                    if current_head != bin {
+                        // nope -- try again from the start
+                        continue;
+                    }
+
+                    // we don't actually count bins, just set this low enough
+                    // that we don't try to treeify the bin later
+                    bin_count = 2;
+                    let root = tree_bin.root.load(Ordering::SeqCst, guard);
+                    let p = if root.is_null() {
+                        Shared::null()
+                    } else {
+                        TreeNode::find_tree_node(root, hash, key, guard)
+                    };
+                    outcome = if p.is_null() {
+                        // the given key is not present in the map
+                        match f(None) {
+                            Some(value) => {
+                                let value = Owned::new(value).into_shared(guard);
+                                let existing = tree_bin.find_or_put_tree_val(
+                                    hash,
+                                    key.to_owned(),
+                                    value,
+                                    guard,
+                                );
+                                debug_assert!(existing.is_null(), "we hold the bin lock");
+                                // the new entry is now the first node of the tree bin
+                                // safety: we hold the bin lock, so the new node is still in the bin.
+                                #[cfg(any(feature = "versioning", feature = "insertion-order"))]
+                                let _ = self.stamp(
+                                    &unsafe {
+                                        TreeNode::get_tree_node(
+                                            tree_bin.first.load(Ordering::SeqCst, guard),
+                                        )
+                                    }
+                                    .node,
+                                );
+                                // safety: as for the linear bin.
+                                Compute::Inserted(unsafe { value.deref() })
+                            }
+                            None => Compute::NoOp,
+                        }
+                    } else {
+                        // safety: the TreeBin was read under our guard, at which point the tree
+                        // structure was valid. Since our guard pins the current epoch, the
+                        // TreeNodes and `p` in particular remain valid for at least as long as we
+                        // hold onto the guard.
+                        // Structurally, TreeNodes always point to TreeNodes, so this is sound.
+                        let n = &unsafe { TreeNode::get_tree_node(p) }.node;
+                        let current_value = n.value.load(Ordering::SeqCst, guard);
+                        // safety: as for the linear bin.
+                        let old = unsafe { current_value.deref() };
+
+                        if let Some(value) = f(Some((&n.key, old))) {
+                            let value = Owned::new(value).into_shared(guard);
+                            let _pending = self.stamp(n);
+                            let now_garbage = n.value.swap(value, Ordering::SeqCst, guard);
+                            // safety: as for the linear bin.
+                            unsafe { guard.defer_destroy(now_garbage) };
+                            Compute::Updated {
+                                old,
+                                new: unsafe { value.deref() },
+                            }
+                        } else {
+                            // safety: `p` is either marked for garbage collection in
+                            // `remove_tree_node` directly, or we will `need_to_untreeify`, and
+                            // its value is retired along with it. see compute_if_present for the
+                            // full argument.
+                            let need_to_untreeify =
+                                unsafe { tree_bin.remove_tree_node(p, false, guard) };
+                            if need_to_untreeify {
+                                let linear_bin = Self::untreeify(
+                                    tree_bin.first.load(Ordering::SeqCst, guard),
+                                    guard,
+                                );
+                                t.store_bin(bini, linear_bin);
+                                unsafe {
+                                    TreeBin::defer_drop_without_values(bin, guard);
+                                    guard.defer_destroy(p);
+                                }
+                            }
+                            unsafe { self.retire_value(&n.key, current_value, guard) };
+                            Compute::Removed(old)
+                        }
+                    };
+                    drop(bin_lock);
+                }
+                BinEntry::TreeNode(_) => unreachable!(
+                    "The head of a bin cannot be a TreeNode directly without BinEntry::Tree"
+                ),
+            }
+            if let Compute::Inserted(_) = outcome {
+                if bin_count >= TREEIFY_THRESHOLD {
+                    self.try_treeify_bin(t, bini, blocking, guard);
+                }
+            }
+            break;
         }
-
-        // safety: bin is a valid pointer.
-        //
-        // there are three cases when a bin pointer is invalidated:
-        //
-        //  1. if the table was resized, bin is a move entry, and the resize has completed. in
-        //     that case, the table (and all its heads) will be dropped in the next epoch
-        //     following that.
-        //  2. if the table is being resized, bin may be swapped with a move entry. the old bin
-        //     will then be dropped in the following epoch after that happens.
-        //  3. when elements are inserted into or removed from the map, bin may be changed into
-        //     or from a TreeBin from or into a regular, linear bin. the old bin will also be
-        //     dropped in the following epoch if that happens.
-        //
-        // in all cases, we held the guard when we got the reference to the bin. if any such
-        // swap happened, it must have happened _after_ we read. since we did the read while
-        // pinning the epoch, the drop must happen in the _next_ epoch (i.e., the one that we
-        // are holding up by holding on to our guard).
-        let node = table.find(unsafe { bin.deref() }, h, key, guard);
-        if node.is_null() {
-            return None;
+        match outcome {
+            Compute::Inserted(_) if !blocking => {
+                self.add_count_nonblocking(1, guard);
+            }
+            Compute::Inserted(_) => {
+                self.add_count(1, Some(bin_count), guard);
+            }
+            Compute::Removed(_) => {
+                self.add_count(-1, if blocking { Some(bin_count) } else { None }, guard);
+            }
+            Compute::Updated { .. } | Compute::NoOp => {}
         }
-        // safety: we read the bin while pinning the epoch. a bin will never be dropped until the
-        // next epoch after it is removed. since it wasn't removed, and the epoch was pinned, that
-        // cannot be until after we drop our guard.
-        let node = unsafe { node.deref() };
-        Some(match node {
-            BinEntry::Node(ref n) => n,
-            BinEntry::TreeNode(ref tn) => &tn.node,
-            _ => panic!("`Table::find` should always return a Node"),
-        })
+        guard.flush();
+        Ok(outcome)
     }
 
-    /// Returns `true` if the map contains a value for the specified key.
+    /// Applies a batch of operations to the map, in order, and returns the outcome of each.
     ///
-    /// The key may be any borrowed form of the map's key type, but
-    /// [`Hash`] and [`Ord`] on the borrowed form *must* match those for
-    /// the key type.
+    /// Each [`MapOp`] is applied just like the corresponding single-key method would, so
+    /// operations on the same key see the effects of earlier operations in the batch. The batch
+    /// as a whole is *not* atomic: other threads may observe, or interleave with, the map in
+    /// between any two operations. What a batch saves is the per-operation cost of checking and
+    /// pinning a guard, which makes it a good fit for replaying a log of writes.
     ///
-    /// [`Ord`]: std::cmp::Ord
-    /// [`Hash`]: std::hash::Hash
+    /// The outcome of each operation is reported as a [`Compute`], as with
+    /// [`compute_full`](HashMap::compute_full).
     ///
     /// # Examples
     ///
     /// ```
-    /// use flurry::HashMap;
+    /// use flurry::{Compute, HashMap, MapOp};
     ///
     /// let map = HashMap::new();
-    /// let mref = map.pin();
-    /// mref.insert(1, "a");
-    /// assert_eq!(mref.contains_key(&1), true);
-    /// assert_eq!(mref.contains_key(&2), false);
+    /// let guard = map.guard();
+    /// let outcomes = map.apply_batch(
+    ///     vec![
+    ///         MapOp::Insert(1, 10),
+    ///         MapOp::Update(1, Box::new(|_, v| Some(v + 1))),
+    ///         MapOp::Remove(2),
+    ///     ],
+    ///     &guard,
+    /// );
+    /// assert_eq!(
+    ///     outcomes,
+    ///     vec![
+    ///         Compute::Inserted(&10),
+    ///         Compute::Updated { old: &10, new: &11 },
+    ///         Compute::NoOp,
+    ///     ]
+    /// );
     /// ```
-    pub fn contains_key<Q>(&self, key: &Q, guard: &Guard) -> bool
+    pub fn apply_batch<'g, I>(&'g self, ops: I, guard: &'g Guard) -> Vec<Compute<'g, V>>
     where
-        K: Borrow<Q>,
-        Q: ?Sized + Hash + Ord,
+        K: Clone,
+        I: IntoIterator<Item = MapOp<K, V>>,
     {
         self.check_guard(guard);
-        self.get(key, &guard).is_some()
+        ops.into_iter()
+            .map(|op| match op {
+                MapOp::Insert(key, value) => self.compute_full(&key, |_| Some(value), guard),
+                MapOp::Remove(key) => self.compute_full(&key, |_| None, guard),
+                MapOp::Update(key, f) => {
+                    self.compute_full(&key, |entry| entry.and_then(|(k, v)| f(k, v)), guard)
+                }
+            })
+            .collect()
     }
 
-    /// Returns a reference to the value corresponding to the key.
-    ///
-    /// The key may be any borrowed form of the map's key type, but
-    /// [`Hash`] and [`Ord`] on the borrowed form *must* match those for
-    /// the key type.
-    ///
-    /// [`Ord`]: std::cmp::Ord
-    /// [`Hash`]: std::hash::Hash
-    ///
-    /// To obtain a `Guard`, use [`HashMap::guard`].
-    ///
-    /// # Examples
+    /// Atomically computes new mappings for several keys at once from their current values.
     ///
-    /// ```
-    /// use flurry::HashMap;
-    ///
-    /// let map = HashMap::new();
-    /// let mref = map.pin();
-    /// mref.insert(1, "a");
-    /// assert_eq!(mref.get(&1), Some(&"a"));
-    /// assert_eq!(mref.get(&2), None);
-    /// ```
-    #[inline]
-    pub fn get<'g, Q>(&'g self, key: &Q, guard: &'g Guard) -> Option<&'g V>
-    where
-        K: Borrow<Q>,
-        Q: ?Sized + Hash + Ord,
-    {
-        self.check_guard(guard);
-        let node = self.get_node(key, guard)?;
-
-        let v = node.value.load(Ordering::SeqCst, guard);
-        assert!(!v.is_null());
-        // safety: the lifetime of the reference is bound to the guard
-        // supplied which means that the memory will not be modified
-        // until at least after the guard goes out of scope
-        unsafe { v.as_ref() }
-    }
-
-    /// Returns the key-value pair corresponding to `key`.
+    /// `f` is given the current values of `keys`, in the same order, with `None` for keys that
+    /// are not present. It returns the new values for the keys: `Some(value)` inserts `value`
+    /// for the key in the same position or replaces its current value, and `None` removes the
+    /// key, if present. The returned array holds the values the keys map to afterwards.
     ///
-    /// Returns `None` if this map contains no mapping for `key`.
+    /// While `f` runs and its results are installed, the bins of all the keys are locked, so no
+    /// other thread can modify any of the keys in the meantime. This makes it possible to update
+    /// several related entries consistently, for example to move an amount from one entry to
+    /// another. Readers do not take locks however, and may see some of the new values before
+    /// others. The supplied function is invoked exactly once. Since other updates of the map may
+    /// be blocked while it runs, it should be short and simple, and must not attempt to update
+    /// this map.
     ///
-    /// The key may be any borrowed form of the map's key type, but
-    /// [`Hash`] and [`Ord`] on the borrowed form *must* match those for
-    /// the key type.
+    /// The keys may be any borrowed form of the map's key type, but [`Hash`] and [`Ord`] on the
+    /// borrowed form *must* match those for the key type.
     ///
     /// [`Ord`]: std::cmp::Ord
     /// [`Hash`]: std::hash::Hash
-    #[inline]
-    pub fn get_key_value<'g, Q>(&'g self, key: &Q, guard: &'g Guard) -> Option<(&'g K, &'g V)>
-    where
-        K: Borrow<Q>,
-        Q: ?Sized + Hash + Ord,
-    {
-        self.check_guard(guard);
-        let node = self.get_node(key, guard)?;
-
-        let v = node.value.load(Ordering::SeqCst, guard);
-        assert!(!v.is_null());
-        // safety: the lifetime of the reference is bound to the guard
-        // supplied which means that the memory will not be modified
-        // until at least after the guard goes out of scope
-        unsafe { v.as_ref() }.map(|v| (&node.key, v))
-    }
-
-    pub(crate) fn guarded_eq(&self, other: &Self, our_guard: &Guard, their_guard: &Guard) -> bool
-    where
-        V: PartialEq,
-    {
-        if self.len() != other.len() {
-            return false;
-        }
-
-        self.iter(our_guard)
-            .all(|(key, value)| other.get(key, their_guard).map_or(false, |v| *value == *v))
-    }
-}
-
-// ===
-// the following methods only ever _remove_ items, but never introduce them, so they do not need
-// the thread-safety bounds.
-// ===
-
-impl<K, V, S> HashMap<K, V, S>
-where
-    K: Clone + Ord,
-{
-    /// Clears the map, removing all key-value pairs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the same key appears more than once in `keys`.
     ///
     /// # Examples
     ///
     /// ```
     /// use flurry::HashMap;
     ///
-    /// let map = HashMap::new();
-    ///
-    /// map.pin().insert(1, "a");
-    /// map.pin().clear();
-    /// assert!(map.pin().is_empty());
-    /// ```
-    pub fn clear(&self, guard: &Guard) {
-        // Negative number of deletions
-        let mut delta = 0;
-        let mut idx = 0usize;
-
-        let mut table = self.table.load(Ordering::SeqCst, guard);
-        // Safety: self.table is a valid pointer because we checked it above.
-        while !table.is_null() && idx < unsafe { table.deref() }.len() {
-            let tab = unsafe { table.deref() };
-            let raw_node = tab.bin(idx, guard);
-            if raw_node.is_null() {
-                idx += 1;
-                continue;
-            }
-            // Safety: node is a valid pointer because we checked
-            // it in the above if stmt.
-            match unsafe { raw_node.deref() } {
-                BinEntry::Moved => {
-                    table = self.help_transfer(table, guard);
-                    // start from the first bin again in the new table
-                    idx = 0;
-                }
-                BinEntry::Node(ref node) => {
-                    let head_lock = node.lock.lock();
-                    // need to check that this is _still_ the head
-                    let current_head = tab.bin(idx, guard);
-                    if current_head != raw_node {
-                        // nope -- try the bin again
-                        continue;
-                    }
-                    // we now own the bin
-                    // unlink it from the map to prevent others from entering it
-                    // NOTE: The Java code stores the null bin _after_ the loop, and thus also has
-                    // to hold the lock until that point. However, after the store happens new
-                    // threads and threads waiting on the lock will read the new bin, so we can
-                    // drop the lock early and do the counting and garbage collection outside the
-                    // critical section.
-                    tab.store_bin(idx, Shared::null());
-                    drop(head_lock);
-                    // next, walk the nodes of the bin and free the nodes and their values as we go
-                    // note that we do not free the head node yet, since we're holding the lock it contains
-                    let mut p = node.next.load(Ordering::SeqCst, guard);
-                    while !p.is_null() {
-                        delta -= 1;
-                        p = {
-                            // safety: we loaded p under guard, and guard is still pinned, so p has not been dropped.
-                            let node = unsafe { p.deref() }
-                                .as_node()
-                                .expect("entry following Node should always be a Node");
-                            let next = node.next.load(Ordering::SeqCst, guard);
-                            let value = node.value.load(Ordering::SeqCst, guard);
-                            // NOTE: do not use the reference in `node` after this point!
-
-                            // free the node's value
-                            // safety: any thread that sees this p's value must have read the bin before we stored null
-                            // into it above. it must also have pinned the epoch before that time. therefore, the
-                            // defer_destroy below won't be executed until that thread's guard is dropped, at which
-                            // point it holds no outstanding references to the value anyway.
-                            unsafe { guard.defer_destroy(value) };
-                            // free the bin entry itself
-                            // safety: same argument as for value above.
-                            unsafe { guard.defer_destroy(p) };
-                            next
-                        };
-                    }
-                    // finally, we can drop the head node and its value
-                    let value = node.value.load(Ordering::SeqCst, guard);
-                    // NOTE: do not use the reference in `node` after this point!
-                    // safety: same as the argument for being allowed to free the nodes beyond the head above
-                    unsafe { guard.defer_destroy(value) };
-                    unsafe { guard.defer_destroy(raw_node) };
-                    delta -= 1;
-                    idx += 1;
-                }
-                BinEntry::Tree(ref tree_bin) => {
-                    let bin_lock = tree_bin.lock.lock();
-                    // need to check that this is _still_ the correct bin
-                    let current_head = tab.bin(idx, guard);
-                    if current_head != raw_node {
-                        // nope -- try the bin again
-                        continue;
-                    }
-                    // we now own the bin
-                    // unlink it from the map to prevent others from entering it
-                    // NOTE: The Java code stores the null bin _after_ the loop, and thus also has
-                    // to hold the lock until that point. However, after the store happens new
-                    // threads and threads waiting on the lock will read the new bin, so we can
-                    // drop the lock early and do the counting and garbage collection outside the
-                    // critical section.
-                    tab.store_bin(idx, Shared::null());
-                    drop(bin_lock);
-                    // next, walk the nodes of the bin and count how many values we remove
-                    let mut p = tree_bin.first.load(Ordering::SeqCst, guard);
-                    while !p.is_null() {
-                        delta -= 1;
-                        p = {
-                            // safety: the TreeBin was read under our guard, at
-                            // which point the tree was valid. Since our guard
-                            // pins the current epoch, the TreeNodes remain
-                            // valid for at least as long as we hold onto the
-                            // guard.
-                            // Structurally, TreeNodes always point to TreeNodes, so this is sound.
-                            let tree_node = unsafe { TreeNode::get_tree_node(p) };
-                            // NOTE: we do not drop the TreeNodes or their
-                            // values here, since they will be dropped together
-                            // with the containing TreeBin (`tree_bin`) in its
-                            // `drop`
-                            tree_node.node.next.load(Ordering::SeqCst, guard)
-                        };
-                    }
-                    // safety: same as in the BinEntry::Node case above
-                    unsafe { guard.defer_destroy(raw_node) };
-                    idx += 1;
-                }
-                BinEntry::TreeNode(_) => unreachable!(
-                    "The head of a bin cannot be a TreeNode directly without BinEntry::Tree"
-                ),
-            };
+    /// let accounts = HashMap::new();
+    /// let guard = accounts.guard();
+    /// accounts.insert("alice".to_string(), 100, &guard);
+    /// accounts.insert("bob".to_string(), 50, &guard);
+    ///
+    /// let balances = accounts.compute_many(
+    ///     ["alice", "bob"],
+    ///     |[alice, bob]| [Some(alice.unwrap() - 30), Some(bob.unwrap() + 30)],
+    ///     &guard,
+    /// );
+    /// assert_eq!(balances, [Some(&70), Some(&80)]);
+    /// ```
+    pub fn compute_many<'g, Q, F, const N: usize>(
+        &'g self,
+        keys: [&Q; N],
+        f: F,
+        guard: &'g Guard,
+    ) -> [Option<&'g V>; N]
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord + ToOwned<Owned = K>,
+        F: FnOnce([Option<&V>; N]) -> [Option<V>; N],
+    {
+        self.check_guard(guard);
+        for (i, key) in keys.iter().enumerate() {
+            assert!(
+                !keys[..i].contains(key),
+                "compute_many was given the same key more than once"
+            );
         }
-
-        if delta != 0 {
-            self.add_count(delta, None, guard);
+        let results = self.compute_locked(
+            &keys,
+            |current| {
+                let current = match current.try_into() {
+                    Ok(current) => current,
+                    Err(_) => unreachable!("there is a current value for every key"),
+                };
+                IntoIterator::into_iter(f(current)).map(Some).collect()
+            },
+            guard,
+        );
+        match results.try_into() {
+            Ok(results) => results,
+            Err(_) => unreachable!("there is a result for every key"),
         }
     }
-}
-
-// ===
-// the following methods _do_ introduce items into the map, and so must require that the keys and
-// values are thread safe, and can be garbage collected at a later time.
-// ===
 
-impl<K, V, S> HashMap<K, V, S>
-where
-    K: 'static + Sync + Send + Clone + Hash + Ord,
-    V: 'static + Sync + Send,
-    S: BuildHasher,
-{
-    /// Inserts a key-value pair into the map.
+    /// Replaces the value of `key` with `new`, but only if the current value equals `expected`.
     ///
-    /// If the map did not have this key present, [`None`] is returned.
+    /// Returns a reference to the new value if it was put in place. Otherwise, returns the
+    /// current value that did not equal `expected`, or `None` if `key` is not in the map, and
+    /// drops `new`. The comparison and the replacement happen while holding the lock of the key's
+    /// bin, so no other write to the key can come in between. This makes it possible to build
+    /// optimistic updates: read a value, compute its successor, and retry from the value returned
+    /// in `Err` if some other thread got there first.
     ///
-    /// If the map did have this key present, the value is updated, and the old
-    /// value is returned. The key is left unchanged. See the [std-collections
-    /// documentation] for more.
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`Hash`] and [`Ord`] on the borrowed form *must* match those for
+    /// the key type.
     ///
-    /// [`None`]: std::option::Option::None
-    /// [std-collections documentation]: https://doc.rust-lang.org/std/collections/index.html#insert-and-complex-keys
+    /// [`Ord`]: std::cmp::Ord
+    /// [`Hash`]: std::hash::Hash
     ///
     /// # Examples
     ///
@@ -1608,402 +6796,771 @@ where
     /// use flurry::HashMap;
     ///
     /// let map = HashMap::new();
-    /// assert_eq!(map.pin().insert(37, "a"), None);
-    /// assert_eq!(map.pin().is_empty(), false);
-    ///
-    /// // you can also re-use a map pin like so:
-    /// let mref = map.pin();
+    /// let guard = map.guard();
+    /// map.insert(1, "a", &guard);
     ///
-    /// mref.insert(37, "b");
-    /// assert_eq!(mref.insert(37, "c"), Some(&"b"));
-    /// assert_eq!(mref.get(&37), Some(&"c"));
+    /// assert_eq!(map.compare_exchange(&1, &"a", "b", &guard), Ok(&"b"));
+    /// assert_eq!(map.compare_exchange(&1, &"a", "c", &guard), Err(Some(&"b")));
+    /// assert_eq!(map.compare_exchange(&2, &"a", "c", &guard), Err(None));
     /// ```
-    pub fn insert<'g>(&'g self, key: K, value: V, guard: &'g Guard) -> Option<&'g V> {
+    pub fn compare_exchange<'g, Q>(
+        &'g self,
+        key: &Q,
+        expected: &V,
+        new: V,
+        guard: &'g Guard,
+    ) -> Result<&'g V, Option<&'g V>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord + ToOwned<Owned = K>,
+        V: PartialEq,
+    {
         self.check_guard(guard);
-        self.put(key, value, false, guard).before()
+        let mut exchanged = false;
+        let current = self.compute_locked(
+            &[key],
+            |current| match current[0] {
+                Some(current) if current == expected => {
+                    exchanged = true;
+                    vec![Some(Some(new))]
+                }
+                _ => vec![None],
+            },
+            guard,
+        )[0];
+        match current {
+            Some(new) if exchanged => Ok(new),
+            _ => Err(current),
+        }
     }
 
-    /// Inserts a key-value pair into the map unless the key already exists.
+    /// Replaces the value of `key` with `new`, but only if `pred` returns `true` for the current
+    /// value.
     ///
-    /// If the map does not contain the key, the key-value pair is inserted
-    /// and this method returns `Ok`.
+    /// This generalizes [`compare_exchange`](HashMap::compare_exchange) to any condition on the
+    /// current value. The returned [`ReplaceIf`] holds the old value if it was replaced, and
+    /// otherwise tells whether the key was absent or its value did not satisfy `pred`, in which
+    /// case `new` is dropped. `pred` is called while holding the lock of the key's bin, so no
+    /// other write to the key can come in between it and the replacement, and so it should be
+    /// short and simple.
     ///
-    /// If the map does contain the key, the map is left unchanged and this
-    /// method returns `Err`.
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`Hash`] and [`Ord`] on the borrowed form *must* match those for
+    /// the key type.
     ///
-    /// [std-collections documentation]: https://doc.rust-lang.org/std/collections/index.html#insert-and-complex-keys
+    /// [`Ord`]: std::cmp::Ord
+    /// [`Hash`]: std::hash::Hash
     ///
     /// # Examples
     ///
     /// ```
-    /// use flurry::{HashMap, TryInsertError};
+    /// use flurry::{HashMap, ReplaceIf};
     ///
     /// let map = HashMap::new();
-    /// let mref = map.pin();
+    /// let guard = map.guard();
+    /// map.insert(1, 10, &guard);
     ///
-    /// mref.insert(37, "a");
-    /// assert_eq!(
-    ///     mref.try_insert(37, "b"),
-    ///     Err(TryInsertError { current: &"a", not_inserted: &"b"})
-    /// );
-    /// assert_eq!(mref.try_insert(42, "c"), Ok(&"c"));
-    /// assert_eq!(mref.get(&37), Some(&"a"));
-    /// assert_eq!(mref.get(&42), Some(&"c"));
+    /// assert_eq!(map.replace_if_with(&1, |&v| v < 20, 20, &guard), ReplaceIf::Replaced(&10));
+    /// assert_eq!(map.replace_if_with(&1, |&v| v < 20, 30, &guard), ReplaceIf::Rejected(&20));
+    /// assert_eq!(map.replace_if_with(&2, |&v| v < 20, 30, &guard), ReplaceIf::Absent);
     /// ```
-    #[inline]
-    pub fn try_insert<'g>(
+    pub fn replace_if_with<'g, Q, P>(
         &'g self,
-        key: K,
-        value: V,
+        key: &Q,
+        pred: P,
+        new: V,
         guard: &'g Guard,
-    ) -> Result<&'g V, TryInsertError<'g, V>> {
-        match self.put(key, value, true, guard) {
-            PutResult::Exists {
-                current,
-                not_inserted,
-            } => Err(TryInsertError {
-                current,
-                not_inserted: *not_inserted,
-            }),
-            PutResult::Inserted { new } => Ok(new),
-            PutResult::Replaced { .. } => {
-                unreachable!("no_replacement cannot result in PutResult::Replaced")
+    ) -> ReplaceIf<'g, V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord + ToOwned<Owned = K>,
+        P: FnOnce(&V) -> bool,
+    {
+        self.check_guard(guard);
+        let mut outcome = ReplaceIf::Absent;
+        self.compute_locked(
+            &[key],
+            |current| match current[0] {
+                Some(current) if pred(current) => {
+                    outcome = ReplaceIf::Replaced(current);
+                    vec![Some(Some(new))]
+                }
+                Some(current) => {
+                    outcome = ReplaceIf::Rejected(current);
+                    vec![None]
+                }
+                None => vec![None],
+            },
+            guard,
+        );
+        outcome
+    }
+
+    /// Returns a reference to the value corresponding to the key, after bringing it up to date
+    /// with `migrate`.
+    ///
+    /// If the key is present, `migrate` is given its current value. If it returns `Some(new)`,
+    /// `new` replaces that value in the map, provided no other thread has written the key in the
+    /// meantime, and a reference to `new` is returned. If another thread did write the key
+    /// first, `new` is dropped, and `migrate` is given the value that thread wrote instead. If
+    /// `migrate` returns `None`, the value is left as it is, and returned.
+    ///
+    /// This makes it possible to move stored values to a new format lazily, as they are read,
+    /// rather than all at once. `migrate` should return `None` for values that are already up to
+    /// date, so that reads of those do not write to the map.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`Hash`] and [`Ord`] on the borrowed form *must* match those for
+    /// the key type.
+    ///
+    /// [`Ord`]: std::cmp::Ord
+    /// [`Hash`]: std::hash::Hash
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// let guard = map.guard();
+    /// map.insert("port".to_string(), "8080".to_string(), &guard);
+    ///
+    /// // prefix values that are missing a host
+    /// let migrate = |v: &String| (!v.contains(':')).then(|| format!("localhost:{}", v));
+    /// assert_eq!(map.get_migrating("port", migrate, &guard), Some(&"localhost:8080".to_string()));
+    /// assert_eq!(map.get("port", &guard), Some(&"localhost:8080".to_string()));
+    /// ```
+    pub fn get_migrating<'g, Q, F>(&'g self, key: &Q, migrate: F, guard: &'g Guard) -> Option<&'g V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord + ToOwned<Owned = K>,
+        F: Fn(&V) -> Option<V>,
+    {
+        self.check_guard(guard);
+        loop {
+            let current = self.get(key, guard)?;
+            let new = match migrate(current) {
+                Some(new) => new,
+                None => return Some(current),
+            };
+            let mut migrated = false;
+            let now = self.compute_locked(
+                &[key],
+                |now| match now[0] {
+                    Some(now) if std::ptr::eq(now, current) => {
+                        migrated = true;
+                        vec![Some(Some(new))]
+                    }
+                    _ => vec![None],
+                },
+                guard,
+            )[0];
+            if migrated {
+                return now;
             }
         }
     }
 
-    fn put<'g>(
+    /// Scans all entries of the map, and replaces each value for which `repair` returns
+    /// `Some(fixed)` with `fixed`. Returns the number of values that were replaced.
+    ///
+    /// This is a maintenance pass that brings values that violate some invariant back in line,
+    /// for example by clamping values that are out of range. `repair` should return `None` for
+    /// values that are fine as they are, so that those are not written.
+    ///
+    /// Each fixed value is only put in place if no other thread has written the key since
+    /// `repair` looked at its value. If one has, `repair` is called again on the value that
+    /// thread wrote, until either `repair` accepts the current value or the fix is put in place.
+    /// Keys that are removed in the meantime are skipped. Like with iteration, entries that are
+    /// inserted while the scan is underway may or may not be visited.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// let guard = map.guard();
+    /// map.insert(1, 50, &guard);
+    /// map.insert(2, 150, &guard);
+    ///
+    /// let repaired = map.repair(|_, &v| (v > 100).then(|| 100), &guard);
+    /// assert_eq!(repaired, 1);
+    /// assert_eq!(map.get(&2, &guard), Some(&100));
+    /// ```
+    pub fn repair<F>(&self, repair: F, guard: &Guard) -> usize
+    where
+        F: Fn(&K, &V) -> Option<V>,
+    {
+        self.check_guard(guard);
+        let mut repaired = 0;
+        for (key, value) in self.iter(guard) {
+            let mut current = value;
+            while let Some(fixed) = repair(key, current) {
+                let observed = Shared::from(current as *const V);
+                if self
+                    .replace_node(key, Some(fixed), Some(observed), guard)
+                    .is_some()
+                {
+                    repaired += 1;
+                    break;
+                }
+                // another thread wrote the key first, so check what it wrote instead.
+                current = match self.get_uncounted(key, guard) {
+                    Some(current) => current,
+                    None => break,
+                };
+            }
+        }
+        repaired
+    }
+
+    /// Locks the bins of all `keys`, and computes new mappings for them from their current values
+    /// with `f`, like `compute_many`.
+    ///
+    /// `f` returns one entry per key: `None` leaves the key as it is, `Some(None)` removes it, and
+    /// `Some(Some(value))` maps it to `value`. The keys must be distinct.
+    pub(crate) fn compute_locked<'g, Q, F>(
         &'g self,
-        mut key: K,
-        value: V,
-        no_replacement: bool,
+        keys: &[&Q],
+        f: F,
         guard: &'g Guard,
-    ) -> PutResult<'g, V> {
-        let hash = self.hash(&key);
+    ) -> Vec<Option<&'g V>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord + ToOwned<Owned = K>,
+        F: FnOnce(Vec<Option<&'g V>>) -> Vec<Option<Option<V>>>,
+    {
+        let hashes: Vec<_> = keys.iter().map(|key| self.hash(*key)).collect();
+        let (t, locked) = self.lock_bins(&hashes, guard);
+        self.write_locked(t, locked, keys, &hashes, f, guard)
+    }
+
+    /// Locks the bins of all the keys with the given `hashes`, and returns the table they are in.
+    ///
+    /// The bins stay locked until they are handed to `write_locked` or `release_bins`.
+    pub(crate) fn lock_bins<'g>(
+        &'g self,
+        hashes: &[u64],
+        guard: &'g Guard,
+    ) -> (&'g Table<K, V>, Vec<LockedBin<'g, K, V>>) {
+        // we always lock the bins in order of their index, so that two threads that both lock
+        // multiple bins cannot deadlock.
         let mut table = self.table.load(Ordering::SeqCst, guard);
-        let mut bin_count;
-        let value = Owned::new(value).into_shared(guard);
-        let mut old_val = None;
-        loop {
+        let (t, locked) = 'lock: loop {
             // safety: see argument below for !is_null case
             if table.is_null() || unsafe { table.deref() }.is_empty() {
                 table = self.init_table(guard);
                 continue;
             }
 
-            // safety: table is a valid pointer.
-            //
-            // we are in one of three cases:
-            //
-            //  1. if table is the one we read before the loop, then we read it while holding the
-            //     guard, so it won't be dropped until after we drop that guard b/c the drop logic
-            //     only queues a drop for the next epoch after removing the table.
-            //
-            //  2. if table is read by init_table, then either we did a load, and the argument is
-            //     as for point 1. or, we allocated a table, in which case the earliest it can be
-            //     deallocated is in the next epoch. we are holding up the epoch by holding the
-            //     guard, so this deref is safe.
-            //
-            //  3. if table is set by a Moved node (below) through help_transfer, it will _either_
-            //     keep using `table` (which is fine by 1. and 2.), or use the `next_table` raw
-            //     pointer from inside the Moved. to see that if a Moved(t) is _read_, then t must
-            //     still be valid, see the safety comment on Table.next_table.
+            // safety: table is a valid pointer. see compute_if_present.
             let t = unsafe { table.deref() };
 
-            let bini = t.bini(hash);
-            let mut bin = t.bin(bini, guard);
-            if bin.is_null() {
-                // fast path -- bin is empty so stick us at the front
-                let node = Owned::new(BinEntry::Node(Node::new(hash, key, value)));
-                match t.cas_bin(bini, bin, node, guard) {
-                    Ok(_old_null_ptr) => {
-                        self.add_count(1, Some(0), guard);
-                        guard.flush();
-                        // safety: we have not moved the node's value since we placed it into
-                        // its `Atomic` in the very beginning of the method, so the ref is still
-                        // valid. since the value is not currently marked as garbage, we know it
-                        // will not collected until at least one epoch passes, and since `value`
-                        // was produced under a guard the pins the current epoch, the returned
-                        // reference will remain valid for the guard's lifetime.
-                        return PutResult::Inserted {
-                            new: unsafe { value.deref() },
-                        };
-                    }
-                    Err(changed) => {
-                        assert!(!changed.current.is_null());
-                        bin = changed.current;
-                        if let BinEntry::Node(node) = *changed.new.into_box() {
-                            key = node.key;
+            let mut binis: Vec<_> = hashes.iter().map(|&hash| t.bini(hash)).collect();
+            binis.sort_unstable();
+            binis.dedup();
+
+            let mut locked: Vec<LockedBin<'g, K, V>> = Vec::with_capacity(binis.len());
+            for &bini in &binis {
+                loop {
+                    let bin = t.bin(bini, guard);
+                    if bin.is_null() {
+                        // the bin is empty, so there is no lock to take. we place a reservation in
+                        // it instead, so that no other thread can fill it until we are done.
+                        let reservation =
+                            Owned::new(BinEntry::Reserved(Mutex::new(()))).into_shared(guard);
+                        // safety: we just allocated the reservation, and only free it after
+                        // replacing it.
+                        let lock = if let BinEntry::Reserved(ref lock) =
+                            unsafe { reservation.deref() }
+                        {
+                            lock.lock()
                         } else {
-                            unreachable!("we declared node and it is a BinEntry::Node");
+                            unreachable!("we declared reservation and it is a BinEntry::Reserved");
+                        };
+                        if t.cas_bin(bini, bin, reservation, guard).is_err() {
+                            // someone else got to the bin first -- try again
+                            drop(lock);
+                            // safety: the reservation never made it into the table, so we own it.
+                            drop(unsafe { reservation.into_owned() });
+                            continue;
                         }
+                        locked.push(LockedBin {
+                            bini,
+                            bin: reservation,
+                            _lock: lock,
+                        });
+                        break;
                     }
-                }
-            }
-
-            // slow path -- bin is non-empty
 
-            // safety: bin is a valid pointer.
-            //
-            // there are three cases when a bin pointer is invalidated:
-            //
-            //  1. if the table was resized, bin is a move entry, and the resize has completed. in
-            //     that case, the table (and all its heads) will be dropped in the next epoch
-            //     following that.
-            //  2. if the table is being resized, bin may be swapped with a move entry. the old bin
-            //     will then be dropped in the following epoch after that happens.
-            //  3. when elements are inserted into or removed from the map, bin may be changed into
-            //     or from a TreeBin from or into a regular, linear bin. the old bin will also be
-            //     dropped in the following epoch if that happens.
-            //
-            // in all cases, we held the guard when we got the reference to the bin. if any such
-            // swap happened, it must have happened _after_ we read. since we did the read while
-            // pinning the epoch, the drop must happen in the _next_ epoch (i.e., the one that we
-            // are holding up by holding on to our guard).
-            match *unsafe { bin.deref() } {
-                BinEntry::Moved => {
-                    table = self.help_transfer(table, guard);
-                    continue;
-                }
-                BinEntry::Node(ref head)
-                    if no_replacement && head.hash == hash && head.key == key =>
-                {
-                    // fast path if replacement is disallowed and first bin matches
-                    let v = head.value.load(Ordering::SeqCst, guard);
-                    // safety (for v): since the value is present now, and we've held a guard from
-                    // the beginning of the search, the value cannot be dropped until the next
-                    // epoch, which won't arrive until after we drop our guard.
-                    // safety (for value): since we never inserted the value in the tree, `value`
-                    // is the last remaining pointer to the initial value.
-                    return PutResult::Exists {
-                        current: unsafe { v.deref() },
-                        not_inserted: unsafe { value.into_owned().into_box() },
+                    // safety: bin is a valid pointer. see compute_if_present.
+                    let lock = match *unsafe { bin.deref() } {
+                        BinEntry::Moved => {
+                            // the table is being resized. give up the bins we have, and start over
+                            // once the resize is done. we cannot simply move on to the new table
+                            // like other writers do, since the bins of the other keys may not
+                            // have been moved to it yet.
+                            self.release_bins(t, locked, guard);
+                            self.help_transfer(table, guard);
+                            let current = self.table.load(Ordering::SeqCst, guard);
+                            if current == table {
+                                std::thread::yield_now();
+                            }
+                            table = current;
+                            continue 'lock;
+                        }
+                        BinEntry::Reserved(ref lock) => {
+                            // another thread is computing the first entries of this bin. wait
+                            // until it is done, and then look at the bin again.
+                            drop(self.wait_for_bin(lock));
+                            continue;
+                        }
+                        BinEntry::Node(ref head) => self.wait_for_bin(&head.lock),
+                        BinEntry::Tree(ref tree_bin) => self.wait_for_bin(&tree_bin.lock),
+                        BinEntry::TreeNode(_) => unreachable!(
+                            "The head of a bin cannot be a TreeNode directly without BinEntry::Tree"
+                        ),
                     };
-                }
-                BinEntry::Node(ref head) => {
-                    // bin is non-empty, need to link into it, so we must take the lock
-                    let head_lock = head.lock.lock();
 
                     // need to check that this is _still_ the head
-                    let current_head = t.bin(bini, guard);
-                    if current_head != bin {
-                        // nope -- try again from the start
+                    if t.bin(bini, guard) != bin {
+                        // nope -- try again
                         continue;
                     }
+                    locked.push(LockedBin {
+                        bini,
+                        bin,
+                        _lock: lock,
+                    });
+                    break;
+                }
+            }
+            break (t, locked);
+        };
+        (t, locked)
+    }
 
-                    // yes, it is still the head, so we can now "own" the bin
-                    // note that there can still be readers in the bin!
-
-                    // TODO: ReservationNode
-
-                    bin_count = 1;
-                    let mut p = bin;
-
-                    old_val = loop {
-                        // safety: we read the bin while pinning the epoch. a bin will never be
-                        // dropped until the next epoch after it is removed. since it wasn't
-                        // removed, and the epoch was pinned, that cannot be until after we drop
-                        // our guard.
-                        let n = unsafe { p.deref() }.as_node().unwrap();
-                        if n.hash == hash && n.key == key {
-                            // the key already exists in the map!
-                            let current_value = n.value.load(Ordering::SeqCst, guard);
-
-                            // safety: since the value is present now, and we've held a guard from
-                            // the beginning of the search, the value cannot be dropped until the
-                            // next epoch, which won't arrive until after we drop our guard.
-                            let current_value = unsafe { current_value.deref() };
+    /// Computes new mappings for `keys` with `f` like `compute_locked`, in bins that `lock_bins`
+    /// locked for their `hashes`, and then releases the bins.
+    pub(crate) fn write_locked<'g, Q, F>(
+        &'g self,
+        t: &'g Table<K, V>,
+        locked: Vec<LockedBin<'g, K, V>>,
+        keys: &[&Q],
+        hashes: &[u64],
+        f: F,
+        guard: &'g Guard,
+    ) -> Vec<Option<&'g V>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord + ToOwned<Owned = K>,
+        F: FnOnce(Vec<Option<&'g V>>) -> Vec<Option<Option<V>>>,
+    {
+        let n = keys.len();
+        // we "own" all the bins, so the current values cannot change until we release them.
+        let mut slots = vec![0; n];
+        let mut nodes = vec![Shared::null(); n];
+        let mut current = vec![None; n];
+        for i in 0..n {
+            let bini = t.bini(hashes[i]);
+            slots[i] = locked
+                .binary_search_by_key(&bini, |locked| locked.bini)
+                .expect("all bins are locked");
+            let (hash, key) = (hashes[i], keys[i]);
+            // safety: we hold the lock of the bin, so it and all of its entries stay in the map
+            // until we release it, and are not dropped until after our guard is dropped.
+            nodes[i] = match *unsafe { locked[slots[i]].bin.deref() } {
+                BinEntry::Reserved(_) => Shared::null(),
+                BinEntry::Node(_) => {
+                    let mut p = locked[slots[i]].bin;
+                    loop {
+                        // safety: as above.
+                        let n = match unsafe { p.as_ref() } {
+                            Some(entry) => entry.as_node().unwrap(),
+                            None => break Shared::null(),
+                        };
+                        if n.hash == hash && n.key.borrow() == key {
+                            break p;
+                        }
+                        p = n.next.load(Ordering::SeqCst, guard);
+                    }
+                }
+                BinEntry::Tree(ref tree_bin) => {
+                    let root = tree_bin.root.load(Ordering::SeqCst, guard);
+                    if root.is_null() {
+                        Shared::null()
+                    } else {
+                        TreeNode::find_tree_node(root, hash, key, guard)
+                    }
+                }
+                BinEntry::Moved | BinEntry::TreeNode(_) => {
+                    unreachable!("locked bins hold a head or a reservation")
+                }
+            };
+            // safety: as above.
+            current[i] = unsafe { nodes[i].as_ref() }.map(|entry| {
+                let node = match entry {
+                    BinEntry::Node(ref n) => n,
+                    BinEntry::TreeNode(ref tn) => &tn.node,
+                    _ => unreachable!("keys are only ever found in Nodes or TreeNodes"),
+                };
+                let value = node.value.load(Ordering::SeqCst, guard);
+                // safety: the value cannot be replaced while we hold the bin lock, and it is not
+                // dropped until after our guard is dropped.
+                unsafe { value.deref() }
+            });
+        }
 
-                            if no_replacement {
-                                // the key is not absent, so don't update because of
-                                // `no_replacement`, we don't use the new value, so we need to clean
-                                // it up and return it back to the caller
-                                // safety: we own value and did not share it
-                                return PutResult::Exists {
-                                    current: current_value,
-                                    not_inserted: unsafe { value.into_owned().into_box() },
-                                };
-                            } else {
-                                // update the value in the existing node
+        // if `f` panics, we must not leave any reservations behind, or writers would keep
+        // waiting for them to be replaced.
+        let mut new_values = match panic::catch_unwind(AssertUnwindSafe(|| f(current.clone()))) {
+            Ok(new_values) => new_values,
+            Err(payload) => {
+                self.release_bins(t, locked, guard);
+                panic::resume_unwind(payload);
+            }
+        };
+        assert_eq!(new_values.len(), n);
+
+        let mut results = vec![None; n];
+        let mut inserted = 0;
+        let mut removed = 0;
+        let mut grown = Vec::new();
+        // the new entries of an empty bin only become visible once the bin is filled.
+        let pending = self.begin_write();
+        for (slot, locked_bin) in locked.iter().enumerate() {
+            let keys_in_bin = (0..n).filter(|&i| slots[i] == slot);
+            let bini = locked_bin.bini;
+            // safety: as above.
+            match *unsafe { locked_bin.bin.deref() } {
+                BinEntry::Reserved(_) => {
+                    // the bin was empty, so all we need to do is fill it with the new entries.
+                    let mut head: Shared<'g, BinEntry<K, V>> = Shared::null();
+                    let mut len = 0;
+                    for i in keys_in_bin {
+                        if let Some(Some(value)) = new_values[i].take() {
+                            let value = Owned::new(value).into_shared(guard);
+                            let node = Node::new(hashes[i], keys[i].to_owned(), value);
+                            node.next.store(head, Ordering::SeqCst);
+                            let _ = self.stamp(&node);
+                            head = Owned::new(BinEntry::Node(node)).into_shared(guard);
+                            // safety: we have not moved the value since we placed it into its
+                            // `Atomic`, and it will not be collected until after our guard is
+                            // dropped.
+                            results[i] = Some(unsafe { value.deref() });
+                            len += 1;
+                        }
+                    }
+                    t.store_bin(bini, head);
+                    inserted += len;
+                    if len >= TREEIFY_THRESHOLD {
+                        grown.push(bini);
+                    }
+                }
+                BinEntry::Node(_) => {
+                    // a new head only becomes visible to other writers as the head of the bin, so
+                    // we must not unlink the head until we are done with everything else.
+                    let mut remove_head = None;
+                    let mut appended = false;
+                    for i in keys_in_bin {
+                        let new_value = match new_values[i].take() {
+                            Some(new_value) => new_value,
+                            None => {
+                                results[i] = current[i];
+                                continue;
+                            }
+                        };
+                        // safety: as above.
+                        match (unsafe { nodes[i].as_ref() }, new_value) {
+                            (Some(entry), Some(value)) => {
+                                let n = entry.as_node().unwrap();
+                                let value = Owned::new(value).into_shared(guard);
+                                let _ = self.stamp(n);
                                 let now_garbage = n.value.swap(value, Ordering::SeqCst, guard);
-                                // NOTE: now_garbage == current_value
-
-                                // safety: need to guarantee that now_garbage is no longer
-                                // reachable. more specifically, no thread that executes _after_
-                                // this line can ever get a reference to now_garbage.
-                                //
-                                // here are the possible cases:
-                                //
-                                //  - another thread already has a reference to now_garbage.
-                                //    they must have read it before the call to swap.
-                                //    because of this, that thread must be pinned to an epoch <=
-                                //    the epoch of our guard. since the garbage is placed in our
-                                //    epoch, it won't be freed until the _next_ epoch, at which
-                                //    point, that thread must have dropped its guard, and with it,
-                                //    any reference to the value.
-                                //  - another thread is about to get a reference to this value.
-                                //    they execute _after_ the swap, and therefore do _not_ get a
-                                //    reference to now_garbage (they get value instead). there are
-                                //    no other ways to get to a value except through its Node's
-                                //    `value` field (which is what we swapped), so freeing
-                                //    now_garbage is fine.
+                                // safety: now_garbage is no longer reachable for threads that
+                                // come after the swap. see compute_if_present for the full
+                                // argument.
                                 unsafe { guard.defer_destroy(now_garbage) };
+                                // safety: as for the reserved bin.
+                                results[i] = Some(unsafe { value.deref() });
                             }
-                            break Some(current_value);
+                            (Some(_), None) if nodes[i] == locked_bin.bin => {
+                                remove_head = Some(i);
+                            }
+                            (Some(entry), None) => {
+                                let n = entry.as_node().unwrap();
+                                let next = n.next.load(Ordering::SeqCst, guard);
+                                let mut pred = locked_bin.bin;
+                                loop {
+                                    // safety: as above.
+                                    let p = unsafe { pred.deref() }.as_node().unwrap();
+                                    let p_next = p.next.load(Ordering::SeqCst, guard);
+                                    if p_next == nodes[i] {
+                                        p.next.store(next, Ordering::SeqCst);
+                                        break;
+                                    }
+                                    pred = p_next;
+                                }
+                                // safety: the node and its value are no longer reachable for
+                                // threads that come after the unlink. see compute_if_present for
+                                // the full argument.
+                                unsafe {
+                                    self.retire_value(
+                                        &n.key,
+                                        n.value.load(Ordering::SeqCst, guard),
+                                        guard,
+                                    );
+                                    guard.defer_destroy(nodes[i]);
+                                }
+                                removed += 1;
+                            }
+                            (None, Some(value)) => {
+                                let mut tail = locked_bin.bin;
+                                loop {
+                                    // safety: as above.
+                                    let next = unsafe { tail.deref() }
+                                        .as_node()
+                                        .unwrap()
+                                        .next
+                                        .load(Ordering::SeqCst, guard);
+                                    if next.is_null() {
+                                        break;
+                                    }
+                                    tail = next;
+                                }
+                                let value = Owned::new(value).into_shared(guard);
+                                let node = Owned::new(BinEntry::Node(Node::new(
+                                    hashes[i],
+                                    keys[i].to_owned(),
+                                    value,
+                                )));
+                                let _ = self.stamp(node.as_node().unwrap());
+                                // safety: as above.
+                                unsafe { tail.deref() }
+                                    .as_node()
+                                    .unwrap()
+                                    .next
+                                    .store(node, Ordering::SeqCst);
+                                // safety: as for the reserved bin.
+                                results[i] = Some(unsafe { value.deref() });
+                                inserted += 1;
+                                appended = true;
+                            }
+                            (None, None) => {}
                         }
+                    }
 
-                        // TODO: This Ordering can probably be relaxed due to the Mutex
-                        let next = n.next.load(Ordering::SeqCst, guard);
-                        if next.is_null() {
-                            // we're at the end of the bin -- stick the node here!
-                            let node = Owned::new(BinEntry::Node(Node::new(hash, key, value)));
-                            n.next.store(node, Ordering::SeqCst);
-                            break None;
+                    if let Some(i) = remove_head {
+                        // safety: as above.
+                        let n = unsafe { nodes[i].deref() }.as_node().unwrap();
+                        t.store_bin(bini, n.next.load(Ordering::SeqCst, guard));
+                        // safety: as for the other removals.
+                        unsafe {
+                            self.retire_value(&n.key, n.value.load(Ordering::SeqCst, guard), guard);
+                            guard.defer_destroy(nodes[i]);
                         }
-                        p = next;
-
-                        bin_count += 1;
-                    };
-                    drop(head_lock);
+                        removed += 1;
+                    }
+                    if appended {
+                        grown.push(bini);
+                    }
                 }
-                // NOTE: BinEntry::Tree(ref tree_bin) if no_replacement && head.hash == h && &head.key == key
-                // cannot occur as in the Java code, TreeBins have a special, indicator hash value
                 BinEntry::Tree(ref tree_bin) => {
-                    // bin is non-empty, need to link into it, so we must take the lock
-                    let head_lock = tree_bin.lock.lock();
-
-                    // need to check that this is _still_ the correct bin
-                    let current_head = t.bin(bini, guard);
-                    if current_head != bin {
-                        // nope -- try again from the start
-                        continue;
+                    // removals may turn the bin back into a linear bin, so we do them last.
+                    let mut removals = Vec::new();
+                    for i in keys_in_bin {
+                        let new_value = match new_values[i].take() {
+                            Some(new_value) => new_value,
+                            None => {
+                                results[i] = current[i];
+                                continue;
+                            }
+                        };
+                        match (nodes[i].is_null(), new_value) {
+                            (false, Some(value)) => {
+                                // safety: the TreeBin was read under our guard, at which point
+                                // the tree structure was valid. Since our guard pins the current
+                                // epoch, the TreeNodes remain valid for at least as long as we
+                                // hold onto the guard.
+                                let n = &unsafe { TreeNode::get_tree_node(nodes[i]) }.node;
+                                let value = Owned::new(value).into_shared(guard);
+                                let _ = self.stamp(n);
+                                let now_garbage = n.value.swap(value, Ordering::SeqCst, guard);
+                                // safety: as for the linear bin.
+                                unsafe { guard.defer_destroy(now_garbage) };
+                                results[i] = Some(unsafe { value.deref() });
+                            }
+                            (false, None) => removals.push(i),
+                            (true, Some(value)) => {
+                                let value = Owned::new(value).into_shared(guard);
+                                let existing = tree_bin.find_or_put_tree_val(
+                                    hashes[i],
+                                    keys[i].to_owned(),
+                                    value,
+                                    guard,
+                                );
+                                debug_assert!(existing.is_null(), "we hold the bin lock");
+                                // the new entry is now the first node of the tree bin
+                                // safety: we hold the bin lock, so the new node is still in the bin.
+                                #[cfg(any(feature = "versioning", feature = "insertion-order"))]
+                                let _ = self.stamp(
+                                    &unsafe {
+                                        TreeNode::get_tree_node(
+                                            tree_bin.first.load(Ordering::SeqCst, guard),
+                                        )
+                                    }
+                                    .node,
+                                );
+                                // safety: as for the reserved bin.
+                                results[i] = Some(unsafe { value.deref() });
+                                inserted += 1;
+                            }
+                            (true, None) => {}
+                        }
                     }
 
-                    // yes, it is still the head, so we can now "own" the bin
-                    // note that there can still be readers in the bin!
-
-                    // we don't actually count bins, just set this low enough
-                    // that we don't try to treeify the bin later
-                    bin_count = 2;
-                    let p = tree_bin.find_or_put_tree_val(hash, key, value, guard);
-                    if p.is_null() {
-                        // no TreeNode was returned, so the key did not previously exist in the
-                        // TreeBin. This means it was successfully put there by the call above
-                        // and we are done.
-                        break;
-                    }
-                    // safety: the TreeBin was read under our guard, at
-                    // which point the tree structure was valid. Since our
-                    // guard pins the current epoch, the TreeNodes remain
-                    // valid for at least as long as we hold onto the guard.
-                    // Structurally, TreeNodes always point to TreeNodes, so this is sound.
-                    let tree_node = unsafe { TreeNode::get_tree_node(p) };
-                    old_val = {
-                        let current_value = tree_node.node.value.load(Ordering::SeqCst, guard);
-                        // safety: since the value is present now, and we've held a guard from
-                        // the beginning of the search, the value cannot be dropped until the
-                        // next epoch, which won't arrive until after we drop our guard.
-                        let current_value = unsafe { current_value.deref() };
-                        if no_replacement {
-                            // the key is not absent, so don't update because of
-                            // `no_replacement`, we don't use the new value, so we need to clean
-                            // it up and return it back to the caller
-                            // safety: we own value and did not share it
-                            return PutResult::Exists {
-                                current: current_value,
-                                not_inserted: unsafe { value.into_owned().into_box() },
-                            };
+                    // the nodes that are unlinked from the bin, but can only be dropped once the
+                    // bin has been untreeified.
+                    let mut unlinked = Vec::new();
+                    for i in removals {
+                        let p = nodes[i];
+                        // safety: as above.
+                        let p_deref = unsafe { TreeNode::get_tree_node(p) };
+                        if unlinked.is_empty() {
+                            // safety: `p` is either marked for garbage collection in
+                            // `remove_tree_node` directly, in which case we retire its value
+                            // along with it, or we will untreeify below. see compute_if_present
+                            // for the full argument.
+                            if unsafe { tree_bin.remove_tree_node(p, false, guard) } {
+                                unlinked.push(p);
+                            } else {
+                                let value = p_deref.node.value.load(Ordering::SeqCst, guard);
+                                unsafe { self.retire_value(&p_deref.node.key, value, guard) };
+                            }
                         } else {
-                            let now_garbage =
-                                tree_node.node.value.swap(value, Ordering::SeqCst, guard);
-                            // NOTE: now_garbage == current_value
-
-                            // safety: need to guarantee that now_garbage is no longer
-                            // reachable. more specifically, no thread that executes _after_
-                            // this line can ever get a reference to now_garbage.
-                            //
-                            // here are the possible cases:
-                            //
-                            //  - another thread already has a reference to now_garbage.
-                            //    they must have read it before the call to swap.
-                            //    because of this, that thread must be pinned to an epoch <=
-                            //    the epoch of our guard. since the garbage is placed in our
-                            //    epoch, it won't be freed until the _next_ epoch, at which
-                            //    point, that thread must have dropped its guard, and with it,
-                            //    any reference to the value.
-                            //  - another thread is about to get a reference to this value.
-                            //    they execute _after_ the swap, and therefore do _not_ get a
-                            //    reference to now_garbage (they get `value` instead). there are
-                            //    no other ways to get to a value except through its Node's
-                            //    `value` field (which is what we swapped), so freeing
-                            //    now_garbage is fine.
-                            unsafe { guard.defer_destroy(now_garbage) };
+                            // the tree is about to be replaced by a linear bin built from the
+                            // `next` pointers, so we only unlink the node from those.
+                            let next = p_deref.node.next.load(Ordering::SeqCst, guard);
+                            let prev = p_deref.prev.load(Ordering::SeqCst, guard);
+                            if prev.is_null() {
+                                tree_bin.first.store(next, Ordering::SeqCst);
+                            } else {
+                                // safety: as above.
+                                unsafe { TreeNode::get_tree_node(prev) }
+                                    .node
+                                    .next
+                                    .store(next, Ordering::SeqCst);
+                            }
+                            if !next.is_null() {
+                                // safety: as above.
+                                unsafe { TreeNode::get_tree_node(next) }
+                                    .prev
+                                    .store(prev, Ordering::SeqCst);
+                            }
+                            unlinked.push(p);
                         }
-                        Some(current_value)
-                    };
-                    drop(head_lock);
-                }
-                BinEntry::TreeNode(_) => unreachable!(
-                    "The head of a bin cannot be a TreeNode directly without BinEntry::Tree"
-                ),
-            }
-            // NOTE: the Java code checks `bin_count` here because they also
-            // reach this point if the bin changed while obtaining the lock.
-            // However, our code doesn't (it uses continue) and `bin_count`
-            // _cannot_ be 0 at this point.
-            debug_assert_ne!(bin_count, 0);
-            if bin_count >= TREEIFY_THRESHOLD {
-                self.treeify_bin(t, bini, guard);
-            }
-            if let Some(old_val) = old_val {
-                return PutResult::Replaced {
-                    old: old_val,
-                    // safety: we have not moved the node's value since we placed it into its
-                    // `Atomic` in the very beginning of the method, so the ref is still valid.
-                    // since the value is not currently marked as garbage, we know it will not
-                    // collected until at least one epoch passes, and since `value` was produced
-                    // under a guard the pins the current epoch, the returned reference will remain
-                    // valid for the guard's lifetime.
-                    new: unsafe { value.deref() },
-                };
-            }
-            break;
-        }
-        // increment count, since we only get here if we did not return an old (updated) value
-        debug_assert!(old_val.is_none());
-        self.add_count(1, Some(bin_count), guard);
-        guard.flush();
-        PutResult::Inserted {
-            // safety: we have not moved the node's value since we placed it into its
-            // `Atomic` in the very beginning of the method, so the ref is still valid.
-            // since the value is not currently marked as garbage, we know it will not
-            // collected until at least one epoch passes, and since `value` was produced
-            // under a guard the pins the current epoch, the returned reference will remain
-            // valid for the guard's lifetime.
-            new: unsafe { value.deref() },
+                        removed += 1;
+                    }
+                    if !unlinked.is_empty() {
+                        let linear_bin =
+                            Self::untreeify(tree_bin.first.load(Ordering::SeqCst, guard), guard);
+                        t.store_bin(bini, linear_bin);
+                        // safety: the tree bin and the unlinked nodes are no longer reachable
+                        // after the store above. see compute_if_present for the full argument.
+                        unsafe {
+                            TreeBin::defer_drop_without_values(locked_bin.bin, guard);
+                            for p in unlinked {
+                                let n = &TreeNode::get_tree_node(p).node;
+                                self.retire_value(
+                                    &n.key,
+                                    n.value.load(Ordering::SeqCst, guard),
+                                    guard,
+                                );
+                                guard.defer_destroy(p);
+                            }
+                        }
+                    }
+                }
+                BinEntry::Moved | BinEntry::TreeNode(_) => {
+                    unreachable!("locked bins hold a head or a reservation")
+                }
+            }
+        }
+        drop(pending);
+        self.release_bins(t, locked, guard);
+
+        for bini in grown {
+            // safety: as above.
+            if let Some(BinEntry::Node(head)) = unsafe { t.bin(bini, guard).as_ref() } {
+                if Self::chain_len(head, guard) >= TREEIFY_THRESHOLD {
+                    self.treeify_bin(t, bini, guard);
+                }
+            }
         }
+        let delta = inserted as isize - removed as isize;
+        self.add_count(delta, if delta > 0 { Some(0) } else { None }, guard);
+        guard.flush();
+        results
     }
 
-    fn put_all<I: Iterator<Item = (K, V)>>(&self, iter: I, guard: &Guard) {
-        for (key, value) in iter {
-            self.put(key, value, false, guard);
+    /// Unlocks bins locked by `compute_locked`, and takes out any reservations it left in them.
+    pub(crate) fn release_bins<'g>(
+        &'g self,
+        t: &'g Table<K, V>,
+        locked: Vec<LockedBin<'g, K, V>>,
+        guard: &'g Guard,
+    ) {
+        for locked_bin in locked {
+            // safety: we hold the bin's lock, so its entry has not been dropped.
+            if let BinEntry::Reserved(_) = unsafe { locked_bin.bin.deref() } {
+                if t.bin(locked_bin.bini, guard) == locked_bin.bin {
+                    // the reservation was never replaced with any entries
+                    t.store_bin(locked_bin.bini, Shared::null());
+                }
+                let reservation = locked_bin.bin;
+                drop(locked_bin);
+                // safety: the reservation is no longer reachable for threads that load the bin
+                // now. threads that read it before must be pinned to an epoch <= ours, so it is
+                // not freed until they are done.
+                unsafe { guard.defer_destroy(reservation) };
+            }
         }
     }
 
-    /// If the value for the specified `key` is present, attempts to
-    /// compute a new mapping given the key and its current mapped value.
+    /// Removes a key-value pair from the map, and returns the removed value (if any).
     ///
-    /// The new mapping is computed by the `remapping_function`, which may
-    /// return `None` to signalize that the mapping should be removed.
-    /// The entire method invocation is performed atomically.
-    /// The supplied function is invoked exactly once per invocation of
-    /// this method if the key is present, else not at all.  Some
-    /// attempted update operations on this map by other threads may be
-    /// blocked while computation is in progress, so the computation
-    /// should be short and simple.
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`Hash`] and [`Ord`] on the borrowed form *must* match those for
+    /// the key type.
     ///
-    /// Returns the new value associated with the specified `key`, or `None`
-    /// if no value for the specified `key` is present.
+    /// [`Ord`]: std::cmp::Ord
+    /// [`Hash`]: std::hash::Hash
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// map.pin().insert(1, "a");
+    /// assert_eq!(map.pin().remove(&1), Some(&"a"));
+    /// assert_eq!(map.pin().remove(&1), None);
+    /// ```
+    pub fn remove<'g, Q>(&'g self, key: &Q, guard: &'g Guard) -> Option<&'g V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+    {
+        // NOTE: _technically_, this method shouldn't require the thread-safety bounds, but a) that
+        // would require special-casing replace_node for when new_value.is_none(), and b) it's sort
+        // of useless to call remove on a collection that you know you can never insert into.
+        self.check_guard(guard);
+        self.replace_node(key, None, None, guard).map(|(_, v)| v)
+    }
+
+    /// Removes a key from the map, returning the stored key and value if the
+    /// key was previously in the map.
     ///
     /// The key may be any borrowed form of the map's key type, but
     /// [`Hash`] and [`Ord`] on the borrowed form *must* match those for
@@ -2011,58 +7568,148 @@ where
     ///
     /// [`Ord`]: std::cmp::Ord
     /// [`Hash`]: std::hash::Hash
-    pub fn compute_if_present<'g, Q, F>(
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// let guard = map.guard();
+    /// map.insert(1, "a", &guard);
+    /// assert_eq!(map.remove_entry(&1, &guard), Some((&1, &"a")));
+    /// assert_eq!(map.remove(&1, &guard), None);
+    /// ```
+    pub fn remove_entry<'g, Q>(&'g self, key: &Q, guard: &'g Guard) -> Option<(&'g K, &'g V)>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+    {
+        self.check_guard(guard);
+        self.replace_node(key, None, None, guard)
+    }
+
+    /// Removes a key from the map, unless that would mean waiting for another thread.
+    ///
+    /// This behaves like [`remove`](HashMap::remove), except that if the lock of the key's bin is
+    /// held by another thread, it returns [`WouldBlock`] right away instead of waiting for the
+    /// lock. Like [`try_insert_nonblocking`](HashMap::try_insert_nonblocking), it does not help
+    /// with an ongoing resize.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// let guard = map.guard();
+    /// map.insert(1, "a", &guard);
+    /// assert_eq!(map.try_remove_nonblocking(&1, &guard), Ok(Some(&"a")));
+    /// assert_eq!(map.try_remove_nonblocking(&1, &guard), Ok(None));
+    /// ```
+    pub fn try_remove_nonblocking<'g, Q>(
         &'g self,
         key: &Q,
-        remapping_function: F,
         guard: &'g Guard,
-    ) -> Option<&'g V>
+    ) -> Result<Option<&'g V>, WouldBlock>
     where
         K: Borrow<Q>,
         Q: ?Sized + Hash + Ord,
-        F: FnOnce(&K, &V) -> Option<V>,
     {
         self.check_guard(guard);
-        let hash = self.hash(&key);
+        match self.try_replace_node(key, None, None, false, guard) {
+            Ok(old) => Ok(old.map(|(_, v)| v)),
+            Err(_) => Err(WouldBlock(())),
+        }
+    }
+
+    /// Replaces node value with `new_value`.
+    ///
+    /// If an `observed_value` is provided, the replacement only happens if `observed_value` equals
+    /// the value that is currently associated with the given key.
+    ///
+    /// If `new_value` is `None`, it removes the key (and its corresponding value) from this map.
+    ///
+    /// This method does nothing if the key is not in the map.
+    ///
+    /// Returns the previous key and value associated with the given key.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`Hash`] and [`Ord`] on the borrowed form *must* match those for
+    /// the key type.
+    ///
+    /// [`Ord`]: std::cmp::Ord
+    /// [`Hash`]: std::hash::Hash
+    pub(crate) fn replace_node<'g, Q>(
+        &'g self,
+        key: &Q,
+        new_value: Option<V>,
+        observed_value: Option<Shared<'g, V>>,
+        guard: &'g Guard,
+    ) -> Option<(&'g K, &'g V)>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+    {
+        match self.try_replace_node(key, new_value, observed_value, true, guard) {
+            Ok(old) => {
+                // removals do not resize the table, but may have to catch up on growth that a
+                // nonblocking write left behind.
+                self.grow_if_pending(guard);
+                old
+            }
+            Err(_) => unreachable!("a blocking replacement always takes the bin lock"),
+        }
+    }
+
+    /// Like `replace_node`, except that if `blocking` is `false`, `new_value` is handed back in
+    /// `Err` rather than waiting for the lock of the key's bin when another thread holds it.
+    ///
+    /// Like a nonblocking insert, a nonblocking replacement does not help with an ongoing resize.
+    fn try_replace_node<'g, Q>(
+        &'g self,
+        key: &Q,
+        new_value: Option<V>,
+        observed_value: Option<Shared<'g, V>>,
+        blocking: bool,
+        guard: &'g Guard,
+    ) -> Result<Option<(&'g K, &'g V)>, Option<V>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+    {
+        let hash = self.hash(key);
 
+        let is_remove = new_value.is_none();
+        let mut old_val = None;
         let mut table = self.table.load(Ordering::SeqCst, guard);
-        let mut new_val = None;
-        let mut removed_node = false;
-        let mut bin_count;
         loop {
-            // safety: see argument below for !is_null case
-            if table.is_null() || unsafe { table.deref() }.is_empty() {
-                table = self.init_table(guard);
-                continue;
+            if table.is_null() {
+                break;
             }
 
             // safety: table is a valid pointer.
             //
-            // we are in one of three cases:
+            // we are in one of two cases:
             //
             //  1. if table is the one we read before the loop, then we read it while holding the
             //     guard, so it won't be dropped until after we drop that guard b/c the drop logic
             //     only queues a drop for the next epoch after removing the table.
             //
-            //  2. if table is read by init_table, then either we did a load, and the argument is
-            //     as for point 1. or, we allocated a table, in which case the earliest it can be
-            //     deallocated is in the next epoch. we are holding up the epoch by holding the
-            //     guard, so this deref is safe.
-            //
-            //  3. if table is set by a Moved node (below) through help_transfer, it will _either_
-            //     keep using `table` (which is fine by 1. and 2.), or use the `next_table` raw
-            //     pointer from inside the Moved. to see that if a Moved(t) is _read_, then t must
-            //     still be valid, see the safety comment on Table.next_table.
+            //  2. if table is set by a Moved node (below) through help_transfer, it will use the
+            //     `next_table` raw pointer from inside the Moved. to see that if a Moved(t) is
+            //     _read_, then t must still be valid, see the safety comment on Table.next_table.
             let t = unsafe { table.deref() };
-
+            let n = t.len() as u64;
+            if n == 0 {
+                break;
+            }
             let bini = t.bini(hash);
             let bin = t.bin(bini, guard);
             if bin.is_null() {
-                // fast path -- bin is empty so key is not present
-                return None;
+                break;
             }
 
-            // slow path -- bin is non-empty
             // safety: bin is a valid pointer.
             //
             // there are three cases when a bin pointer is invalidated:
@@ -2081,83 +7728,66 @@ where
             // pinning the epoch, the drop must happen in the _next_ epoch (i.e., the one that we
             // are holding up by holding on to our guard).
             match *unsafe { bin.deref() } {
+                BinEntry::Moved if !blocking => {
+                    table = t.next_table(guard);
+                    continue;
+                }
                 BinEntry::Moved => {
                     table = self.help_transfer(table, guard);
                     continue;
                 }
+                BinEntry::Reserved(ref lock) => {
+                    // another thread is computing the first entry of this bin. wait until it is
+                    // done, and then look at the bin again.
+                    if self.lock_bin(lock, blocking, &mut 0).is_none() {
+                        return Err(new_value);
+                    }
+                    continue;
+                }
                 BinEntry::Node(ref head) => {
-                    // bin is non-empty, need to link into it, so we must take the lock
-                    let head_lock = head.lock.lock();
+                    let head_lock = match self.lock_bin(&head.lock, blocking, &mut 0) {
+                        Some(head_lock) => head_lock,
+                        None => return Err(new_value),
+                    };
 
                     // need to check that this is _still_ the head
-                    let current_head = t.bin(bini, guard);
-                    if current_head != bin {
-                        // nope -- try again from the start
+                    if t.bin(bini, guard) != bin {
                         continue;
                     }
 
-                    // yes, it is still the head, so we can now "own" the bin
-                    // note that there can still be readers in the bin!
-
-                    // TODO: ReservationNode
-                    bin_count = 1;
-                    let mut p = bin;
+                    let mut e = bin;
                     let mut pred: Shared<'_, BinEntry<K, V>> = Shared::null();
-
-                    new_val = loop {
-                        // safety: we read the bin while pinning the epoch. a bin will never be
-                        // dropped until the next epoch after it is removed. since it wasn't
-                        // removed, and the epoch was pinned, that cannot be until after we drop
-                        // our guard.
-                        let n = unsafe { p.deref() }.as_node().unwrap();
-                        // TODO: This Ordering can probably be relaxed due to the Mutex
+                    loop {
+                        // safety: either e is bin, in which case it is valid due to the above,
+                        // or e was obtained from a next pointer. Any next pointer obtained from
+                        // bin is valid at the time we look up bin in the table, at which point
+                        // the epoch is pinned by our guard. Since we found the next pointer
+                        // in a valid map and it is not null (as checked above and below), the
+                        // node it points to was present (i.e. not removed) from the map in the
+                        // current epoch. Thus, because the epoch cannot advance until we release
+                        // our guard, e is also valid if it was obtained from a next pointer.
+                        let n = unsafe { e.deref() }.as_node().unwrap();
                         let next = n.next.load(Ordering::SeqCst, guard);
                         if n.hash == hash && n.key.borrow() == key {
-                            // the key already exists in the map!
-                            let current_value = n.value.load(Ordering::SeqCst, guard);
-
-                            // safety: since the value is present now, and we've held a guard from
-                            // the beginning of the search, the value cannot be dropped until the
-                            // next epoch, which won't arrive until after we drop our guard.
-                            let new_value =
-                                remapping_function(&n.key, unsafe { current_value.deref() });
-
-                            if let Some(value) = new_value {
-                                let value = Owned::new(value).into_shared(guard);
-                                let now_garbage = n.value.swap(value, Ordering::SeqCst, guard);
-                                // NOTE: now_garbage == current_value
-
-                                // safety: need to guarantee that now_garbage is no longer
-                                // reachable. more specifically, no thread that executes _after_
-                                // this line can ever get a reference to now_garbage.
-                                //
-                                // here are the possible cases:
-                                //
-                                //  - another thread already has a reference to now_garbage.
-                                //    they must have read it before the call to swap.
-                                //    because of this, that thread must be pinned to an epoch <=
-                                //    the epoch of our guard. since the garbage is placed in our
-                                //    epoch, it won't be freed until the _next_ epoch, at which
-                                //    point, that thread must have dropped its guard, and with it,
-                                //    any reference to the value.
-                                //  - another thread is about to get a reference to this value.
-                                //    they execute _after_ the swap, and therefore do _not_ get a
-                                //    reference to now_garbage (they get value instead). there are
-                                //    no other ways to get to a value except through its Node's
-                                //    `value` field (which is what we swapped), so freeing
-                                //    now_garbage is fine.
-                                unsafe { guard.defer_destroy(now_garbage) };
+                            let ev = n.value.load(Ordering::SeqCst, guard);
 
-                                // safety: since the value is present now, and we've held a guard from
-                                // the beginning of the search, the value cannot be dropped until the
-                                // next epoch, which won't arrive until after we drop our guard.
-                                break Some(unsafe { value.deref() });
-                            } else {
-                                removed_node = true;
+                            // only replace the node if the value is the one we expected at method call
+                            if observed_value.map(|ov| ov == ev).unwrap_or(true) {
+                                // we remember the old value so that we can return it and mark it for deletion below
+                                old_val = Some((&n.key, ev));
+
+                                // found the node but we have a new value to replace the old one
+                                if let Some(nv) = new_value {
+                                    let _pending = self.stamp(n);
+                                    n.value.store(Owned::new(nv), Ordering::SeqCst);
+                                    // we are just replacing entry value and we do not want to remove the node
+                                    // so we stop iterating here
+                                    break;
+                                }
                                 // remove the BinEntry containing the removed key value pair from the bucket
                                 if !pred.is_null() {
                                     // either by changing the pointer of the previous BinEntry, if present
-                                    // safety: see remove
+                                    // safety: as above
                                     unsafe { pred.deref() }
                                         .as_node()
                                         .unwrap()
@@ -2169,57 +7799,37 @@ where
                                 }
 
                                 // in either case, mark the BinEntry as garbage, since it was just removed
-                                // safety: need to guarantee that the old value is no longer
-                                // reachable. more specifically, no thread that executes _after_
-                                // this line can ever get a reference to val.
-                                //
-                                // here are the possible cases:
-                                //
-                                //  - another thread already has a reference to the old value.
-                                //    they must have read it before the call to store_bin.
-                                //    because of this, that thread must be pinned to an epoch <=
-                                //    the epoch of our guard. since the garbage is placed in our
-                                //    epoch, it won't be freed until the _next_ epoch, at which
-                                //    point, that thread must have dropped its guard, and with it,
-                                //    any reference to the value.
-                                //  - another thread is about to get a reference to this value.
-                                //    they execute _after_ the store_bin, and therefore do _not_ get a
-                                //    reference to the old value. there are no other ways to get to a
-                                //    value except through its Node's `value` field (which is now gone
-                                //    together with the node), so freeing the old value is fine.
-                                unsafe { guard.defer_destroy(p) };
-                                unsafe { guard.defer_destroy(current_value) };
-                                break None;
+                                // safety: as for val below / in put
+                                unsafe { self.retire_node(e, guard) };
                             }
+                            // since the key was found and only one node exists per key, we can break here
+                            break;
                         }
-
-                        pred = p;
+                        pred = e;
                         if next.is_null() {
-                            // we're at the end of the bin
-                            break None;
+                            break;
+                        } else {
+                            e = next;
                         }
-                        p = next;
-
-                        bin_count += 1;
-                    };
+                    }
                     drop(head_lock);
                 }
                 BinEntry::Tree(ref tree_bin) => {
-                    // bin is non-empty, need to link into it, so we must take the lock
-                    let bin_lock = tree_bin.lock.lock();
+                    let bin_lock = match self.lock_bin(&tree_bin.lock, blocking, &mut 0) {
+                        Some(bin_lock) => bin_lock,
+                        None => return Err(new_value),
+                    };
 
                     // need to check that this is _still_ the head
-                    let current_head = t.bin(bini, guard);
-                    if current_head != bin {
-                        // nope -- try again from the start
+                    if t.bin(bini, guard) != bin {
                         continue;
                     }
 
-                    // yes, it is still the head, so we can now "own" the bin
-                    // note that there can still be readers in the bin!
-                    bin_count = 2;
                     let root = tree_bin.root.load(Ordering::SeqCst, guard);
                     if root.is_null() {
+                        // we are in the correct bin for the given key's hash and the bin is not
+                        // `Moved`, but it is empty. This means there is no node for the given
+                        // key that could be replaced
                         // TODO: Is this even reachable?
                         // The Java code has `NULL` checks for this, but in theory it should not be possible to
                         // encounter a tree that has no root when we have its lock. It should always have at
@@ -2227,126 +7837,190 @@ where
                         // this with an assertion instead.
                         break;
                     }
-                    new_val = {
-                        let p = TreeNode::find_tree_node(root, hash, key, guard);
-                        if p.is_null() {
-                            // the given key is not present in the map
-                            None
-                        } else {
-                            // a node for the given key exists, so we try to update it
-                            // safety: the TreeBin was read under our guard,
-                            // at which point the tree structure was valid.
-                            // Since our guard pins the current epoch, the
-                            // TreeNodes and `p` in particular remain valid
-                            // for at least as long as we hold onto the
-                            // guard.
-                            // Structurally, TreeNodes always point to TreeNodes, so this is sound.
-                            let n = &unsafe { TreeNode::get_tree_node(p) }.node;
-                            let current_value = n.value.load(Ordering::SeqCst, guard);
-
-                            // safety: since the value is present now, and we've held a guard from
-                            // the beginning of the search, the value cannot be dropped until the
-                            // next epoch, which won't arrive until after we drop our guard.
-                            let new_value =
-                                remapping_function(&n.key, unsafe { current_value.deref() });
+                    let p = TreeNode::find_tree_node(root, hash, key, guard);
+                    if p.is_null() {
+                        // similarly to the above, there now are entries in this bin, but none of
+                        // them matches the given key
+                        break;
+                    }
+                    // safety: the TreeBin was read under our guard,
+                    // at which point the tree structure was valid.
+                    // Since our guard pins the current epoch, the
+                    // TreeNodes and `p` in particular remain valid
+                    // for at least as long as we hold onto the
+                    // guard.
+                    // Structurally, TreeNodes always point to TreeNodes, so this is sound.
+                    let n = &unsafe { TreeNode::get_tree_node(p) }.node;
+                    let pv = n.value.load(Ordering::SeqCst, guard);
 
-                            if let Some(value) = new_value {
-                                let value = Owned::new(value).into_shared(guard);
-                                let now_garbage = n.value.swap(value, Ordering::SeqCst, guard);
-                                // NOTE: now_garbage == current_value
+                    // only replace the node if the value is the one we expected at method call
+                    if observed_value.map(|ov| ov == pv).unwrap_or(true) {
+                        // we remember the old value so that we can return it and mark it for deletion below
+                        old_val = Some((&n.key, pv));
 
-                                // safety: need to guarantee that now_garbage is no longer
-                                // reachable. more specifically, no thread that executes _after_
-                                // this line can ever get a reference to now_garbage.
-                                //
-                                // here are the possible cases:
-                                //
-                                //  - another thread already has a reference to now_garbage.
-                                //    they must have read it before the call to swap.
-                                //    because of this, that thread must be pinned to an epoch <=
-                                //    the epoch of our guard. since the garbage is placed in our
-                                //    epoch, it won't be freed until the _next_ epoch, at which
-                                //    point, that thread must have dropped its guard, and with it,
-                                //    any reference to the value.
-                                //  - another thread is about to get a reference to this value.
-                                //    they execute _after_ the swap, and therefore do _not_ get a
-                                //    reference to now_garbage (they get value instead). there are
-                                //    no other ways to get to a value except through its Node's
-                                //    `value` field (which is what we swapped), so freeing
-                                //    now_garbage is fine.
-                                unsafe { guard.defer_destroy(now_garbage) };
-                                // safety: since the value is present now, and we've held a guard from
-                                // the beginning of the search, the value cannot be dropped until the
-                                // next epoch, which won't arrive until after we drop our guard.
-                                Some(unsafe { value.deref() })
-                            } else {
-                                removed_node = true;
-                                // remove the BinEntry::TreeNode containing the removed key value pair from the bucket
-                                // also drop the old value stored in the tree node, as it was removed from the map
-                                // safety: `p` and its value are either marked for garbage collection in `remove_tree_node`
-                                // directly, or we will `need_to_untreeify`. In the latter case, we `defer_destroy`
-                                // both `p` and its value below, after storing the linear bin. Thus, everything is
-                                // always marked for garbage collection _after_ it becomes unaccessible by other threads.
-                                let need_to_untreeify =
-                                    unsafe { tree_bin.remove_tree_node(p, true, guard) };
-                                if need_to_untreeify {
-                                    let linear_bin = Self::untreeify(
-                                        tree_bin.first.load(Ordering::SeqCst, guard),
-                                        guard,
-                                    );
-                                    t.store_bin(bini, linear_bin);
-                                    // the old bin is now garbage, but its values are not,
-                                    // since they are re-used in the linear bin.
-                                    // safety: in the same way as for `now_garbage` above, any existing
-                                    // references to `bin` must have been obtained before storing the
-                                    // linear bin. These references were obtained while pinning an epoch
-                                    // <= our epoch and have to be dropped before the epoch can advance
-                                    // past the destruction of the old bin. After the store, threads will
-                                    // always see the linear bin, so the cannot obtain new references either.
-                                    //
-                                    // The same holds for `p` and its value, which does not get dropped together
-                                    // with `bin` here since `remove_tree_node` indicated that the bin needs to
-                                    // be untreeified.
-                                    unsafe {
-                                        TreeBin::defer_drop_without_values(bin, guard);
-                                        guard.defer_destroy(p);
-                                        guard.defer_destroy(current_value);
-                                    }
+                        if let Some(nv) = new_value {
+                            // found the node but we have a new value to replace the old one
+                            let _pending = self.stamp(n);
+                            n.value.store(Owned::new(nv), Ordering::SeqCst);
+                        } else {
+                            // drop `p` without its value, since the old value is dropped
+                            // in the check on `old_val` below
+                            // safety: `p` is either marked for garbage collection in `remove_tree_node` directly,
+                            // or we will `need_to_untreeify`. In the latter case, we `defer_destroy` `p` below,
+                            // after storing the linear bin. The value stored in `p` is `defer_destroy`ed from within
+                            // `old_val` at the end of the method. Thus, everything is always marked for garbage
+                            // collection _after_ it becomes unaccessible by other threads.
+                            let need_to_untreeify =
+                                unsafe { tree_bin.remove_tree_node(p, false, guard) };
+                            if need_to_untreeify {
+                                let linear_bin = Self::untreeify(
+                                    tree_bin.first.load(Ordering::SeqCst, guard),
+                                    guard,
+                                );
+                                t.store_bin(bini, linear_bin);
+                                // the old bin is now garbage, but its values are not,
+                                // since they get re-used in the linear bin
+                                // safety: same as in put
+                                unsafe {
+                                    TreeBin::defer_drop_without_values(bin, guard);
+                                    guard.defer_destroy(p);
                                 }
-                                None
                             }
                         }
-                    };
+                    }
+
                     drop(bin_lock);
                 }
                 BinEntry::TreeNode(_) => unreachable!(
                     "The head of a bin cannot be a TreeNode directly without BinEntry::Tree"
                 ),
             }
-            // NOTE: the Java code checks `bin_count` here because they also
-            // reach this point if the bin changed while obtaining the lock.
-            // However, our code doesn't (it uses continue) and making this
-            // break conditional confuses the compiler about how many times we
-            // use the `remapping_function`.
-            debug_assert_ne!(bin_count, 0);
-            break;
+            if let Some((key, val)) = old_val {
+                if is_remove {
+                    self.add_count(-1, None, guard);
+                }
+
+                // safety: need to guarantee that the old value is no longer
+                // reachable. more specifically, no thread that executes _after_
+                // this line can ever get a reference to val.
+                //
+                // here are the possible cases:
+                //
+                //  - another thread already has a reference to the old value.
+                //    they must have read it before the call to store_bin.
+                //    because of this, that thread must be pinned to an epoch <=
+                //    the epoch of our guard. since the garbage is placed in our
+                //    epoch, it won't be freed until the _next_ epoch, at which
+                //    point, that thread must have dropped its guard, and with it,
+                //    any reference to the value.
+                //  - another thread is about to get a reference to this value.
+                //    they execute _after_ the store_bin, and therefore do _not_ get a
+                //    reference to the old value. there are no other ways to get to a
+                //    value except through its Node's `value` field (which is now gone
+                //    together with the node), so freeing the old value is fine.
+                if is_remove {
+                    unsafe { self.retire_value(key, val, guard) };
+                } else {
+                    unsafe { guard.defer_destroy(val) };
+                }
+
+                // safety: the lifetime of the reference is bound to the guard
+                // supplied which means that the memory will not be freed
+                // until at least after the guard goes out of scope
+                return Ok(unsafe { val.as_ref() }.map(move |v| (key, v)));
+            }
+            break;
+        }
+        Ok(None)
+    }
+
+    /// Creates a new map that holds a copy of every entry in this map, but uses `new_hasher` to
+    /// hash its keys.
+    ///
+    /// This is a migration tool for moving away from a hasher that turned out to be weak for
+    /// the keys in the map (for example, because it is subject to a HashDoS attack). The current
+    /// map is left unchanged, and modifications made to it concurrently with the migration may or
+    /// may not be reflected in the returned map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let map = HashMap::new();
+    /// map.pin().insert(1, "a");
+    ///
+    /// let rehashed = map.pin().rehash_with(RandomState::new());
+    /// assert_eq!(rehashed.pin().get(&1), Some(&"a"));
+    /// ```
+    pub fn rehash_with<S2>(&self, new_hasher: S2, guard: &Guard) -> HashMap<K, V, S2>
+    where
+        V: Clone,
+        S2: BuildHasher,
+    {
+        self.check_guard(guard);
+        let rehashed = HashMap::with_capacity_and_hasher(self.len(), new_hasher);
+        {
+            let rehashed_guard = rehashed.guard();
+            for (key, value) in self.iter(guard) {
+                rehashed.insert(key.clone(), value.clone(), &rehashed_guard);
+            }
+        }
+        rehashed
+    }
+
+    /// Splits the map into two new maps: one with the entries for which `pred` returns `true`,
+    /// and one with the rest.
+    ///
+    /// This scans the map once, and copies every entry into one of the two new maps, which use
+    /// clones of this map's hasher. The current map is left unchanged, and modifications made to
+    /// it concurrently with the split may or may not be reflected in the returned maps.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// for i in 0..10 {
+    ///     map.pin().insert(i, i * 10);
+    /// }
+    ///
+    /// let (large, small) = map.pin().partition(|_, &v| v >= 50);
+    /// assert_eq!(large.len(), 5);
+    /// assert_eq!(small.len(), 5);
+    /// assert_eq!(large.pin().get(&7), Some(&70));
+    /// assert_eq!(small.pin().get(&7), None);
+    /// ```
+    pub fn partition<P>(&self, pred: P, guard: &Guard) -> (HashMap<K, V, S>, HashMap<K, V, S>)
+    where
+        V: Clone,
+        S: Clone,
+        P: Fn(&K, &V) -> bool,
+    {
+        self.check_guard(guard);
+        let matching = HashMap::with_hasher(self.build_hasher.clone());
+        let rest = HashMap::with_hasher(self.build_hasher.clone());
+        {
+            let matching_guard = matching.guard();
+            let rest_guard = rest.guard();
+            for (key, value) in self.iter(guard) {
+                if pred(key, value) {
+                    matching.insert(key.clone(), value.clone(), &matching_guard);
+                } else {
+                    rest.insert(key.clone(), value.clone(), &rest_guard);
+                }
+            }
         }
-        if removed_node {
-            // decrement count
-            self.add_count(-1, Some(bin_count), guard);
-        }
-        guard.flush();
-        new_val
+        (matching, rest)
     }
 
-    /// Removes a key-value pair from the map, and returns the removed value (if any).
-    ///
-    /// The key may be any borrowed form of the map's key type, but
-    /// [`Hash`] and [`Ord`] on the borrowed form *must* match those for
-    /// the key type.
+    /// Creates a new map that holds a copy of the entries for just the given keys.
     ///
-    /// [`Ord`]: std::cmp::Ord
-    /// [`Hash`]: std::hash::Hash
+    /// Keys that are not in the map are skipped. The new map uses a clone of this map's hasher.
+    /// The current map is left unchanged, and modifications made to it concurrently may or may
+    /// not be reflected in the returned map.
     ///
     /// # Examples
     ///
@@ -2354,31 +8028,49 @@ where
     /// use flurry::HashMap;
     ///
     /// let map = HashMap::new();
-    /// map.pin().insert(1, "a");
-    /// assert_eq!(map.pin().remove(&1), Some(&"a"));
-    /// assert_eq!(map.pin().remove(&1), None);
+    /// for i in 0..10 {
+    ///     map.pin().insert(i, i * 10);
+    /// }
+    ///
+    /// let subset = map.pin().clone_subset(vec![2, 3, 42]);
+    /// assert_eq!(subset.len(), 2);
+    /// assert_eq!(subset.pin().get(&3), Some(&30));
     /// ```
-    pub fn remove<'g, Q>(&'g self, key: &Q, guard: &'g Guard) -> Option<&'g V>
+    pub fn clone_subset<Q, I>(&self, keys: I, guard: &Guard) -> HashMap<K, V, S>
     where
         K: Borrow<Q>,
-        Q: ?Sized + Hash + Ord,
+        Q: Hash + Ord,
+        V: Clone,
+        S: Clone,
+        I: IntoIterator<Item = Q>,
     {
-        // NOTE: _technically_, this method shouldn't require the thread-safety bounds, but a) that
-        // would require special-casing replace_node for when new_value.is_none(), and b) it's sort
-        // of useless to call remove on a collection that you know you can never insert into.
         self.check_guard(guard);
-        self.replace_node(key, None, None, guard).map(|(_, v)| v)
+        let subset = HashMap::with_hasher(self.build_hasher.clone());
+        {
+            let subset_guard = subset.guard();
+            for key in keys {
+                if let Some(node) = self.get_node(&key, guard) {
+                    let value = node.value.load(Ordering::SeqCst, guard);
+                    // safety: see get
+                    let value = unsafe { value.deref() };
+                    subset.insert(node.key.clone(), value.clone(), &subset_guard);
+                }
+            }
+        }
+        subset
     }
 
-    /// Removes a key from the map, returning the stored key and value if the
-    /// key was previously in the map.
+    /// Consumes the map and splits its entries into `n` independent maps.
     ///
-    /// The key may be any borrowed form of the map's key type, but
-    /// [`Hash`] and [`Ord`] on the borrowed form *must* match those for
-    /// the key type.
+    /// Entries are assigned to shards by the index of the bin they are in, modulo `n`, so all the
+    /// entries of a bin end up in the same shard, and the shards hold about equally many entries
+    /// if the hasher spreads keys evenly. Since the map is consumed, entries are moved into the
+    /// shards rather than cloned. The shards use clones of this map's hasher, and can then be
+    /// handed to separate threads to work on without contending with each other.
     ///
-    /// [`Ord`]: std::cmp::Ord
-    /// [`Hash`]: std::hash::Hash
+    /// # Panics
+    ///
+    /// Panics if `n` is 0.
     ///
     /// # Examples
     ///
@@ -2386,278 +8078,668 @@ where
     /// use flurry::HashMap;
     ///
     /// let map = HashMap::new();
-    /// let guard = map.guard();
-    /// map.insert(1, "a", &guard);
-    /// assert_eq!(map.remove_entry(&1, &guard), Some((&1, &"a")));
-    /// assert_eq!(map.remove(&1, &guard), None);
+    /// for i in 0..100 {
+    ///     map.pin().insert(i, i.to_string());
+    /// }
+    ///
+    /// let shards = map.into_shards(4);
+    /// assert_eq!(shards.len(), 4);
+    /// assert_eq!(shards.iter().map(|shard| shard.len()).sum::<usize>(), 100);
     /// ```
-    pub fn remove_entry<'g, Q>(&'g self, key: &Q, guard: &'g Guard) -> Option<(&'g K, &'g V)>
+    pub fn into_shards(self, n: usize) -> Vec<HashMap<K, V, S>>
     where
-        K: Borrow<Q>,
-        Q: ?Sized + Hash + Ord,
+        S: Clone,
     {
-        self.check_guard(guard);
-        self.replace_node(key, None, None, guard)
+        assert!(n > 0, "cannot split a map into 0 shards");
+        let shards: Vec<_> = (0..n)
+            .map(|_| HashMap::with_capacity_and_hasher(self.len() / n, self.build_hasher.clone()))
+            .collect();
+
+        // safety: we own the map, so there cannot be any outstanding references to anything in
+        // it, just like when it is dropped.
+        let guard = unsafe { crossbeam_epoch::unprotected() };
+        assert!(self.next_table.load(Ordering::SeqCst, guard).is_null());
+        // the map is left without a table, and so drops nothing once we are done with it.
+        let table = self.table.swap(Shared::null(), Ordering::SeqCst, guard);
+        if !table.is_null() {
+            // safety: same as above + we own the table
+            let mut table = unsafe { table.into_owned() }.into_box();
+            let guards: Vec<_> = shards.iter().map(HashMap::guard).collect();
+            table.drain_bins(|bini, key, value| {
+                let shard = bini % n;
+                shards[shard].insert(key, value, &guards[shard]);
+            });
+        }
+        shards
     }
 
-    /// Replaces node value with `new_value`.
+    /// Creates a new [`HashSet`](crate::HashSet) that holds clones of all the keys of the map.
     ///
-    /// If an `observed_value` is provided, the replacement only happens if `observed_value` equals
-    /// the value that is currently associated with the given key.
+    /// The set is created with room for as many keys as the map holds, and filled in a single
+    /// pass over the map. Keys that are inserted into or removed from the map concurrently may
+    /// or may not be included.
     ///
-    /// If `new_value` is `None`, it removes the key (and its corresponding value) from this map.
+    /// # Examples
     ///
-    /// This method does nothing if the key is not in the map.
+    /// ```
+    /// use flurry::HashMap;
     ///
-    /// Returns the previous key and value associated with the given key.
+    /// let map = HashMap::new();
+    /// map.pin().insert("a", 1);
+    /// map.pin().insert("b", 2);
     ///
-    /// The key may be any borrowed form of the map's key type, but
-    /// [`Hash`] and [`Ord`] on the borrowed form *must* match those for
-    /// the key type.
+    /// let keys = map.pin().key_set();
+    /// assert_eq!(keys.len(), 2);
+    /// assert!(keys.pin().contains(&"a"));
+    /// ```
+    pub fn key_set(&self, guard: &Guard) -> crate::HashSet<K> {
+        self.check_guard(guard);
+        let keys = crate::HashSet::with_capacity(self.len());
+        {
+            let keys_guard = keys.guard();
+            for key in self.keys(guard) {
+                keys.insert(key.clone(), &keys_guard);
+            }
+        }
+        keys
+    }
+
+    /// Creates a new map from each value of this map to the [`HashSet`](crate::HashSet) of keys
+    /// that map to it.
     ///
-    /// [`Ord`]: std::cmp::Ord
-    /// [`Hash`]: std::hash::Hash
-    fn replace_node<'g, Q>(
-        &'g self,
-        key: &Q,
-        new_value: Option<V>,
-        observed_value: Option<Shared<'g, V>>,
-        guard: &'g Guard,
-    ) -> Option<(&'g K, &'g V)>
+    /// The keys and values are cloned into the new map, which is filled in a single pass over
+    /// this map. Entries that are inserted into or removed from the map concurrently may or may
+    /// not be included.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let owners = HashMap::new();
+    /// owners.pin().insert("a.txt", "alice");
+    /// owners.pin().insert("b.txt", "bob");
+    /// owners.pin().insert("c.txt", "alice");
+    ///
+    /// let files = owners.pin().invert();
+    /// assert_eq!(files.len(), 2);
+    /// assert_eq!(files.pin().get(&"alice").unwrap().len(), 2);
+    /// ```
+    pub fn invert(&self, guard: &Guard) -> HashMap<V, crate::HashSet<K>>
     where
-        K: Borrow<Q>,
-        Q: ?Sized + Hash + Ord,
+        V: Clone + Hash + Ord,
     {
-        let hash = self.hash(key);
-
-        let is_remove = new_value.is_none();
-        let mut old_val = None;
-        let mut table = self.table.load(Ordering::SeqCst, guard);
-        loop {
-            if table.is_null() {
-                break;
+        self.check_guard(guard);
+        let inverted: HashMap<V, crate::HashSet<K>> = HashMap::new();
+        {
+            let inverted_guard = inverted.guard();
+            for (key, value) in self.iter(guard) {
+                let keys = match inverted.get(value, &inverted_guard) {
+                    Some(keys) => keys,
+                    None => match inverted.try_insert(
+                        value.clone(),
+                        crate::HashSet::new(),
+                        &inverted_guard,
+                    ) {
+                        Ok(keys) => keys,
+                        Err(e) => e.current,
+                    },
+                };
+                keys.insert(key.clone(), &inverted_guard);
             }
+        }
+        inverted
+    }
 
-            // safety: table is a valid pointer.
-            //
-            // we are in one of two cases:
-            //
-            //  1. if table is the one we read before the loop, then we read it while holding the
-            //     guard, so it won't be dropped until after we drop that guard b/c the drop logic
-            //     only queues a drop for the next epoch after removing the table.
-            //
-            //  2. if table is set by a Moved node (below) through help_transfer, it will use the
-            //     `next_table` raw pointer from inside the Moved. to see that if a Moved(t) is
-            //     _read_, then t must still be valid, see the safety comment on Table.next_table.
-            let t = unsafe { table.deref() };
-            let n = t.len() as u64;
-            if n == 0 {
-                break;
+    /// Folds every entry of `other` into this map, using `resolve` for keys that both maps hold.
+    ///
+    /// Keys that are only in `other` are copied over as they are. For keys that are in both
+    /// maps, `resolve` is called with the value in this map and the value in `other`, in that
+    /// order, and its result replaces the value in this map. Each key is merged atomically, as
+    /// with [`compute_full`](HashMap::compute_full), so `resolve` should be short and simple,
+    /// and must not attempt to update this map. The merge as a whole is not atomic, and entries
+    /// that are written to `other` while it is being merged may or may not be included.
+    ///
+    /// Both maps are accessed with `guard`, so they must use the same collector.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let ours = HashMap::new();
+    /// let theirs = HashMap::new();
+    /// let guard = ours.guard();
+    /// ours.insert("a", 1, &guard);
+    /// ours.insert("b", 5, &guard);
+    /// theirs.insert("b", 3, &guard);
+    /// theirs.insert("c", 4, &guard);
+    ///
+    /// ours.merge_from(&theirs, |existing, incoming| existing + incoming, &guard);
+    /// assert_eq!(ours.get("a", &guard), Some(&1));
+    /// assert_eq!(ours.get("b", &guard), Some(&8));
+    /// assert_eq!(ours.get("c", &guard), Some(&4));
+    /// ```
+    pub fn merge_from<F>(&self, other: &HashMap<K, V, S>, resolve: F, guard: &Guard)
+    where
+        V: Clone,
+        F: Fn(&V, &V) -> V,
+    {
+        self.check_guard(guard);
+        for (key, incoming) in other.iter(guard) {
+            self.compute_full(
+                key,
+                |entry| {
+                    Some(match entry {
+                        Some((_, existing)) => resolve(existing, incoming),
+                        None => incoming.clone(),
+                    })
+                },
+                guard,
+            );
+        }
+    }
+
+    /// Moves every entry for which `pred` returns `true` from this map into `dest`, and returns
+    /// the number of entries moved.
+    ///
+    /// Each entry is first copied into `dest`, replacing any value `dest` has for its key, and
+    /// only then removed from this map, so a concurrent reader never finds the entry in neither
+    /// map, though it may find it in both. If the entry is written to concurrently between those
+    /// two steps, it is left in this map with its new value, and `dest` is rolled back to the
+    /// value it had for the key before, if any. Each entry is moved on its own; the move as a
+    /// whole is not atomic.
+    ///
+    /// Both maps are accessed with `guard`, so they must use the same collector.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `dest` is this map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let live = HashMap::new();
+    /// let archive = HashMap::new();
+    /// let guard = live.guard();
+    /// for day in 0..10 {
+    ///     live.insert(day, format!("log of day {}", day), &guard);
+    /// }
+    ///
+    /// assert_eq!(live.move_matching(&archive, |&day, _| day < 7, &guard), 7);
+    /// assert_eq!(live.len(), 3);
+    /// assert_eq!(archive.get(&0, &guard).unwrap(), "log of day 0");
+    /// ```
+    pub fn move_matching<P>(&self, dest: &HashMap<K, V, S>, pred: P, guard: &Guard) -> usize
+    where
+        V: Clone,
+        P: Fn(&K, &V) -> bool,
+    {
+        self.check_guard(guard);
+        assert!(
+            !std::ptr::eq(self, dest),
+            "cannot move the entries of a map into itself"
+        );
+        let mut moved = 0;
+        for (key, value) in self.iter(guard) {
+            if !pred(key, value) {
+                continue;
             }
-            let bini = t.bini(hash);
-            let bin = t.bin(bini, guard);
-            if bin.is_null() {
-                break;
+            let (copy, replaced) = match dest.compute_full(key, |_| Some(value.clone()), guard) {
+                Compute::Inserted(copy) => (copy, None),
+                Compute::Updated { old, new: copy } => (copy, Some(old)),
+                Compute::Removed(_) | Compute::NoOp => unreachable!("the copy is always stored"),
+            };
+            let observed = Shared::from(value as *const V);
+            if self
+                .replace_node(key, None, Some(observed), guard)
+                .is_some()
+            {
+                moved += 1;
+            } else {
+                // put back whatever `dest` held before, unless that has been overwritten too.
+                let copy = Shared::from(copy as *const V);
+                dest.replace_node(key, replaced.cloned(), Some(copy), guard);
             }
+        }
+        moved
+    }
 
-            // safety: bin is a valid pointer.
-            //
-            // there are three cases when a bin pointer is invalidated:
-            //
-            //  1. if the table was resized, bin is a move entry, and the resize has completed. in
-            //     that case, the table (and all its heads) will be dropped in the next epoch
-            //     following that.
-            //  2. if the table is being resized, bin may be swapped with a move entry. the old bin
-            //     will then be dropped in the following epoch after that happens.
-            //  3. when elements are inserted into or removed from the map, bin may be changed into
-            //     or from a TreeBin from or into a regular, linear bin. the old bin will also be
-            //     dropped in the following epoch if that happens.
-            //
-            // in all cases, we held the guard when we got the reference to the bin. if any such
-            // swap happened, it must have happened _after_ we read. since we did the read while
-            // pinning the epoch, the drop must happen in the _next_ epoch (i.e., the one that we
-            // are holding up by holding on to our guard).
-            match *unsafe { bin.deref() } {
-                BinEntry::Moved => {
-                    table = self.help_transfer(table, guard);
-                    continue;
+    /// Replaces the entire contents of the map with `entries`, swapping in the new contents all
+    /// at once.
+    ///
+    /// The new contents are assembled in a fresh table off to the side, which is then swapped in
+    /// for the current table. Lookups and iterators that start after the swap only ever see the
+    /// new entries. The bins of the old table are then forwarded to the new table one at a time,
+    /// just like a resize moves them, so a lookup or iterator that started before the swap sees,
+    /// for each bin, either the old entries or the new ones. Since the old bins are forwarded into
+    /// the new table, the new table is never smaller than the current one. If `entries` yields the
+    /// same key more than once, the last value wins.
+    ///
+    /// Writes from other threads that race with `replace_all` are never lost: a write that
+    /// reaches a bin of the old table before the bin is forwarded is replaced along with the rest
+    /// of the old entries, and one that comes later lands in the new table. Either way,
+    /// [`len`](HashMap::len) accounts for it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// map.pin().insert("timeout", 30);
+    ///
+    /// map.pin().replace_all(vec![("retries", 3), ("backoff", 2)]);
+    /// assert_eq!(map.pin().len(), 2);
+    /// assert_eq!(map.pin().get("timeout"), None);
+    /// assert_eq!(map.pin().get("retries"), Some(&3));
+    /// ```
+    pub fn replace_all<I>(&self, entries: I, guard: &Guard)
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        self.check_guard(guard);
+        self.replace_table(entries, DEFAULT_CAPACITY, guard);
+    }
+
+    /// Replaces the entire contents of the map with `entries`, reusing the capacity of the map's
+    /// current table.
+    ///
+    /// This is [`replace_all`](HashMap::replace_all) for periodically reloading a map whose size
+    /// stays about the same, such as one that holds configuration. Like with `replace_all`, the
+    /// new table is at least as large as the current one, so a map that has grown to its working
+    /// size does not have to grow again if the reloaded contents are smaller for a while. What
+    /// readers and racing writes see is the same as for `replace_all`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// let guard = map.guard();
+    /// for i in 0..1000 {
+    ///     map.insert(i, i, &guard);
+    /// }
+    /// let capacity = map.capacity(&guard);
+    ///
+    /// map.refill((0..10).map(|i| (i, i * 2)), &guard);
+    /// assert_eq!(map.len(), 10);
+    /// assert_eq!(map.get(&3, &guard), Some(&6));
+    /// assert_eq!(map.capacity(&guard), capacity);
+    /// ```
+    pub fn refill<I>(&self, entries: I, guard: &Guard)
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        self.check_guard(guard);
+        let capacity = self.capacity(guard).max(DEFAULT_CAPACITY);
+        self.replace_table(entries, capacity, guard);
+    }
+
+    /// Builds a table of at least `min_capacity` bins that holds `entries`, and swaps it in for
+    /// the map's current table.
+    ///
+    /// The bins of the current table are forwarded into the new one, which iterators can only
+    /// follow into a table that is at least as large, so the new table never has fewer bins than
+    /// the current one.
+    fn replace_table<I>(&self, entries: I, min_capacity: usize, guard: &Guard)
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let mut entries: Vec<_> = entries.into_iter().collect();
+        let mut min_capacity = min_capacity.max(self.capacity(guard));
+
+        // the entries we stamp only become visible once the new table is swapped in.
+        let pending = self.begin_write();
+        let (table, capacity, count) = loop {
+            // size the table like try_presize would.
+            let capacity = self.table_size_for(entries.len()).max(min_capacity);
+            let (mut table, count) = self.build_table(entries, capacity, guard);
+
+            // take the same lock as the table initialization does, so that we do not swap out a
+            // table that is in the middle of being resized.
+            let sc = self.lock_table();
+            // safety: we loaded the table while epoch was pinned. table won't be deallocated
+            // until next epoch at the earliest.
+            let current = unsafe { self.table.load(Ordering::SeqCst, guard).as_ref() };
+            match current {
+                Some(current) if current.len() > capacity => {
+                    // the table grew while we built ours, so build a larger one.
+                    self.size_ctl.store(sc, Ordering::SeqCst);
+                    min_capacity = current.len();
+                    entries = Vec::with_capacity(count as usize);
+                    table.drain_bins(|_, key, value| entries.push((key, value)));
                 }
-                BinEntry::Node(ref head) => {
-                    let head_lock = head.lock.lock();
+                _ => break (table, capacity, count),
+            }
+        };
 
-                    // need to check that this is _still_ the head
-                    if t.bin(bini, guard) != bin {
-                        continue;
+        let table = Owned::new(table).into_shared(guard);
+        let old_table = self.table.load(Ordering::SeqCst, guard);
+        // safety: we loaded the table while epoch was pinned. table won't be deallocated until
+        // next epoch at the earliest.
+        let old = unsafe { old_table.as_ref() };
+        // point the old table at the new one _before_ publishing the new one, so that any thread
+        // that finds a Moved bin in the old table knows where to go.
+        let moved = old.map(|old| old.get_moved(table, guard));
+        self.table.store(table, Ordering::SeqCst);
+        drop(pending);
+        self.size_ctl
+            .store(self.resize_threshold(capacity), Ordering::SeqCst);
+
+        let mut delta = count;
+        if let (Some(old), Some(moved)) = (old, moved) {
+            delta -= self.forward_bins(old, moved, guard, |_, _| {});
+            // safety: all bins of the old table are Moved now, so it no longer owns any entries.
+            // threads that still hold a reference to it loaded it before we swapped in the new
+            // table, and must thus be pinned to an epoch <= ours.
+            unsafe { guard.defer_destroy(old_table) };
+        }
+        if delta != 0 {
+            self.add_count(delta, None, guard);
+        }
+        guard.flush();
+    }
+
+    /// Fills a new table of `capacity` bins with `entries`, and returns it along with the number
+    /// of entries it holds. If `entries` holds the same key more than once, the last value wins.
+    fn build_table(
+        &self,
+        entries: Vec<(K, V)>,
+        capacity: usize,
+        guard: &Guard,
+    ) -> (Table<K, V>, isize) {
+        // the new table is not shared with anyone until it is swapped in, so we can fill it
+        // without taking any locks.
+        let table = Table::new(capacity);
+        let mut count = 0;
+        for (key, value) in entries {
+            let hash = self.hash(&key);
+            let bini = table.bini(hash);
+            let head = table.bin(bini, guard);
+            // safety: all bins of the new table were created by us, and are not freed until the
+            // table is.
+            let existing = match unsafe { head.as_ref() } {
+                Some(bin) => table.find(bin, hash, &key, guard),
+                None => Shared::null(),
+            };
+            // safety: as above.
+            if let Some(BinEntry::Node(node)) = unsafe { existing.as_ref() } {
+                let old_value = node.value.swap(Owned::new(value), Ordering::SeqCst, guard);
+                let _ = self.stamp(node);
+                // safety: the new table has not been shared, so no-one else can have a reference
+                // to the old value.
+                drop(unsafe { old_value.into_owned() });
+                continue;
+            }
+            let node = Node::with_next(hash, key, Owned::new(value), Atomic::from(head));
+            let _ = self.stamp(&node);
+            table.store_bin(bini, Owned::new(BinEntry::Node(node)));
+            count += 1;
+        }
+        if capacity >= MIN_TREEIFY_CAPACITY {
+            for i in 0..capacity {
+                // safety: as above.
+                if let Some(BinEntry::Node(node)) = unsafe { table.bin(i, guard).as_ref() } {
+                    if Self::chain_len(node, guard) >= TREEIFY_THRESHOLD {
+                        self.treeify_bin(&table, i, guard);
                     }
+                }
+            }
+        }
+
+        (table, count)
+    }
+
+    /// Empties the map, and returns what it held.
+    ///
+    /// The current table is swapped out for a fresh, empty table of the same capacity all at
+    /// once, so lookups and iterators that start after the swap see none of the old entries. The
+    /// bins of the old table are then forwarded to the new table one at a time, just like a
+    /// resize moves them, so a lookup or iterator that started before the swap sees, for each
+    /// bin, either all of its old entries or none of them. Writes that race with `take_all` end
+    /// up either in the returned map or in this map, but are never lost: every bin of the old
+    /// table is locked and forwarded before its entries are copied out.
+    ///
+    /// Since readers may still hold references to the old entries, the keys and values are cloned
+    /// into the returned map, and the originals are freed once no guard can see them anymore.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// map.pin().insert("a", 1);
+    /// map.pin().insert("b", 2);
+    ///
+    /// let taken = map.pin().take_all();
+    /// assert_eq!(taken.len(), 2);
+    /// assert_eq!(taken["a"], 1);
+    /// assert!(map.pin().is_empty());
+    /// ```
+    pub fn take_all(&self, guard: &Guard) -> std::collections::HashMap<K, V>
+    where
+        V: Clone,
+    {
+        self.check_guard(guard);
 
-                    let mut e = bin;
-                    let mut pred: Shared<'_, BinEntry<K, V>> = Shared::null();
-                    loop {
-                        // safety: either e is bin, in which case it is valid due to the above,
-                        // or e was obtained from a next pointer. Any next pointer obtained from
-                        // bin is valid at the time we look up bin in the table, at which point
-                        // the epoch is pinned by our guard. Since we found the next pointer
-                        // in a valid map and it is not null (as checked above and below), the
-                        // node it points to was present (i.e. not removed) from the map in the
-                        // current epoch. Thus, because the epoch cannot advance until we release
-                        // our guard, e is also valid if it was obtained from a next pointer.
-                        let n = unsafe { e.deref() }.as_node().unwrap();
-                        let next = n.next.load(Ordering::SeqCst, guard);
-                        if n.hash == hash && n.key.borrow() == key {
-                            let ev = n.value.load(Ordering::SeqCst, guard);
+        // take the same lock as the table initialization does, so that we do not swap out a
+        // table that is in the middle of being resized.
+        let sc = self.lock_table();
 
-                            // only replace the node if the value is the one we expected at method call
-                            if observed_value.map(|ov| ov == ev).unwrap_or(true) {
-                                // we remember the old value so that we can return it and mark it for deletion below
-                                old_val = Some((&n.key, ev));
+        let old_table = self.table.load(Ordering::SeqCst, guard);
+        // safety: we loaded the table while epoch was pinned. table won't be deallocated until
+        // next epoch at the earliest.
+        let capacity = match unsafe { old_table.as_ref() } {
+            Some(t) if !t.is_empty() => t.len(),
+            _ => {
+                // there is nothing to take
+                self.size_ctl.store(sc, Ordering::SeqCst);
+                return std::collections::HashMap::new();
+            }
+        };
+        // safety: as above.
+        let old = unsafe { old_table.deref() };
+
+        // point the old table at the new one _before_ publishing the new one, so that any thread
+        // that finds a Moved bin in the old table knows where to go.
+        let table = Owned::new(Table::new(capacity)).into_shared(guard);
+        let moved = old.get_moved(table, guard);
+        self.table.store(table, Ordering::SeqCst);
+        self.size_ctl
+            .store(self.resize_threshold(capacity), Ordering::SeqCst);
+
+        let mut taken = std::collections::HashMap::new();
+        let retired = self.forward_bins(old, moved, guard, |key, value| {
+            taken.insert(key.clone(), value.clone());
+        });
 
-                                // found the node but we have a new value to replace the old one
-                                if let Some(nv) = new_value {
-                                    n.value.store(Owned::new(nv), Ordering::SeqCst);
-                                    // we are just replacing entry value and we do not want to remove the node
-                                    // so we stop iterating here
-                                    break;
-                                }
-                                // remove the BinEntry containing the removed key value pair from the bucket
-                                if !pred.is_null() {
-                                    // either by changing the pointer of the previous BinEntry, if present
-                                    // safety: as above
-                                    unsafe { pred.deref() }
-                                        .as_node()
-                                        .unwrap()
-                                        .next
-                                        .store(next, Ordering::SeqCst);
-                                } else {
-                                    // or by setting the next node as the first BinEntry if there is no previous entry
-                                    t.store_bin(bini, next);
-                                }
+        // safety: all bins of the old table are Moved now, so it no longer owns any entries.
+        // threads that still hold a reference to it loaded it before we swapped in the new table,
+        // and must thus be pinned to an epoch <= ours.
+        unsafe { guard.defer_destroy(old_table) };
+        if retired != 0 {
+            self.add_count(-retired, None, guard);
+        }
+        taken
+    }
 
-                                // in either case, mark the BinEntry as garbage, since it was just removed
-                                // safety: as for val below / in put
-                                unsafe { guard.defer_destroy(e) };
-                            }
-                            // since the key was found and only one node exists per key, we can break here
-                            break;
-                        }
-                        pred = e;
-                        if next.is_null() {
-                            break;
-                        } else {
-                            e = next;
-                        }
+    /// Forwards every bin of `old`, which has just been replaced as the map's table, to the new
+    /// table through `moved`, and retires the entries it held. `f` is called with each of those
+    /// entries first. Returns the number of entries retired.
+    ///
+    /// Each bin is locked and marked as moved before its entries are retired, just like a resize
+    /// does, so a writer that still holds on to the old table either finishes its write before
+    /// the bin is forwarded, in which case the write is retired along with the bin, or follows
+    /// the forwarding to the new table. No write is lost, and `count` stays in line with the
+    /// entries the map holds as long as the caller adjusts it by the returned number.
+    ///
+    /// The table lock must have been held while `old` was replaced, so that no resize of `old`
+    /// can be in progress.
+    fn forward_bins<'g, F>(
+        &'g self,
+        old: &'g Table<K, V>,
+        moved: Shared<'g, BinEntry<K, V>>,
+        guard: &'g Guard,
+        mut f: F,
+    ) -> isize
+    where
+        F: FnMut(&'g K, &'g V),
+    {
+        let mut retired = 0;
+        for i in 0..old.len() {
+            loop {
+                let head = old.bin(i, guard);
+                if head.is_null() {
+                    if old.cas_bin(i, head, moved, guard).is_ok() {
+                        break;
                     }
-                    drop(head_lock);
+                    // someone inserted into the bin just now
+                    continue;
                 }
-                BinEntry::Tree(ref tree_bin) => {
-                    let bin_lock = tree_bin.lock.lock();
-
-                    // need to check that this is _still_ the head
-                    if t.bin(bini, guard) != bin {
-                        continue;
+                // safety: the bin was read under our guard, so it has not been dropped.
+                match unsafe { head.deref() } {
+                    BinEntry::Moved => {
+                        unreachable!("no resize was in progress when the table was replaced")
                     }
-
-                    let root = tree_bin.root.load(Ordering::SeqCst, guard);
-                    if root.is_null() {
-                        // we are in the correct bin for the given key's hash and the bin is not
-                        // `Moved`, but it is empty. This means there is no node for the given
-                        // key that could be replaced
-                        // TODO: Is this even reachable?
-                        // The Java code has `NULL` checks for this, but in theory it should not be possible to
-                        // encounter a tree that has no root when we have its lock. It should always have at
-                        // least `UNTREEIFY_THRESHOLD` nodes. If it is indeed impossible we should replace
-                        // this with an assertion instead.
-                        break;
+                    BinEntry::Reserved(ref lock) => {
+                        // another thread is computing the first entry of this bin. wait until it
+                        // is done, and then look at the bin again.
+                        drop(self.wait_for_bin(lock));
                     }
-                    let p = TreeNode::find_tree_node(root, hash, key, guard);
-                    if p.is_null() {
-                        // similarly to the above, there now are entries in this bin, but none of
-                        // them matches the given key
+                    BinEntry::Node(ref node) => {
+                        let head_lock = self.wait_for_bin(&node.lock);
+                        // need to check that this is _still_ the head
+                        if old.bin(i, guard) != head {
+                            continue;
+                        }
+                        // from here on, writers that find the bin go to the new table instead.
+                        old.store_bin(i, moved);
+                        drop(head_lock);
+
+                        let mut p = head;
+                        while !p.is_null() {
+                            // safety: we loaded p under guard, and guard is still pinned, so p
+                            // has not been dropped.
+                            let node = unsafe { p.deref() }
+                                .as_node()
+                                .expect("entry following Node should always be a Node");
+                            let next = node.next.load(Ordering::SeqCst, guard);
+                            let value = node.value.load(Ordering::SeqCst, guard);
+                            // safety: as above. values are only ever freed after they are
+                            // unlinked, which requires the bin lock.
+                            f(&node.key, unsafe { value.deref() });
+                            retired += 1;
+                            // safety: any thread that sees this node or its value must have read
+                            // the bin before we stored Moved into it above. it must also have
+                            // pinned the epoch before that time. therefore, the defer_destroy
+                            // below won't be executed until that thread's guard is dropped, at
+                            // which point it holds no outstanding references to either.
+                            unsafe { self.retire_value(&node.key, value, guard) };
+                            unsafe { guard.defer_destroy(p) };
+                            p = next;
+                        }
                         break;
                     }
-                    // safety: the TreeBin was read under our guard,
-                    // at which point the tree structure was valid.
-                    // Since our guard pins the current epoch, the
-                    // TreeNodes and `p` in particular remain valid
-                    // for at least as long as we hold onto the
-                    // guard.
-                    // Structurally, TreeNodes always point to TreeNodes, so this is sound.
-                    let n = &unsafe { TreeNode::get_tree_node(p) }.node;
-                    let pv = n.value.load(Ordering::SeqCst, guard);
-
-                    // only replace the node if the value is the one we expected at method call
-                    if observed_value.map(|ov| ov == pv).unwrap_or(true) {
-                        // we remember the old value so that we can return it and mark it for deletion below
-                        old_val = Some((&n.key, pv));
-
-                        if let Some(nv) = new_value {
-                            // found the node but we have a new value to replace the old one
-                            n.value.store(Owned::new(nv), Ordering::SeqCst);
-                        } else {
-                            // drop `p` without its value, since the old value is dropped
-                            // in the check on `old_val` below
-                            // safety: `p` is either marked for garbage collection in `remove_tree_node` directly,
-                            // or we will `need_to_untreeify`. In the latter case, we `defer_destroy` `p` below,
-                            // after storing the linear bin. The value stored in `p` is `defer_destroy`ed from within
-                            // `old_val` at the end of the method. Thus, everything is always marked for garbage
-                            // collection _after_ it becomes unaccessible by other threads.
-                            let need_to_untreeify =
-                                unsafe { tree_bin.remove_tree_node(p, false, guard) };
-                            if need_to_untreeify {
-                                let linear_bin = Self::untreeify(
-                                    tree_bin.first.load(Ordering::SeqCst, guard),
-                                    guard,
-                                );
-                                t.store_bin(bini, linear_bin);
-                                // the old bin is now garbage, but its values are not,
-                                // since they get re-used in the linear bin
-                                // safety: same as in put
-                                unsafe {
-                                    TreeBin::defer_drop_without_values(bin, guard);
-                                    guard.defer_destroy(p);
-                                }
-                            }
+                    BinEntry::Tree(ref tree_bin) => {
+                        let bin_lock = self.wait_for_bin(&tree_bin.lock);
+                        // need to check that this is _still_ the correct bin
+                        if old.bin(i, guard) != head {
+                            continue;
+                        }
+                        old.store_bin(i, moved);
+                        drop(bin_lock);
+
+                        let mut p = tree_bin.first.load(Ordering::SeqCst, guard);
+                        while !p.is_null() {
+                            // safety: the TreeBin was read under our guard, at which point the
+                            // tree was valid. Since our guard pins the current epoch, the
+                            // TreeNodes remain valid for at least as long as we hold onto the
+                            // guard. Structurally, TreeNodes always point to TreeNodes, so this
+                            // is sound.
+                            let tree_node = unsafe { TreeNode::get_tree_node(p) };
+                            let value = tree_node.node.value.load(Ordering::SeqCst, guard);
+                            // safety: as for the values of a linear bin above.
+                            f(&tree_node.node.key, unsafe { value.deref() });
+                            retired += 1;
+                            // safety: same as in the BinEntry::Node case above
+                            unsafe { self.retire_value(&tree_node.node.key, value, guard) };
+                            p = tree_node.node.next.load(Ordering::SeqCst, guard);
                         }
+                        // the TreeNodes are dropped together with the containing TreeBin.
+                        // safety: same as in the BinEntry::Node case above
+                        unsafe { TreeBin::defer_drop_without_values(head, guard) };
+                        break;
                     }
-
-                    drop(bin_lock);
+                    BinEntry::TreeNode(_) => unreachable!(
+                        "The head of a bin cannot be a TreeNode directly without BinEntry::Tree"
+                    ),
                 }
-                BinEntry::TreeNode(_) => unreachable!(
-                    "The head of a bin cannot be a TreeNode directly without BinEntry::Tree"
-                ),
             }
-            if let Some((key, val)) = old_val {
-                if is_remove {
-                    self.add_count(-1, None, guard);
-                }
+        }
 
-                // safety: need to guarantee that the old value is no longer
-                // reachable. more specifically, no thread that executes _after_
-                // this line can ever get a reference to val.
-                //
-                // here are the possible cases:
-                //
-                //  - another thread already has a reference to the old value.
-                //    they must have read it before the call to store_bin.
-                //    because of this, that thread must be pinned to an epoch <=
-                //    the epoch of our guard. since the garbage is placed in our
-                //    epoch, it won't be freed until the _next_ epoch, at which
-                //    point, that thread must have dropped its guard, and with it,
-                //    any reference to the value.
-                //  - another thread is about to get a reference to this value.
-                //    they execute _after_ the store_bin, and therefore do _not_ get a
-                //    reference to the old value. there are no other ways to get to a
-                //    value except through its Node's `value` field (which is now gone
-                //    together with the node), so freeing the old value is fine.
-                unsafe { guard.defer_destroy(val) };
+        retired
+    }
 
-                // safety: the lifetime of the reference is bound to the guard
-                // supplied which means that the memory will not be freed
-                // until at least after the guard goes out of scope
-                return unsafe { val.as_ref() }.map(move |v| (key, v));
+    /// Removes the entry with the smallest value according to `cmp`, and returns clones of its
+    /// key and value.
+    ///
+    /// This scans the whole map for the smallest value, and then removes its entry, as long as
+    /// the entry still holds that very value. If another thread removed or replaced the value in
+    /// the meantime, the scan starts over, so no two calls ever pop the same entry. Ties are
+    /// broken arbitrarily.
+    ///
+    /// Together with [`insert`](HashMap::insert), this makes the map usable as a concurrent
+    /// priority queue, albeit one whose pops take time linear in the size of the map. Like
+    /// [`take_all`](HashMap::take_all), this clones the popped key and value, since readers may
+    /// still hold references to the originals.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let tasks = HashMap::new();
+    /// let guard = tasks.guard();
+    /// tasks.insert("write docs", 2, &guard);
+    /// tasks.insert("fix bug", 1, &guard);
+    ///
+    /// assert_eq!(tasks.pop_min_by(Ord::cmp, &guard), Some(("fix bug", 1)));
+    /// assert_eq!(tasks.pop_min_by(Ord::cmp, &guard), Some(("write docs", 2)));
+    /// assert_eq!(tasks.pop_min_by(Ord::cmp, &guard), None);
+    /// ```
+    pub fn pop_min_by<F>(&self, cmp: F, guard: &Guard) -> Option<(K, V)>
+    where
+        V: Clone,
+        F: Fn(&V, &V) -> std::cmp::Ordering,
+    {
+        self.check_guard(guard);
+        loop {
+            let mut min: Option<(&K, &V)> = None;
+            for (key, value) in self.iter(guard) {
+                let smaller = match min {
+                    Some((_, m)) => cmp(value, m) == std::cmp::Ordering::Less,
+                    None => true,
+                };
+                if smaller {
+                    min = Some((key, value));
+                }
+            }
+            let (key, value) = min?;
+
+            // only remove the value we found, not one that replaced it concurrently.
+            let observed = Shared::from(value as *const _);
+            if self
+                .replace_node(key, None, Some(observed), guard)
+                .is_some()
+            {
+                return Some((key.clone(), value.clone()));
             }
-            break;
         }
-        None
     }
 
     /// Retains only the elements specified by the predicate.
@@ -2697,37 +8779,147 @@ where
         }
     }
 
-    /// Retains only the elements specified by the predicate.
-    ///
-    /// In other words, remove all pairs `(k, v)` such that `f(&k,&v)` returns `false`.
+    /// Retains only the elements specified by the predicate.
+    ///
+    /// In other words, remove all pairs `(k, v)` such that `f(&k,&v)` returns `false`.
+    ///
+    /// This method always deletes any key/value pair that `f` returns `false` for, even if if the
+    /// value is updated concurrently. If you do not want that behavior, use [`HashMap::retain`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    ///
+    /// for i in 0..8 {
+    ///     map.pin().insert(i, i*10);
+    /// }
+    /// map.pin().retain_force(|&k, _| k % 2 == 0);
+    /// assert_eq!(map.pin().len(), 4);
+    /// ```
+    pub fn retain_force<F>(&self, mut f: F, guard: &Guard)
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        self.check_guard(guard);
+        // removed selected keys
+        for (k, v) in self.iter(guard) {
+            if !f(k, v) {
+                self.replace_node(k, None, None, guard);
+            }
+        }
+    }
+}
+
+impl<K, S> HashMap<K, u32, S>
+where
+    K: 'static + Sync + Send + Clone + Hash + Ord,
+    S: BuildHasher,
+{
+    /// Returns the id of `key`, and assigns it the next unused id first if it does not have one.
+    ///
+    /// This turns the map into an interner: ids are handed out sequentially from 0 by a counter
+    /// in the map, and a key is inserted together with its id in a single atomic step, so even
+    /// when several threads intern the same key at once, exactly one id is assigned to it, and
+    /// every thread gets that id back. No two keys are ever given the same id, and ids are not
+    /// reused, so a key that is removed and then interned again gets a new id.
+    ///
+    /// Ids should only be assigned through `intern`. A value that is inserted for a key in some
+    /// other way is treated as the id of that key, but may clash with ids that `intern` assigns.
+    /// [`Interner`](crate::Interner) wraps a map so that ids can only be assigned through
+    /// `intern`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if all `u32::MAX` ids have been handed out.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let symbols = HashMap::new();
+    /// let guard = symbols.guard();
+    /// assert_eq!(symbols.intern(String::from("foo"), &guard), 0);
+    /// assert_eq!(symbols.intern(String::from("bar"), &guard), 1);
+    /// assert_eq!(symbols.intern(String::from("foo"), &guard), 0);
+    /// ```
+    pub fn intern(&self, key: K, guard: &Guard) -> u32 {
+        if let Some(&id) = self.get_uncounted(&key, guard) {
+            return id;
+        }
+        match self.compute_full(
+            &key,
+            |current| match current {
+                Some((_, &id)) => Some(id),
+                None => Some(
+                    self.next_id
+                        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |id| id.checked_add(1))
+                        .expect("ran out of ids to intern keys with"),
+                ),
+            },
+            guard,
+        ) {
+            Compute::Inserted(&id) | Compute::Updated { new: &id, .. } => id,
+            Compute::Removed(_) | Compute::NoOp => unreachable!("interning always maps the key"),
+        }
+    }
+}
+
+impl<K, S> HashMap<K, u64, S>
+where
+    K: 'static + Sync + Send + Clone + Hash + Ord,
+    S: BuildHasher,
+{
+    /// Adds `delta` to the counter for `key`, unless that would take it above `cap`.
     ///
-    /// This method always deletes any key/value pair that `f` returns `false` for, even if if the
-    /// value is updated concurrently. If you do not want that behavior, use [`HashMap::retain`].
+    /// A key that is not present counts as 0, and is inserted with a value of `delta`. Returns
+    /// `Ok` with the new value of the counter if `delta` was added, and `Err` with its current
+    /// value, which is left as it is, if the sum would be greater than `cap`. Checking the sum
+    /// against `cap` and storing it is a single atomic step, so no matter how many threads add
+    /// to the same counter at once, it never goes above `cap`, which makes this suitable for
+    /// rate limits and token buckets.
     ///
     /// # Examples
     ///
     /// ```
     /// use flurry::HashMap;
     ///
-    /// let map = HashMap::new();
-    ///
-    /// for i in 0..8 {
-    ///     map.pin().insert(i, i*10);
-    /// }
-    /// map.pin().retain_force(|&k, _| k % 2 == 0);
-    /// assert_eq!(map.pin().len(), 4);
+    /// let requests = HashMap::new();
+    /// let guard = requests.guard();
+    /// assert_eq!(requests.increment_capped("client", 2, 3, &guard), Ok(2));
+    /// assert_eq!(requests.increment_capped("client", 2, 3, &guard), Err(2));
+    /// assert_eq!(requests.increment_capped("client", 1, 3, &guard), Ok(3));
     /// ```
-    pub fn retain_force<F>(&self, mut f: F, guard: &Guard)
-    where
-        F: FnMut(&K, &V) -> bool,
-    {
+    pub fn increment_capped(
+        &self,
+        key: K,
+        delta: u64,
+        cap: u64,
+        guard: &Guard,
+    ) -> Result<u64, u64> {
         self.check_guard(guard);
-        // removed selected keys
-        for (k, v) in self.iter(guard) {
-            if !f(k, v) {
-                self.replace_node(k, None, None, guard);
-            }
-        }
+        let mut outcome = Err(0);
+        self.compute_locked(
+            &[&key],
+            |current| {
+                let current = current[0].copied().unwrap_or(0);
+                match current.checked_add(delta).filter(|&new| new <= cap) {
+                    Some(new) => {
+                        outcome = Ok(new);
+                        vec![Some(Some(new))]
+                    }
+                    None => {
+                        outcome = Err(current);
+                        vec![None]
+                    }
+                }
+            },
+            guard,
+        );
+        outcome
     }
 }
 
@@ -2738,10 +8930,27 @@ where
     /// Replaces all linked nodes in the bin at the given index unless the table
     /// is too small, in which case a resize is initiated instead.
     fn treeify_bin<'g>(&'g self, tab: &Table<K, V>, index: usize, guard: &'g Guard) {
+        self.try_treeify_bin(tab, index, true, guard);
+    }
+
+    /// Like `treeify_bin`, except that if `blocking` is `false`, the bin is left as it is if its
+    /// lock is held by another thread, and growing a small table is left to the next write that
+    /// may block.
+    fn try_treeify_bin<'g>(
+        &'g self,
+        tab: &Table<K, V>,
+        index: usize,
+        blocking: bool,
+        guard: &'g Guard,
+    ) {
         let n = tab.len();
         if n < MIN_TREEIFY_CAPACITY {
-            self.try_presize(n << 1, guard);
-        } else {
+            if blocking {
+                self.try_presize(n << 1, guard);
+            } else {
+                self.defer_growth(n << 1);
+            }
+        } else if self.treeify {
             let bin = tab.bin(index, guard);
             if bin.is_null() {
                 return;
@@ -2751,7 +8960,11 @@ where
             // won't be dropped until after we release our guard.
             match unsafe { bin.deref() } {
                 BinEntry::Node(ref node) => {
-                    let lock = node.lock.lock();
+                    let lock = match self.lock_bin(&node.lock, blocking, &mut 0) {
+                        Some(lock) => lock,
+                        // the next insert into the bin tries again.
+                        None => return,
+                    };
                     // check if `bin` is still the head
                     if tab.bin(index, guard) != bin {
                         return;
@@ -2778,6 +8991,7 @@ where
                             Atomic::null(),
                             Atomic::null(),
                         );
+                        new_tree_node.node.copy_metadata(e_deref);
                         new_tree_node.prev.store(tail, Ordering::Relaxed);
                         let new_tree_node =
                             Owned::new(BinEntry::TreeNode(new_tree_node)).into_shared(guard);
@@ -2870,6 +9084,10 @@ where
                     // need to perform any action on the bin anymore, since either it has already
                     // been treeified or it was moved to a new table.
                 }
+                BinEntry::Reserved(_) => {
+                    // The bin was emptied and is being refilled by a compute. In either case it
+                    // is far below the `TREEIFY_THRESHOLD` again.
+                }
                 BinEntry::TreeNode(_) => unreachable!("TreeNode cannot be the head of a bin"),
             }
         }
@@ -2895,12 +9113,13 @@ where
             let q_deref = unsafe { q.deref() }.as_tree_node().unwrap();
             // NOTE: cloning the value uses a load with Ordering::Relaxed, but
             // write access is synchronized through the bin lock
-            let new_node = Owned::new(BinEntry::Node(Node::new(
+            let new_node = Node::new(
                 q_deref.node.hash,
                 q_deref.node.key.clone(),
                 q_deref.node.value.clone(),
-            )))
-            .into_shared(guard);
+            );
+            new_node.copy_metadata(&q_deref.node);
+            let new_node = Owned::new(BinEntry::Node(new_node)).into_shared(guard);
             if tail.is_null() {
                 head = new_node;
             } else {
@@ -2919,6 +9138,183 @@ where
         head
     }
 }
+
+/// Exchanges the contents of two maps.
+///
+/// The tables of the two maps are swapped, along with their sizes, so no entries are copied, and
+/// the swap takes constant time. Readers of either map see either all of its old entries or all
+/// of its new ones, but never a mix of the two, which makes this a good fit for double
+/// buffering: fill a standby map off to the side, and then swap it with the map that readers
+/// use. The two maps are not swapped at a single point in time, so a reader that looks at both
+/// maps during the swap may find the same contents in both.
+///
+/// Only reads may race with the swap: the caller must make sure that no other thread writes to
+/// either map until `swap` returns, for example by only ever writing to the standby map from the
+/// thread that swaps it in. A write that races with the swap is not lost, but it may be applied to
+/// a table after that table has moved to the other map, and then ends up in the wrong map and
+/// throws off the [`len`](HashMap::len) of both.
+///
+/// Since the entries stay in their tables, they stay where the hasher of their old map put them.
+/// The two maps must therefore use hashers that hash alike, such as clones of one another or
+/// [`DefaultHashBuilder`](crate::DefaultHashBuilder)s created with the same seed.
+///
+/// # Panics
+///
+/// Panics if the hashers of the two maps do not hash alike.
+///
+/// # Examples
+///
+/// ```
+/// use flurry::HashMap;
+///
+/// let active = HashMap::new();
+/// let standby = HashMap::with_hasher(active.hasher().clone());
+/// let guard = active.guard();
+/// active.insert("timeout", 30, &guard);
+/// standby.insert("timeout", 60, &guard);
+///
+/// flurry::swap(&active, &standby, &guard);
+/// assert_eq!(active.get("timeout", &guard), Some(&60));
+/// assert_eq!(standby.get("timeout", &guard), Some(&30));
+/// ```
+pub fn swap<K, V, S>(a: &HashMap<K, V, S>, b: &HashMap<K, V, S>, guard: &Guard)
+where
+    S: BuildHasher,
+{
+    a.check_guard(guard);
+    b.check_guard(guard);
+    if std::ptr::eq(a, b) {
+        return;
+    }
+    // this is not a proof that the hashers hash everything alike, but hashers with different
+    // keys, like two default ones, essentially never agree on the hash of a value.
+    let probe = |map: &HashMap<K, V, S>| {
+        let mut h = map.build_hasher.build_hasher();
+        h.write_u64(0x9e37_79b9_7f4a_7c15);
+        h.finish()
+    };
+    assert_eq!(
+        probe(a),
+        probe(b),
+        "can only swap maps whose hashers hash alike"
+    );
+
+    // lock the maps in a fixed order, so that concurrent swaps of the same two maps cannot
+    // deadlock.
+    let (first, second) = if (a as *const HashMap<K, V, S>) < (b as *const HashMap<K, V, S>) {
+        (a, b)
+    } else {
+        (b, a)
+    };
+    let first_sc = first.lock_table();
+    let second_sc = second.lock_table();
+
+    let first_table = first.table.load(Ordering::SeqCst, guard);
+    let second_table = second.table.swap(first_table, Ordering::SeqCst, guard);
+    first.table.store(second_table, Ordering::SeqCst);
+    // move the sizes over as deltas, so that the count changes of any racing writes survive.
+    let moved = second.count.load(Ordering::SeqCst) - first.count.load(Ordering::SeqCst);
+    first.count.fetch_add(moved, Ordering::SeqCst);
+    second.count.fetch_sub(moved, Ordering::SeqCst);
+
+    // entries keep the versions and sequence numbers their old map gave them, so both maps must
+    // now hand out numbers above those of either map.
+    #[cfg(feature = "versioning")]
+    {
+        let version = std::cmp::max(
+            first.version.load(Ordering::SeqCst),
+            second.version.load(Ordering::SeqCst),
+        );
+        first.version.fetch_max(version, Ordering::SeqCst);
+        second.version.fetch_max(version, Ordering::SeqCst);
+    }
+    #[cfg(feature = "insertion-order")]
+    {
+        let seq = std::cmp::max(
+            first.seq.load(Ordering::SeqCst),
+            second.seq.load(Ordering::SeqCst),
+        );
+        first.seq.fetch_max(seq, Ordering::SeqCst);
+        second.seq.fetch_max(seq, Ordering::SeqCst);
+    }
+
+    // the resize thresholds go with the tables.
+    first.size_ctl.store(second_sc, Ordering::SeqCst);
+    second.size_ctl.store(first_sc, Ordering::SeqCst);
+}
+
+/// The differences between two maps, as computed by [`diff`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MapDiff<K, V> {
+    /// The entries whose keys are only in the new map.
+    pub added: Vec<(K, V)>,
+    /// The entries whose keys are only in the old map.
+    pub removed: Vec<(K, V)>,
+    /// The keys that are in both maps with different values, along with their old and new
+    /// values.
+    pub changed: Vec<(K, V, V)>,
+}
+
+/// Computes which keys were added, removed, and changed between `old` and `new`.
+///
+/// This supports propagating changes and audit logging: take a snapshot of a map, for example
+/// with [`Clone`], and diff it against the map later on. The entries of the diff are cloned, so
+/// they outlive the guards. Since keys that are only in one of the maps can only be found by
+/// walking that map, both maps are walked once, and every key is looked up in the other map. As
+/// with all operations on a concurrent map, the diff may not reflect modifications that race
+/// with it.
+///
+/// `old_guard` is used to access `old`, and `new_guard` to access `new`.
+///
+/// # Examples
+///
+/// ```
+/// use flurry::HashMap;
+///
+/// let old: HashMap<_, _> = vec![(1, "a"), (2, "b")].into_iter().collect();
+/// let new: HashMap<_, _> = vec![(2, "c"), (3, "d")].into_iter().collect();
+///
+/// let diff = flurry::diff(&old, &new, &old.guard(), &new.guard());
+/// assert_eq!(diff.added, vec![(3, "d")]);
+/// assert_eq!(diff.removed, vec![(1, "a")]);
+/// assert_eq!(diff.changed, vec![(2, "b", "c")]);
+/// ```
+pub fn diff<K, V, S>(
+    old: &HashMap<K, V, S>,
+    new: &HashMap<K, V, S>,
+    old_guard: &Guard,
+    new_guard: &Guard,
+) -> MapDiff<K, V>
+where
+    K: Clone + Hash + Ord,
+    V: Clone + PartialEq,
+    S: BuildHasher,
+{
+    old.check_guard(old_guard);
+    new.check_guard(new_guard);
+    let mut diff = MapDiff {
+        added: Vec::new(),
+        removed: Vec::new(),
+        changed: Vec::new(),
+    };
+    for (key, value) in old.iter(old_guard) {
+        match new.get(key, new_guard) {
+            None => diff.removed.push((key.clone(), value.clone())),
+            Some(new_value) if new_value != value => {
+                diff.changed
+                    .push((key.clone(), value.clone(), new_value.clone()))
+            }
+            Some(_) => {}
+        }
+    }
+    for (key, value) in new.iter(new_guard) {
+        if !old.contains_key(key, old_guard) {
+            diff.added.push((key.clone(), value.clone()));
+        }
+    }
+    diff
+}
+
 impl<K, V, S> PartialEq for HashMap<K, V, S>
 where
     K: Ord + Hash,
@@ -2941,6 +9337,23 @@ where
 {
 }
 
+impl<K, V, S, S2> PartialEq<std::collections::HashMap<K, V, S2>> for HashMap<K, V, S>
+where
+    K: Ord + Hash,
+    V: PartialEq,
+    S: BuildHasher,
+    S2: BuildHasher,
+{
+    fn eq(&self, other: &std::collections::HashMap<K, V, S2>) -> bool {
+        if self.len() != other.len() {
+            return false;
+        }
+        let guard = self.guard();
+        self.iter(&guard)
+            .all(|(key, value)| other.get(key) == Some(value))
+    }
+}
+
 impl<K, V, S> fmt::Debug for HashMap<K, V, S>
 where
     K: Debug,
@@ -3067,7 +9480,14 @@ where
     S: BuildHasher + Clone,
 {
     fn clone(&self) -> HashMap<K, V, S> {
-        let cloned_map = Self::with_capacity_and_hasher(self.len(), self.build_hasher.clone());
+        let mut cloned_map = Self::with_hasher(self.build_hasher.clone());
+        cloned_map.configure(self.len(), 0, self.load_factor, self.treeify);
+        cloned_map
+            .lock_spins
+            .store(self.lock_spins.load(Ordering::Relaxed), Ordering::Relaxed);
+        cloned_map
+            .next_id
+            .store(self.next_id.load(Ordering::SeqCst), Ordering::SeqCst);
         {
             let guard = self.collector.register().pin();
             for (k, v) in self.iter(&guard) {
@@ -3114,9 +9534,51 @@ fn capacity() {
     // since we inserted more elements than it can hold
 }
 
+#[test]
+fn table_bytes() {
+    let map = HashMap::<usize, usize>::new();
+    let guard = epoch::pin();
+    assert_eq!(map.table_bytes(&guard), 0);
+
+    map.insert(0, 0, &guard);
+    let bytes = map.table_bytes(&guard);
+    assert_eq!(bytes, 16 * std::mem::size_of::<usize>());
+    for i in 0..16 {
+        map.insert(i, i, &guard);
+    }
+    assert_eq!(map.capacity(&guard), 32);
+    assert_eq!(map.table_bytes(&guard), bytes * 2);
+}
+
+#[test]
+fn load() {
+    let map = HashMap::<usize, usize>::new();
+    let guard = epoch::pin();
+    assert_eq!(map.load(&guard), 0.0);
+
+    let mut capacity = 0;
+    let mut last_load = 0.0;
+    for i in 0..1000 {
+        map.insert(i, i, &guard);
+        let load = map.load(&guard);
+        assert!(load < 0.75, "load {} at {} entries", load, i + 1);
+        if map.capacity(&guard) != capacity {
+            if capacity >= 16 {
+                // right before the resize, the map was almost at its load factor
+                assert!(last_load > 0.65, "resized at load {}", last_load);
+                // and right after it, it is at about half of it
+                assert!(load < 0.4, "load {} after resize", load);
+            }
+            capacity = map.capacity(&guard);
+        }
+        last_load = load;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+
     #[test]
     fn reserve() {
         let map = HashMap::<usize, usize>::new();
@@ -3141,6 +9603,154 @@ mod tests {
         assert!(capacity >= 32);
     }
 
+    #[test]
+    fn recount() {
+        let map = HashMap::<usize, usize>::new();
+        let guard = epoch::pin();
+        assert_eq!(map.recount(&guard), 0);
+        for i in 0..100 {
+            map.insert(i, i, &guard);
+        }
+
+        map.count.store(42, Ordering::SeqCst);
+        assert_eq!(map.len(), 42);
+        assert_eq!(map.recount(&guard), 100);
+        assert_eq!(map.len(), 100);
+
+        map.count.store(-3, Ordering::SeqCst);
+        assert_eq!(map.recount(&guard), 100);
+        assert_eq!(map.len(), 100);
+    }
+
+    #[test]
+    fn bin_index() {
+        let map = HashMap::<String, usize>::new();
+        let guard = epoch::pin();
+        assert_eq!(map.bin_index("missing", &guard), 0);
+        for i in 0..100 {
+            map.insert(i.to_string(), i, &guard);
+        }
+
+        let t = map.table.load(Ordering::SeqCst, &guard);
+        let t = unsafe { t.deref() };
+        let mut seen = 0;
+        for bini in 0..t.len() {
+            let mut entry = t.bin(bini, &guard);
+            while let Some(BinEntry::Node(node)) = unsafe { entry.as_ref() } {
+                assert_eq!(map.bin_index(&node.key, &guard), bini);
+                // lookups through the borrowed form end up in the same bin
+                assert_eq!(map.bin_index(node.key.as_str(), &guard), bini);
+                seen += 1;
+                entry = node.next.load(Ordering::SeqCst, &guard);
+            }
+        }
+        assert_eq!(seen, 100);
+    }
+
+    #[test]
+    fn iter_rev() {
+        let map = HashMap::<usize, usize>::new();
+        let guard = epoch::pin();
+        for i in 0..100 {
+            map.insert(i, i, &guard);
+        }
+
+        let mask = map.capacity(&guard) as u64 - 1;
+        let bin = |k: &usize| map.hash(k) & mask;
+        let forward: Vec<_> = map.iter(&guard).map(|(k, _)| *k).collect();
+        let backward: Vec<_> = map.iter_rev(&guard).map(|(k, _)| *k).collect();
+        assert_ne!(forward, backward);
+
+        let mut forward_set = forward.clone();
+        let mut backward_set = backward.clone();
+        forward_set.sort_unstable();
+        backward_set.sort_unstable();
+        assert_eq!(forward_set, backward_set);
+
+        // bins are visited in ascending order by iter, and in descending order by iter_rev
+        assert!(forward.windows(2).all(|w| bin(&w[0]) <= bin(&w[1])));
+        assert!(backward.windows(2).all(|w| bin(&w[0]) >= bin(&w[1])));
+    }
+
+    #[test]
+    fn iter_checked() {
+        let map = HashMap::<usize, usize>::new();
+        let guard = epoch::pin();
+        for i in 0..4 {
+            map.insert(i, i, &guard);
+        }
+        // safety: the table has been allocated by the inserts above.
+        let table = unsafe { map.table.load(Ordering::SeqCst, &guard).deref() };
+        let mut empty = (0..table.len()).filter(|&i| table.bin(i, &guard).is_null());
+        let (stray_bin, cycle_bin) = (empty.next().unwrap(), empty.next().unwrap());
+
+        // a node whose hash belongs in another bin.
+        let stray = Node::new(stray_bin as u64 + 1, 100, Owned::new(100));
+        table.store_bin(stray_bin, Owned::new(BinEntry::Node(stray)));
+        // two nodes that link to each other.
+        let second = Owned::new(BinEntry::Node(Node::new(
+            cycle_bin as u64,
+            102,
+            Owned::new(102),
+        )))
+        .into_shared(&guard);
+        let first = Node::with_next(cycle_bin as u64, 101, Owned::new(101), Atomic::from(second));
+        table.store_bin(cycle_bin, Owned::new(BinEntry::Node(first)));
+        // safety: the node is in the table, which we hold a guard for.
+        let second_node = unsafe { second.deref() }.as_node().unwrap();
+        second_node
+            .next
+            .store(table.bin(cycle_bin, &guard), Ordering::SeqCst);
+
+        let mut errors = Vec::new();
+        let mut keys = Vec::new();
+        for entry in map.iter_checked(&guard) {
+            match entry {
+                Ok((&k, &v)) => {
+                    assert_eq!(k, v);
+                    keys.push(k);
+                }
+                Err(e) => errors.push(e.bin),
+            }
+        }
+        keys.sort_unstable();
+        assert_eq!(keys, vec![0, 1, 2, 3]);
+        errors.sort_unstable();
+        let mut expected = vec![stray_bin, cycle_bin];
+        expected.sort_unstable();
+        assert_eq!(errors, expected);
+
+        // break the cycle again, so that the map can be dropped.
+        second_node.next.store(Shared::null(), Ordering::SeqCst);
+
+        // compact does not look for cycles, but does report the stray node.
+        assert_eq!(map.compact(&guard).map_err(|e| e.bin), Err(stray_bin));
+    }
+
+    #[test]
+    fn iter_shuffled() {
+        let map = HashMap::<usize, usize>::new();
+        let guard = epoch::pin();
+        for i in 0..100 {
+            map.insert(i, i, &guard);
+        }
+
+        let shuffled =
+            |seed| -> Vec<usize> { map.iter_shuffled(seed, &guard).map(|(k, _)| *k).collect() };
+        let (a, b) = (shuffled(1), shuffled(2));
+        assert_ne!(a, b);
+        assert_eq!(a, shuffled(1));
+        assert_eq!(map.iter_shuffled(1, &guard).len(), 100);
+
+        let forward: Vec<_> = map.iter(&guard).map(|(k, _)| *k).collect();
+        assert_ne!(a, forward);
+        let (mut a_set, mut b_set) = (a, b);
+        a_set.sort_unstable();
+        b_set.sort_unstable();
+        assert_eq!(a_set, b_set);
+        assert_eq!(a_set, (0..100).collect::<Vec<_>>());
+    }
+
     #[test]
     fn resize_stamp_negative() {
         let resize_stamp = HashMap::<usize, usize>::resize_stamp(1);
@@ -3149,6 +9759,273 @@ mod tests {
         let resize_stamp = HashMap::<usize, usize>::resize_stamp(MAXIMUM_CAPACITY);
         assert!(resize_stamp << RESIZE_STAMP_SHIFT < 0);
     }
+
+    #[test]
+    fn resizing_until_table_is_swapped() {
+        let map = HashMap::<usize, usize>::new();
+        let guard = epoch::pin();
+        map.insert(1, 1, &guard);
+        let sc = map.size_ctl.load(Ordering::SeqCst);
+
+        // the window in which the finishing thread has cleared next_table, but not yet swapped
+        // in the new table and stored the new threshold.
+        let n = map.capacity(&guard);
+        let rs = HashMap::<usize, usize>::resize_stamp(n) << RESIZE_STAMP_SHIFT;
+        map.size_ctl.store(rs + 1, Ordering::SeqCst);
+        assert!(map.next_table.load(Ordering::SeqCst, &guard).is_null());
+        assert!(map.is_resizing(&guard));
+
+        map.size_ctl.store(sc, Ordering::SeqCst);
+        assert!(!map.is_resizing(&guard));
+    }
+
+    #[test]
+    #[cfg(feature = "hot-keys")]
+    fn hot_keys() {
+        let map = HashMap::<usize, usize>::new();
+        let guard = epoch::pin();
+        for i in 0..100 {
+            map.insert(i, i, &guard);
+        }
+        for i in 0..100 {
+            map.get(&i, &guard);
+        }
+        // 7 and 42 are hot, and their counts survive resizes
+        for round in 0..1000 {
+            map.get(&7, &guard);
+            map.get(&42, &guard);
+            if round % 2 == 0 {
+                assert!(map.contains_key(&42, &guard));
+            }
+            if round == 500 {
+                map.reserve(1000, &guard);
+            }
+        }
+        // lookups of missing keys do not count, and neither do lookups that other operations
+        // make internally
+        map.get(&1000, &guard);
+        assert_eq!(
+            map.get_or_try_insert_with(7, || Ok::<_, ()>(0), &guard),
+            Ok(&7)
+        );
+
+        assert_eq!(map.hot_keys(2, &guard), vec![(&42, 1501), (&7, 1001)]);
+        let all = map.hot_keys(1000, &guard);
+        assert_eq!(all.len(), 100);
+        assert!(all[2..].iter().all(|&(_, count)| count == 1));
+        assert_eq!(map.hot_keys(0, &guard), vec![]);
+    }
+
+    #[test]
+    #[cfg(feature = "versioning")]
+    fn iter_since() {
+        let map = HashMap::<usize, usize>::new();
+        let guard = epoch::pin();
+        for i in 0..10 {
+            map.insert(i, i, &guard);
+        }
+        let version = map.current_version();
+        assert_eq!(map.iter_since(version, &guard).count(), 0);
+
+        map.insert(10, 10, &guard);
+        map.insert(3, 30, &guard);
+        map.compute_if_present(&5, |_, v| Some(v * 10), &guard);
+        map.insert(7, 70, &guard);
+        map.remove(&7, &guard);
+
+        let mut changed: Vec<_> = map
+            .iter_since(version, &guard)
+            .map(|(&k, &v)| (k, v))
+            .collect();
+        changed.sort();
+        assert_eq!(changed, vec![(3, 30), (5, 50), (10, 10)]);
+        assert!(map.current_version() > version);
+        assert_eq!(map.iter_since(0, &guard).count(), map.len());
+    }
+
+    #[test]
+    #[cfg(feature = "versioning")]
+    fn iter_since_across_resize() {
+        let map = HashMap::<usize, usize>::new();
+        let guard = epoch::pin();
+        for i in 0..8 {
+            map.insert(i, i, &guard);
+        }
+        let version = map.current_version();
+        // grow the table a few times, which moves the old entries into new bins
+        for i in 8..1000 {
+            map.insert(i, i, &guard);
+        }
+        assert!(map.iter_since(version, &guard).all(|(&k, _)| k >= 8));
+        assert_eq!(map.iter_since(version, &guard).count(), 992);
+    }
+
+    #[test]
+    #[cfg(feature = "versioning")]
+    fn settled_version_waits_for_pending_writes() {
+        let map = HashMap::<usize, usize>::new();
+        let guard = epoch::pin();
+        map.insert(1, 1, &guard);
+        let settled = map.settled_version();
+        assert_eq!(settled, map.current_version());
+
+        // a write that has taken its version, but is not done yet, holds the settled version
+        // back, even for the writes that finish after it.
+        let pending = map.begin_write();
+        map.insert(2, 2, &guard);
+        assert!(map.current_version() > settled);
+        assert_eq!(map.settled_version(), settled);
+        let mut reader = map.delta_reader();
+        assert_eq!(reader.version(), settled);
+        assert_eq!(reader.poll(&guard).collect::<Vec<_>>(), vec![(&2, &2)]);
+
+        // once it finishes, the settled version catches up, and the reader sees 2 once more.
+        drop(pending);
+        assert_eq!(map.settled_version(), map.current_version());
+        assert_eq!(reader.poll(&guard).collect::<Vec<_>>(), vec![(&2, &2)]);
+        assert_eq!(reader.poll(&guard).count(), 0);
+    }
+
+    #[test]
+    #[cfg(feature = "insertion-order")]
+    fn iter_enumerated() {
+        let map = HashMap::<usize, usize>::new();
+        let guard = epoch::pin();
+        for i in 0..100 {
+            map.insert(i, i, &guard);
+        }
+        let first: std::collections::HashMap<_, _> = map
+            .iter_enumerated(&guard)
+            .map(|(seq, &k, _)| (k, seq))
+            .collect();
+        let mut seqs: Vec<_> = first.values().copied().collect();
+        seqs.sort_unstable();
+        assert_eq!(seqs, (1..=100).collect::<Vec<_>>());
+
+        // updates and resizes keep the numbers of existing entries, and new keys get new ones
+        for i in 0..1000 {
+            map.insert(i, i + 1, &guard);
+        }
+        let second: std::collections::HashMap<_, _> = map
+            .iter_enumerated(&guard)
+            .map(|(seq, &k, _)| (k, seq))
+            .collect();
+        assert_eq!(second.len(), 1000);
+        for (k, seq) in &second {
+            match first.get(k) {
+                Some(first_seq) => assert_eq!(seq, first_seq),
+                None => assert!(*seq > 100),
+            }
+        }
+
+        // a removed and reinserted key is a new entry
+        map.remove(&0, &guard);
+        map.insert(0, 0, &guard);
+        let (seq, _, _) = map
+            .iter_enumerated(&guard)
+            .find(|&(_, &k, _)| k == 0)
+            .unwrap();
+        assert_eq!(seq, 1001);
+    }
+
+    #[test]
+    #[cfg(feature = "insertion-order")]
+    fn recent() {
+        let map = HashMap::<usize, usize>::new();
+        let guard = epoch::pin();
+        assert!(map.recent(3, &guard).is_empty());
+        for i in (0..100).rev() {
+            map.insert(i, i, &guard);
+        }
+        assert_eq!(map.recent(3, &guard), vec![(&0, &0), (&1, &1), (&2, &2)]);
+        assert!(map.recent(0, &guard).is_empty());
+        assert_eq!(map.recent(1000, &guard).len(), 100);
+        assert_eq!(map.recent(usize::MAX, &guard).len(), 100);
+
+        // an update keeps the place of an entry, and a reinsert moves it to the front
+        map.insert(50, 500, &guard);
+        map.remove(&60, &guard);
+        map.insert(60, 600, &guard);
+        assert_eq!(map.recent(3, &guard), vec![(&60, &600), (&0, &0), (&1, &1)]);
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn resize_span() {
+        use std::sync::{Arc, Mutex};
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Metadata, Subscriber};
+
+        /// The fields of a span, as they were recorded.
+        #[derive(Default)]
+        struct Fields(Vec<(String, String)>);
+
+        impl Visit for Fields {
+            fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+                self.0
+                    .push((field.name().to_string(), format!("{:?}", value)));
+            }
+        }
+
+        /// A subscriber that remembers the name and fields of every span, and counts events.
+        #[derive(Clone, Default)]
+        struct Recorder {
+            spans: Arc<Mutex<Vec<(&'static str, Fields)>>>,
+            events: Arc<Mutex<usize>>,
+        }
+
+        impl Subscriber for Recorder {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+            fn new_span(&self, span: &Attributes<'_>) -> Id {
+                let mut fields = Fields::default();
+                span.record(&mut fields);
+                let mut spans = self.spans.lock().unwrap();
+                spans.push((span.metadata().name(), fields));
+                Id::from_u64(spans.len() as u64)
+            }
+            fn record(&self, span: &Id, values: &Record<'_>) {
+                let mut spans = self.spans.lock().unwrap();
+                values.record(&mut spans[span.into_u64() as usize - 1].1);
+            }
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+            fn event(&self, _: &Event<'_>) {
+                *self.events.lock().unwrap() += 1;
+            }
+            fn enter(&self, _: &Id) {}
+            fn exit(&self, _: &Id) {}
+        }
+
+        let recorder = Recorder::default();
+        tracing::subscriber::with_default(recorder.clone(), || {
+            let map = HashMap::<usize, usize>::new();
+            let guard = map.guard();
+            // 16 bins resize once they hold 12 entries
+            for i in 0..12 {
+                map.insert(i, i, &guard);
+            }
+            assert_eq!(map.capacity(&guard), 32);
+        });
+
+        let spans = recorder.spans.lock().unwrap();
+        assert_eq!(spans.len(), 1);
+        let (name, Fields(fields)) = &spans[0];
+        assert_eq!(*name, "resize");
+        let field = |name: &str| {
+            fields
+                .iter()
+                .find(|(field, _)| field == name)
+                .map(|(_, value)| value.clone())
+        };
+        assert_eq!(field("capacity").as_deref(), Some("16"));
+        assert_eq!(field("new_capacity").as_deref(), Some("32"));
+        assert_eq!(field("initiated").as_deref(), Some("true"));
+        assert!(field("duration_us").is_some());
+        // the resizing thread claimed at least one range of bins
+        assert!(*recorder.events.lock().unwrap() >= 1);
+    }
 }
 
 /// It's kind of stupid, but apparently there is no way to write a regular `#[test]` that is _not_
@@ -3387,6 +10264,75 @@ mod tree_bins {
         }
     }
 
+    #[test]
+    #[cfg(feature = "versioning")]
+    fn iter_since_tree_bin() {
+        let map = HashMap::<usize, usize, _>::with_hasher(ZeroHashBuilder);
+        let guard = &map.guard();
+        for i in 0..4 {
+            map.insert(i, i, guard);
+        }
+        let version = map.current_version();
+        // these collide with the first entries, so the bin is turned into a tree
+        for i in 4..20 {
+            map.insert(i, i, guard);
+        }
+        map.insert(0, 100, guard);
+
+        let mut changed: Vec<_> = map.iter_since(version, guard).map(|(&k, _)| k).collect();
+        changed.sort();
+        assert_eq!(changed, std::iter::once(0).chain(4..20).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn compute_many_tree_bin() {
+        let map = HashMap::<usize, usize, _>::with_hasher(ZeroHashBuilder);
+        let guard = &map.guard();
+        for i in 0..10 {
+            map.insert(i, i, guard);
+        }
+
+        // removing most of the entries turns the bin back into a linear bin
+        let results = map.compute_many(
+            [&0, &1, &2, &3, &4, &5, &6, &9, &20],
+            |values| {
+                let mut new = [None, None, None, None, None, None, None, None, None];
+                new[7] = values[7].map(|v| v * 10);
+                new[8] = Some(20);
+                new
+            },
+            guard,
+        );
+        assert_eq!(
+            results,
+            [
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                Some(&90),
+                Some(&20)
+            ]
+        );
+        assert_eq!(map.len(), 4);
+        for (k, v) in [(7, 7), (8, 8), (9, 90), (20, 20)].iter() {
+            assert_eq!(map.get(k, guard), Some(v));
+        }
+
+        let t = map.table.load(Ordering::Relaxed, guard);
+        let t = unsafe { t.deref() };
+        match unsafe { t.bin(t.bini(0), guard).deref() } {
+            BinEntry::Node(_) => {} // pass
+            BinEntry::Tree(_) => panic!("bin was not correctly untreeified -- is Tree"),
+            BinEntry::Moved => panic!("bin was not correctly untreeified -- is Moved"),
+            BinEntry::TreeNode(_) => panic!("bin was not correctly untreeified -- is TreeNode"),
+            BinEntry::Reserved(_) => panic!("bin was not correctly untreeified -- is Reserved"),
+        }
+    }
+
     #[test]
     #[cfg_attr(miri, ignore)]
     fn concurrent_tree_bin() {
@@ -3408,6 +10354,7 @@ mod tree_bins {
                 BinEntry::Moved => panic!("bin was not correctly treeified -- is Moved"),
                 BinEntry::Node(_) => panic!("bin was not correctly treeified -- is Node"),
                 BinEntry::TreeNode(_) => panic!("bin was not correctly treeified -- is TreeNode"),
+                BinEntry::Reserved(_) => panic!("bin was not correctly treeified -- is Reserved"),
             }
 
             guard.flush();
@@ -3506,6 +10453,7 @@ mod tree_bins {
                 BinEntry::Moved => panic!("bin was not correctly treeified -- is Moved"),
                 BinEntry::Node(_) => panic!("bin was not correctly treeified -- is Node"),
                 BinEntry::TreeNode(_) => panic!("bin was not correctly treeified -- is TreeNode"),
+                BinEntry::Reserved(_) => panic!("bin was not correctly treeified -- is Reserved"),
             }
 
             // Delete keys to force untreeifying the bin
@@ -3529,6 +10477,7 @@ mod tree_bins {
                 BinEntry::Moved => panic!("bin was not correctly untreeified -- is Moved"),
                 BinEntry::Node(_) => {} // pass
                 BinEntry::TreeNode(_) => panic!("bin was not correctly untreeified -- is TreeNode"),
+                BinEntry::Reserved(_) => panic!("bin was not correctly untreeified -- is Reserved"),
             }
         }
 
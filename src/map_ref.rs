@@ -1,10 +1,20 @@
+#[cfg(feature = "ffi")]
+use crate::ffi::RawTableParts;
 use crate::iter::*;
-use crate::{GuardRef, HashMap, TryInsertError};
+#[cfg(feature = "versioning")]
+use crate::DeltaReader;
+#[cfg(feature = "metrics")]
+use crate::MapStats;
+use crate::{
+    BinError, BinGuard, BinStats, Compute, GetTrace, GuardRef, HashMap, LockStrategy, MapOp,
+    ReplaceIf, TryInsertError, TxnView, WouldBlock,
+};
 use crossbeam_epoch::Guard;
 use std::borrow::Borrow;
 use std::fmt::{self, Debug, Formatter};
 use std::hash::{BuildHasher, Hash};
-use std::ops::Index;
+use std::ops::{Index, RangeBounds};
+use std::time::Duration;
 
 /// A reference to a [`HashMap`], constructed with [`HashMap::pin`] or [`HashMap::with_guard`].
 ///
@@ -12,7 +22,7 @@ use std::ops::Index;
 /// Keep in mind that this prevents the collection of garbage generated by the map.
 pub struct HashMapRef<'map, K, V, S = crate::DefaultHashBuilder> {
     pub(crate) map: &'map HashMap<K, V, S>,
-    guard: GuardRef<'map>,
+    pub(crate) guard: GuardRef<'map>,
 }
 
 impl<K, V, S> HashMap<K, V, S> {
@@ -20,6 +30,22 @@ impl<K, V, S> HashMap<K, V, S> {
     ///
     /// Keep in mind that for as long as you hold onto this, you are preventing the collection of
     /// garbage generated by the map.
+    ///
+    /// The returned reference also supports indexing, which is how to read a value with `[]`.
+    /// `HashMap` itself does not implement [`Index`]: the reference it would hand out has to stay
+    /// valid for as long as the map is borrowed, but the guard it pinned to find the value would
+    /// be gone by the time the reference is used, and with it the guarantee that the value has
+    /// not been freed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// map.pin().insert("a", 1);
+    /// assert_eq!(map.pin()[&"a"], 1);
+    /// ```
     pub fn pin(&self) -> HashMapRef<'_, K, V, S> {
         HashMapRef {
             guard: GuardRef::Owned(self.guard()),
@@ -34,6 +60,42 @@ impl<K, V, S> HashMap<K, V, S> {
             guard: GuardRef::Ref(guard),
         }
     }
+
+    /// Pins the current thread once, and runs `f` with a reference to this map that uses that
+    /// pin for every operation.
+    ///
+    /// Pinning the thread is cheap, but not free, and for workloads that do many small
+    /// operations in a row, calling [`pin`](HashMap::pin) for each of them can end up dominating
+    /// the cost. Batching those operations in `f` is the preferred pattern in that case. Once `f`
+    /// returns, the garbage that the batch produced is flushed, so that it can be reclaimed
+    /// without waiting for this thread to pin again.
+    ///
+    /// Keep in mind that for as long as `f` runs, you are preventing the collection of garbage
+    /// generated by the map, so very long batches are best split up.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// let sum = map.pin_batched(|map| {
+    ///     for i in 0..100 {
+    ///         map.insert(i, i);
+    ///     }
+    ///     (0..100).map(|i| map[&i]).sum::<i32>()
+    /// });
+    /// assert_eq!(sum, 4950);
+    /// ```
+    pub fn pin_batched<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&HashMapRef<'_, K, V, S>) -> R,
+    {
+        let map = self.pin();
+        let result = f(&map);
+        map.guard.flush();
+        result
+    }
 }
 
 impl<K, V, S> HashMapRef<'_, K, V, S> {
@@ -51,6 +113,41 @@ impl<K, V, S> HashMapRef<'_, K, V, S> {
         self.map.is_empty()
     }
 
+    /// Blocks until the map holds at least `target` entries, or until `timeout` has elapsed.
+    ///
+    /// See also [`HashMap::wait_until_len`].
+    pub fn wait_until_len(&self, target: usize, timeout: Duration) -> bool {
+        self.map.wait_until_len(target, timeout)
+    }
+
+    /// Returns the capacity of the map.
+    ///
+    /// See also [`HashMap::capacity`].
+    pub fn capacity(&self) -> usize {
+        self.map.capacity(&self.guard)
+    }
+
+    /// Returns the size in bytes of the map's array of bins.
+    ///
+    /// See also [`HashMap::table_bytes`].
+    pub fn table_bytes(&self) -> usize {
+        self.map.table_bytes(&self.guard)
+    }
+
+    /// Returns the fraction of the map's capacity that is in use.
+    ///
+    /// See also [`HashMap::load`].
+    pub fn load(&self) -> f64 {
+        self.map.load(&self.guard)
+    }
+
+    /// Sets how threads wait for the lock of a bin that another thread holds.
+    ///
+    /// See also [`HashMap::set_lock_strategy`].
+    pub fn set_lock_strategy(&self, strategy: LockStrategy) {
+        self.map.set_lock_strategy(strategy)
+    }
+
     /// An iterator visiting all key-value pairs in arbitrary order.
     ///
     /// The iterator element type is `(&'g K, &'g V)`.
@@ -60,6 +157,40 @@ impl<K, V, S> HashMapRef<'_, K, V, S> {
         self.map.iter(&self.guard)
     }
 
+    /// An iterator visiting clones of all key-value pairs in arbitrary order.
+    ///
+    /// The iterator element type is `(K, V)`.
+    ///
+    /// See also [`HashMap::iter_owned`].
+    pub fn iter_owned(&self) -> IterOwned<'_, K, V>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        self.map.iter_owned(&self.guard)
+    }
+
+    /// An iterator visiting all key-value pairs in arbitrary order, yielding clones of the keys
+    /// alongside references to the values.
+    ///
+    /// The iterator element type is `(K, &'g V)`.
+    ///
+    /// See also [`HashMap::iter_owned_keys`].
+    pub fn iter_owned_keys(&self) -> IterOwnedKeys<'_, K, V>
+    where
+        K: Clone,
+    {
+        self.map.iter_owned_keys(&self.guard)
+    }
+
+    /// An iterator visiting all key-value pairs in arbitrary order, along with whether the lock of
+    /// the bin each entry is in is currently held by some thread.
+    ///
+    /// See also [`HashMap::iter_with_lock_state`].
+    pub fn iter_with_lock_state(&self) -> IterWithLockState<'_, K, V> {
+        self.map.iter_with_lock_state(&self.guard)
+    }
+
     /// An iterator visiting all keys in arbitrary order.
     ///
     /// The iterator element type is `&'g K`.
@@ -69,6 +200,37 @@ impl<K, V, S> HashMapRef<'_, K, V, S> {
         self.map.keys(&self.guard)
     }
 
+    /// An iterator visiting all key-value pairs, walking the map's bins from the last one to the
+    /// first one.
+    ///
+    /// See also [`HashMap::iter_rev`].
+    pub fn iter_rev(&self) -> Iter<'_, K, V> {
+        self.map.iter_rev(&self.guard)
+    }
+
+    /// An iterator visiting all key-value pairs in a pseudo-random order determined by `seed`.
+    ///
+    /// See also [`HashMap::iter_shuffled`].
+    pub fn iter_shuffled(&self, seed: u64) -> IterShuffled<'_, K, V> {
+        self.map.iter_shuffled(seed, &self.guard)
+    }
+
+    /// An iterator visiting all key-value pairs in arbitrary order, `chunk_size` pairs at a
+    /// time.
+    ///
+    /// See also [`HashMap::iter_chunks`].
+    pub fn iter_chunks(&self, chunk_size: usize) -> Chunks<'_, K, V> {
+        self.map.iter_chunks(chunk_size, &self.guard)
+    }
+
+    /// Returns a cursor over all key-value pairs of the map, which hands out up to `per_poll`
+    /// pairs each time the caller asks for more.
+    ///
+    /// See also [`HashMap::iter_throttled`].
+    pub fn iter_throttled(&self, per_poll: usize) -> ThrottledIter<'_, K, V> {
+        self.map.iter_throttled(per_poll, &self.guard)
+    }
+
     /// An iterator visiting all values in arbitrary order.
     ///
     /// The iterator element type is `&'g V`.
@@ -77,6 +239,126 @@ impl<K, V, S> HashMapRef<'_, K, V, S> {
     pub fn values(&self) -> Values<'_, K, V> {
         self.map.values(&self.guard)
     }
+
+    /// An iterator visiting every distinct value of the map once, in arbitrary order.
+    ///
+    /// See also [`HashMap::distinct_values_iter`].
+    pub fn distinct_values_iter(&self) -> DistinctValues<'_, K, V>
+    where
+        V: Hash + Eq,
+    {
+        self.map.distinct_values_iter(&self.guard)
+    }
+
+    /// Walks all the entries of the map, and hints to the CPU to bring their keys and values into
+    /// its cache.
+    ///
+    /// See also [`HashMap::prefetch_all`].
+    pub fn prefetch_all(&self) {
+        self.map.prefetch_all(&self.guard)
+    }
+
+    /// Returns a reference to the map's [`BuildHasher`].
+    ///
+    /// See also [`HashMap::hasher`].
+    pub fn hasher(&self) -> &S {
+        self.map.hasher()
+    }
+
+    /// Returns the untyped parts that make up the map's current table.
+    ///
+    /// See also [`HashMap::as_raw_parts`].
+    ///
+    /// # Safety
+    ///
+    /// The returned parts must not be used after this reference is dropped. The other
+    /// requirements of [`HashMap::as_raw_parts`] apply as well.
+    #[cfg(feature = "ffi")]
+    pub unsafe fn as_raw_parts(&self) -> RawTableParts {
+        self.map.as_raw_parts(&self.guard)
+    }
+
+    /// Returns a snapshot of the counters of operations performed on the map.
+    ///
+    /// See also [`HashMap::stats`].
+    #[cfg(feature = "metrics")]
+    pub fn stats(&self) -> MapStats {
+        self.map.stats()
+    }
+
+    /// Returns the current version of the map.
+    ///
+    /// See also [`HashMap::current_version`].
+    #[cfg(feature = "versioning")]
+    pub fn current_version(&self) -> u64 {
+        self.map.current_version()
+    }
+
+    /// An iterator visiting all key-value pairs that were inserted or updated after the map was
+    /// at `version`.
+    ///
+    /// See also [`HashMap::iter_since`].
+    #[cfg(feature = "versioning")]
+    pub fn iter_since(&self, version: u64) -> IterSince<'_, K, V> {
+        self.map.iter_since(version, &self.guard)
+    }
+
+    /// Returns a reader that yields the entries written to the map since it last looked.
+    ///
+    /// See also [`HashMap::delta_reader`].
+    #[cfg(feature = "versioning")]
+    pub fn delta_reader(&self) -> DeltaReader<'_, K, V, S> {
+        self.map.delta_reader()
+    }
+
+    /// An iterator visiting all key-value pairs in arbitrary order, along with the sequence
+    /// number each entry was inserted with.
+    ///
+    /// See also [`HashMap::iter_enumerated`].
+    #[cfg(feature = "insertion-order")]
+    pub fn iter_enumerated(&self) -> IterEnumerated<'_, K, V> {
+        self.map.iter_enumerated(&self.guard)
+    }
+
+    /// Returns the `n` most recently inserted entries of the map, newest first.
+    ///
+    /// See also [`HashMap::recent`].
+    #[cfg(feature = "insertion-order")]
+    pub fn recent(&self, n: usize) -> Vec<(&K, &V)> {
+        self.map.recent(n, &self.guard)
+    }
+
+    /// Returns the `top_n` most frequently looked up keys of the map along with how often they
+    /// were looked up.
+    ///
+    /// See also [`HashMap::hot_keys`].
+    #[cfg(feature = "hot-keys")]
+    pub fn hot_keys(&self, top_n: usize) -> Vec<(&'_ K, u32)> {
+        self.map.hot_keys(top_n, &self.guard)
+    }
+
+    /// Buckets references to all the map's entries by a group derived from each entry.
+    ///
+    /// See also [`HashMap::group_by`].
+    pub fn group_by<G, F>(&self, key_fn: F) -> std::collections::HashMap<G, Vec<(&'_ K, &'_ V)>>
+    where
+        G: Hash + Eq,
+        F: Fn(&K, &V) -> G,
+    {
+        self.map.group_by(key_fn, &self.guard)
+    }
+
+    /// An iterator visiting all key-value pairs whose keys fall within `range`, in arbitrary
+    /// order.
+    ///
+    /// See also [`HashMap::range`].
+    pub fn range<R>(&self, range: R) -> Range<'_, K, V, R>
+    where
+        K: Ord,
+        R: RangeBounds<K>,
+    {
+        self.map.range(range, &self.guard)
+    }
 }
 
 impl<K, V, S> HashMapRef<'_, K, V, S>
@@ -92,6 +374,112 @@ where
     pub fn reserve(&self, additional: usize) {
         self.map.reserve(additional, &self.guard)
     }
+
+    /// Reserves capacity for the size the map is projected to reach if it keeps growing by
+    /// `entries_per_interval` entries per interval for another `intervals_ahead` intervals.
+    ///
+    /// See also [`HashMap::reserve_for_rate`].
+    pub fn reserve_for_rate(&self, entries_per_interval: usize, intervals_ahead: usize) {
+        self.map
+            .reserve_for_rate(entries_per_interval, intervals_ahead, &self.guard)
+    }
+
+    /// Tries to reserve capacity for exactly `additional` more elements to be inserted in the
+    /// `HashMap`.
+    ///
+    /// See also [`HashMap::reserve_exact`].
+    pub fn reserve_exact(&self, additional: usize) {
+        self.map.reserve_exact(additional, &self.guard)
+    }
+
+    /// Returns `true` if a resize of the map is in progress.
+    ///
+    /// See also [`HashMap::is_resizing`].
+    pub fn is_resizing(&self) -> bool {
+        self.map.is_resizing(&self.guard)
+    }
+
+    /// Waits until the resize that is in progress, if any, has completed.
+    ///
+    /// See also [`HashMap::await_resize`].
+    pub fn await_resize(&self) {
+        self.map.await_resize(&self.guard)
+    }
+
+    /// Calls `f` on every key-value pair of the map, after waiting for any resize that is in
+    /// progress to complete.
+    ///
+    /// See also [`HashMap::stable_for_each`].
+    pub fn stable_for_each<F>(&self, f: F)
+    where
+        F: FnMut(&K, &V),
+    {
+        self.map.stable_for_each(f, &self.guard)
+    }
+
+    /// Allocates the map's table now, so that the first write to the map does not have to.
+    ///
+    /// See also [`HashMap::prefault`].
+    pub fn prefault(&self) {
+        self.map.prefault(&self.guard)
+    }
+
+    /// Returns a snapshot of how the map's entries are distributed over its bins.
+    ///
+    /// See also [`HashMap::bin_stats`].
+    pub fn bin_stats(&self) -> BinStats {
+        self.map.bin_stats(&self.guard)
+    }
+
+    /// Returns the indices of the `n` fullest bins of the map, paired with the number of entries
+    /// in them, fullest first.
+    ///
+    /// See also [`HashMap::worst_bins`].
+    pub fn worst_bins(&self, n: usize) -> Vec<(usize, usize)> {
+        self.map.worst_bins(n, &self.guard)
+    }
+
+    /// Returns the average number of entries in the bins of the map that are not empty.
+    ///
+    /// See also [`HashMap::avg_chain_length`].
+    pub fn avg_chain_length(&self) -> f64 {
+        self.map.avg_chain_length(&self.guard)
+    }
+
+    /// Returns a textual description of the map's bins.
+    ///
+    /// See also [`HashMap::dump_structure`].
+    #[cfg(feature = "debug-dump")]
+    pub fn dump_structure(&self) -> String
+    where
+        K: Debug,
+    {
+        self.map.dump_structure(&self.guard)
+    }
+
+    /// An iterator visiting all key-value pairs in arbitrary order, that checks the structure of
+    /// every bin before it visits its entries.
+    ///
+    /// See also [`HashMap::iter_checked`].
+    pub fn iter_checked(&self) -> IterChecked<'_, K, V> {
+        self.map.iter_checked(&self.guard)
+    }
+
+    /// Reports, for every bin of the map's table, the bin's index and whether its lock is
+    /// currently held by some thread.
+    ///
+    /// See also [`HashMap::bin_lock_contention`].
+    #[cfg(feature = "metrics")]
+    pub fn bin_lock_contention(&self) -> Vec<(usize, bool)> {
+        self.map.bin_lock_contention(&self.guard)
+    }
+
+    /// Counts the entries in the map, and resets the map's size counter to that count.
+    ///
+    /// See also [`HashMap::recount`].
+    pub fn recount(&self) -> usize {
+        self.map.recount(&self.guard)
+    }
 }
 
 impl<K, V, S> HashMapRef<'_, K, V, S>
@@ -110,6 +498,20 @@ where
         self.map.contains_key(key, &self.guard)
     }
 
+    /// An iterator visiting the key-value pairs of the map whose keys are also in `other`, in
+    /// arbitrary order.
+    ///
+    /// See also [`HashMap::intersect_keys`].
+    pub fn intersect_keys<'a, V2, S2>(
+        &'a self,
+        other: &'a HashMap<K, V2, S2>,
+    ) -> IntersectKeys<'a, K, V, S, V2, S2>
+    where
+        S2: BuildHasher,
+    {
+        self.map.intersect_keys(other, &self.guard)
+    }
+
     /// Returns a reference to the value corresponding to the key.
     ///
     /// See also [`HashMap::get`].
@@ -122,6 +524,30 @@ where
         self.map.get(key, &self.guard)
     }
 
+    /// Looks up each of `keys`, and returns an iterator over the keys paired with their values,
+    /// in the order of `keys`.
+    ///
+    /// See also [`HashMap::get_ordered`].
+    pub fn get_ordered<'g, Q>(&'g self, keys: &'g [Q]) -> GetOrdered<'g, Q, K, V, S>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Ord,
+    {
+        self.map.get_ordered(keys, &self.guard)
+    }
+
+    /// Returns a reference to the value corresponding to the key, given the key's hash.
+    ///
+    /// See also [`HashMap::get_prehashed`].
+    #[inline]
+    pub fn get_prehashed<'g, Q>(&'g self, hash: u64, key: &Q) -> Option<&'g V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.map.get_prehashed(hash, key, &self.guard)
+    }
+
     /// Returns the key-value pair corresponding to `key`.
     ///
     /// See also [`HashMap::get_key_value`].
@@ -133,6 +559,148 @@ where
     {
         self.map.get_key_value(key, &self.guard)
     }
+
+    /// Returns `true` if `key` currently maps to the very same value as `value`.
+    ///
+    /// See also [`HashMap::is_still_present`].
+    pub fn is_still_present<Q>(&self, key: &Q, value: &V) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+    {
+        self.map.is_still_present(key, value, &self.guard)
+    }
+
+    /// Returns a clone of the value corresponding to the key.
+    ///
+    /// See also [`HashMap::get_cloned`].
+    pub fn get_cloned<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+        V: Clone,
+    {
+        self.map.get_cloned(key, &self.guard)
+    }
+
+    /// Returns the number of entries in the key's bin that have to be visited to find the key.
+    ///
+    /// See also [`HashMap::probe_length`].
+    pub fn probe_length<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+    {
+        self.map.probe_length(key, &self.guard)
+    }
+
+    /// Looks up the value corresponding to the key, and reports how the lookup went.
+    ///
+    /// See also [`HashMap::get_traced`].
+    pub fn get_traced<'g, Q>(&'g self, key: &Q) -> (Option<&'g V>, GetTrace)
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+    {
+        self.map.get_traced(key, &self.guard)
+    }
+
+    /// Returns the index of the bin that `key` maps to in the map's current table.
+    ///
+    /// See also [`HashMap::bin_index`].
+    pub fn bin_index<Q>(&self, key: &Q) -> usize
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash,
+    {
+        self.map.bin_index(key, &self.guard)
+    }
+
+    /// Returns `true` if `a` and `b` map to the same bin in the map's current table.
+    ///
+    /// See also [`HashMap::same_bin`].
+    pub fn same_bin<Q>(&self, a: &Q, b: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash,
+    {
+        self.map.same_bin(a, b, &self.guard)
+    }
+
+    /// Folds `f` over every entry in the bin that `key` maps to, while holding the lock of that
+    /// bin.
+    ///
+    /// See also [`HashMap::reduce_bin`].
+    pub fn reduce_bin<Q, F, R>(&self, key: &Q, init: R, f: F) -> R
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash,
+        F: FnMut(R, &K, &V) -> R,
+    {
+        self.map.reduce_bin(key, init, f, &self.guard)
+    }
+
+    /// Returns `true` if inserting a new key into the bin that `key` maps to would make the map
+    /// turn that bin into a tree.
+    ///
+    /// See also [`HashMap::would_treeify`].
+    pub fn would_treeify<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash,
+    {
+        self.map.would_treeify(key, &self.guard)
+    }
+
+    /// Returns a fingerprint of the map's contents, for cheaply detecting whether the map has
+    /// changed between two points in time.
+    ///
+    /// See also [`HashMap::content_hash`].
+    pub fn content_hash(&self) -> u64
+    where
+        V: Hash,
+    {
+        self.map.content_hash(&self.guard)
+    }
+
+    /// Returns an estimate of the number of distinct values in the map.
+    ///
+    /// See also [`HashMap::approx_distinct_values`].
+    pub fn approx_distinct_values(&self) -> u64
+    where
+        V: Hash,
+    {
+        self.map.approx_distinct_values(&self.guard)
+    }
+
+    /// Checks the integrity of the chains of entries in every bin of the map.
+    ///
+    /// See also [`HashMap::compact`].
+    pub fn compact(&self) -> Result<(), BinError> {
+        self.map.compact(&self.guard)
+    }
+
+    /// Turns tree bins that have shrunk to at most 6 entries back into linked lists, and returns
+    /// how many bins it converted.
+    ///
+    /// See also [`HashMap::maybe_untreeify`].
+    pub fn maybe_untreeify(&self) -> usize
+    where
+        K: Clone,
+    {
+        self.map.maybe_untreeify(&self.guard)
+    }
+
+    /// Returns `true` if both maps contain the same keys, and `eq` returns `true` for the values
+    /// each map holds for every one of those keys.
+    ///
+    /// See also [`HashMap::eq_by`].
+    pub fn eq_by<F>(&self, other: &HashMapRef<'_, K, V, S>, eq: F) -> bool
+    where
+        F: Fn(&V, &V) -> bool,
+    {
+        self.map.eq_by(other.map, eq, &self.guard, &other.guard)
+    }
 }
 
 impl<K, V, S> HashMapRef<'_, K, V, S>
@@ -145,6 +713,17 @@ where
     pub fn clear(&self) {
         self.map.clear(&self.guard);
     }
+
+    /// Clears the map if `pred` returns `true` for the map's current length, and returns whether
+    /// it did.
+    ///
+    /// See also [`HashMap::clear_if`].
+    pub fn clear_if<P>(&self, pred: P) -> bool
+    where
+        P: FnOnce(usize) -> bool,
+    {
+        self.map.clear_if(pred, &self.guard)
+    }
 }
 
 impl<K, V, S> HashMapRef<'_, K, V, S>
@@ -160,6 +739,21 @@ where
         self.map.insert(key, value, &self.guard)
     }
 
+    /// Inserts a key-value pair into the map, given the key's hash.
+    ///
+    /// See also [`HashMap::insert_prehashed`].
+    pub fn insert_prehashed(&self, hash: u64, key: K, value: V) -> Option<&'_ V> {
+        self.map.insert_prehashed(hash, key, value, &self.guard)
+    }
+
+    /// Inserts a key-value pair into the map, and returns the old value along with the number of
+    /// entries in the map right after the insert.
+    ///
+    /// See also [`HashMap::insert_and_len`].
+    pub fn insert_and_len(&self, key: K, value: V) -> (Option<&'_ V>, usize) {
+        self.map.insert_and_len(key, value, &self.guard)
+    }
+
     /// Inserts a key-value pair into the map unless the key already exists.
     ///
     /// See also [`HashMap::try_insert`].
@@ -168,6 +762,49 @@ where
         self.map.try_insert(key, value, &self.guard)
     }
 
+    /// Returns a reference to the value corresponding to the key, and inserts the value that
+    /// `make` produces first if the key is not present.
+    ///
+    /// See also [`HashMap::get_or_try_insert_with`].
+    pub fn get_or_try_insert_with<F, E>(&self, key: K, make: F) -> Result<&'_ V, E>
+    where
+        F: FnOnce() -> Result<V, E>,
+    {
+        self.map.get_or_try_insert_with(key, make, &self.guard)
+    }
+
+    /// Inserts a key-value pair into the map, and reports how contended the insert was.
+    ///
+    /// See also [`HashMap::insert_counted`].
+    pub fn insert_counted(&self, key: K, value: V) -> (Option<&'_ V>, u32) {
+        self.map.insert_counted(key, value, &self.guard)
+    }
+
+    /// Inserts a key-value pair into the map, unless that would mean waiting for another thread.
+    ///
+    /// See also [`HashMap::try_insert_nonblocking`].
+    pub fn try_insert_nonblocking(
+        &self,
+        key: K,
+        value: V,
+    ) -> Result<Option<&'_ V>, WouldBlock<(K, V)>> {
+        self.map.try_insert_nonblocking(key, value, &self.guard)
+    }
+
+    /// Inserts a key-value pair into the map as long as doing so does not grow the map beyond
+    /// `max_len` entries.
+    ///
+    /// See also [`HashMap::insert_bounded`].
+    #[inline]
+    pub fn insert_bounded(
+        &self,
+        key: K,
+        value: V,
+        max_len: usize,
+    ) -> Result<Option<&'_ V>, (K, V)> {
+        self.map.insert_bounded(key, value, max_len, &self.guard)
+    }
+
     /// If the value for the specified `key` is present, attempts to
     /// compute a new mapping given the key and its current mapped value.
     ///
@@ -182,6 +819,156 @@ where
             .compute_if_present(key, remapping_function, &self.guard)
     }
 
+    /// Computes a new value for `key` like [`compute_if_present`](HashMapRef::compute_if_present),
+    /// unless that would mean waiting for another thread.
+    ///
+    /// See also [`HashMap::try_compute_if_present_nonblocking`].
+    pub fn try_compute_if_present_nonblocking<'g, Q, F>(
+        &'g self,
+        key: &Q,
+        remapping_function: F,
+    ) -> Result<Option<&'g V>, WouldBlock<F>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+        F: FnOnce(&K, &V) -> Option<V>,
+    {
+        self.map
+            .try_compute_if_present_nonblocking(key, remapping_function, &self.guard)
+    }
+
+    /// Atomically computes a new mapping for `key` from its current mapping, if any.
+    ///
+    /// See also [`HashMap::compute_full`].
+    pub fn compute_full<'g, Q, F>(&'g self, key: &Q, f: F) -> Compute<'g, V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord + ToOwned<Owned = K>,
+        F: FnOnce(Option<(&K, &V)>) -> Option<V>,
+    {
+        self.map.compute_full(key, f, &self.guard)
+    }
+
+    /// Computes a new mapping for `key` like [`compute_full`](HashMapRef::compute_full), unless
+    /// that would mean waiting for another thread.
+    ///
+    /// See also [`HashMap::try_compute_full_nonblocking`].
+    pub fn try_compute_full_nonblocking<'g, Q, F>(
+        &'g self,
+        key: &Q,
+        f: F,
+    ) -> Result<Compute<'g, V>, WouldBlock<F>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord + ToOwned<Owned = K>,
+        F: FnOnce(Option<(&K, &V)>) -> Option<V>,
+    {
+        self.map.try_compute_full_nonblocking(key, f, &self.guard)
+    }
+
+    /// Applies a batch of operations to the map, in order, and returns the outcome of each.
+    ///
+    /// See also [`HashMap::apply_batch`].
+    pub fn apply_batch<'g, I>(&'g self, ops: I) -> Vec<Compute<'g, V>>
+    where
+        K: Clone,
+        I: IntoIterator<Item = MapOp<K, V>>,
+    {
+        self.map.apply_batch(ops, &self.guard)
+    }
+
+    /// Atomically computes new mappings for several keys at once from their current values.
+    ///
+    /// See also [`HashMap::compute_many`].
+    pub fn compute_many<'g, Q, F, const N: usize>(
+        &'g self,
+        keys: [&Q; N],
+        f: F,
+    ) -> [Option<&'g V>; N]
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord + ToOwned<Owned = K>,
+        F: FnOnce([Option<&V>; N]) -> [Option<V>; N],
+    {
+        self.map.compute_many(keys, f, &self.guard)
+    }
+
+    /// Replaces the value of `key` with `new`, but only if the current value equals `expected`.
+    ///
+    /// See also [`HashMap::compare_exchange`].
+    pub fn compare_exchange<'g, Q>(
+        &'g self,
+        key: &Q,
+        expected: &V,
+        new: V,
+    ) -> Result<&'g V, Option<&'g V>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord + ToOwned<Owned = K>,
+        V: PartialEq,
+    {
+        self.map.compare_exchange(key, expected, new, &self.guard)
+    }
+
+    /// Replaces the value of `key` with `new`, but only if `pred` returns `true` for the current
+    /// value.
+    ///
+    /// See also [`HashMap::replace_if_with`].
+    pub fn replace_if_with<'g, Q, P>(&'g self, key: &Q, pred: P, new: V) -> ReplaceIf<'g, V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord + ToOwned<Owned = K>,
+        P: FnOnce(&V) -> bool,
+    {
+        self.map.replace_if_with(key, pred, new, &self.guard)
+    }
+
+    /// Returns a reference to the value corresponding to the key, after bringing it up to date
+    /// with `migrate`.
+    ///
+    /// See also [`HashMap::get_migrating`].
+    pub fn get_migrating<'g, Q, F>(&'g self, key: &Q, migrate: F) -> Option<&'g V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord + ToOwned<Owned = K>,
+        F: Fn(&V) -> Option<V>,
+    {
+        self.map.get_migrating(key, migrate, &self.guard)
+    }
+
+    /// Replaces each value for which `repair` returns `Some(fixed)` with `fixed`, and returns the
+    /// number of values that were replaced.
+    ///
+    /// See also [`HashMap::repair`].
+    pub fn repair<F>(&self, repair: F) -> usize
+    where
+        F: Fn(&K, &V) -> Option<V>,
+    {
+        self.map.repair(repair, &self.guard)
+    }
+
+    /// Runs `f` as a transaction over any number of keys of the map, and returns its result.
+    ///
+    /// See also [`HashMap::transaction`].
+    pub fn transaction<'g, F, R>(&'g self, f: F) -> R
+    where
+        F: FnMut(&mut TxnView<'g, K, V, S>) -> R,
+    {
+        self.map.transaction(f, &self.guard)
+    }
+
+    /// Locks the bin that `key` belongs in, and returns a [`BinGuard`] through which the keys in
+    /// that bin can be read and written while the lock is held.
+    ///
+    /// See also [`HashMap::lock_bin_for`].
+    pub fn lock_bin_for<Q>(&self, key: &Q) -> BinGuard<'_, K, V, S>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+    {
+        self.map.lock_bin_for(key, &self.guard)
+    }
+
     /// Removes a key-value pair from the map, and returns the removed value (if any).
     ///
     /// See also [`HashMap::remove`].
@@ -205,6 +992,140 @@ where
         self.map.remove_entry(key, &self.guard)
     }
 
+    /// Removes a key from the map, unless that would mean waiting for another thread.
+    ///
+    /// See also [`HashMap::try_remove_nonblocking`].
+    pub fn try_remove_nonblocking<'g, Q>(&'g self, key: &Q) -> Result<Option<&'g V>, WouldBlock>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+    {
+        self.map.try_remove_nonblocking(key, &self.guard)
+    }
+
+    /// Creates a new map that holds a copy of every entry in this map, but uses `new_hasher` to
+    /// hash its keys.
+    ///
+    /// See also [`HashMap::rehash_with`].
+    pub fn rehash_with<S2>(&self, new_hasher: S2) -> HashMap<K, V, S2>
+    where
+        V: Clone,
+        S2: BuildHasher,
+    {
+        self.map.rehash_with(new_hasher, &self.guard)
+    }
+
+    /// Splits the map into two new maps: one with the entries for which `pred` returns `true`,
+    /// and one with the rest.
+    ///
+    /// See also [`HashMap::partition`].
+    pub fn partition<P>(&self, pred: P) -> (HashMap<K, V, S>, HashMap<K, V, S>)
+    where
+        V: Clone,
+        S: Clone,
+        P: Fn(&K, &V) -> bool,
+    {
+        self.map.partition(pred, &self.guard)
+    }
+
+    /// Creates a new map that holds a copy of the entries for just the given keys.
+    ///
+    /// See also [`HashMap::clone_subset`].
+    pub fn clone_subset<Q, I>(&self, keys: I) -> HashMap<K, V, S>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Ord,
+        V: Clone,
+        S: Clone,
+        I: IntoIterator<Item = Q>,
+    {
+        self.map.clone_subset(keys, &self.guard)
+    }
+
+    /// Creates a new [`HashSet`](crate::HashSet) that holds clones of all the keys of the map.
+    ///
+    /// See also [`HashMap::key_set`].
+    pub fn key_set(&self) -> crate::HashSet<K> {
+        self.map.key_set(&self.guard)
+    }
+
+    /// Creates a new map from each value of this map to the set of keys that map to it.
+    ///
+    /// See also [`HashMap::invert`].
+    pub fn invert(&self) -> HashMap<V, crate::HashSet<K>>
+    where
+        V: Clone + Hash + Ord,
+    {
+        self.map.invert(&self.guard)
+    }
+
+    /// Folds every entry of `other` into this map, using `resolve` for keys that both maps hold.
+    ///
+    /// See also [`HashMap::merge_from`].
+    pub fn merge_from<F>(&self, other: &HashMap<K, V, S>, resolve: F)
+    where
+        V: Clone,
+        F: Fn(&V, &V) -> V,
+    {
+        self.map.merge_from(other, resolve, &self.guard);
+    }
+
+    /// Moves every entry for which `pred` returns `true` from this map into `dest`, and returns
+    /// the number of entries moved.
+    ///
+    /// See also [`HashMap::move_matching`].
+    pub fn move_matching<P>(&self, dest: &HashMap<K, V, S>, pred: P) -> usize
+    where
+        V: Clone,
+        P: Fn(&K, &V) -> bool,
+    {
+        self.map.move_matching(dest, pred, &self.guard)
+    }
+
+    /// Replaces the entire contents of the map with `entries`, swapping in the new contents all
+    /// at once.
+    ///
+    /// See also [`HashMap::replace_all`].
+    pub fn replace_all<I>(&self, entries: I)
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        self.map.replace_all(entries, &self.guard);
+    }
+
+    /// Replaces the entire contents of the map with `entries`, reusing the capacity of the map's
+    /// current table.
+    ///
+    /// See also [`HashMap::refill`].
+    pub fn refill<I>(&self, entries: I)
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        self.map.refill(entries, &self.guard);
+    }
+
+    /// Empties the map, and returns what it held.
+    ///
+    /// See also [`HashMap::take_all`].
+    pub fn take_all(&self) -> std::collections::HashMap<K, V>
+    where
+        V: Clone,
+    {
+        self.map.take_all(&self.guard)
+    }
+
+    /// Removes the entry with the smallest value according to `cmp`, and returns clones of its
+    /// key and value.
+    ///
+    /// See also [`HashMap::pop_min_by`].
+    pub fn pop_min_by<F>(&self, cmp: F) -> Option<(K, V)>
+    where
+        V: Clone,
+        F: Fn(&V, &V) -> std::cmp::Ordering,
+    {
+        self.map.pop_min_by(cmp, &self.guard)
+    }
+
     /// Retains only the elements specified by the predicate.
     ///
     /// See also [`HashMap::retain`].
@@ -226,6 +1147,32 @@ where
     }
 }
 
+impl<K, S> HashMapRef<'_, K, u32, S>
+where
+    K: 'static + Sync + Send + Clone + Hash + Ord,
+    S: BuildHasher,
+{
+    /// Returns the id of `key`, and assigns it the next unused id first if it does not have one.
+    ///
+    /// See also [`HashMap::intern`].
+    pub fn intern(&self, key: K) -> u32 {
+        self.map.intern(key, &self.guard)
+    }
+}
+
+impl<K, S> HashMapRef<'_, K, u64, S>
+where
+    K: 'static + Sync + Send + Clone + Hash + Ord,
+    S: BuildHasher,
+{
+    /// Adds `delta` to the counter for `key`, unless that would take it above `cap`.
+    ///
+    /// See also [`HashMap::increment_capped`].
+    pub fn increment_capped(&self, key: K, delta: u64, cap: u64) -> Result<u64, u64> {
+        self.map.increment_capped(key, delta, cap, &self.guard)
+    }
+}
+
 impl<'g, K, V, S> IntoIterator for &'g HashMapRef<'_, K, V, S> {
     type IntoIter = Iter<'g, K, V>;
     type Item = (&'g K, &'g V);
@@ -300,6 +1247,11 @@ where
 {
     type Output = V;
 
+    /// Returns a reference to the value corresponding to `key`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key is not present in the map.
     fn index(&self, key: &Q) -> &V {
         self.get(key).expect("no entry found for key")
     }
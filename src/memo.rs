@@ -0,0 +1,252 @@
+//! A map that caches a value derived from each of its values.
+//!
+//! See `MemoMap` for details.
+
+use crate::epoch::Guard;
+use crate::HashMap;
+use std::borrow::Borrow;
+use std::fmt::{self, Debug, Formatter};
+use std::hash::{BuildHasher, Hash};
+
+/// A [`HashMap`] that lazily computes, and then caches, a value derived from the value of each
+/// key.
+///
+/// A `MemoMap` holds two maps: the primary map from keys to values, and a cache from keys to
+/// derived values. [`get_derived`](MemoMap::get_derived) returns the cached derived value for a
+/// key, or computes it from the key's value and caches it if there is none. Writes to the
+/// primary map through the `MemoMap` invalidate the cached derived value of the key they write,
+/// so the next `get_derived` computes it anew, and [`invalidate`](MemoMap::invalidate) does so
+/// explicitly, for example when the derived value depends on more than the value.
+///
+/// # Examples
+///
+/// ```
+/// use flurry::MemoMap;
+///
+/// let docs = MemoMap::new();
+/// let guard = docs.guard();
+/// docs.insert("readme", "a short text".to_string(), &guard);
+///
+/// let words = |_: &&str, text: &String| text.split_whitespace().count();
+/// assert_eq!(docs.get_derived("readme", words, &guard), Some(&3));
+///
+/// docs.insert("readme", "a somewhat longer text".to_string(), &guard);
+/// assert_eq!(docs.get_derived("readme", words, &guard), Some(&4));
+/// ```
+pub struct MemoMap<K, V, D, S = crate::DefaultHashBuilder> {
+    primary: HashMap<K, V, S>,
+    derived: HashMap<K, D, S>,
+}
+
+impl<K, V, D> MemoMap<K, V, D, crate::DefaultHashBuilder> {
+    /// Creates an empty `MemoMap`.
+    pub fn new() -> Self {
+        Self::with_hasher(crate::DefaultHashBuilder::default())
+    }
+}
+
+impl<K, V, D> Default for MemoMap<K, V, D, crate::DefaultHashBuilder> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V, D, S> MemoMap<K, V, D, S> {
+    /// Creates an empty `MemoMap` whose maps will use clones of `hash_builder` to hash keys.
+    pub fn with_hasher(hash_builder: S) -> Self
+    where
+        S: Clone,
+    {
+        MemoMap {
+            primary: HashMap::with_hasher(hash_builder.clone()),
+            derived: HashMap::with_hasher(hash_builder),
+        }
+    }
+
+    /// Pin a `Guard` for use with this map.
+    ///
+    /// See also [`HashMap::guard`].
+    pub fn guard(&self) -> Guard {
+        self.primary.guard()
+    }
+
+    /// Returns the primary map, which maps keys to their values.
+    ///
+    /// Writes made directly to the primary map do not invalidate cached derived values.
+    pub fn primary(&self) -> &HashMap<K, V, S> {
+        &self.primary
+    }
+
+    /// Returns the number of entries in the primary map.
+    ///
+    /// See also [`HashMap::len`].
+    pub fn len(&self) -> usize {
+        self.primary.len()
+    }
+
+    /// Returns `true` if the primary map is empty. Otherwise returns `false`.
+    ///
+    /// See also [`HashMap::is_empty`].
+    pub fn is_empty(&self) -> bool {
+        self.primary.is_empty()
+    }
+}
+
+impl<K, V, D, S> MemoMap<K, V, D, S>
+where
+    K: 'static + Sync + Send + Clone + Hash + Ord,
+    V: 'static + Sync + Send,
+    D: 'static + Sync + Send,
+    S: BuildHasher,
+{
+    /// Returns a reference to the value corresponding to the key.
+    ///
+    /// See also [`HashMap::get`].
+    pub fn get<'g, Q>(&'g self, key: &Q, guard: &'g Guard) -> Option<&'g V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+    {
+        self.primary.get(key, guard)
+    }
+
+    /// Returns the derived value for the key, computing it with `compute` from the key and its
+    /// value if it is not cached yet.
+    ///
+    /// Returns `None` if the key has no value. If several threads compute the derived value of
+    /// a key at once, the value computed first is cached and returned to all of them. If the
+    /// value of the key is written while its derived value is being computed, the derived value
+    /// is still returned, but not kept in the cache.
+    pub fn get_derived<'g, Q, F>(&'g self, key: &Q, compute: F, guard: &'g Guard) -> Option<&'g D>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+        F: FnOnce(&K, &V) -> D,
+    {
+        if let Some(derived) = self.derived.get(key, guard) {
+            return Some(derived);
+        }
+        let (stored_key, value) = self.primary.get_key_value(key, guard)?;
+        let derived =
+            match self
+                .derived
+                .try_insert(stored_key.clone(), compute(stored_key, value), guard)
+            {
+                Ok(derived) => derived,
+                Err(e) => e.current,
+            };
+        // a write that landed in the meantime has invalidated the cache before we filled it, so
+        // we invalidate it again on its behalf.
+        if !self.primary.is_still_present(key, value, guard) {
+            self.derived.remove(key, guard);
+        }
+        Some(derived)
+    }
+
+    /// Drops the cached derived value of the key, if there is one, so that the next call to
+    /// [`get_derived`](MemoMap::get_derived) computes it anew.
+    ///
+    /// Returns `true` if a derived value was dropped.
+    pub fn invalidate<Q>(&self, key: &Q, guard: &Guard) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+    {
+        self.derived.remove(key, guard).is_some()
+    }
+
+    /// Drops all cached derived values.
+    ///
+    /// See also [`HashMap::clear`].
+    pub fn invalidate_all(&self, guard: &Guard) {
+        self.derived.clear(guard);
+    }
+
+    /// Inserts a key-value pair into the map, and invalidates the derived value of the key.
+    ///
+    /// See also [`HashMap::insert`].
+    pub fn insert<'g>(&'g self, key: K, value: V, guard: &'g Guard) -> Option<&'g V> {
+        // the derived value is dropped only after the new value is in place, so that a
+        // concurrent `get_derived` either sees the new value or has its result dropped.
+        let derived_key = key.clone();
+        let old = self.primary.insert(key, value, guard);
+        self.derived.remove(&derived_key, guard);
+        old
+    }
+
+    /// Removes a key from the map, along with its derived value, returning the value at the key
+    /// if the key was previously in the map.
+    ///
+    /// See also [`HashMap::remove`].
+    pub fn remove<'g, Q>(&'g self, key: &Q, guard: &'g Guard) -> Option<&'g V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+    {
+        let old = self.primary.remove(key, guard);
+        self.derived.remove(key, guard);
+        old
+    }
+}
+
+impl<K, V, D, S> Debug for MemoMap<K, V, D, S>
+where
+    K: Debug,
+    V: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let guard = self.guard();
+        f.debug_map().entries(self.primary.iter(&guard)).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::MemoMap;
+    use std::cell::Cell;
+
+    #[test]
+    fn compute_once_until_invalidated() {
+        let map = MemoMap::<usize, usize, String>::new();
+        let guard = map.guard();
+        for i in 0..10 {
+            map.insert(i, i * 10, &guard);
+        }
+
+        let computed = Cell::new(0);
+        let describe = |k: &usize, v: &usize| {
+            computed.set(computed.get() + 1);
+            format!("{} -> {}", k, v)
+        };
+        for _ in 0..3 {
+            for i in 0..10 {
+                let expected = format!("{} -> {}", i, i * 10);
+                assert_eq!(map.get_derived(&i, describe, &guard), Some(&expected));
+            }
+        }
+        assert_eq!(computed.get(), 10);
+        assert_eq!(map.get_derived(&10, describe, &guard), None);
+        assert_eq!(computed.get(), 10);
+
+        // an explicit invalidation makes the next lookup compute again
+        assert!(map.invalidate(&3, &guard));
+        assert!(!map.invalidate(&3, &guard));
+        let expected = "3 -> 30".to_string();
+        assert_eq!(map.get_derived(&3, describe, &guard), Some(&expected));
+        assert_eq!(computed.get(), 11);
+
+        // and so do writes to the value
+        map.insert(4, 44, &guard);
+        let expected = "4 -> 44".to_string();
+        assert_eq!(map.get_derived(&4, describe, &guard), Some(&expected));
+        assert_eq!(computed.get(), 12);
+        map.remove(&4, &guard);
+        assert_eq!(map.get_derived(&4, describe, &guard), None);
+
+        map.invalidate_all(&guard);
+        for i in 0..4 {
+            map.get_derived(&i, describe, &guard);
+        }
+        assert_eq!(computed.get(), 16);
+    }
+}
@@ -0,0 +1,155 @@
+//! Counters that track the operations performed on a map.
+//!
+//! See [`HashMap::stats`](crate::HashMap::stats) for details.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// The live counters of a single map.
+///
+/// All counters are bumped with relaxed atomic adds, since they are only ever read as a whole
+/// for reporting, and need not be consistent with each other or with the map's contents.
+#[derive(Debug, Default)]
+pub(crate) struct Metrics {
+    pub(crate) inserts: AtomicU64,
+    pub(crate) removals: AtomicU64,
+    pub(crate) hits: AtomicU64,
+    pub(crate) misses: AtomicU64,
+    pub(crate) resizes: AtomicU64,
+    pub(crate) resize_helps: AtomicU64,
+    pub(crate) lock_waits: AtomicU64,
+}
+
+impl Metrics {
+    pub(crate) fn snapshot(&self) -> MapStats {
+        MapStats {
+            inserts: self.inserts.load(Ordering::Relaxed),
+            removals: self.removals.load(Ordering::Relaxed),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            resizes: self.resizes.load(Ordering::Relaxed),
+            resize_helps: self.resize_helps.load(Ordering::Relaxed),
+            lock_waits: self.lock_waits.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// A snapshot of the operation counters of a map, as returned by
+/// [`HashMap::stats`](crate::HashMap::stats).
+///
+/// All counts are cumulative over the lifetime of the map.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct MapStats {
+    /// The number of entries that were added to the map. Updates of existing keys are not
+    /// counted.
+    pub inserts: u64,
+    /// The number of entries that were removed from the map, including by `clear` and `retain`.
+    pub removals: u64,
+    /// The number of lookups that found the key they were looking for.
+    pub hits: u64,
+    /// The number of lookups that did not find the key they were looking for.
+    pub misses: u64,
+    /// The number of times the table was resized.
+    pub resizes: u64,
+    /// The number of times a thread joined a resize started by another thread to help move
+    /// entries to the new table.
+    pub resize_helps: u64,
+    /// The number of times a write found the lock of its bin held by another thread and had to
+    /// wait for it.
+    pub lock_waits: u64,
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{HashMap, MapStats};
+    use std::sync::{mpsc, Arc};
+
+    #[test]
+    fn operation_counts() {
+        let map = HashMap::<usize, usize>::new();
+        let guard = map.guard();
+        assert_eq!(map.stats(), MapStats::default());
+
+        for i in 0..100 {
+            map.insert(i, i, &guard);
+        }
+        // updates do not count as inserts
+        map.insert(0, 1, &guard);
+        for i in 0..10 {
+            assert!(map.get(&i, &guard).is_some());
+        }
+        for i in 100..105 {
+            assert!(!map.contains_key(&i, &guard));
+        }
+        for i in 0..20 {
+            map.remove(&i, &guard);
+        }
+        // removals of missing keys do not count
+        map.remove(&1001, &guard);
+
+        let stats = map.stats();
+        assert_eq!(stats.inserts, 100);
+        assert_eq!(stats.removals, 20);
+        assert_eq!(stats.hits, 10);
+        assert_eq!(stats.misses, 5);
+        // lookups that other operations make internally are not counted
+        assert!(map.insert_bounded(1000, 0, 0, &guard).is_err());
+        map.get_or_try_insert_with(50, || Err::<_, ()>(()), &guard)
+            .unwrap();
+        let stats = map.stats();
+        assert_eq!(stats.hits, 10);
+        assert_eq!(stats.misses, 5);
+        // 16 -> 32 -> 64 -> 128 -> 256 bins
+        assert_eq!(stats.resizes, 4);
+        assert_eq!(stats.resize_helps, 0);
+        assert_eq!(stats.lock_waits, 0);
+
+        map.clear(&guard);
+        assert_eq!(map.stats().removals, 100);
+    }
+
+    #[test]
+    fn bin_lock_contention() {
+        let map = Arc::new(HashMap::<usize, usize>::new());
+        {
+            let guard = map.guard();
+            for i in 0..10 {
+                map.insert(i, i, &guard);
+            }
+        }
+        let bin = map.bin_index(&3, &map.guard());
+
+        let (locked_tx, locked_rx) = mpsc::channel();
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+        let map1 = map.clone();
+        // hold on to the lock of the bin of 3 until told to let go
+        let t = std::thread::spawn(move || {
+            let guard = map1.guard();
+            map1.compute_if_present(
+                &3,
+                |_, v| {
+                    locked_tx.send(()).unwrap();
+                    release_rx.recv().unwrap();
+                    Some(v + 1)
+                },
+                &guard,
+            );
+        });
+
+        locked_rx.recv().unwrap();
+        let guard = map.guard();
+        let contention = map.bin_lock_contention(&guard);
+        assert_eq!(contention.len(), map.capacity(&guard));
+        for (i, locked) in contention {
+            assert_eq!(locked, i == bin, "bin {}", i);
+        }
+
+        release_tx.send(()).unwrap();
+        t.join().unwrap();
+        assert!(map
+            .bin_lock_contention(&guard)
+            .iter()
+            .all(|&(_, locked)| !locked));
+        assert_eq!(map.get(&3, &guard), Some(&4));
+    }
+}
@@ -1,4 +1,8 @@
 use crate::raw::Table;
+#[cfg(feature = "hot-keys")]
+use core::sync::atomic::AtomicU32;
+#[cfg(any(feature = "versioning", feature = "insertion-order"))]
+use core::sync::atomic::AtomicU64;
 use core::sync::atomic::{spin_loop_hint, AtomicBool, AtomicI64, Ordering};
 use crossbeam_epoch::{Atomic, Guard, Owned, Shared};
 use parking_lot::Mutex;
@@ -14,6 +18,12 @@ pub(crate) enum BinEntry<K, V> {
     Tree(TreeBin<K, V>),
     TreeNode(TreeNode<K, V>),
     Moved,
+    /// A placeholder for an empty bin whose first entry is being computed.
+    ///
+    /// The lock is taken before the reservation is placed in the bin, and is only released once
+    /// the reservation has been replaced again. Other writers treat it like the lock of a head
+    /// node, while readers treat the bin as empty.
+    Reserved(Mutex<()>),
 }
 
 unsafe impl<K, V> Send for BinEntry<K, V>
@@ -67,6 +77,16 @@ pub(crate) struct Node<K, V> {
     pub(crate) value: Atomic<V>,
     pub(crate) next: Atomic<BinEntry<K, V>>,
     pub(crate) lock: Mutex<()>,
+    /// The map version at which this entry was last inserted or updated.
+    #[cfg(feature = "versioning")]
+    pub(crate) version: AtomicU64,
+    /// The number of times this entry has been looked up.
+    #[cfg(feature = "hot-keys")]
+    pub(crate) accesses: AtomicU32,
+    /// The sequence number the entry was given when its key was inserted, or 0 if it has not
+    /// been given one yet.
+    #[cfg(feature = "insertion-order")]
+    pub(crate) seq: AtomicU64,
 }
 
 impl<K, V> Node<K, V> {
@@ -86,8 +106,46 @@ impl<K, V> Node<K, V> {
             value: value.into(),
             next,
             lock: Mutex::new(()),
+            #[cfg(feature = "versioning")]
+            version: AtomicU64::new(0),
+            #[cfg(feature = "hot-keys")]
+            accesses: AtomicU32::new(0),
+            #[cfg(feature = "insertion-order")]
+            seq: AtomicU64::new(0),
         }
     }
+
+    /// Takes the key and the value out of the node.
+    ///
+    /// # Safety
+    ///
+    /// The value must be valid, and the caller must be its single owner: no other thread may
+    /// hold or be able to obtain a reference to it.
+    pub(crate) unsafe fn into_entry(self) -> (K, V) {
+        let value = *self.value.into_owned().into_box();
+        (self.key, value)
+    }
+
+    /// Copies the per-entry metadata of `other`, that is its version, access count and sequence
+    /// number, to this node, which replaces `other` in a moved or restructured bin.
+    #[inline]
+    pub(crate) fn copy_metadata(&self, other: &Node<K, V>) {
+        #[cfg(feature = "versioning")]
+        self.version
+            .store(other.version.load(Ordering::SeqCst), Ordering::SeqCst);
+        #[cfg(feature = "hot-keys")]
+        self.accesses
+            .store(other.accesses.load(Ordering::Relaxed), Ordering::Relaxed);
+        #[cfg(feature = "insertion-order")]
+        self.seq
+            .store(other.seq.load(Ordering::Relaxed), Ordering::Relaxed);
+        #[cfg(not(any(
+            feature = "versioning",
+            feature = "hot-keys",
+            feature = "insertion-order"
+        )))]
+        let _ = other;
+    }
 }
 
 /* ------------------------ TreeNodes ------------------------ */
@@ -1451,13 +1509,7 @@ mod tests {
     use std::sync::atomic::Ordering;
 
     fn new_node(hash: u64, key: usize, value: usize) -> Node<usize, usize> {
-        Node {
-            hash,
-            key,
-            value: Atomic::new(value),
-            next: Atomic::null(),
-            lock: Mutex::new(()),
-        }
+        Node::new(hash, key, Atomic::new(value))
     }
 
     #[test]
@@ -0,0 +1,167 @@
+//! A concurrent map that normalizes its keys.
+//!
+//! See `NormalizedMap` for details.
+
+use crate::epoch::Guard;
+use crate::HashMap;
+use std::fmt::{self, Debug, Formatter};
+use std::hash::{BuildHasher, Hash};
+use std::marker::PhantomData;
+
+/// A way of mapping keys that should be treated as equal onto a single canonical key.
+///
+/// A [`NormalizedMap`] normalizes every key it is given before hashing it, so two keys are
+/// treated as the same key if and only if they normalize to equal keys. For that to be
+/// consistent, normalizing a key that is already normalized should leave it unchanged.
+pub trait Normalizer<K> {
+    /// Returns the canonical form of `key`.
+    fn normalize(key: &K) -> K;
+}
+
+/// A [`Normalizer`] that lowercases `String` keys, for case-insensitive maps.
+///
+/// Lowercasing is done with [`str::to_lowercase`], and so follows the Unicode definition of
+/// lowercase.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Lowercase;
+
+impl Normalizer<String> for Lowercase {
+    fn normalize(key: &String) -> String {
+        key.to_lowercase()
+    }
+}
+
+/// A concurrent map that applies a [`Normalizer`] to every key before hashing it.
+///
+/// Keys are normalized both when they are inserted and when they are looked up, so any key that
+/// normalizes to the same canonical key finds the same entry. The map only ever stores the
+/// normalized form of a key, which is what iterating over it and [`Debug`] show.
+///
+/// The map is built on a [`HashMap`] that is keyed by the normalized keys.
+///
+/// # Examples
+///
+/// ```
+/// use flurry::{Lowercase, NormalizedMap};
+///
+/// let headers = NormalizedMap::<_, _, Lowercase>::new();
+/// let guard = headers.guard();
+///
+/// headers.insert("Content-Type".to_string(), "text/plain", &guard);
+/// assert_eq!(headers.get(&"content-type".to_string(), &guard), Some(&"text/plain"));
+/// assert_eq!(headers.get(&"CONTENT-TYPE".to_string(), &guard), Some(&"text/plain"));
+/// ```
+pub struct NormalizedMap<K, V, N, S = crate::DefaultHashBuilder> {
+    map: HashMap<K, V, S>,
+    normalizer: PhantomData<fn() -> N>,
+}
+
+impl<K, V, N> NormalizedMap<K, V, N, crate::DefaultHashBuilder> {
+    /// Creates an empty `NormalizedMap`.
+    pub fn new() -> Self {
+        Self::with_hasher(crate::DefaultHashBuilder::default())
+    }
+}
+
+impl<K, V, N> Default for NormalizedMap<K, V, N, crate::DefaultHashBuilder> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V, N, S> NormalizedMap<K, V, N, S> {
+    /// Creates an empty `NormalizedMap` which will use `hash_builder` to hash the normalized
+    /// keys.
+    pub fn with_hasher(hash_builder: S) -> Self {
+        NormalizedMap {
+            map: HashMap::with_hasher(hash_builder),
+            normalizer: PhantomData,
+        }
+    }
+
+    /// Pin a `Guard` for use with this map.
+    ///
+    /// See also [`HashMap::guard`].
+    pub fn guard(&self) -> Guard {
+        self.map.guard()
+    }
+
+    /// Returns the number of entries in the map.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if the map is empty.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+impl<K, V, N, S> NormalizedMap<K, V, N, S>
+where
+    K: 'static + Sync + Send + Clone + Hash + Ord,
+    V: 'static + Sync + Send,
+    N: Normalizer<K>,
+    S: BuildHasher,
+{
+    /// Inserts a key-value pair into the map under the normalized form of `key`.
+    ///
+    /// If the map had a value for a key that normalizes to the same key, the old value is
+    /// returned.
+    pub fn insert<'g>(&'g self, key: K, value: V, guard: &'g Guard) -> Option<&'g V> {
+        self.map.insert(N::normalize(&key), value, guard)
+    }
+
+    /// Returns a reference to the value of the key that `key` normalizes to.
+    pub fn get<'g>(&'g self, key: &K, guard: &'g Guard) -> Option<&'g V> {
+        self.map.get(&N::normalize(key), guard)
+    }
+
+    /// Returns `true` if the map holds a value for the key that `key` normalizes to.
+    pub fn contains_key(&self, key: &K, guard: &Guard) -> bool {
+        self.map.contains_key(&N::normalize(key), guard)
+    }
+
+    /// Removes the key that `key` normalizes to from the map, returning its value if it was
+    /// present.
+    pub fn remove<'g>(&'g self, key: &K, guard: &'g Guard) -> Option<&'g V> {
+        self.map.remove(&N::normalize(key), guard)
+    }
+}
+
+impl<K, V, N, S> Debug for NormalizedMap<K, V, N, S>
+where
+    K: Debug,
+    V: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let guard = self.guard();
+        f.debug_map().entries(self.map.iter(&guard)).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Lowercase, NormalizedMap};
+
+    #[test]
+    fn mixed_case() {
+        let map = NormalizedMap::<String, usize, Lowercase>::new();
+        let guard = map.guard();
+        assert_eq!(map.insert("Hello".to_string(), 1, &guard), None);
+        assert_eq!(map.insert("WORLD".to_string(), 2, &guard), None);
+        assert_eq!(map.insert("hELLo".to_string(), 3, &guard), Some(&1));
+        assert_eq!(map.len(), 2);
+
+        assert_eq!(map.get(&"hello".to_string(), &guard), Some(&3));
+        assert_eq!(map.get(&"HELLO".to_string(), &guard), Some(&3));
+        assert_eq!(map.get(&"World".to_string(), &guard), Some(&2));
+        assert!(map.contains_key(&"wOrLd".to_string(), &guard));
+        assert!(!map.contains_key(&"other".to_string(), &guard));
+        assert!(format!("{:?}", map).contains("\"world\": 2"));
+
+        assert_eq!(map.remove(&"WoRlD".to_string(), &guard), Some(&2));
+        assert_eq!(map.get(&"world".to_string(), &guard), None);
+        assert_eq!(map.len(), 1);
+    }
+}
@@ -0,0 +1,89 @@
+//! A free-list of node allocations that a map reuses instead of going to the allocator.
+//!
+//! See the `node-pool` feature for details.
+
+use crate::node::BinEntry;
+use crossbeam_deque::{Injector, Steal};
+use crossbeam_epoch::{Guard, Owned, Shared};
+use std::mem::MaybeUninit;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// The most allocations a pool keeps around. Any node that is retired while the pool is full is
+/// freed as usual.
+const MAX_FREE_NODES: usize = 1024;
+
+/// The allocation of a node that has been reclaimed, and holds no `K` or `V`.
+struct FreeNode<K, V>(Box<MaybeUninit<BinEntry<K, V>>>);
+
+// safety: a free node is just memory that is sized for an entry, and holds no key or value.
+unsafe impl<K, V> Send for FreeNode<K, V> {}
+
+/// A free-list of node allocations.
+///
+/// Nodes that are removed from a map are handed to [`retire`](NodePool::retire). Once no thread
+/// can still be reading them, their contents are dropped just as with `defer_destroy`, but their
+/// allocations are kept in the pool rather than freed. [`alloc`](NodePool::alloc) then places new
+/// entries into those allocations before turning to the allocator.
+///
+/// The allocations are kept in a lock-free queue, so that threads that insert and remove at the
+/// same time do not contend on a lock.
+pub(crate) struct NodePool<K, V> {
+    free: Injector<FreeNode<K, V>>,
+    /// The number of allocations in `free`, or about to be pushed to it.
+    len: AtomicUsize,
+}
+
+impl<K, V> NodePool<K, V> {
+    pub(crate) fn new() -> Self {
+        NodePool {
+            free: Injector::new(),
+            len: AtomicUsize::new(0),
+        }
+    }
+
+    /// Moves `entry` onto the heap, reusing a pooled allocation if there is one.
+    pub(crate) fn alloc(&self, entry: BinEntry<K, V>) -> Owned<BinEntry<K, V>> {
+        loop {
+            match self.free.steal() {
+                Steal::Success(FreeNode(mut node)) => {
+                    self.len.fetch_sub(1, Ordering::Relaxed);
+                    node.write(entry);
+                    // safety: we just initialized the node, and MaybeUninit<T> has the same
+                    // layout as T.
+                    let node = unsafe { Box::from_raw(Box::into_raw(node) as *mut BinEntry<K, V>) };
+                    return Owned::from(node);
+                }
+                Steal::Empty => return Owned::new(entry),
+                Steal::Retry => {}
+            }
+        }
+    }
+
+    /// Drops `entry` once no thread can still be reading it, and then returns its allocation to
+    /// the pool.
+    ///
+    /// # Safety
+    ///
+    /// The same rules as for [`Guard::defer_destroy`] apply: `entry` must have been allocated by
+    /// [`alloc`](NodePool::alloc) or [`Owned::new`], and must no longer be reachable by threads
+    /// that pin the epoch after this call.
+    pub(crate) unsafe fn retire(
+        self: &Arc<Self>,
+        entry: Shared<'_, BinEntry<K, V>>,
+        guard: &Guard,
+    ) {
+        let pool = Arc::clone(self);
+        let entry = entry.as_raw() as *mut BinEntry<K, V>;
+        guard.defer_unchecked(move || {
+            std::ptr::drop_in_place(entry);
+            let node = Box::from_raw(entry as *mut MaybeUninit<BinEntry<K, V>>);
+            // claim a place in the pool before pushing, so that it never grows past its limit.
+            if pool.len.fetch_add(1, Ordering::Relaxed) < MAX_FREE_NODES {
+                pool.free.push(FreeNode(node));
+            } else {
+                pool.len.fetch_sub(1, Ordering::Relaxed);
+            }
+        });
+    }
+}
@@ -72,6 +72,11 @@ impl<K, V> Table<K, V> {
         self.bins.len()
     }
 
+    #[cfg(feature = "ffi")]
+    pub(crate) fn bins_ptr(&self) -> *const Atomic<BinEntry<K, V>> {
+        self.bins.as_ptr()
+    }
+
     pub(crate) fn get_moved<'g>(
         &'g self,
         for_table: Shared<'g, Table<K, V>>,
@@ -162,6 +167,7 @@ impl<K, V> Table<K, V> {
                             table = unsafe { table.next_table(guard).deref() };
                             continue;
                         }
+                        BinEntry::Reserved(_) => break Shared::null(),
                         BinEntry::TreeNode(_) => unreachable!("`find` was called on a Moved entry pointing to a TreeNode, which cannot be the first entry in a bin"),
                     }
                 }
@@ -171,6 +177,8 @@ impl<K, V> Table<K, V> {
                     "`find` was called on a TreeNode, which cannot be the first entry in a bin"
                 );
             }
+            // the first entry of a reserved bin is still being computed, so the bin is empty.
+            BinEntry::Reserved(_) => Shared::null(),
             BinEntry::Tree(_) => TreeBin::find(Shared::from(bin as *const _), hash, key, guard),
         }
     }
@@ -233,6 +241,84 @@ impl<K, V> Table<K, V> {
                 BinEntry::TreeNode(_) => unreachable!(
                     "The head of a bin cannot be a TreeNode directly without BinEntry::Tree"
                 ),
+                BinEntry::Reserved(_) => unreachable!(
+                    "A reservation is always replaced before the thread that placed it returns"
+                ),
+            }
+        }
+    }
+
+    /// Moves every entry out of the table, and hands it to `f` along with the index of its bin.
+    ///
+    /// This leaves all bins empty, just like `drop_bins`, but keeps the keys and values alive.
+    /// The table must not be in the middle of being resized.
+    pub(crate) fn drain_bins<F>(&mut self, mut f: F)
+    where
+        F: FnMut(usize, K, V),
+    {
+        // safety: we have &mut self _and_ all references we have returned are bound to the
+        // lifetime of their borrow of self, so there cannot be any outstanding references to
+        // anything in the map.
+        let guard = unsafe { crossbeam_epoch::unprotected() };
+
+        let bins = Vec::from(std::mem::replace(&mut self.bins, vec![].into_boxed_slice()));
+        for (i, bin) in bins.into_iter().enumerate() {
+            let mut p = bin.load(Ordering::SeqCst, guard);
+            if p.is_null() {
+                continue;
+            }
+
+            // safety: same as above.
+            match *unsafe { p.deref() } {
+                BinEntry::Node(_) | BinEntry::Tree(_) => {}
+                // the Moved entry is shared by all bins, so it must not be taken ownership of.
+                BinEntry::Moved => {
+                    unreachable!("drain_bins was called on a table that is being resized")
+                }
+                BinEntry::TreeNode(_) => unreachable!(
+                    "The head of a bin cannot be a TreeNode directly without BinEntry::Tree"
+                ),
+                BinEntry::Reserved(_) => unreachable!(
+                    "A reservation is always replaced before the thread that placed it returns"
+                ),
+            }
+
+            // safety: same as above + we own the bin, and we replaced self.bins, so there's no
+            // future way to access its entries either. the values are reclaimed along with the
+            // nodes, so no other thread can hold a reference to them.
+            match *unsafe { p.into_owned() }.into_box() {
+                BinEntry::Node(node) => {
+                    let mut node = node;
+                    loop {
+                        p = node.next.load(Ordering::SeqCst, guard);
+                        let (key, value) = unsafe { node.into_entry() };
+                        f(i, key, value);
+                        if p.is_null() {
+                            break;
+                        }
+                        node = match *unsafe { p.into_owned() }.into_box() {
+                            BinEntry::Node(node) => node,
+                            _ => unreachable!("linked bins only ever contain Nodes"),
+                        };
+                    }
+                }
+                BinEntry::Tree(bin) => {
+                    // the tree links point to the same nodes as the list, so following `first`
+                    // is enough. it is swapped out so that dropping the bin does not drop the
+                    // nodes a second time.
+                    p = bin.first.swap(Shared::null(), Ordering::SeqCst, guard);
+                    drop(bin);
+                    while !p.is_null() {
+                        let tree_node = match *unsafe { p.into_owned() }.into_box() {
+                            BinEntry::TreeNode(tree_node) => tree_node,
+                            _ => unreachable!("Trees can only ever contain TreeNodes"),
+                        };
+                        p = tree_node.node.next.load(Ordering::SeqCst, guard);
+                        let (key, value) = unsafe { tree_node.node.into_entry() };
+                        f(i, key, value);
+                    }
+                }
+                _ => unreachable!(),
             }
         }
     }
@@ -1,7 +1,13 @@
+use crate::iter::{Iter, NodeIter};
 use crate::{HashMap, HashMapRef, HashSet, HashSetRef};
+use crossbeam_epoch::{Guard, Shared};
 use rayon::iter::{FromParallelIterator, IntoParallelIterator, ParallelExtend, ParallelIterator};
 use std::hash::{BuildHasher, Hash};
 
+/// The most bins that a parallel scan of a map hands to a single task. Smaller tables are split
+/// into smaller ranges, so that every rayon thread gets a share of them.
+const BINS_PER_TASK: usize = 1024;
+
 impl<K, V, S> FromParallelIterator<(K, V)> for HashMap<K, V, S>
 where
     K: Clone + Hash + Ord + Send + Sync + 'static,
@@ -12,7 +18,7 @@ where
     where
         I: IntoParallelIterator<Item = (K, V)>,
     {
-        let mut created_map = HashMap::with_hasher(S::default());
+        let created_map = HashMap::with_hasher(S::default());
         created_map.par_extend(par_iter);
         created_map
     }
@@ -28,27 +34,156 @@ where
     where
         I: IntoParallelIterator<Item = (K, V)>,
     {
-        (&*self).par_extend(par_iter);
+        HashMap::par_extend(self, par_iter);
     }
 }
 
-impl<K, V, S> ParallelExtend<(K, V)> for &HashMap<K, V, S>
+impl<K, V, S> HashMap<K, V, S>
 where
     K: Clone + Hash + Ord + Send + Sync + 'static,
     V: Send + Sync + 'static,
     S: BuildHasher + Sync,
 {
-    fn par_extend<I>(&mut self, par_iter: I)
+    /// Inserts all the key-value pairs of a parallel iterator into the map, with every rayon
+    /// worker thread inserting into the map concurrently.
+    ///
+    /// If the length of the iterator is known up front, the map is first presized so that it
+    /// resizes at most a couple of times while the entries are inserted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    /// use rayon::prelude::*;
+    ///
+    /// let map = HashMap::new();
+    /// map.par_extend((0..1000).into_par_iter().map(|i| (i, i * 2)));
+    /// assert_eq!(map.len(), 1000);
+    /// assert_eq!(map.pin().get(&21), Some(&42));
+    /// ```
+    pub fn par_extend<I>(&self, par_iter: I)
     where
         I: IntoParallelIterator<Item = (K, V)>,
     {
-        par_iter.into_par_iter().for_each_init(
+        let par_iter = par_iter.into_par_iter();
+        if let Some(len) = par_iter.opt_len() {
+            // like `Extend`: keys may be already present or show multiple times in the
+            // iterator, so reserve the whole length only if the map is empty, and half of it
+            // (rounded up) otherwise.
+            let reserve = if self.is_empty() {
+                len
+            } else {
+                len / 2 + len % 2
+            };
+            self.reserve(reserve, &self.guard());
+        }
+
+        par_iter.for_each_init(
             || self.guard(),
             |guard, (k, v)| {
-                self.insert(k, v, &guard);
+                self.insert(k, v, guard);
             },
         );
     }
+
+    /// Counts the entries of the map for which `pred` returns `true`, with the bins of the map
+    /// split into ranges that rayon worker threads count in parallel.
+    ///
+    /// This gives the same result as `map.iter(guard).filter(..).count()`, only faster for large
+    /// maps. Like [`iter`](HashMap::iter), it does not see the map at a single point in time if
+    /// it is modified concurrently.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// let guard = map.guard();
+    /// for i in 0..1000 {
+    ///     map.insert(i, i, &guard);
+    /// }
+    /// assert_eq!(map.par_count(|_, &v| v % 10 == 0, &guard), 100);
+    /// ```
+    pub fn par_count<P>(&self, pred: P, guard: &Guard) -> usize
+    where
+        P: Fn(&K, &V) -> bool + Sync,
+    {
+        self.check_guard(guard);
+        let table = self.current_table(guard);
+        // safety: the table was read under `guard`, which stays pinned until all the tasks
+        // below have returned, so the table is not dropped before then.
+        let table = match unsafe { table.as_ref() } {
+            Some(table) => table,
+            None => return 0,
+        };
+        let bins = table.len();
+        // tables have a power-of-two number of bins, so power-of-two chunks divide them evenly.
+        let chunk = (bins / rayon::current_num_threads())
+            .clamp(1, BINS_PER_TASK)
+            .next_power_of_two();
+        let tasks = bins / chunk;
+        (0..tasks)
+            .into_par_iter()
+            .map(|task| {
+                // guards cannot be shared across threads, so every task pins its own.
+                let guard = self.guard();
+                let start = task * chunk;
+                let node_iter = NodeIter::with_range(
+                    Shared::from(table as *const _),
+                    start..start + chunk,
+                    &guard,
+                );
+                Iter {
+                    node_iter,
+                    guard: &guard,
+                }
+                .filter(|(key, value)| pred(key, value))
+                .count()
+            })
+            .sum()
+    }
+}
+
+impl<K, V, S> HashMapRef<'_, K, V, S>
+where
+    K: Clone + Hash + Ord + Send + Sync + 'static,
+    V: Send + Sync + 'static,
+    S: BuildHasher + Sync,
+{
+    /// Inserts all the key-value pairs of a parallel iterator into the map.
+    ///
+    /// See also [`HashMap::par_extend`].
+    pub fn par_extend<I>(&self, par_iter: I)
+    where
+        I: IntoParallelIterator<Item = (K, V)>,
+    {
+        self.map.par_extend(par_iter);
+    }
+
+    /// Counts the entries of the map for which `pred` returns `true`, in parallel.
+    ///
+    /// See also [`HashMap::par_count`].
+    pub fn par_count<P>(&self, pred: P) -> usize
+    where
+        P: Fn(&K, &V) -> bool + Sync,
+    {
+        self.map.par_count(pred, &self.guard)
+    }
+}
+
+impl<K, V, S> ParallelExtend<(K, V)> for &HashMap<K, V, S>
+where
+    K: Clone + Hash + Ord + Send + Sync + 'static,
+    V: Send + Sync + 'static,
+    S: BuildHasher + Sync,
+{
+    fn par_extend<I>(&mut self, par_iter: I)
+    where
+        I: IntoParallelIterator<Item = (K, V)>,
+    {
+        HashMap::par_extend(*self, par_iter);
+    }
 }
 
 impl<'map, K, V, S> ParallelExtend<(K, V)> for HashMapRef<'map, K, V, S>
@@ -102,8 +237,11 @@ where
     where
         I: IntoParallelIterator<Item = K>,
     {
-        let tuple_iter = par_iter.into_par_iter().map(|k| (k, ()));
-        (&self.map).par_extend(tuple_iter);
+        let tuple_iter = par_iter.into_par_iter().map(|k| {
+            self.bloom_insert(&k);
+            (k, ())
+        });
+        self.map.par_extend(tuple_iter);
     }
 }
 
@@ -120,10 +258,58 @@ where
     }
 }
 
+impl<K, S> HashSet<K, S>
+where
+    K: Clone + Hash + Ord + Send + Sync + 'static,
+    S: BuildHasher + Sync,
+{
+    /// Counts the elements of the set for which `pred` returns `true`, with the bins of the set
+    /// split into ranges that rayon worker threads count in parallel.
+    ///
+    /// See [`HashMap::par_count`] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashSet;
+    ///
+    /// let set = HashSet::new();
+    /// let guard = set.guard();
+    /// for i in 0..1000 {
+    ///     set.insert(i, &guard);
+    /// }
+    /// assert_eq!(set.par_count(|&i| i % 10 == 0, &guard), 100);
+    /// ```
+    pub fn par_count<P>(&self, pred: P, guard: &Guard) -> usize
+    where
+        P: Fn(&K) -> bool + Sync,
+    {
+        self.map.par_count(|key, _| pred(key), guard)
+    }
+}
+
+impl<K, S> HashSetRef<'_, K, S>
+where
+    K: Clone + Hash + Ord + Send + Sync + 'static,
+    S: BuildHasher + Sync,
+{
+    /// Counts the elements of the set for which `pred` returns `true`, in parallel.
+    ///
+    /// See also [`HashSet::par_count`].
+    pub fn par_count<P>(&self, pred: P) -> usize
+    where
+        P: Fn(&K) -> bool + Sync,
+    {
+        self.set.par_count(pred, &self.guard)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use crate::{HashMap, HashSet};
-    use rayon::iter::{FromParallelIterator, IntoParallelIterator, ParallelExtend};
+    use rayon::iter::{
+        FromParallelIterator, IntoParallelIterator, ParallelExtend, ParallelIterator,
+    };
 
     #[test]
     fn hm_from_empty_parallel_iter() {
@@ -146,6 +332,40 @@ mod test {
         assert_eq!(created_map.get(&199, &guard), Some(&990));
     }
 
+    #[test]
+    fn hm_par_count() {
+        let map = HashMap::<usize, usize>::new();
+        map.par_extend(
+            (0..1_000_000)
+                .into_par_iter()
+                .map(|i: usize| (i, i * 7 % 1000)),
+        );
+
+        let guard = map.guard();
+        let pred = |_: &usize, &v: &usize| v > 900;
+        let serial = map.iter(&guard).filter(|(k, v)| pred(k, v)).count();
+        assert_eq!(serial, 99_000);
+        assert_eq!(map.par_count(pred, &guard), serial);
+        assert_eq!(map.pin().par_count(|_, _| true), 1_000_000);
+        assert_eq!(
+            HashMap::<usize, usize>::new().pin().par_count(|_, _| true),
+            0
+        );
+    }
+
+    #[test]
+    fn hs_par_count() {
+        let set: HashSet<usize> = (0..1_000_000).into_par_iter().collect();
+
+        let guard = set.guard();
+        let pred = |&i: &usize| i >= 900_000;
+        let serial = set.iter(&guard).filter(|i| pred(i)).count();
+        assert_eq!(serial, 100_000);
+        assert_eq!(set.par_count(pred, &guard), serial);
+        assert_eq!(set.pin().par_count(|_| true), 1_000_000);
+        assert_eq!(HashSet::<usize>::new().pin().par_count(|_| true), 0);
+    }
+
     #[test]
     fn hs_from_empty_parallel_iter() {
         let to_create_from: Vec<i32> = Vec::new();
@@ -172,7 +392,7 @@ mod test {
     fn hm_parallel_extend_by_nothing() {
         let to_extend_with = Vec::new();
 
-        let mut map = HashMap::new();
+        let map = HashMap::new();
         let guard = map.guard();
         map.insert(1, 2, &guard);
         map.insert(3, 4, &guard);
@@ -192,7 +412,7 @@ mod test {
             to_extend_with.push((i + 100, i * 10));
         }
 
-        let mut map = HashMap::new();
+        let map = HashMap::new();
         let guard = map.guard();
         map.insert(1, 2, &guard);
         map.insert(3, 4, &guard);
@@ -206,6 +426,21 @@ mod test {
         assert_eq!(map.get(&199, &guard), Some(&990));
     }
 
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn hm_parallel_extend_concurrently() {
+        let map = HashMap::new();
+        map.par_extend((0..1_000_000).into_par_iter().map(|i| (i, i * 2)));
+        assert_eq!(map.len(), 1_000_000);
+
+        let guard = map.guard();
+        for i in (0..1_000_000).step_by(9973) {
+            assert_eq!(map.get(&i, &guard), Some(&(i * 2)));
+        }
+        assert_eq!(map.get(&999_999, &guard), Some(&1_999_998));
+        assert_eq!(map.get(&1_000_000, &guard), None);
+    }
+
     #[test]
     fn hm_ref_parallel_extend_by_nothing() {
         let to_extend_with = Vec::new();
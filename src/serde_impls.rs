@@ -1,10 +1,20 @@
 use crate::{HashMap, HashMapRef, HashSet, HashSetRef};
+#[cfg(feature = "bincode")]
+use bincode_crate::{self as bincode, Options};
+#[cfg(any(feature = "ndjson", feature = "bincode"))]
+use crossbeam_epoch::Guard;
+#[cfg(feature = "bincode")]
+use serde::de::DeserializeOwned;
+#[cfg(feature = "ndjson")]
+use serde::ser::SerializeStruct;
 use serde::{
     de::{MapAccess, SeqAccess, Visitor},
     Deserialize, Deserializer, Serialize, Serializer,
 };
 use std::fmt::{self, Formatter};
 use std::hash::{BuildHasher, Hash};
+#[cfg(feature = "ndjson")]
+use std::io::{self, Write};
 use std::marker::PhantomData;
 
 struct HashMapVisitor<K, V, S> {
@@ -39,6 +49,146 @@ where
     }
 }
 
+/// A single entry of a map, as written by [`HashMap::write_ndjson`].
+#[cfg(feature = "ndjson")]
+struct Record<'a, K, V> {
+    key: &'a K,
+    value: &'a V,
+}
+
+#[cfg(feature = "ndjson")]
+impl<K, V> Serialize for Record<'_, K, V>
+where
+    K: Serialize,
+    V: Serialize,
+{
+    fn serialize<Sr>(&self, serializer: Sr) -> Result<Sr::Ok, Sr::Error>
+    where
+        Sr: Serializer,
+    {
+        let mut record = serializer.serialize_struct("Record", 2)?;
+        record.serialize_field("key", self.key)?;
+        record.serialize_field("value", self.value)?;
+        record.end()
+    }
+}
+
+#[cfg(feature = "ndjson")]
+impl<K, V, S> HashMap<K, V, S>
+where
+    K: Serialize,
+    V: Serialize,
+{
+    /// Writes the entries of the map to `writer` as newline-delimited JSON.
+    ///
+    /// Every entry is written as a JSON object with a `key` and a `value` field on a line of its
+    /// own. Unlike serializing the whole map at once, this never holds more than one entry in
+    /// serialized form, so it is suitable for streaming a large map to disk. `writer` is flushed
+    /// every so often while the entries are written, and once more at the end. Since this
+    /// performs many small writes, `writer` should usually be buffered.
+    ///
+    /// Like with [`iter`](HashMap::iter), entries that are inserted or removed while the map is
+    /// being written may or may not be written.
+    ///
+    /// This method is only available with the `ndjson` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// map.pin().insert(1, "a");
+    ///
+    /// let mut out = Vec::new();
+    /// map.write_ndjson(&mut out, &map.guard()).unwrap();
+    /// assert_eq!(out, b"{\"key\":1,\"value\":\"a\"}\n");
+    /// ```
+    pub fn write_ndjson<W: Write>(&self, mut writer: W, guard: &Guard) -> io::Result<()> {
+        const FLUSH_EVERY: usize = 1024;
+
+        for (i, (key, value)) in self.iter(guard).enumerate() {
+            serde_json::to_writer(&mut writer, &Record { key, value })?;
+            writer.write_all(b"\n")?;
+            if (i + 1) % FLUSH_EVERY == 0 {
+                writer.flush()?;
+            }
+        }
+        writer.flush()
+    }
+}
+
+#[cfg(feature = "bincode")]
+impl<K, V, S> HashMap<K, V, S>
+where
+    K: Serialize,
+    V: Serialize,
+{
+    /// Encodes the entries of the map into a compact binary snapshot, which can be read back
+    /// with [`from_bytes`](HashMap::from_bytes).
+    ///
+    /// The entries are first gathered under `guard`, and only then encoded, so the snapshot
+    /// holds each entry at most once even if the map is written to in the meantime. Like with
+    /// [`iter`](HashMap::iter), entries that are inserted or removed while the snapshot is taken
+    /// may or may not be included.
+    ///
+    /// This method is only available with the `bincode` feature.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a key or value fails to serialize.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// map.pin().insert(1, "a".to_string());
+    ///
+    /// let bytes = map.to_bytes(&map.guard());
+    /// let copy: HashMap<i32, String> = HashMap::from_bytes(&bytes).unwrap();
+    /// assert_eq!(map, copy);
+    /// ```
+    pub fn to_bytes(&self, guard: &Guard) -> Vec<u8> {
+        // bincode needs to know the number of entries up front, which the map cannot promise
+        // while it is being written to. the default options encode integers, including all
+        // lengths, in as few bytes as they fit in.
+        let entries: Vec<_> = self.iter(guard).collect();
+        bincode::DefaultOptions::new()
+            .serialize(&entries)
+            .expect("failed to serialize map entry")
+    }
+}
+
+#[cfg(feature = "bincode")]
+impl<K, V, S> HashMap<K, V, S>
+where
+    K: 'static + DeserializeOwned + Send + Sync + Hash + Clone + Ord,
+    V: 'static + DeserializeOwned + Send + Sync,
+    S: Default + BuildHasher,
+{
+    /// Builds a map from a snapshot made by [`to_bytes`](HashMap::to_bytes).
+    ///
+    /// This method is only available with the `bincode` feature.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `bytes` is not a valid snapshot of a map with these key and value
+    /// types.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, bincode::Error> {
+        let entries: Vec<(K, V)> = bincode::DefaultOptions::new().deserialize(bytes)?;
+        let map = HashMap::with_capacity_and_hasher(entries.len(), S::default());
+        {
+            let guard = map.guard();
+            for (key, value) in entries {
+                map.insert(key, value, &guard);
+            }
+        }
+        Ok(map)
+    }
+}
+
 impl<'de, K, V, S> Deserialize<'de> for HashMap<K, V, S>
 where
     K: 'static + Deserialize<'de> + Send + Sync + Hash + Clone + Ord,
@@ -195,6 +345,49 @@ mod test {
         assert_eq!(map, deserialized);
     }
 
+    #[test]
+    #[cfg(feature = "ndjson")]
+    fn test_map_ndjson() {
+        let map: HashMap<u32, String> = HashMap::new();
+        let guard = map.guard();
+        for i in 0..2000 {
+            map.insert(i, i.to_string(), &guard);
+        }
+
+        let mut out = Vec::new();
+        map.write_ndjson(&mut out, &guard)
+            .expect("Couldn't write map");
+
+        let out = String::from_utf8(out).expect("ndjson is not utf-8");
+        assert_eq!(out.lines().count(), map.len());
+        for line in out.lines() {
+            let record: serde_json::Value =
+                serde_json::from_str(line).expect("Couldn't parse record");
+            let key = record["key"].as_u64().expect("key is not a number") as u32;
+            let value = record["value"].as_str().expect("value is not a string");
+            assert_eq!(map.get(&key, &guard).map(String::as_str), Some(value));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "bincode")]
+    fn test_map_bytes() {
+        let map: HashMap<u32, String> = HashMap::new();
+        let guard = map.guard();
+        for i in 0..10_000 {
+            map.insert(i, i.to_string(), &guard);
+        }
+
+        let bytes = map.to_bytes(&guard);
+        let copy: HashMap<u32, String> = HashMap::from_bytes(&bytes).expect("Couldn't decode map");
+        assert!(map.guarded_eq(&copy, &guard, &copy.guard()));
+
+        let json = serde_json::to_vec(&map).expect("Couldn't serialize map");
+        assert!(bytes.len() < json.len());
+
+        assert!(HashMap::<u32, String>::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+
     #[test]
     fn test_set() {
         let set: HashSet<u8> = HashSet::with_capacity(5);
@@ -2,9 +2,12 @@
 //!
 //! See `HashSet` for details.
 
+#[cfg(feature = "bloom")]
+use crate::bloom::Bloom;
+use crate::builder::SetBuilder;
 use crate::epoch::Guard;
-use crate::iter::Keys;
-use crate::HashMap;
+use crate::iter::{ExtractIf, Keys};
+use crate::{BinStats, Compute, HashMap};
 use std::borrow::Borrow;
 use std::fmt::{self, Debug, Formatter};
 use std::hash::{BuildHasher, Hash};
@@ -42,6 +45,8 @@ use std::iter::FromIterator;
 /// ```
 pub struct HashSet<T, S = crate::DefaultHashBuilder> {
     pub(crate) map: HashMap<T, (), S>,
+    #[cfg(feature = "bloom")]
+    pub(crate) bloom: Option<Bloom>,
 }
 
 impl<T> HashSet<T, crate::DefaultHashBuilder> {
@@ -83,6 +88,58 @@ impl<T> HashSet<T, crate::DefaultHashBuilder> {
     pub fn with_capacity(capacity: usize) -> Self {
         Self::with_capacity_and_hasher(capacity, crate::DefaultHashBuilder::default())
     }
+
+    /// Returns a [`SetBuilder`] that configures a new `HashSet`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashSet;
+    ///
+    /// let set: HashSet<i32> = HashSet::builder().min_capacity(64).build();
+    /// set.pin().insert(1);
+    /// assert_eq!(set.pin().len(), 1);
+    /// ```
+    pub fn builder() -> SetBuilder<T> {
+        SetBuilder::new()
+    }
+
+    /// Creates an empty `HashSet` with the specified capacity that also maintains a Bloom filter
+    /// over its elements.
+    ///
+    /// The filter is sized so that, once the set holds `capacity` elements, it wrongly reports an
+    /// absent element as possibly present with probability `fp_rate`. Every insertion also sets
+    /// the element's bits in the filter, which lets [`contains`](HashSet::contains) and
+    /// [`get`](HashSet::get) return early for elements that were never inserted, without
+    /// touching the set's table. When the filter reports a possible hit, the lookup falls
+    /// through to the table as usual, so the filter never causes a false negative.
+    ///
+    /// Removing an element does _not_ clear its bits, since they may be shared with other
+    /// elements. A set that sees many removals (or grows well beyond `capacity`) thus gradually
+    /// loses the benefit of the filter. Use [`rebuild_bloom`](HashSet::rebuild_bloom) to reset it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `fp_rate` is not strictly between 0 and 1.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashSet;
+    ///
+    /// let set = HashSet::with_bloom(1000, 0.01);
+    /// let guard = set.guard();
+    /// set.insert("a", &guard);
+    ///
+    /// assert!(set.contains(&"a", &guard));
+    /// assert!(!set.contains(&"b", &guard));
+    /// ```
+    #[cfg(feature = "bloom")]
+    pub fn with_bloom(capacity: usize, fp_rate: f64) -> Self {
+        let mut set = Self::with_capacity(capacity);
+        set.bloom = Some(Bloom::new(capacity, fp_rate));
+        set
+    }
 }
 
 impl<T, S> Default for HashSet<T, S>
@@ -116,6 +173,8 @@ impl<T, S> HashSet<T, S> {
     pub fn with_hasher(hash_builder: S) -> Self {
         Self {
             map: HashMap::with_hasher(hash_builder),
+            #[cfg(feature = "bloom")]
+            bloom: None,
         }
     }
 
@@ -144,6 +203,8 @@ impl<T, S> HashSet<T, S> {
     pub fn with_capacity_and_hasher(capacity: usize, hash_builder: S) -> Self {
         Self {
             map: HashMap::with_capacity_and_hasher(capacity, hash_builder),
+            #[cfg(feature = "bloom")]
+            bloom: None,
         }
     }
 
@@ -189,6 +250,43 @@ impl<T, S> HashSet<T, S> {
         self.len() == 0
     }
 
+    /// Returns a reference to the set's [`BuildHasher`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::{DefaultHashBuilder, HashSet};
+    ///
+    /// let set: HashSet<usize> = HashSet::default();
+    /// let hasher: &DefaultHashBuilder = set.hasher();
+    /// ```
+    pub fn hasher(&self) -> &S {
+        self.map.hasher()
+    }
+
+    /// Returns a read-only view of the [`HashMap`] that backs the set.
+    ///
+    /// A set is a map from its elements to `()`, and this gives access to the diagnostics of the
+    /// map that the set does not offer itself, such as [`HashMap::bin_stats`]. The view cannot
+    /// modify the map, since entries inserted behind the set's back would be missed by its
+    /// Bloom filter, if it has one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashSet;
+    ///
+    /// let set = HashSet::new();
+    /// set.pin().insert(1);
+    /// set.pin().insert(2);
+    ///
+    /// let stats = set.as_map().bin_stats(&set.guard());
+    /// assert_eq!(stats.bin_lengths.iter().sum::<usize>(), 2);
+    /// ```
+    pub fn as_map(&self) -> MapView<'_, T, S> {
+        MapView { map: &self.map }
+    }
+
     /// An iterator visiting all elements in arbitrary order.
     ///
     /// The iterator element type is `&'g T`.
@@ -212,6 +310,65 @@ impl<T, S> HashSet<T, S> {
     pub fn iter<'g>(&'g self, guard: &'g Guard) -> Keys<'g, T, ()> {
         self.map.keys(guard)
     }
+
+    /// An iterator visiting all elements in ascending order.
+    ///
+    /// The elements are collected and sorted up front, so the order is deterministic, which is
+    /// useful for snapshot tests and for displaying the contents of the set. Like with
+    /// [`iter`](HashSet::iter), elements that are inserted or removed concurrently may or may not
+    /// be included.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashSet;
+    ///
+    /// let set = HashSet::new();
+    /// let guard = set.guard();
+    /// set.insert(3, &guard);
+    /// set.insert(1, &guard);
+    /// set.insert(2, &guard);
+    ///
+    /// let sorted: Vec<_> = set.iter_sorted(&guard).collect();
+    /// assert_eq!(sorted, vec![&1, &2, &3]);
+    /// ```
+    pub fn iter_sorted<'g>(&'g self, guard: &'g Guard) -> std::vec::IntoIter<&'g T>
+    where
+        T: Ord,
+    {
+        self.iter_sorted_by(Ord::cmp, guard)
+    }
+
+    /// An iterator visiting all elements in the order given by `compare`.
+    ///
+    /// See [`iter_sorted`](HashSet::iter_sorted) for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashSet;
+    ///
+    /// let set = HashSet::new();
+    /// let guard = set.guard();
+    /// set.insert(1, &guard);
+    /// set.insert(3, &guard);
+    /// set.insert(2, &guard);
+    ///
+    /// let sorted: Vec<_> = set.iter_sorted_by(|a, b| b.cmp(a), &guard).collect();
+    /// assert_eq!(sorted, vec![&3, &2, &1]);
+    /// ```
+    pub fn iter_sorted_by<'g, F>(
+        &'g self,
+        mut compare: F,
+        guard: &'g Guard,
+    ) -> std::vec::IntoIter<&'g T>
+    where
+        F: FnMut(&T, &T) -> std::cmp::Ordering,
+    {
+        let mut elements: Vec<_> = self.iter(guard).collect();
+        elements.sort_by(|a, b| compare(a, b));
+        elements.into_iter()
+    }
 }
 
 impl<T, S> HashSet<T, S>
@@ -246,6 +403,9 @@ where
         T: Borrow<Q>,
         Q: ?Sized + Hash + Ord,
     {
+        if self.bloom_rejects(value) {
+            return false;
+        }
         self.map.contains_key(value, guard)
     }
 
@@ -273,6 +433,9 @@ where
         T: Borrow<Q>,
         Q: ?Sized + Hash + Ord,
     {
+        if self.bloom_rejects(value) {
+            return None;
+        }
         self.map.get_key_value(value, guard).map(|(k, _)| k)
     }
 
@@ -367,9 +530,130 @@ where
         other.is_subset(self, their_guard, our_guard)
     }
 
+    /// Returns `true` if the set is a proper subset of another, i.e., `other` contains all the
+    /// values in `self` and at least one more.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::iter::FromIterator;
+    /// use flurry::HashSet;
+    ///
+    /// let sup = HashSet::from_iter(&[1, 2]);
+    /// let set = HashSet::new();
+    ///
+    /// set.pin().insert(1);
+    /// assert!(set.pin().is_proper_subset(&sup.pin()));
+    /// set.pin().insert(2);
+    /// assert!(!set.pin().is_proper_subset(&sup.pin()));
+    /// ```
+    pub fn is_proper_subset(
+        &self,
+        other: &HashSet<T, S>,
+        our_guard: &Guard,
+        their_guard: &Guard,
+    ) -> bool {
+        self.len() < other.len() && self.is_subset(other, our_guard, their_guard)
+    }
+
+    /// Returns `true` if the set is a proper superset of another, i.e., `self` contains all the
+    /// values in `other` and at least one more.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::iter::FromIterator;
+    /// use flurry::HashSet;
+    ///
+    /// let sub = HashSet::from_iter(&[1, 2]);
+    /// let set = HashSet::new();
+    ///
+    /// set.pin().insert(1);
+    /// set.pin().insert(2);
+    /// assert!(!set.pin().is_proper_superset(&sub.pin()));
+    /// set.pin().insert(3);
+    /// assert!(set.pin().is_proper_superset(&sub.pin()));
+    /// ```
+    pub fn is_proper_superset(
+        &self,
+        other: &HashSet<T, S>,
+        our_guard: &Guard,
+        their_guard: &Guard,
+    ) -> bool {
+        other.is_proper_subset(self, their_guard, our_guard)
+    }
+
+    /// Returns the Jaccard similarity of the set and `other`, which is the size of their
+    /// intersection divided by the size of their union.
+    ///
+    /// The similarity is between `0.0` for sets that have no elements in common, and `1.0` for
+    /// sets that are equal. Two empty sets are considered equal, and so have a similarity of
+    /// `1.0`.
+    ///
+    /// This walks `self` once to count the elements that are also in `other`, and derives the size
+    /// of the union from that and the length of `other`, without building either set. If either
+    /// set is modified concurrently, the result is only an approximation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::iter::FromIterator;
+    /// use flurry::HashSet;
+    ///
+    /// let a: HashSet<i32> = HashSet::from_iter(&[1, 2, 3]);
+    /// let b: HashSet<i32> = HashSet::from_iter(&[2, 3, 4]);
+    ///
+    /// // {2, 3} out of {1, 2, 3, 4}
+    /// assert_eq!(a.pin().jaccard(&b.pin()), 0.5);
+    /// ```
+    pub fn jaccard(&self, other: &HashSet<T, S>, our_guard: &Guard, their_guard: &Guard) -> f64 {
+        let mut ours = 0;
+        let mut both = 0;
+        for value in self.iter(our_guard) {
+            ours += 1;
+            if other.contains(value, their_guard) {
+                both += 1;
+            }
+        }
+        // other may have shrunk since we checked it for our elements.
+        let theirs = std::cmp::max(other.len(), both);
+        let union = ours + theirs - both;
+        if union == 0 {
+            return 1.0;
+        }
+        both as f64 / union as f64
+    }
+
     pub(crate) fn guarded_eq(&self, other: &Self, our_guard: &Guard, their_guard: &Guard) -> bool {
         self.map.guarded_eq(&other.map, our_guard, their_guard)
     }
+
+    /// Records `value` in the set's Bloom filter, if it has one.
+    ///
+    /// This must be called before `value` is inserted into the set's map.
+    #[inline]
+    #[cfg_attr(not(feature = "bloom"), allow(unused_variables))]
+    pub(crate) fn bloom_insert<Q: ?Sized + Hash>(&self, value: &Q) {
+        #[cfg(feature = "bloom")]
+        {
+            if let Some(ref bloom) = self.bloom {
+                bloom.insert(self.map.hash(value));
+            }
+        }
+    }
+
+    /// Returns `true` if the set's Bloom filter shows that `value` was never inserted.
+    #[inline]
+    #[cfg_attr(not(feature = "bloom"), allow(unused_variables))]
+    fn bloom_rejects<Q: ?Sized + Hash>(&self, value: &Q) -> bool {
+        #[cfg(feature = "bloom")]
+        {
+            if let Some(ref bloom) = self.bloom {
+                return !bloom.may_contain(self.map.hash(value));
+            }
+        }
+        false
+    }
 }
 
 impl<T, S> HashSet<T, S>
@@ -396,6 +680,7 @@ where
     /// assert!(set.contains(&2, &guard));
     /// ```
     pub fn insert(&self, value: T, guard: &Guard) -> bool {
+        self.bloom_insert(&value);
         let old = self.map.insert(value, (), guard);
         old.is_none()
     }
@@ -435,6 +720,39 @@ where
         removed.is_some()
     }
 
+    /// Removes `value` from the set if it is present, and adds it otherwise.
+    ///
+    /// Returns `true` if the value was added, and `false` if it was removed. Checking for the
+    /// value and adding or removing it is a single atomic step, so when several threads toggle
+    /// the same value at once, each of them sees the outcome of the toggle before it, and an even
+    /// number of toggles always leaves the set as it was.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashSet;
+    ///
+    /// let set = HashSet::new();
+    /// let guard = set.guard();
+    ///
+    /// assert_eq!(set.toggle(2, &guard), true);
+    /// assert!(set.contains(&2, &guard));
+    /// assert_eq!(set.toggle(2, &guard), false);
+    /// assert!(!set.contains(&2, &guard));
+    /// ```
+    pub fn toggle(&self, value: T, guard: &Guard) -> bool {
+        self.bloom_insert(&value);
+        let toggled = self.map.compute_full(
+            &value,
+            |current| match current {
+                Some(_) => None,
+                None => Some(()),
+            },
+            guard,
+        );
+        matches!(toggled, Compute::Inserted(_))
+    }
+
     /// Removes and returns the value in the set, if any, that is equal to the given one.
     ///
     /// The value may be any borrowed form of the set's value type, but
@@ -462,6 +780,119 @@ where
         self.map.remove_entry(value, guard).map(|(k, _)| k)
     }
 
+    /// Removes and returns an arbitrary element of the set, or `None` if the set is empty.
+    ///
+    /// Several threads can pop from the same set at once to share out its elements as work
+    /// items: each element is only ever handed to one of them. This walks the set from its first
+    /// bin on every call, so draining a large, sparsely populated set one element at a time is
+    /// slower than iterating over it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashSet;
+    ///
+    /// let set: HashSet<_> = [1, 2].iter().cloned().collect();
+    /// let guard = set.guard();
+    /// let a = *set.pop(&guard).unwrap();
+    /// let b = *set.pop(&guard).unwrap();
+    /// assert_ne!(a, b);
+    /// assert_eq!(set.pop(&guard), None);
+    /// ```
+    pub fn pop<'g>(&'g self, guard: &'g Guard) -> Option<&'g T> {
+        loop {
+            let mut seen = false;
+            for value in self.map.keys(guard) {
+                seen = true;
+                // only the thread whose removal actually unlinks the element gets it
+                if let Some((value, _)) = self.map.remove_entry(value, guard) {
+                    return Some(value);
+                }
+            }
+            if !seen {
+                return None;
+            }
+            // other threads took everything we saw; look again in case more was inserted
+        }
+    }
+
+    /// Adds every element of `other` to the set, making it the union of the two sets.
+    ///
+    /// This reserves room for all of `other`'s elements up front, and clones each of them into
+    /// the set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashSet;
+    ///
+    /// let a: HashSet<i32> = [1, 2].iter().copied().collect();
+    /// let b: HashSet<i32> = [2, 3].iter().copied().collect();
+    ///
+    /// a.pin().extend_from(&b.pin());
+    /// let union: HashSet<i32> = [1, 2, 3].iter().copied().collect();
+    /// assert_eq!(a, union);
+    /// ```
+    pub fn extend_from(&self, other: &HashSet<T, S>, our_guard: &Guard, their_guard: &Guard) {
+        self.reserve(other.len(), our_guard);
+        for value in other.iter(their_guard) {
+            self.insert(value.clone(), our_guard);
+        }
+    }
+
+    /// Removes every element that is not also in `other`, making the set the intersection of the
+    /// two sets.
+    ///
+    /// `other` is left unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashSet;
+    ///
+    /// let a: HashSet<i32> = [1, 2, 3].iter().copied().collect();
+    /// let b: HashSet<i32> = [2, 3, 4].iter().copied().collect();
+    ///
+    /// a.pin().retain_intersection(&b.pin());
+    /// let intersection: HashSet<i32> = [2, 3].iter().copied().collect();
+    /// assert_eq!(a, intersection);
+    /// ```
+    pub fn retain_intersection(
+        &self,
+        other: &HashSet<T, S>,
+        our_guard: &Guard,
+        their_guard: &Guard,
+    ) {
+        self.retain(|value| other.contains(value, their_guard), our_guard)
+    }
+
+    /// Removes every element that is also in `other`, leaving the difference of the two sets.
+    ///
+    /// This walks whichever of the two sets is smaller at the time of the call. `other` is left
+    /// unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashSet;
+    ///
+    /// let a: HashSet<i32> = [1, 2, 3].iter().copied().collect();
+    /// let b: HashSet<i32> = [2, 3, 4].iter().copied().collect();
+    ///
+    /// a.pin().subtract(&b.pin());
+    /// let difference: HashSet<i32> = [1].iter().copied().collect();
+    /// assert_eq!(a, difference);
+    /// ```
+    pub fn subtract(&self, other: &HashSet<T, S>, our_guard: &Guard, their_guard: &Guard) {
+        if self.len() <= other.len() {
+            self.retain(|value| !other.contains(value, their_guard), our_guard)
+        } else {
+            for value in other.iter(their_guard) {
+                self.remove(value, our_guard);
+            }
+        }
+    }
+
     /// Retains only the elements specified by the predicate.
     ///
     /// In other words, remove all elements `e` such that `f(&e)` returns `false`.
@@ -485,6 +916,70 @@ where
     {
         self.map.retain(|value, ()| f(value), guard)
     }
+
+    /// Resets the set's Bloom filter so that it only covers the elements currently in the set.
+    ///
+    /// Since removals do not clear bits in the filter, rebuilding it after many removals restores
+    /// its ability to reject lookups for absent elements. This requires exclusive access to the
+    /// set, as elements inserted concurrently with the rebuild could otherwise be left out of the
+    /// filter. If the set was not created with [`with_bloom`](HashSet::with_bloom), this does
+    /// nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashSet;
+    ///
+    /// let mut set = HashSet::with_bloom(100, 0.01);
+    /// set.pin().insert(1);
+    /// set.pin().remove(&1);
+    ///
+    /// set.rebuild_bloom();
+    /// assert!(!set.pin().contains(&1));
+    /// ```
+    #[cfg(feature = "bloom")]
+    pub fn rebuild_bloom(&mut self) {
+        if let Some(mut bloom) = self.bloom.take() {
+            bloom.clear();
+            let guard = self.guard();
+            for value in self.iter(&guard) {
+                bloom.insert(self.map.hash(value));
+            }
+            drop(guard);
+            self.bloom = Some(bloom);
+        }
+    }
+
+    /// Creates an iterator which removes and yields every element for which `pred` returns `true`.
+    ///
+    /// Elements are removed lazily as the iterator is advanced, so if the iterator is dropped
+    /// before it is exhausted, the remaining matching elements stay in the set. Elements that are
+    /// removed by another thread before the iterator gets to them are not yielded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashSet;
+    ///
+    /// let set: HashSet<_> = (0..8).collect();
+    /// let guard = set.guard();
+    ///
+    /// let mut evens: Vec<_> = set.extract_if(|&e| e % 2 == 0, &guard).copied().collect();
+    /// evens.sort();
+    /// assert_eq!(evens, vec![0, 2, 4, 6]);
+    /// assert_eq!(set.len(), 4);
+    /// ```
+    pub fn extract_if<'g, F>(&'g self, pred: F, guard: &'g Guard) -> ExtractIf<'g, T, F, S>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        ExtractIf {
+            set: self,
+            keys: self.iter(guard),
+            pred,
+            guard,
+        }
+    }
 }
 
 impl<T, S> HashSet<T, S>
@@ -534,6 +1029,21 @@ where
 {
 }
 
+impl<T, S, S2> PartialEq<std::collections::HashSet<T, S2>> for HashSet<T, S>
+where
+    T: Ord + Hash,
+    S: BuildHasher,
+    S2: BuildHasher,
+{
+    fn eq(&self, other: &std::collections::HashSet<T, S2>) -> bool {
+        if self.len() != other.len() {
+            return false;
+        }
+        let guard = self.guard();
+        self.iter(&guard).all(|value| other.contains(value))
+    }
+}
+
 impl<T, S> fmt::Debug for HashSet<T, S>
 where
     T: Debug,
@@ -550,7 +1060,13 @@ where
     S: BuildHasher,
 {
     fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
-        Extend::extend(&mut &self.map, iter.into_iter().map(|v| (v, ())))
+        Extend::extend(
+            &mut &self.map,
+            iter.into_iter().map(|v| {
+                self.bloom_insert(&v);
+                (v, ())
+            }),
+        )
     }
 }
 
@@ -560,7 +1076,13 @@ where
     S: BuildHasher,
 {
     fn extend<I: IntoIterator<Item = &'a T>>(&mut self, iter: I) {
-        Extend::extend(&mut &self.map, iter.into_iter().map(|&v| (v, ())))
+        Extend::extend(
+            &mut &self.map,
+            iter.into_iter().map(|&v| {
+                self.bloom_insert(&v);
+                (v, ())
+            }),
+        )
     }
 }
 
@@ -572,6 +1094,8 @@ where
     fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
         Self {
             map: HashMap::from_iter(iter.into_iter().map(|v| (v, ()))),
+            #[cfg(feature = "bloom")]
+            bloom: None,
         }
     }
 }
@@ -584,6 +1108,8 @@ where
     fn from_iter<I: IntoIterator<Item = &'a T>>(iter: I) -> Self {
         Self {
             map: HashMap::from_iter(iter.into_iter().map(|&v| (v, ()))),
+            #[cfg(feature = "bloom")]
+            bloom: None,
         }
     }
 }
@@ -596,6 +1122,120 @@ where
     fn clone(&self) -> HashSet<T, S> {
         Self {
             map: self.map.clone(),
+            #[cfg(feature = "bloom")]
+            bloom: self.bloom.clone(),
         }
     }
 }
+
+/// A read-only view of the [`HashMap`] that backs a [`HashSet`].
+///
+/// See [`HashSet::as_map`] for details.
+pub struct MapView<'set, T, S> {
+    map: &'set HashMap<T, (), S>,
+}
+
+impl<T, S> Clone for MapView<'_, T, S> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T, S> Copy for MapView<'_, T, S> {}
+
+impl<T, S> MapView<'_, T, S> {
+    /// Returns the number of entries in the map.
+    ///
+    /// See also [`HashMap::len`].
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if the map is empty. Otherwise returns `false`.
+    ///
+    /// See also [`HashMap::is_empty`].
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Returns the capacity of the map, that is, the number of bins in its table.
+    ///
+    /// See also [`HashMap::capacity`].
+    pub fn capacity(&self, guard: &Guard) -> usize {
+        self.map.capacity(guard)
+    }
+
+    /// Returns the size in bytes of the map's array of bins.
+    ///
+    /// See also [`HashMap::table_bytes`].
+    pub fn table_bytes(&self, guard: &Guard) -> usize {
+        self.map.table_bytes(guard)
+    }
+
+    /// Returns the fraction of the map's capacity that is in use.
+    ///
+    /// See also [`HashMap::load`].
+    pub fn load(&self, guard: &Guard) -> f64 {
+        self.map.load(guard)
+    }
+
+    /// Returns a reference to the map's [`BuildHasher`].
+    ///
+    /// See also [`HashMap::hasher`].
+    pub fn hasher(&self) -> &S {
+        self.map.hasher()
+    }
+}
+
+impl<T, S> MapView<'_, T, S>
+where
+    T: Clone + Ord,
+{
+    /// Returns a snapshot of how the map's entries are distributed over its bins.
+    ///
+    /// See also [`HashMap::bin_stats`].
+    pub fn bin_stats(&self, guard: &Guard) -> BinStats {
+        self.map.bin_stats(guard)
+    }
+
+    /// Returns the indices of the `n` fullest bins of the map, paired with the number of entries
+    /// in them, fullest first.
+    ///
+    /// See also [`HashMap::worst_bins`].
+    pub fn worst_bins(&self, n: usize, guard: &Guard) -> Vec<(usize, usize)> {
+        self.map.worst_bins(n, guard)
+    }
+
+    /// Returns the average number of entries in the bins of the map that are not empty.
+    ///
+    /// See also [`HashMap::avg_chain_length`].
+    pub fn avg_chain_length(&self, guard: &Guard) -> f64 {
+        self.map.avg_chain_length(guard)
+    }
+}
+
+impl<T, S> MapView<'_, T, S>
+where
+    T: Hash + Ord,
+    S: BuildHasher,
+{
+    /// Returns the number of entries in the bin of `value` that have to be visited to find it.
+    ///
+    /// See also [`HashMap::probe_length`].
+    pub fn probe_length<Q>(&self, value: &Q, guard: &Guard) -> Option<usize>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+    {
+        self.map.probe_length(value, guard)
+    }
+}
+
+impl<T, S> fmt::Debug for MapView<'_, T, S>
+where
+    T: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Debug::fmt(self.map, f)
+    }
+}
@@ -1,5 +1,5 @@
 use crate::iter::*;
-use crate::{GuardRef, HashSet};
+use crate::{GuardRef, HashSet, MapView};
 use crossbeam_epoch::Guard;
 use std::borrow::Borrow;
 use std::fmt::{self, Debug, Formatter};
@@ -11,7 +11,7 @@ use std::hash::{BuildHasher, Hash};
 /// Keep in mind that this prevents the collection of garbage generated by the set.
 pub struct HashSetRef<'set, T, S = crate::DefaultHashBuilder> {
     pub(crate) set: &'set HashSet<T, S>,
-    guard: GuardRef<'set>,
+    pub(crate) guard: GuardRef<'set>,
 }
 
 impl<T, S> HashSet<T, S> {
@@ -50,6 +50,20 @@ impl<T, S> HashSetRef<'_, T, S> {
         self.set.is_empty()
     }
 
+    /// Returns a reference to the set's [`BuildHasher`].
+    ///
+    /// See also [`HashSet::hasher`].
+    pub fn hasher(&self) -> &S {
+        self.set.hasher()
+    }
+
+    /// Returns a read-only view of the [`HashMap`](crate::HashMap) that backs the set.
+    ///
+    /// See also [`HashSet::as_map`].
+    pub fn as_map(&self) -> MapView<'_, T, S> {
+        self.set.as_map()
+    }
+
     /// An iterator visiting all elements in arbitrary order.
     ///
     /// The iterator element type is `&'g T`.
@@ -58,6 +72,26 @@ impl<T, S> HashSetRef<'_, T, S> {
     pub fn iter(&self) -> Keys<'_, T, ()> {
         self.set.iter(&self.guard)
     }
+
+    /// An iterator visiting all elements in ascending order.
+    ///
+    /// See also [`HashSet::iter_sorted`].
+    pub fn iter_sorted(&self) -> std::vec::IntoIter<&'_ T>
+    where
+        T: Ord,
+    {
+        self.set.iter_sorted(&self.guard)
+    }
+
+    /// An iterator visiting all elements in the order given by `compare`.
+    ///
+    /// See also [`HashSet::iter_sorted_by`].
+    pub fn iter_sorted_by<F>(&self, compare: F) -> std::vec::IntoIter<&'_ T>
+    where
+        F: FnMut(&T, &T) -> std::cmp::Ordering,
+    {
+        self.set.iter_sorted_by(compare, &self.guard)
+    }
 }
 
 impl<T, S> HashSetRef<'_, T, S>
@@ -108,6 +142,32 @@ where
     pub fn is_superset<'other>(&self, other: &HashSetRef<'other, T, S>) -> bool {
         self.set.is_superset(other.set, &self.guard, &other.guard)
     }
+
+    /// Returns `true` if the set is a proper subset of another, i.e., `other` contains all the
+    /// values in `self` and at least one more.
+    ///
+    /// See also [`HashSet::is_proper_subset`].
+    pub fn is_proper_subset(&self, other: &HashSetRef<'_, T, S>) -> bool {
+        self.set
+            .is_proper_subset(other.set, &self.guard, &other.guard)
+    }
+
+    /// Returns `true` if the set is a proper superset of another, i.e., `self` contains all the
+    /// values in `other` and at least one more.
+    ///
+    /// See also [`HashSet::is_proper_superset`].
+    pub fn is_proper_superset(&self, other: &HashSetRef<'_, T, S>) -> bool {
+        self.set
+            .is_proper_superset(other.set, &self.guard, &other.guard)
+    }
+
+    /// Returns the Jaccard similarity of the set and `other`, which is the size of their
+    /// intersection divided by the size of their union.
+    ///
+    /// See also [`HashSet::jaccard`].
+    pub fn jaccard(&self, other: &HashSetRef<'_, T, S>) -> f64 {
+        self.set.jaccard(other.set, &self.guard, &other.guard)
+    }
 }
 
 impl<T, S> HashSetRef<'_, T, S>
@@ -133,6 +193,13 @@ where
         self.set.remove(value, &self.guard)
     }
 
+    /// Removes `value` from the set if it is present, and adds it otherwise.
+    ///
+    /// See also [`HashSet::toggle`].
+    pub fn toggle(&self, value: T) -> bool {
+        self.set.toggle(value, &self.guard)
+    }
+
     /// Removes and returns the value in the set, if any, that is equal to the given one.
     ///
     /// See also [`HashSet::take`].
@@ -144,6 +211,36 @@ where
         self.set.take(value, &self.guard)
     }
 
+    /// Removes and returns an arbitrary element of the set, or `None` if the set is empty.
+    ///
+    /// See also [`HashSet::pop`].
+    pub fn pop(&self) -> Option<&T> {
+        self.set.pop(&self.guard)
+    }
+
+    /// Adds every element of `other` to the set, making it the union of the two sets.
+    ///
+    /// See also [`HashSet::extend_from`].
+    pub fn extend_from(&self, other: &HashSetRef<'_, T, S>) {
+        self.set.extend_from(other.set, &self.guard, &other.guard)
+    }
+
+    /// Removes every element that is not also in `other`, making the set the intersection of the
+    /// two sets.
+    ///
+    /// See also [`HashSet::retain_intersection`].
+    pub fn retain_intersection(&self, other: &HashSetRef<'_, T, S>) {
+        self.set
+            .retain_intersection(other.set, &self.guard, &other.guard)
+    }
+
+    /// Removes every element that is also in `other`, leaving the difference of the two sets.
+    ///
+    /// See also [`HashSet::subtract`].
+    pub fn subtract(&self, other: &HashSetRef<'_, T, S>) {
+        self.set.subtract(other.set, &self.guard, &other.guard)
+    }
+
     /// Retains only the elements specified by the predicate.
     ///
     /// See also [`HashSet::retain`].
@@ -153,6 +250,16 @@ where
     {
         self.set.retain(f, &self.guard);
     }
+
+    /// Creates an iterator which removes and yields every element for which `pred` returns `true`.
+    ///
+    /// See also [`HashSet::extract_if`].
+    pub fn extract_if<F>(&self, pred: F) -> ExtractIf<'_, T, F, S>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.set.extract_if(pred, &self.guard)
+    }
 }
 
 impl<T, S> HashSetRef<'_, T, S>
@@ -0,0 +1,265 @@
+//! Traits for code that is generic over concurrent map implementations.
+//!
+//! These are opt-in: bring the traits into scope with `use flurry::traits::ConcurrentMap;`.
+//! Library authors can then write code against [`ConcurrentMap`] instead of [`HashMap`], and let
+//! their users pick the map that backs it.
+
+use crate::{Compute, HashMap};
+use std::fmt::{self, Debug, Formatter};
+use std::hash::{BuildHasher, Hash};
+
+/// The operations that a concurrent map offers, independent of how it is implemented.
+///
+/// Since implementations differ in how they keep values alive while they are being read, the
+/// trait hands out clones of values rather than references to them, and so requires values to
+/// implement [`Clone`]. Each method is atomic with respect to the key it operates on.
+///
+/// The methods of this trait share their names with the inherent methods of [`HashMap`], which
+/// take precedence when called on a `HashMap` directly. Outside of generic code, call them as
+/// `ConcurrentMap::get(&map, &key)`.
+///
+/// # Examples
+///
+/// ```
+/// use flurry::traits::ConcurrentMap;
+/// use flurry::HashMap;
+///
+/// fn count_word<M: ConcurrentMap<String, usize>>(words: &M, word: &str) {
+///     words.upsert(word.to_string(), |n| n.map_or(1, |n| n + 1));
+/// }
+///
+/// let words = HashMap::new();
+/// count_word(&words, "a");
+/// count_word(&words, "a");
+/// assert_eq!(ConcurrentMap::get(&words, &"a".to_string()), Some(2));
+/// ```
+pub trait ConcurrentMap<K, V> {
+    /// Returns a clone of the value corresponding to the key.
+    fn get(&self, key: &K) -> Option<V>;
+
+    /// Inserts a key-value pair into the map, and returns the value the key had before, if any.
+    fn insert(&self, key: K, value: V) -> Option<V>;
+
+    /// Removes a key from the map, and returns its value, if it was present.
+    fn remove(&self, key: &K) -> Option<V>;
+
+    /// Returns the value corresponding to the key, and inserts the result of `f` for it first if
+    /// the key is not present.
+    ///
+    /// Implementations may call `f` even if another thread concurrently inserts a value for the
+    /// key first, in which case that value is returned, and the result of `f` is discarded.
+    fn get_or_insert_with<F>(&self, key: K, f: F) -> V
+    where
+        F: FnOnce() -> V;
+
+    /// Replaces the value corresponding to the key with the result of `f`, and returns the new
+    /// value. Returns `None`, without calling `f`, if the key is not present.
+    fn update<F>(&self, key: &K, f: F) -> Option<V>
+    where
+        F: FnOnce(&V) -> V;
+
+    /// Sets the value corresponding to the key to the result of `f`, which is given the current
+    /// value if the key is present, and returns the new value.
+    ///
+    /// Unlike a call to [`update`](ConcurrentMap::update) followed by one to
+    /// [`get_or_insert_with`](ConcurrentMap::get_or_insert_with), this is a single atomic
+    /// operation, so no update made by another thread in between can get lost.
+    fn upsert<F>(&self, key: K, f: F) -> V
+    where
+        F: FnOnce(Option<&V>) -> V;
+
+    /// Gets the given key's entry in the map, for updating or inserting its value in the style
+    /// of the entry API of [`std::collections::HashMap`].
+    fn entry(&self, key: K) -> Entry<'_, K, V, Self>
+    where
+        Self: Sized,
+    {
+        Entry {
+            map: self,
+            key,
+            value: std::marker::PhantomData,
+        }
+    }
+}
+
+/// A key's entry in a [`ConcurrentMap`].
+///
+/// Unlike an entry of a [`std::collections::HashMap`], an `Entry` does not lock the key, and so
+/// does not know whether the key is present: other threads may insert or remove it at any time.
+/// Each of its methods instead performs one atomic operation on the map.
+///
+/// In particular, `entry(key).and_modify(f).or_insert(default)` is two operations. If two threads
+/// run it for an absent key at the same time, both may find the key absent in `and_modify`, and
+/// then only one of their inserts takes effect. Use [`ConcurrentMap::upsert`] where every update
+/// has to count.
+///
+/// This `struct` is created by [`ConcurrentMap::entry`].
+pub struct Entry<'a, K, V, M> {
+    map: &'a M,
+    key: K,
+    value: std::marker::PhantomData<fn() -> V>,
+}
+
+impl<'a, K, V, M> Entry<'a, K, V, M>
+where
+    M: ConcurrentMap<K, V>,
+{
+    /// Returns a reference to this entry's key.
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Replaces the value of the key with the result of `f`, if the key is present.
+    pub fn and_modify<F>(self, f: F) -> Self
+    where
+        F: FnOnce(&V) -> V,
+    {
+        self.map.update(&self.key, f);
+        self
+    }
+
+    /// Returns the value of the key, and inserts `default` for it first if the key is not
+    /// present.
+    pub fn or_insert(self, default: V) -> V {
+        self.or_insert_with(|| default)
+    }
+
+    /// Returns the value of the key, and inserts the result of `default` for it first if the key
+    /// is not present.
+    pub fn or_insert_with<F>(self, default: F) -> V
+    where
+        F: FnOnce() -> V,
+    {
+        self.map.get_or_insert_with(self.key, default)
+    }
+
+    /// Returns the value of the key, and inserts the default value for it first if the key is
+    /// not present.
+    pub fn or_default(self) -> V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+}
+
+impl<K, V, M> Debug for Entry<'_, K, V, M>
+where
+    K: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Entry").field(&self.key).finish()
+    }
+}
+
+impl<K, V, S> ConcurrentMap<K, V> for HashMap<K, V, S>
+where
+    K: 'static + Sync + Send + Clone + Hash + Ord,
+    V: 'static + Sync + Send + Clone,
+    S: BuildHasher,
+{
+    fn get(&self, key: &K) -> Option<V> {
+        self.pin().get(key).cloned()
+    }
+
+    fn insert(&self, key: K, value: V) -> Option<V> {
+        self.pin().insert(key, value).cloned()
+    }
+
+    fn remove(&self, key: &K) -> Option<V> {
+        self.pin().remove(key).cloned()
+    }
+
+    fn get_or_insert_with<F>(&self, key: K, f: F) -> V
+    where
+        F: FnOnce() -> V,
+    {
+        let map = self.pin();
+        if let Some(value) = map.get(&key) {
+            return value.clone();
+        }
+        match map.try_insert(key, f()) {
+            Ok(value) => value.clone(),
+            Err(e) => e.current.clone(),
+        }
+    }
+
+    fn update<F>(&self, key: &K, f: F) -> Option<V>
+    where
+        F: FnOnce(&V) -> V,
+    {
+        self.pin()
+            .compute_if_present(key, |_, v| Some(f(v)))
+            .cloned()
+    }
+
+    fn upsert<F>(&self, key: K, f: F) -> V
+    where
+        F: FnOnce(Option<&V>) -> V,
+    {
+        match self
+            .pin()
+            .compute_full(&key, |entry| Some(f(entry.map(|(_, v)| v))))
+        {
+            Compute::Inserted(new) | Compute::Updated { new, .. } => new.clone(),
+            Compute::Removed(_) | Compute::NoOp => unreachable!("`f` always returns a value"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ConcurrentMap;
+    use crate::HashMap;
+
+    /// Counts how often each key occurs, on any map.
+    fn tally<M>(map: &M, keys: &[usize])
+    where
+        M: ConcurrentMap<usize, usize>,
+    {
+        for &key in keys {
+            map.upsert(key, |n| n.map_or(1, |n| n + 1));
+        }
+    }
+
+    #[test]
+    fn generic_over_flurry() {
+        let map = HashMap::new();
+        assert_eq!(ConcurrentMap::insert(&map, 1, 10), None);
+        assert_eq!(ConcurrentMap::insert(&map, 1, 11), Some(10));
+        assert_eq!(ConcurrentMap::get(&map, &1), Some(11));
+        assert_eq!(
+            map.get_or_insert_with(1, || unreachable!("1 is present")),
+            11
+        );
+        assert_eq!(map.get_or_insert_with(2, || 20), 20);
+        assert_eq!(map.update(&2, |v| v + 1), Some(21));
+        assert_eq!(map.update(&3, |_| unreachable!("3 is absent")), None);
+        assert_eq!(ConcurrentMap::remove(&map, &2), Some(21));
+        assert_eq!(ConcurrentMap::get(&map, &2), None);
+        assert_eq!(map.entry(4).or_default(), 0);
+        assert_eq!(map.entry(4).key(), &4);
+        assert_eq!(map.entry(4).and_modify(|n| n + 1).or_insert(10), 1);
+
+        tally(&map, &[1, 5, 5, 6, 5]);
+        assert_eq!(ConcurrentMap::get(&map, &1), Some(12));
+        assert_eq!(ConcurrentMap::get(&map, &5), Some(3));
+        assert_eq!(ConcurrentMap::get(&map, &6), Some(1));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn concurrent_upserts() {
+        let map = std::sync::Arc::new(HashMap::new());
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let map = map.clone();
+                std::thread::spawn(move || tally(&*map, &[0; 1000]))
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(ConcurrentMap::get(&*map, &0), Some(4000));
+    }
+}
@@ -0,0 +1,274 @@
+//! A concurrent map whose entries expire.
+//!
+//! See `TtlMap` for details.
+
+use crate::epoch::Guard;
+use crate::HashMap;
+use crossbeam_epoch::Shared;
+use std::borrow::Borrow;
+use std::fmt::{self, Debug, Formatter};
+use std::hash::{BuildHasher, Hash};
+use std::time::{Duration, Instant};
+
+/// A concurrent map whose entries expire a fixed amount of time after they were inserted.
+///
+/// Every entry is stamped with a deadline when it is inserted, computed either from the map's
+/// default time-to-live or from a per-entry one given to
+/// [`insert_with_ttl`](TtlMap::insert_with_ttl). Once an entry's deadline has passed, lookups no
+/// longer return it.
+///
+/// Expiry is lazy: there is no background thread that removes expired entries. Instead, an
+/// expired entry is removed when a lookup comes across it, and
+/// [`purge_expired`](TtlMap::purge_expired) sweeps the whole map for expired entries. Until then,
+/// expired entries still occupy memory and count towards [`len`](TtlMap::len).
+///
+/// The map is built on a [`HashMap`] that stores each value alongside its deadline.
+///
+/// # Examples
+///
+/// ```
+/// use flurry::TtlMap;
+/// use std::time::Duration;
+///
+/// let cache = TtlMap::new(Duration::from_secs(60));
+/// let guard = cache.guard();
+///
+/// cache.insert("key", "value", &guard);
+/// cache.insert_with_ttl("short-lived", "value", Duration::from_millis(1), &guard);
+/// assert_eq!(cache.get(&"key", &guard), Some(&"value"));
+///
+/// std::thread::sleep(Duration::from_millis(5));
+/// assert_eq!(cache.get(&"short-lived", &guard), None);
+/// ```
+pub struct TtlMap<K, V, S = crate::DefaultHashBuilder> {
+    /// Each value is stored with its deadline, which is `None` for entries that never expire.
+    map: HashMap<K, (V, Option<Instant>), S>,
+    ttl: Duration,
+}
+
+impl<K, V> TtlMap<K, V, crate::DefaultHashBuilder> {
+    /// Creates an empty `TtlMap` whose entries expire `ttl` after they are inserted, unless
+    /// they are inserted with a different time-to-live.
+    pub fn new(ttl: Duration) -> Self {
+        Self::with_hasher(ttl, crate::DefaultHashBuilder::default())
+    }
+}
+
+impl<K, V, S> TtlMap<K, V, S> {
+    /// Creates an empty `TtlMap` with the default time-to-live `ttl` which will use
+    /// `hash_builder` to hash keys.
+    pub fn with_hasher(ttl: Duration, hash_builder: S) -> Self {
+        TtlMap {
+            map: HashMap::with_hasher(hash_builder),
+            ttl,
+        }
+    }
+
+    /// Pin a `Guard` for use with this map.
+    ///
+    /// See also [`HashMap::guard`].
+    pub fn guard(&self) -> Guard {
+        self.map.guard()
+    }
+
+    /// Returns the default time-to-live of entries in this map.
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Returns the number of entries in the map, including entries that have expired but have
+    /// not yet been removed.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if the map holds no entries, expired or not.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+impl<K, V, S> TtlMap<K, V, S>
+where
+    K: 'static + Sync + Send + Clone + Hash + Ord,
+    V: 'static + Sync + Send,
+    S: BuildHasher,
+{
+    /// Inserts a key-value pair into the map that expires after the map's default time-to-live.
+    ///
+    /// If the map had an unexpired value for this key, the old value is returned.
+    pub fn insert<'g>(&'g self, key: K, value: V, guard: &'g Guard) -> Option<&'g V> {
+        self.insert_with_ttl(key, value, self.ttl, guard)
+    }
+
+    /// Inserts a key-value pair into the map that expires after `ttl`.
+    ///
+    /// If `ttl` is so long that the deadline cannot be represented, the entry never expires.
+    ///
+    /// If the map had an unexpired value for this key, the old value is returned.
+    pub fn insert_with_ttl<'g>(
+        &'g self,
+        key: K,
+        value: V,
+        ttl: Duration,
+        guard: &'g Guard,
+    ) -> Option<&'g V> {
+        let now = Instant::now();
+        self.map
+            .insert(key, (value, now.checked_add(ttl)), guard)
+            .and_then(|(old, deadline)| {
+                if alive(*deadline, now) {
+                    Some(old)
+                } else {
+                    None
+                }
+            })
+    }
+
+    /// Returns a reference to the value corresponding to the key, unless it has expired.
+    ///
+    /// If the entry for the key has expired, it is removed from the map.
+    pub fn get<'g, Q>(&'g self, key: &Q, guard: &'g Guard) -> Option<&'g V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+    {
+        let entry = self.map.get(key, guard)?;
+        if alive(entry.1, Instant::now()) {
+            return Some(&entry.0);
+        }
+
+        // only remove the entry we looked at, not a fresh one that was inserted concurrently.
+        self.map
+            .replace_node(key, None, Some(Shared::from(entry as *const _)), guard);
+        None
+    }
+
+    /// Returns `true` if the map holds an unexpired value for the specified key.
+    pub fn contains_key<Q>(&self, key: &Q, guard: &Guard) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+    {
+        self.get(key, guard).is_some()
+    }
+
+    /// Removes a key from the map, returning its value if it was present and had not expired.
+    pub fn remove<'g, Q>(&'g self, key: &Q, guard: &'g Guard) -> Option<&'g V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Ord,
+    {
+        let now = Instant::now();
+        self.map.remove(key, guard).and_then(|(value, deadline)| {
+            if alive(*deadline, now) {
+                Some(value)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Removes all expired entries from the map, and returns how many were removed.
+    ///
+    /// Entries whose value is replaced while the sweep is ongoing are left alone.
+    pub fn purge_expired(&self, guard: &Guard) -> usize {
+        let now = Instant::now();
+        let mut purged = 0;
+        for (key, entry) in self.map.iter(guard) {
+            if alive(entry.1, now) {
+                continue;
+            }
+            let observed = Shared::from(entry as *const _);
+            if self
+                .map
+                .replace_node(key, None, Some(observed), guard)
+                .is_some()
+            {
+                purged += 1;
+            }
+        }
+        purged
+    }
+}
+
+/// Returns `true` if an entry with the given deadline has not expired at `now`.
+fn alive(deadline: Option<Instant>, now: Instant) -> bool {
+    match deadline {
+        Some(deadline) => deadline > now,
+        None => true,
+    }
+}
+
+impl<K, V, S> Debug for TtlMap<K, V, S>
+where
+    K: Debug,
+    V: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let guard = self.guard();
+        f.debug_map()
+            .entries(self.map.iter(&guard).map(|(k, (v, _))| (k, v)))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::TtlMap;
+    use std::time::Duration;
+
+    #[test]
+    fn get_expired() {
+        let map = TtlMap::new(Duration::from_millis(10));
+        let guard = map.guard();
+        map.insert(1, "a", &guard);
+        map.insert_with_ttl(2, "b", Duration::from_secs(60), &guard);
+        assert_eq!(map.get(&1, &guard), Some(&"a"));
+
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(map.get(&1, &guard), None);
+        assert_eq!(map.get(&2, &guard), Some(&"b"));
+        // the lazy removal took the expired entry out of the map
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn purge_expired() {
+        let map = TtlMap::new(Duration::from_millis(10));
+        let guard = map.guard();
+        for i in 0..10 {
+            map.insert(i, i, &guard);
+        }
+        map.insert_with_ttl(10, 10, Duration::from_secs(60), &guard);
+        assert_eq!(map.len(), 11);
+
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(map.len(), 11);
+        assert_eq!(map.purge_expired(&guard), 10);
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.get(&10, &guard), Some(&10));
+    }
+
+    #[test]
+    fn insert_over_expired() {
+        let map = TtlMap::new(Duration::from_millis(10));
+        let guard = map.guard();
+        map.insert(1, "a", &guard);
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(map.insert(1, "b", &guard), None);
+        assert_eq!(map.get(&1, &guard), Some(&"b"));
+        assert_eq!(map.remove(&1, &guard), Some(&"b"));
+    }
+
+    #[test]
+    fn huge_ttl() {
+        let map = TtlMap::new(Duration::MAX);
+        let guard = map.guard();
+        map.insert(1, "a", &guard);
+        map.insert_with_ttl(2, "b", Duration::MAX, &guard);
+        assert_eq!(map.get(&1, &guard), Some(&"a"));
+        assert_eq!(map.get(&2, &guard), Some(&"b"));
+        assert_eq!(map.purge_expired(&guard), 0);
+    }
+}
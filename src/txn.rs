@@ -0,0 +1,285 @@
+//! Optimistic transactions over several keys of a map.
+//!
+//! See `HashMap::transaction` for details.
+
+use crate::epoch::Guard;
+use crate::HashMap;
+use std::collections::BTreeMap;
+use std::fmt::{self, Debug, Formatter};
+use std::hash::{BuildHasher, Hash};
+
+/// What a transaction has done with a single key.
+struct TxnEntry<'g, V> {
+    /// The value the transaction read for the key from the map, if it did.
+    read: Option<Option<&'g V>>,
+    /// The value the transaction wrote for the key, if it did, where `None` is a removal.
+    write: Option<Option<V>>,
+}
+
+impl<V> TxnEntry<'_, V> {
+    fn new() -> Self {
+        TxnEntry {
+            read: None,
+            write: None,
+        }
+    }
+}
+
+/// A view of a [`HashMap`] through which a transaction reads and writes.
+///
+/// Reads go to the map the first time a key is read, and are remembered so that they can be
+/// checked when the transaction commits. Writes are buffered in the view, and only applied to the
+/// map when the transaction commits. A transaction always sees its own writes.
+///
+/// See [`HashMap::transaction`] for details.
+pub struct TxnView<'g, K, V, S = crate::DefaultHashBuilder> {
+    map: &'g HashMap<K, V, S>,
+    entries: BTreeMap<K, TxnEntry<'g, V>>,
+    guard: &'g Guard,
+}
+
+impl<'g, K, V, S> TxnView<'g, K, V, S>
+where
+    K: 'static + Sync + Send + Clone + Hash + Ord,
+    V: 'static + Sync + Send,
+    S: BuildHasher,
+{
+    fn new(map: &'g HashMap<K, V, S>, guard: &'g Guard) -> Self {
+        TxnView {
+            map,
+            entries: BTreeMap::new(),
+            guard,
+        }
+    }
+
+    /// Returns a reference to the value corresponding to the key.
+    ///
+    /// If the transaction has written the key, this is the written value. Otherwise, it is the
+    /// value the key had in the map when the transaction first read it.
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        let (map, guard) = (self.map, self.guard);
+        let entry = self
+            .entries
+            .entry(key.clone())
+            .or_insert_with(TxnEntry::new);
+        if let Some(ref write) = entry.write {
+            return write.as_ref();
+        }
+        *entry.read.get_or_insert_with(|| map.get(key, guard))
+    }
+
+    /// Returns `true` if the transaction sees a value for the specified key.
+    ///
+    /// This counts as a read of the key, just like [`get`](TxnView::get).
+    pub fn contains_key(&mut self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Maps `key` to `value` once the transaction commits.
+    pub fn insert(&mut self, key: K, value: V) {
+        self.entries.entry(key).or_insert_with(TxnEntry::new).write = Some(Some(value));
+    }
+
+    /// Removes `key` from the map once the transaction commits.
+    pub fn remove(&mut self, key: &K) {
+        self.entries
+            .entry(key.clone())
+            .or_insert_with(TxnEntry::new)
+            .write = Some(None);
+    }
+
+    /// Checks that none of the keys the transaction read have changed since, and if so, applies
+    /// its writes to the map. Returns `false` if a key has changed, in which case the map is left
+    /// unchanged.
+    fn commit(self) -> bool {
+        if self.entries.is_empty() {
+            return true;
+        }
+        let (keys, entries): (Vec<K>, Vec<TxnEntry<'g, V>>) = self.entries.into_iter().unzip();
+        let keys: Vec<&K> = keys.iter().collect();
+
+        let mut valid = true;
+        self.map.compute_locked(
+            &keys,
+            |current| {
+                // a value that the transaction read cannot have been freed, and its memory reused
+                // for a new value, since the transaction holds on to its guard throughout. so if
+                // the key still maps to the very same value, it has not been written since.
+                valid = entries.iter().zip(&current).all(|(entry, current)| {
+                    match (entry.read, *current) {
+                        (None, _) | (Some(None), None) => true,
+                        (Some(Some(read)), Some(current)) => std::ptr::eq(read, current),
+                        _ => false,
+                    }
+                });
+                if valid {
+                    entries.into_iter().map(|entry| entry.write).collect()
+                } else {
+                    current.iter().map(|_| None).collect()
+                }
+            },
+            self.guard,
+        );
+        valid
+    }
+}
+
+impl<K, V, S> Debug for TxnView<'_, K, V, S>
+where
+    K: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TxnView")
+            .field("keys", &self.entries.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl<K, V, S> HashMap<K, V, S>
+where
+    K: 'static + Sync + Send + Clone + Hash + Ord,
+    V: 'static + Sync + Send,
+    S: BuildHasher,
+{
+    /// Runs `f` as a transaction over any number of keys of the map, and returns its result.
+    ///
+    /// `f` reads and writes the map through a [`TxnView`]. Its writes are buffered, and only
+    /// applied to the map once `f` returns. At that point, the bins of all the keys that `f` read
+    /// or wrote are locked, and it is checked that none of the keys that `f` read have been
+    /// written since it read them. If none have, all the writes are applied before the bins are
+    /// unlocked, so that the transaction takes effect at a single point in time with respect to
+    /// other transactions and to [`compute_many`](HashMap::compute_many). If one has, the writes
+    /// are thrown away, and `f` is run again from the start.
+    ///
+    /// `f` may thus run several times when other threads write to the same keys, and any side
+    /// effects it has beyond the view may happen more than once. It may also see an inconsistent
+    /// state of the map in a run that ends up being retried, for example the values of two keys
+    /// from before and after another transaction committed. Only the result of the run that
+    /// commits is returned.
+    ///
+    /// Like with `compute_many`, readers that do not use transactions do not take locks, and may
+    /// see some of the writes of a transaction before others.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flurry::HashMap;
+    ///
+    /// let accounts = HashMap::new();
+    /// let guard = accounts.guard();
+    /// accounts.insert("alice", 100, &guard);
+    /// accounts.insert("bob", 50, &guard);
+    ///
+    /// let moved = accounts.transaction(
+    ///     |txn| {
+    ///         let alice = *txn.get(&"alice").unwrap();
+    ///         let bob = *txn.get(&"bob").unwrap();
+    ///         let amount = alice.min(30);
+    ///         txn.insert("alice", alice - amount);
+    ///         txn.insert("bob", bob + amount);
+    ///         amount
+    ///     },
+    ///     &guard,
+    /// );
+    /// assert_eq!(moved, 30);
+    /// assert_eq!(accounts.get(&"bob", &guard), Some(&80));
+    /// ```
+    pub fn transaction<'g, F, R>(&'g self, mut f: F, guard: &'g Guard) -> R
+    where
+        F: FnMut(&mut TxnView<'g, K, V, S>) -> R,
+    {
+        self.check_guard(guard);
+        loop {
+            let mut view = TxnView::new(self, guard);
+            let result = f(&mut view);
+            if view.commit() {
+                return result;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::HashMap;
+    use std::sync::Arc;
+
+    #[test]
+    fn read_own_writes() {
+        let map = HashMap::<usize, usize>::new();
+        let guard = map.guard();
+        map.insert(1, 1, &guard);
+
+        map.transaction(
+            |txn| {
+                assert_eq!(txn.get(&1), Some(&1));
+                txn.insert(1, 2);
+                assert_eq!(txn.get(&1), Some(&2));
+                txn.remove(&1);
+                assert!(!txn.contains_key(&1));
+                txn.insert(2, 2);
+            },
+            &guard,
+        );
+        assert_eq!(map.get(&1, &guard), None);
+        assert_eq!(map.get(&2, &guard), Some(&2));
+    }
+
+    #[test]
+    fn conflicting_transactions() {
+        const THREADS: usize = 8;
+        const ROUNDS: usize = 1000;
+        const COMMITS: usize = 3;
+
+        let map = Arc::new(HashMap::<usize, usize>::new());
+        {
+            let guard = map.guard();
+            for i in 0..3 {
+                map.insert(i, i, &guard);
+            }
+            map.insert(COMMITS, 0, &guard);
+        }
+
+        let threads: Vec<_> = (0..THREADS)
+            .map(|_| {
+                let map = map.clone();
+                std::thread::spawn(move || {
+                    let guard = map.guard();
+                    for _ in 0..ROUNDS {
+                        map.transaction(
+                            |txn| {
+                                let a = *txn.get(&0).unwrap();
+                                let b = *txn.get(&1).unwrap();
+                                let c = *txn.get(&2).unwrap();
+                                // swap the first two values if they are in order, and otherwise
+                                // rotate all three.
+                                if a < b {
+                                    txn.insert(0, b);
+                                    txn.insert(1, a);
+                                } else {
+                                    txn.insert(0, c);
+                                    txn.insert(1, a);
+                                    txn.insert(2, b);
+                                }
+                                let commits = *txn.get(&COMMITS).unwrap();
+                                txn.insert(COMMITS, commits + 1);
+                            },
+                            &guard,
+                        );
+                    }
+                })
+            })
+            .collect();
+        for t in threads {
+            t.join().unwrap();
+        }
+
+        // if any two transactions had interleaved, a value would have been duplicated or lost,
+        // or a commit would not have been counted.
+        let guard = map.guard();
+        let mut values: Vec<_> = (0..3).map(|i| *map.get(&i, &guard).unwrap()).collect();
+        values.sort_unstable();
+        assert_eq!(values, vec![0, 1, 2]);
+        assert_eq!(map.get(&COMMITS, &guard), Some(&(THREADS * ROUNDS)));
+    }
+}
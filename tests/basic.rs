@@ -29,6 +29,54 @@ fn insert() {
     assert!(old.is_none());
 }
 
+#[test]
+fn insert_and_len() {
+    let map = HashMap::<usize, usize>::new();
+    let guard = map.guard();
+    for i in 0..100 {
+        assert_eq!(map.insert_and_len(i, i, &guard), (None, i + 1));
+    }
+    // replacing a value does not change the length
+    assert_eq!(map.insert_and_len(0, 1, &guard), (Some(&0), 100));
+    assert_eq!(map.len(), 100);
+}
+
+#[test]
+fn insert_prehashed() {
+    use std::hash::{BuildHasher, Hash, Hasher};
+
+    fn hash_one<S: BuildHasher>(build_hasher: &S, key: usize) -> u64 {
+        let mut hasher = build_hasher.build_hasher();
+        key.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    let map = HashMap::<usize, usize>::new();
+    let other = HashMap::<usize, usize>::with_hasher(map.hasher().clone());
+    let guard = map.guard();
+    for i in 0..100 {
+        // hash once, insert into both maps
+        let hash = hash_one(map.hasher(), i);
+        assert_eq!(map.insert_prehashed(hash, i, i, &guard), None);
+        assert_eq!(other.insert_prehashed(hash, i, i * 2, &guard), None);
+    }
+    assert_eq!(
+        map.insert_prehashed(hash_one(map.hasher(), 0), 0, 1, &guard),
+        Some(&0)
+    );
+    assert_eq!(map.len(), 100);
+    for i in 1..100 {
+        assert_eq!(map.get(&i, &guard), Some(&i));
+        assert_eq!(other.get(&i, &guard), Some(&(i * 2)));
+        let hash = hash_one(map.hasher(), i);
+        assert_eq!(map.get_prehashed(hash, &i, &guard), Some(&i));
+    }
+    assert_eq!(
+        map.get_prehashed(hash_one(map.hasher(), 100), &100, &guard),
+        None
+    );
+}
+
 #[test]
 fn get_empty() {
     let map = HashMap::<usize, usize>::new();
@@ -87,6 +135,32 @@ fn insert_and_get() {
     }
 }
 
+#[test]
+fn is_still_present() {
+    let map = Arc::new(HashMap::<usize, usize>::new());
+    let guard = map.guard();
+    map.insert(42, 0, &guard);
+    map.insert(43, 0, &guard);
+
+    let value = map.get(&42, &guard).unwrap();
+    assert!(map.is_still_present(&42, value, &guard));
+    // an equal value under another key is a different instance
+    assert!(!map.is_still_present(&43, value, &guard));
+
+    let remover = {
+        let map = map.clone();
+        std::thread::spawn(move || {
+            map.remove(&42, &map.guard());
+        })
+    };
+    remover.join().unwrap();
+    assert!(!map.is_still_present(&42, value, &guard));
+
+    // a new value that is equal to the old one is still not the same instance
+    map.insert(42, 0, &guard);
+    assert!(!map.is_still_present(&42, value, &guard));
+}
+
 #[test]
 fn insert_and_get_key_value() {
     let map = HashMap::<usize, usize>::new();
@@ -102,6 +176,60 @@ fn insert_and_get_key_value() {
 mod hasher;
 use hasher::ZeroHashBuilder;
 
+#[test]
+#[cfg_attr(miri, ignore)]
+fn lock_bin_for() {
+    const ROUNDS: usize = 1000;
+
+    // every key shares the one bin, and the values of the two keys always add up to 100.
+    let map = Arc::new(HashMap::<usize, usize, _>::with_hasher(ZeroHashBuilder));
+    {
+        let guard = map.guard();
+        map.insert(0, 50, &guard);
+        map.insert(1, 50, &guard);
+    }
+
+    let racer = {
+        let map = map.clone();
+        std::thread::spawn(move || {
+            let guard = map.guard();
+            for _ in 0..ROUNDS {
+                map.compute_many(
+                    [&0, &1],
+                    |[a, b]| {
+                        let (a, b) = (*a.unwrap(), *b.unwrap());
+                        assert_eq!(a + b, 100);
+                        let a = (a + 1) % 100;
+                        [Some(a), Some(100 - a)]
+                    },
+                    &guard,
+                );
+            }
+        })
+    };
+
+    for _ in 0..ROUNDS {
+        let guard = map.guard();
+        let mut bin = map.lock_bin_for(&0, &guard);
+        let a = *bin.get(&0).unwrap();
+        bin.set(0, a / 2);
+        // the other key has not changed since we locked the bin
+        let b = *bin.get(&1).unwrap();
+        assert_eq!(a + b, 100);
+        bin.set(1, b + (a - a / 2));
+        assert!(bin.contains_key(&0));
+        drop(bin);
+    }
+    racer.join().unwrap();
+
+    let guard = map.guard();
+    assert_eq!(
+        map.get(&0, &guard).unwrap() + map.get(&1, &guard).unwrap(),
+        100
+    );
+    assert_eq!(map.len(), 2);
+}
+
 #[test]
 fn one_bucket() {
     let map = HashMap::<&'static str, usize, _>::with_hasher(ZeroHashBuilder);
@@ -148,6 +276,150 @@ fn one_bucket() {
     assert_eq!(v, Some(&1));
 }
 
+#[test]
+fn probe_length_one_bucket() {
+    let map = HashMap::<usize, usize, _>::with_hasher(ZeroHashBuilder);
+    let guard = map.guard();
+    for i in 0..5 {
+        map.insert(i, i, &guard);
+    }
+    assert_eq!(map.probe_length(&0, &guard), Some(1));
+    assert_eq!(map.probe_length(&4, &guard), Some(5));
+    assert_eq!(map.probe_length(&5, &guard), None);
+
+    map.remove(&0, &guard);
+    assert_eq!(map.probe_length(&4, &guard), Some(4));
+}
+
+#[test]
+fn get_traced_during_resize() {
+    use std::sync::mpsc;
+
+    let map = Arc::new(HashMap::<usize, usize>::new());
+    let guard = map.guard();
+    map.insert(0, 0, &guard);
+    let capacity = map.capacity(&guard);
+    let locked = (0usize..).find(|k| map.bin_index(k, &guard) == 0).unwrap();
+    let moved = (0usize..).find(|k| map.bin_index(k, &guard) != 0).unwrap();
+    map.insert(locked, locked, &guard);
+    map.insert(moved, moved, &guard);
+    assert_eq!(
+        map.get_traced(&moved, &guard),
+        (
+            Some(&moved),
+            GetTrace {
+                bins_traversed: 1,
+                followed_forward: false
+            }
+        )
+    );
+
+    // hold the lock of bin 0, which a resize moves last, so that the resize stalls there.
+    let (locked_tx, locked_rx) = mpsc::channel();
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    let locker = {
+        let map = map.clone();
+        std::thread::spawn(move || {
+            let guard = map.guard();
+            map.reduce_bin(
+                &locked,
+                true,
+                |first, _, _| {
+                    if first {
+                        locked_tx.send(()).unwrap();
+                        release_rx.recv().unwrap();
+                    }
+                    false
+                },
+                &guard,
+            );
+        })
+    };
+    locked_rx.recv().unwrap();
+    let grower = {
+        let map = map.clone();
+        std::thread::spawn(move || {
+            let guard = map.guard();
+            let keys: Vec<usize> = (1000..)
+                .filter(|k| map.bin_index(k, &guard) != 0)
+                .take(capacity)
+                .collect();
+            for k in keys {
+                map.insert(k, k, &guard);
+            }
+        })
+    };
+
+    // wait for the resize to move the bin of `moved`.
+    let trace = loop {
+        let (value, trace) = map.get_traced(&moved, &guard);
+        assert_eq!(value, Some(&moved));
+        if trace.followed_forward {
+            break trace;
+        }
+        std::thread::yield_now();
+    };
+    assert_eq!(trace.bins_traversed, 2);
+    // bin 0 has not been moved yet.
+    let (value, trace) = map.get_traced(&locked, &guard);
+    assert_eq!(value, Some(&locked));
+    assert!(!trace.followed_forward);
+    assert_eq!(trace.bins_traversed, 1);
+
+    release_tx.send(()).unwrap();
+    locker.join().unwrap();
+    grower.join().unwrap();
+    assert!(map.capacity(&guard) > capacity);
+    let (_, trace) = map.get_traced(&moved, &guard);
+    assert!(!trace.followed_forward);
+}
+
+#[test]
+fn prefetch_all() {
+    let map = HashMap::<String, ()>::new();
+    let guard = map.guard();
+    map.prefetch_all(&guard);
+    for i in 0..1000 {
+        map.insert(i.to_string(), (), &guard);
+    }
+    map.prefetch_all(&guard);
+    assert_eq!(map.len(), 1000);
+}
+
+#[test]
+fn compact_after_removals() {
+    let map = HashMap::<usize, usize, _>::with_hasher(ZeroHashBuilder);
+    let guard = map.guard();
+    for i in 0..6 {
+        map.insert(i, i, &guard);
+    }
+    for i in 0..4 {
+        map.remove(&i, &guard);
+    }
+    // removals unlink their entries right away, so there is nothing left for compact to shorten.
+    let before = map.probe_length(&5, &guard).unwrap();
+    assert_eq!(before, 2);
+    assert_eq!(map.compact(&guard), Ok(()));
+    assert_eq!(map.probe_length(&5, &guard), Some(before));
+    assert_eq!(map.get(&4, &guard), Some(&4));
+}
+
+#[test]
+fn get_cloned_arc() {
+    let map = HashMap::<String, Arc<Vec<u8>>>::new();
+    map.pin()
+        .insert("blob".to_string(), Arc::new(vec![1, 2, 3]));
+
+    let blob = {
+        let guard = map.guard();
+        map.get_cloned("blob", &guard).unwrap()
+    };
+    // the guard is gone, and so is the entry, but the Arc keeps the data alive
+    map.pin().remove("blob");
+    assert_eq!(*blob, vec![1, 2, 3]);
+    assert!(map.pin().get_cloned("blob").is_none());
+}
+
 #[test]
 fn update() {
     let map = HashMap::<usize, usize>::new();
@@ -205,6 +477,506 @@ fn compute_if_present_remove() {
     }
 }
 
+#[test]
+fn compute_full() {
+    let map = HashMap::<usize, usize>::new();
+    let guard = map.guard();
+    let increment = |e: Option<(&usize, &usize)>| Some(e.map_or(0, |(_, v)| v + 1));
+
+    assert_eq!(map.compute_full(&42, |_| None, &guard), Compute::NoOp);
+    assert!(map.is_empty());
+    assert_eq!(
+        map.compute_full(&42, increment, &guard),
+        Compute::Inserted(&0)
+    );
+    assert_eq!(
+        map.compute_full(&42, increment, &guard),
+        Compute::Updated { old: &0, new: &1 }
+    );
+    assert_eq!(
+        map.compute_full(
+            &42,
+            |e| {
+                assert_eq!(e, Some((&42, &1)));
+                None
+            },
+            &guard
+        ),
+        Compute::Removed(&1)
+    );
+    assert!(map.get(&42, &guard).is_none());
+    assert!(map.is_empty());
+}
+
+#[test]
+fn compute_full_one_bucket() {
+    let map = HashMap::<usize, usize, _>::with_hasher(ZeroHashBuilder);
+    let guard = map.guard();
+
+    // enough colliding keys for the bucket to be turned into a tree along the way
+    for i in 0..32 {
+        assert_eq!(
+            map.compute_full(&i, |e| Some(e.map_or(i, |(_, v)| v + 1)), &guard),
+            Compute::Inserted(&i)
+        );
+    }
+    for i in 0..32 {
+        assert_eq!(
+            map.compute_full(&i, |e| Some(e.unwrap().1 + 1), &guard),
+            Compute::Updated {
+                old: &i,
+                new: &(i + 1)
+            }
+        );
+    }
+    for i in 0..32 {
+        assert_eq!(
+            map.compute_full(&i, |_| None, &guard),
+            Compute::Removed(&(i + 1))
+        );
+        assert_eq!(map.compute_full(&i, |_| None, &guard), Compute::NoOp);
+    }
+    assert!(map.is_empty());
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn concurrent_compute_full() {
+    let map = Arc::new(HashMap::<usize, usize>::new());
+
+    let threads: Vec<_> = (0..4)
+        .map(|_| {
+            let map = map.clone();
+            std::thread::spawn(move || {
+                let guard = map.guard();
+                let mut inserted = 0;
+                for i in 0..64 {
+                    let outcome =
+                        map.compute_full(&i, |e| Some(e.map_or(1, |(_, v)| v + 1)), &guard);
+                    if let Compute::Inserted(_) = outcome {
+                        inserted += 1;
+                    }
+                }
+                inserted
+            })
+        })
+        .collect();
+    let inserted: usize = threads.into_iter().map(|t| t.join().unwrap()).sum();
+
+    // every key was inserted exactly once, and every update was applied
+    assert_eq!(inserted, 64);
+    let guard = map.guard();
+    for i in 0..64 {
+        assert_eq!(map.get(&i, &guard), Some(&4));
+    }
+}
+
+#[test]
+fn apply_batch() {
+    let map = HashMap::<usize, usize>::new();
+    let guard = map.guard();
+    map.insert(3, 30, &guard);
+
+    let outcomes = map.apply_batch(
+        vec![
+            MapOp::Insert(1, 10),
+            MapOp::Insert(2, 20),
+            MapOp::Insert(1, 11),
+            MapOp::Update(2, Box::new(|_, v| Some(v + 1))),
+            MapOp::Update(4, Box::new(|_, v| Some(v + 1))),
+            MapOp::Update(3, Box::new(|_, _| None)),
+            MapOp::Remove(2),
+            MapOp::Remove(5),
+        ],
+        &guard,
+    );
+    assert_eq!(
+        outcomes,
+        vec![
+            Compute::Inserted(&10),
+            Compute::Inserted(&20),
+            Compute::Updated { old: &10, new: &11 },
+            Compute::Updated { old: &20, new: &21 },
+            Compute::NoOp,
+            Compute::Removed(&30),
+            Compute::Removed(&21),
+            Compute::NoOp,
+        ]
+    );
+    assert_eq!(map.len(), 1);
+    assert_eq!(map.get(&1, &guard), Some(&11));
+}
+
+#[test]
+fn compute_many() {
+    let map = HashMap::<usize, usize>::new();
+    let guard = map.guard();
+    map.insert(1, 10, &guard);
+    map.insert(2, 20, &guard);
+
+    let results = map.compute_many(
+        [&1, &2, &3],
+        |values| {
+            assert_eq!(values, [Some(&10), Some(&20), None]);
+            [None, Some(21), Some(30)]
+        },
+        &guard,
+    );
+    assert_eq!(results, [None, Some(&21), Some(&30)]);
+    assert_eq!(map.len(), 2);
+    assert_eq!(map.get(&1, &guard), None);
+    assert_eq!(map.get(&2, &guard), Some(&21));
+    assert_eq!(map.get(&3, &guard), Some(&30));
+}
+
+#[test]
+fn compute_many_one_bucket() {
+    let map = HashMap::<usize, usize, _>::with_hasher(ZeroHashBuilder);
+    let guard = map.guard();
+
+    // fill the empty bin with several entries at once
+    let results = map.compute_many(
+        [&0, &1, &2, &3],
+        |_| [Some(0), Some(1), None, Some(3)],
+        &guard,
+    );
+    assert_eq!(results, [Some(&0), Some(&1), None, Some(&3)]);
+    assert_eq!(map.len(), 3);
+
+    // remove the head along with other entries of the same bin, and append one
+    let results = map.compute_many(
+        [&3, &0, &4, &1],
+        |values| {
+            assert_eq!(values, [Some(&3), Some(&0), None, Some(&1)]);
+            [None, None, Some(4), Some(10)]
+        },
+        &guard,
+    );
+    assert_eq!(results, [None, None, Some(&4), Some(&10)]);
+    assert_eq!(map.len(), 2);
+    let mut entries: Vec<_> = map.iter(&guard).map(|(&k, &v)| (k, v)).collect();
+    entries.sort_unstable();
+    assert_eq!(entries, vec![(1, 10), (4, 4)]);
+}
+
+#[test]
+#[should_panic(expected = "same key more than once")]
+fn compute_many_duplicate_keys() {
+    let map = HashMap::<usize, usize>::new();
+    map.compute_many([&1, &2, &1], |_| [None, None, None], &map.guard());
+}
+
+#[test]
+fn compute_many_duplicate_keys_untouched() {
+    use std::panic::{self, AssertUnwindSafe};
+
+    let map = HashMap::<usize, usize>::new();
+    let guard = map.guard();
+    map.insert(1, 10, &guard);
+
+    // the keys are checked before anything is locked or computed
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        map.compute_many([&1, &1], |_| unreachable!("f must not be called"), &guard)
+    }));
+    assert!(result.is_err());
+    assert_eq!(map.get(&1, &guard), Some(&10));
+    assert_eq!(map.len(), 1);
+    // and the bin was not left locked
+    map.insert(1, 11, &guard);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn concurrent_compute_many_transfer() {
+    const ACCOUNTS: usize = 8;
+    let map = Arc::new(HashMap::<usize, i64>::new());
+    {
+        let guard = map.guard();
+        for i in 0..ACCOUNTS {
+            map.insert(i, 1000, &guard);
+        }
+    }
+
+    let threads: Vec<_> = (0..4)
+        .map(|t| {
+            let map = map.clone();
+            std::thread::spawn(move || {
+                let guard = map.guard();
+                for i in 0..1000 {
+                    let from = (t + i) % ACCOUNTS;
+                    let to = (t * 3 + i * 5 + 1) % ACCOUNTS;
+                    if from == to {
+                        continue;
+                    }
+                    map.compute_many(
+                        [&from, &to],
+                        |[from, to]| [Some(from.unwrap() - 7), Some(to.unwrap() + 7)],
+                        &guard,
+                    );
+                }
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    let guard = map.guard();
+    let total: i64 = map.values(&guard).sum();
+    assert_eq!(total, 1000 * ACCOUNTS as i64);
+}
+
+#[test]
+fn replace_all() {
+    let map = HashMap::<usize, usize>::new();
+    let guard = map.guard();
+    for i in 0..10 {
+        map.insert(i, i, &guard);
+    }
+
+    map.replace_all(vec![(20, 1), (21, 2), (20, 3)], &guard);
+    assert_eq!(map.len(), 2);
+    assert_eq!(map.get(&0, &guard), None);
+    assert_eq!(map.get(&20, &guard), Some(&3));
+    assert_eq!(map.get(&21, &guard), Some(&2));
+
+    // the map keeps working as usual after the swap
+    for i in 0..100 {
+        map.insert(i, i, &guard);
+    }
+    assert_eq!(map.len(), 100);
+    map.replace_all(std::iter::empty(), &guard);
+    assert!(map.is_empty());
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn concurrent_replace_all() {
+    let map = Arc::new(HashMap::<usize, usize>::new());
+    map.pin().replace_all((0..100).map(|i| (i, 0)));
+
+    let done = Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let reader = {
+        let map = map.clone();
+        let done = done.clone();
+        std::thread::spawn(move || {
+            while !done.load(std::sync::atomic::Ordering::SeqCst) {
+                let guard = map.guard();
+                let mut seen = std::collections::HashSet::new();
+                for (&k, &v) in map.iter(&guard) {
+                    // old bins are forwarded one at a time, so generations may mix, but every
+                    // entry comes from its own generation's range of keys, and shows up only once
+                    assert_eq!(k / 100, v % 2);
+                    assert!(seen.insert(k));
+                }
+            }
+        })
+    };
+
+    for round in 1..=50 {
+        let base = (round % 2) * 100;
+        map.pin()
+            .replace_all((base..base + 100).map(|i| (i, round)));
+    }
+    done.store(true, std::sync::atomic::Ordering::SeqCst);
+    reader.join().unwrap();
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn replace_all_racing_writes() {
+    const THREADS: usize = 4;
+
+    let map = Arc::new(HashMap::<usize, usize>::new());
+    let writers: Vec<_> = (0..THREADS)
+        .map(|t| {
+            let map = map.clone();
+            std::thread::spawn(move || {
+                let guard = map.guard();
+                for i in 0..2000 {
+                    let key = t * 10_000 + i;
+                    map.insert(key, i, &guard);
+                    if i % 3 == 0 {
+                        map.remove(&key, &guard);
+                    }
+                }
+            })
+        })
+        .collect();
+
+    for round in 0..20 {
+        map.pin().replace_all((0..round * 10).map(|i| (i, round)));
+    }
+    for writer in writers {
+        writer.join().unwrap();
+    }
+
+    // none of the racing writes were counted twice, or went missing from the count
+    let guard = map.guard();
+    assert_eq!(map.len(), map.iter(&guard).count());
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn concurrent_refill() {
+    let map = Arc::new(HashMap::<usize, usize>::new());
+    map.pin().refill((0..1000).map(|i| (i, 1000)));
+    let capacity = map.pin().capacity();
+
+    let done = Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let reader = {
+        let map = map.clone();
+        let done = done.clone();
+        std::thread::spawn(move || {
+            while !done.load(std::sync::atomic::Ordering::SeqCst) {
+                let guard = map.guard();
+                let mut seen = std::collections::HashSet::new();
+                for (&k, &n) in map.iter(&guard) {
+                    // every generation holds the keys below the number it maps them to
+                    assert!(k < n);
+                    assert!(seen.insert(k));
+                }
+            }
+        })
+    };
+
+    for round in 1..=50 {
+        let n = if round % 2 == 0 { 1000 } else { 10 };
+        map.pin().refill((0..n).map(|i| (i, n)));
+        // the table never shrinks, so it never has to grow again either
+        assert_eq!(map.pin().capacity(), capacity);
+    }
+    done.store(true, std::sync::atomic::Ordering::SeqCst);
+    reader.join().unwrap();
+    assert_eq!(map.len(), 1000);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn get_or_try_insert_with() {
+    const THREADS: usize = 4;
+    const KEYS: usize = 100;
+
+    let map = Arc::new(HashMap::<usize, usize>::new());
+    let made = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let threads: Vec<_> = (0..THREADS)
+        .map(|t| {
+            let map = map.clone();
+            let made = made.clone();
+            std::thread::spawn(move || {
+                let guard = map.guard();
+                for key in 0..KEYS {
+                    // odd keys can never be created, and even ones only by some threads
+                    let result = map.get_or_try_insert_with(
+                        key,
+                        || {
+                            if key % 2 == 1 || t % 2 == 1 {
+                                Err(t)
+                            } else {
+                                made.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                                Ok(key * 10)
+                            }
+                        },
+                        &guard,
+                    );
+                    match result {
+                        Ok(&value) => assert_eq!(value, key * 10),
+                        Err(e) => assert_eq!(e, t),
+                    }
+                }
+            })
+        })
+        .collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+
+    // every even key was made exactly once, and no odd key was inserted
+    let guard = map.guard();
+    assert_eq!(made.load(std::sync::atomic::Ordering::SeqCst), KEYS / 2);
+    assert_eq!(map.len(), KEYS / 2);
+    for key in 0..KEYS {
+        let expected = if key % 2 == 0 { Some(key * 10) } else { None };
+        assert_eq!(map.get(&key, &guard).copied(), expected);
+    }
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn concurrent_increment_capped() {
+    const THREADS: usize = 4;
+    const CAP: u64 = 1000;
+
+    let map = Arc::new(HashMap::<usize, u64>::new());
+    let threads: Vec<_> = (0..THREADS)
+        .map(|t| {
+            let map = map.clone();
+            std::thread::spawn(move || {
+                let guard = map.guard();
+                let delta = t as u64 + 1;
+                let mut added = 0;
+                for _ in 0..CAP {
+                    match map.increment_capped(0, delta, CAP, &guard) {
+                        Ok(new) => {
+                            assert!(new <= CAP);
+                            added += delta;
+                        }
+                        Err(current) => assert!(current + delta > CAP),
+                    }
+                }
+                added
+            })
+        })
+        .collect();
+    let added: u64 = threads.into_iter().map(|t| t.join().unwrap()).sum();
+
+    // every increment that succeeded is in the counter, and it is as close to the cap as the
+    // smallest increment allows
+    let value = *map.pin().get(&0).unwrap();
+    assert_eq!(value, added);
+    assert_eq!(value, CAP);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn concurrent_intern() {
+    const THREADS: usize = 4;
+    const KEYS: usize = 500;
+
+    let map = Arc::new(HashMap::<String, u32>::new());
+    let threads: Vec<_> = (0..THREADS)
+        .map(|t| {
+            let map = map.clone();
+            std::thread::spawn(move || {
+                let guard = map.guard();
+                // each thread interns an overlapping range of keys, starting at a different one
+                (0..KEYS)
+                    .map(|i| {
+                        let key = ((i + t * KEYS / THREADS) % KEYS).to_string();
+                        (key.clone(), map.intern(key, &guard))
+                    })
+                    .collect::<Vec<_>>()
+            })
+        })
+        .collect();
+    let interned: Vec<_> = threads
+        .into_iter()
+        .flat_map(|t| t.join().unwrap())
+        .collect();
+
+    // every thread got the same id for a key as the one that ended up in the map
+    let guard = map.guard();
+    assert_eq!(map.len(), KEYS);
+    for (key, id) in interned {
+        assert_eq!(map.get(&key, &guard), Some(&id));
+    }
+    // and the ids are exactly the first KEYS ones
+    let mut ids: Vec<_> = map.values(&guard).copied().collect();
+    ids.sort_unstable();
+    assert_eq!(ids, (0..KEYS as u32).collect::<Vec<_>>());
+    assert_eq!(map.intern(String::from("new"), &guard), KEYS as u32);
+}
+
 #[test]
 #[cfg_attr(miri, ignore)]
 fn concurrent_insert() {
@@ -362,6 +1134,47 @@ fn concurrent_resize_and_get() {
     }
 }
 
+#[test]
+#[cfg_attr(miri, ignore)]
+fn concurrent_clear_and_iter() {
+    const KEYS: usize = 256;
+    const ROUNDS: usize = 256;
+
+    let map = Arc::new(HashMap::<usize, String>::new());
+    let done = Arc::new(std::sync::atomic::AtomicBool::new(false));
+
+    let map1 = map.clone();
+    let done1 = done.clone();
+    // t1 repeatedly clears and refills the map, so that the entries t2 sees are freed
+    let t1 = std::thread::spawn(move || {
+        for _ in 0..ROUNDS {
+            let guard = map1.guard();
+            map1.clear(&guard);
+            for i in 0..KEYS {
+                map1.insert(i, i.to_string(), &guard);
+            }
+        }
+        done1.store(true, std::sync::atomic::Ordering::SeqCst);
+    });
+    let map2 = map.clone();
+    // t2 iterates over the map with a fresh guard every pass, so garbage keeps being collected
+    let t2 = std::thread::spawn(move || {
+        while !done.load(std::sync::atomic::Ordering::SeqCst) {
+            let guard = map2.guard();
+            let mut seen = std::collections::HashSet::new();
+            for (key, value) in map2.iter(&guard) {
+                assert!(seen.insert(*key), "key {} was yielded twice", key);
+                assert_eq!(value, &key.to_string());
+            }
+            assert!(seen.len() <= KEYS);
+        }
+    });
+
+    t1.join().unwrap();
+    t2.join().unwrap();
+    assert_eq!(map.pin().len(), KEYS);
+}
+
 #[test]
 fn current_kv_dropped() {
     let dropped1 = Arc::new(0);
@@ -417,6 +1230,27 @@ fn same_values_equal() {
     assert_eq!(map2, map1);
 }
 
+#[test]
+fn equal_to_std_map() {
+    let map = HashMap::<usize, usize>::new();
+    let mut expected = std::collections::HashMap::new();
+    {
+        let guard = map.guard();
+        for i in 0..10 {
+            map.insert(i, i * 2, &guard);
+            expected.insert(i, i * 2);
+        }
+    }
+    assert_eq!(map, expected);
+
+    expected.insert(0, 1);
+    assert_ne!(map, expected);
+
+    expected.insert(0, 0);
+    expected.insert(10, 20);
+    assert_ne!(map, expected);
+}
+
 #[test]
 fn different_values_not_equal() {
     let map1 = HashMap::<usize, usize>::new();
@@ -538,6 +1372,24 @@ fn extend_ref() {
     assert_eq!(entries, collected);
 }
 
+#[test]
+fn extend_from_other_map() {
+    let source: HashMap<usize, usize> = (0..100).map(|i| (i, i * 2)).collect();
+    let map: HashMap<usize, usize> = HashMap::new();
+    map.insert(0, 1000, &map.guard());
+
+    let source_guard = source.guard();
+    (&map).extend(source.iter(&source_guard));
+
+    let guard = map.guard();
+    assert_eq!(map.len(), 100);
+    for i in 0..100 {
+        assert_eq!(map.get(&i, &guard), Some(&(i * 2)));
+    }
+    // the source is left untouched
+    assert_eq!(source.len(), 100);
+}
+
 #[test]
 fn from_iter_ref() {
     use std::iter::FromIterator;
@@ -564,6 +1416,29 @@ fn from_iter_empty() {
     assert_eq!(map.len(), 0)
 }
 
+#[test]
+fn from_slices() {
+    let keys: Vec<usize> = (0..100).collect();
+    let values: Vec<String> = keys.iter().map(|k| k.to_string()).collect();
+    let map = HashMap::from_slices(&keys, &values);
+
+    let guard = map.guard();
+    assert_eq!(map.len(), 100);
+    for k in keys {
+        assert_eq!(map.get(&k, &guard), Some(&k.to_string()));
+    }
+
+    // the last value of a repeated key wins
+    let map = HashMap::from_slices(&[1, 1], &[1, 2]);
+    assert_eq!(map.pin().get(&1), Some(&2));
+}
+
+#[test]
+#[should_panic]
+fn from_slices_length_mismatch() {
+    let _ = HashMap::from_slices(&[1, 2, 3], &[1, 2]);
+}
+
 #[test]
 fn retain_empty() {
     let map = HashMap::<&'static str, u32>::new();
@@ -616,3 +1491,1272 @@ fn retain_force_some() {
     assert_eq!(map.len(), 5);
     assert_eq!(map, expected_map);
 }
+
+#[test]
+fn insert_bounded() {
+    let map = HashMap::<usize, usize>::new();
+    let guard = map.guard();
+    for i in 0..4 {
+        assert_eq!(map.insert_bounded(i, i, 4, &guard), Ok(None));
+    }
+    assert_eq!(map.insert_bounded(4, 4, 4, &guard), Err((4, 4)));
+    assert_eq!(map.insert_bounded(0, 1, 4, &guard), Ok(Some(&0)));
+    assert_eq!(map.len(), 4);
+    assert_eq!(map.get(&4, &guard), None);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn concurrent_insert_bounded() {
+    const THREADS: usize = 4;
+    const MAX_LEN: usize = 100;
+    let map = Arc::new(HashMap::<usize, usize>::new());
+
+    let threads: Vec<_> = (0..THREADS)
+        .map(|t| {
+            let map = map.clone();
+            std::thread::spawn(move || {
+                let guard = map.guard();
+                for i in 0..1000 {
+                    let _ = map.insert_bounded(t * 1000 + i, i, MAX_LEN, &guard);
+                }
+            })
+        })
+        .collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+
+    assert!(map.len() >= MAX_LEN);
+    assert!(map.len() <= MAX_LEN + THREADS);
+}
+
+#[test]
+fn eq_by_ignores_field() {
+    // (value, timestamp)
+    let map1 = HashMap::<usize, (usize, u64)>::new();
+    let map2 = HashMap::<usize, (usize, u64)>::new();
+    {
+        let guard1 = map1.guard();
+        let guard2 = map2.guard();
+        for i in 0..10 {
+            map1.insert(i, (i * 2, 1), &guard1);
+            map2.insert(i, (i * 2, 2), &guard2);
+        }
+    }
+
+    assert_ne!(map1, map2);
+    assert!(map1.eq_by(&map2, |a, b| a.0 == b.0, &map1.guard(), &map2.guard()));
+    assert!(map1.pin().eq_by(&map2.pin(), |a, b| a.0 == b.0));
+
+    map2.pin().insert(3, (0, 2));
+    assert!(!map1.eq_by(&map2, |a, b| a.0 == b.0, &map1.guard(), &map2.guard()));
+    map2.pin().insert(3, (6, 2));
+    map2.pin().insert(10, (20, 2));
+    assert!(!map1.eq_by(&map2, |a, b| a.0 == b.0, &map1.guard(), &map2.guard()));
+}
+
+#[test]
+fn get_pinned() {
+    let map = HashMap::<usize, String>::new();
+    let pinned = map.pin();
+    pinned.insert(1, String::from("one"));
+
+    let value = map.get_pinned(&1).unwrap();
+    drop(pinned);
+
+    // removing the value must not invalidate the handle
+    map.pin().remove(&1);
+    assert_eq!(*value, "one");
+    assert_eq!(value.len(), 3);
+    drop(value);
+
+    assert!(map.get_pinned(&1).is_none());
+}
+
+#[test]
+fn group_by() {
+    let map = HashMap::<usize, usize>::new();
+    let guard = map.guard();
+    for i in 0..10 {
+        map.insert(i, i, &guard);
+    }
+
+    let groups = map.group_by(|_, v| v % 3, &guard);
+    assert_eq!(groups.len(), 3);
+    assert_eq!(groups[&0].len(), 4);
+    assert_eq!(groups[&1].len(), 3);
+    assert_eq!(groups[&2].len(), 3);
+
+    let mut keys: Vec<_> = groups[&1].iter().map(|(&k, _)| k).collect();
+    keys.sort();
+    assert_eq!(keys, vec![1, 4, 7]);
+    for (group, entries) in &groups {
+        for (_, &v) in entries {
+            assert_eq!(v % 3, *group);
+        }
+    }
+}
+
+#[test]
+fn reserve_exact() {
+    for &n in &[1, 5, 12, 13, 100, 768, 1000] {
+        let exact = HashMap::<usize, usize>::new();
+        let guard = exact.guard();
+        exact.reserve_exact(n, &guard);
+
+        let reserved = HashMap::<usize, usize>::new();
+        reserved.reserve(n, &reserved.guard());
+
+        let capacity = exact.capacity(&guard);
+        assert!(capacity <= reserved.capacity(&reserved.guard()));
+        assert!(capacity.is_power_of_two());
+        // it holds n entries below the ¾ load factor, but half the capacity would not.
+        assert!(capacity - capacity / 4 > n);
+        assert!(capacity == 1 || capacity / 2 - capacity / 8 <= n);
+
+        // filling the map up to the reserved size does not resize it.
+        for i in 0..n {
+            exact.insert(i, i, &guard);
+        }
+        assert_eq!(exact.capacity(&guard), capacity);
+    }
+}
+
+#[test]
+fn reserve_exact_initialized() {
+    let map = HashMap::<usize, usize>::new();
+    let guard = map.guard();
+    map.insert(0, 0, &guard);
+    assert_eq!(map.capacity(&guard), 16);
+
+    map.reserve_exact(94, &guard);
+    assert_eq!(map.capacity(&guard), 128);
+
+    // reserving less than what fits already is a no-op
+    map.reserve_exact(10, &guard);
+    assert_eq!(map.capacity(&guard), 128);
+}
+
+#[test]
+fn reserve_for_rate() {
+    let map = HashMap::<usize, usize>::new();
+    let guard = map.guard();
+    for i in 0..100 {
+        map.insert(i, i, &guard);
+    }
+
+    // 50 entries per interval for 20 intervals
+    map.reserve_for_rate(50, 20, &guard);
+    let capacity = map.capacity(&guard);
+    assert!(capacity - capacity / 4 > 1100);
+
+    // ingesting the projected entries does not resize the map any further
+    for i in 100..1100 {
+        map.insert(i, i, &guard);
+    }
+    assert_eq!(map.capacity(&guard), capacity);
+
+    // reserving for growth that already fits is a no-op
+    map.reserve_for_rate(1, 10, &guard);
+    assert_eq!(map.capacity(&guard), capacity);
+}
+
+#[test]
+fn pop_min_by() {
+    let map = HashMap::<usize, usize>::new();
+    let guard = map.guard();
+    // insert the values out of order
+    for i in 0..20 {
+        map.insert(i, (i * 7) % 20, &guard);
+    }
+
+    let mut popped = Vec::new();
+    while let Some((key, value)) = map.pop_min_by(Ord::cmp, &guard) {
+        assert_eq!((key * 7) % 20, value);
+        popped.push(value);
+    }
+    assert_eq!(popped, (0..20).collect::<Vec<_>>());
+    assert!(map.is_empty());
+
+    // the comparator decides what the minimum is
+    for i in 0..5 {
+        map.insert(i, i, &guard);
+    }
+    assert_eq!(map.pop_min_by(|a, b| b.cmp(a), &guard), Some((4, 4)));
+}
+
+#[test]
+fn key_set() {
+    let map = HashMap::<usize, usize>::new();
+    let guard = map.guard();
+    assert!(map.key_set(&guard).is_empty());
+    for i in 0..100 {
+        map.insert(i, i * 2, &guard);
+    }
+
+    let keys = map.key_set(&guard);
+    assert_eq!(keys.len(), map.len());
+    let keys_guard = keys.guard();
+    for i in 0..100 {
+        assert!(keys.contains(&i, &keys_guard));
+    }
+    assert!(!keys.contains(&100, &keys_guard));
+
+    // the set is independent of the map
+    map.remove(&0, &guard);
+    assert!(keys.contains(&0, &keys_guard));
+}
+
+#[test]
+fn invert() {
+    let map = HashMap::<usize, usize>::new();
+    let guard = map.guard();
+    assert!(map.invert(&guard).is_empty());
+    for i in 0..100 {
+        map.insert(i, i % 3, &guard);
+    }
+
+    let inverted = map.invert(&guard);
+    assert_eq!(inverted.len(), 3);
+    let inverted_guard = inverted.guard();
+    for r in 0..3 {
+        let keys = inverted.get(&r, &inverted_guard).unwrap();
+        let mut keys: Vec<_> = keys.iter(&inverted_guard).copied().collect();
+        keys.sort_unstable();
+        assert_eq!(keys, (r..100).step_by(3).collect::<Vec<_>>());
+    }
+}
+
+#[test]
+fn take_all() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    let map = Arc::new(HashMap::<usize, usize>::new());
+    {
+        let guard = map.guard();
+        for i in 0..1000 {
+            map.insert(i, i * 2, &guard);
+        }
+    }
+
+    let done = Arc::new(AtomicBool::new(false));
+    let reader = {
+        let map = map.clone();
+        let done = done.clone();
+        std::thread::spawn(move || {
+            while !done.load(Ordering::SeqCst) {
+                let guard = map.guard();
+                for i in 0..1000 {
+                    if let Some(v) = map.get(&i, &guard) {
+                        assert_eq!(*v, i * 2);
+                    }
+                }
+            }
+        })
+    };
+
+    let taken = map.take_all(&map.guard());
+    done.store(true, Ordering::SeqCst);
+    reader.join().unwrap();
+
+    assert_eq!(taken.len(), 1000);
+    for i in 0..1000 {
+        assert_eq!(taken[&i], i * 2);
+    }
+    let guard = map.guard();
+    assert!(map.is_empty());
+    assert_eq!(map.iter(&guard).count(), 0);
+
+    // the map is still usable afterwards
+    map.insert(1, 1, &guard);
+    assert_eq!(map.get(&1, &guard), Some(&1));
+    assert_eq!(map.len(), 1);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn concurrent_take_all() {
+    const WRITERS: usize = 4;
+    const KEYS: usize = 2000;
+
+    let map = Arc::new(HashMap::<usize, usize>::new());
+    let writers: Vec<_> = (0..WRITERS)
+        .map(|t| {
+            let map = map.clone();
+            std::thread::spawn(move || {
+                let guard = map.guard();
+                for i in (t..KEYS).step_by(WRITERS) {
+                    map.insert(i, i, &guard);
+                }
+            })
+        })
+        .collect();
+
+    let mut taken = Vec::new();
+    for _ in 0..10 {
+        taken.extend(map.take_all(&map.guard()));
+    }
+    for writer in writers {
+        writer.join().unwrap();
+    }
+    taken.extend(map.take_all(&map.guard()));
+
+    // every write is taken exactly once
+    taken.sort_unstable();
+    assert_eq!(
+        taken,
+        (0..KEYS).map(|i| (i, i)).collect::<Vec<_>>(),
+        "writes were lost or taken twice"
+    );
+    assert!(map.is_empty());
+}
+
+#[test]
+fn repin_across_resizes() {
+    let map = HashMap::new();
+    {
+        let guard = map.guard();
+        for i in 0..1000 {
+            map.insert(i, i, &guard);
+        }
+    }
+    let capacity = map.pin().capacity();
+
+    let mut visits = std::collections::HashMap::new();
+    let mut guard = map.guard();
+    map.for_each_repinning(10, &mut guard, |&k, &v| {
+        assert_eq!(k, v);
+        *visits.entry(k).or_insert(0) += 1;
+        // grow the map while it is being scanned
+        if k < 1000 {
+            let guard = map.guard();
+            for i in 0..10 {
+                map.insert(1000 + k * 10 + i, 1000 + k * 10 + i, &guard);
+            }
+        }
+    });
+
+    assert!(map.pin().capacity() > capacity);
+    for i in 0..1000 {
+        assert_eq!(visits.get(&i), Some(&1), "{} not visited exactly once", i);
+    }
+    assert!(visits.values().all(|&n| n == 1));
+}
+
+#[test]
+fn partition() {
+    let map = HashMap::<usize, usize>::new();
+    let guard = map.guard();
+    for i in 0..100 {
+        map.insert(i, (i * 37) % 100, &guard);
+    }
+
+    let (above, rest) = map.partition(|_, &v| v > 60, &guard);
+    assert_eq!(above.len() + rest.len(), map.len());
+    assert_eq!(above.len(), 39);
+    let (above_guard, rest_guard) = (above.guard(), rest.guard());
+    for (key, value) in map.iter(&guard) {
+        let (into, other, into_guard, other_guard) = if *value > 60 {
+            (&above, &rest, &above_guard, &rest_guard)
+        } else {
+            (&rest, &above, &rest_guard, &above_guard)
+        };
+        assert_eq!(into.get(key, into_guard), Some(value));
+        assert!(!other.contains_key(key, other_guard));
+    }
+    // the original map is left as it was
+    assert_eq!(map.len(), 100);
+}
+
+#[test]
+fn clone_subset() {
+    let map = HashMap::<usize, String>::new();
+    let guard = map.guard();
+    for i in 0..100 {
+        map.insert(i, i.to_string(), &guard);
+    }
+
+    let subset = map.clone_subset(vec![3, 50, 99, 100, 3], &guard);
+    let subset_guard = subset.guard();
+    assert_eq!(subset.len(), 3);
+    for i in [3, 50, 99] {
+        assert_eq!(subset.get(&i, &subset_guard), Some(&i.to_string()));
+    }
+    assert!(!subset.contains_key(&100, &subset_guard));
+    // the original map is left as it was
+    assert_eq!(map.len(), 100);
+}
+
+#[test]
+fn into_shards() {
+    let map = HashMap::<usize, String>::new();
+    {
+        let guard = map.guard();
+        for i in 0..1000 {
+            map.insert(i, i.to_string(), &guard);
+        }
+    }
+
+    let shards = map.into_shards(4);
+    assert_eq!(shards.len(), 4);
+    let guards: Vec<_> = shards.iter().map(|shard| shard.guard()).collect();
+    assert_eq!(shards.iter().map(|shard| shard.len()).sum::<usize>(), 1000);
+    for i in 0..1000 {
+        let holders: Vec<_> = shards
+            .iter()
+            .zip(&guards)
+            .filter_map(|(shard, guard)| shard.get(&i, guard))
+            .collect();
+        assert_eq!(holders, vec![&i.to_string()]);
+    }
+    // the shards are independent maps
+    shards[0].insert(1000, "1000".to_string(), &guards[0]);
+    assert!(shards[1..]
+        .iter()
+        .zip(&guards[1..])
+        .all(|(shard, guard)| !shard.contains_key(&1000, guard)));
+}
+
+#[test]
+fn into_shards_trees() {
+    let map = HashMap::<usize, Box<usize>, _>::with_hasher(ZeroHashBuilder);
+    {
+        let guard = map.guard();
+        // enough colliding keys for the one bin to be a tree
+        for i in 0..32 {
+            map.insert(i, Box::new(i), &guard);
+        }
+    }
+    let shards = map.into_shards(3);
+    // every entry is in bin 0, which goes to the first shard
+    assert_eq!(shards[0].len(), 32);
+    assert!(shards[1].is_empty() && shards[2].is_empty());
+    let guard = shards[0].guard();
+    for i in 0..32 {
+        assert_eq!(shards[0].get(&i, &guard), Some(&Box::new(i)));
+    }
+
+    // a map that was never inserted into has no table
+    let shards = HashMap::<usize, usize>::new().into_shards(2);
+    assert!(shards.iter().all(|shard| shard.is_empty()));
+}
+
+#[test]
+fn swap() {
+    let a = HashMap::<usize, usize>::new();
+    let b = HashMap::with_hasher(a.hasher().clone());
+    let guard = a.guard();
+    for i in 0..100 {
+        a.insert(i, i, &guard);
+    }
+    for i in 100..150 {
+        b.insert(i, i * 2, &guard);
+    }
+
+    flurry::swap(&a, &b, &guard);
+    assert_eq!(a.len(), 50);
+    assert_eq!(b.len(), 100);
+    for i in 0..100 {
+        assert!(!a.contains_key(&i, &guard));
+        assert_eq!(b.get(&i, &guard), Some(&i));
+    }
+    for i in 100..150 {
+        assert_eq!(a.get(&i, &guard), Some(&(i * 2)));
+        assert!(!b.contains_key(&i, &guard));
+    }
+
+    // both maps keep working as usual, including resizes of the tables they got
+    for i in 150..1000 {
+        a.insert(i, i * 2, &guard);
+    }
+    assert_eq!(a.len(), 900);
+    assert_eq!(a.get(&120, &guard), Some(&240));
+
+    // a map that was never inserted into has no table to swap in
+    let empty = HashMap::with_hasher(a.hasher().clone());
+    flurry::swap(&a, &empty, &guard);
+    assert!(a.is_empty());
+    assert_eq!(empty.len(), 900);
+    a.insert(1, 1, &guard);
+    assert_eq!(a.get(&1, &guard), Some(&1));
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn swap_racing_resizes() {
+    const KEYS: usize = 4000;
+
+    // swap does not support racing writes, but they must not be lost. both maps start out with
+    // the same capacity, and so have the same resize threshold, which the swap thus stores back
+    // into size_ctl unchanged.
+    let a = Arc::new(HashMap::<usize, usize>::new());
+    let b = Arc::new(HashMap::with_hasher(a.hasher().clone()));
+    let writers: Vec<_> = [a.clone(), b.clone()]
+        .iter()
+        .enumerate()
+        .map(|(t, map)| {
+            let map = map.clone();
+            std::thread::spawn(move || {
+                for i in (t..KEYS).step_by(2) {
+                    map.insert(i, i, &map.guard());
+                }
+            })
+        })
+        .collect();
+
+    for _ in 0..200 {
+        flurry::swap(&a, &b, &a.guard());
+    }
+    for writer in writers {
+        writer.join().unwrap();
+    }
+
+    let guard = a.guard();
+    let mut keys: Vec<_> = a.keys(&guard).chain(b.keys(&guard)).copied().collect();
+    keys.sort_unstable();
+    let entries = keys.len();
+    keys.dedup();
+    assert_eq!(keys.len(), entries, "an entry is held twice");
+    assert_eq!(entries, KEYS, "an entry was lost");
+    // racing writes may be counted towards the wrong map, but never go missing from the count
+    assert_eq!(a.len() + b.len(), KEYS);
+}
+
+#[test]
+#[should_panic(expected = "hash alike")]
+fn swap_different_hashers() {
+    let a = HashMap::<usize, usize>::new();
+    let b = HashMap::<usize, usize>::new();
+    flurry::swap(&a, &b, &a.guard());
+}
+
+#[test]
+fn diff() {
+    let old = HashMap::<usize, String>::new();
+    let guard = old.guard();
+    for i in 0..100 {
+        old.insert(i, i.to_string(), &guard);
+    }
+    let new = old.clone();
+    new.insert(100, "100".to_string(), &guard);
+    new.remove(&7, &guard);
+    new.insert(42, "forty-two".to_string(), &guard);
+
+    let diff = flurry::diff(&old, &new, &guard, &guard);
+    assert_eq!(diff.added, vec![(100, "100".to_string())]);
+    assert_eq!(diff.removed, vec![(7, "7".to_string())]);
+    assert_eq!(
+        diff.changed,
+        vec![(42, "42".to_string(), "forty-two".to_string())]
+    );
+
+    // the other way around
+    let diff = flurry::diff(&new, &old, &guard, &guard);
+    assert_eq!(diff.added, vec![(7, "7".to_string())]);
+    assert_eq!(diff.removed, vec![(100, "100".to_string())]);
+    assert_eq!(
+        diff.changed,
+        vec![(42, "forty-two".to_string(), "42".to_string())]
+    );
+
+    assert_eq!(flurry::diff(&old, &old, &guard, &guard), MapDiff::default());
+}
+
+#[test]
+fn clear_if() {
+    let small = HashMap::<usize, usize>::new();
+    let large = HashMap::<usize, usize>::new();
+    let guard = small.guard();
+    for i in 0..3 {
+        small.insert(i, i, &guard);
+    }
+    for i in 0..10 {
+        large.insert(i, i, &guard);
+    }
+
+    assert!(!small.clear_if(|len| len > 5, &guard));
+    assert!(large.clear_if(|len| len > 5, &guard));
+    assert_eq!(small.len(), 3);
+    assert!(large.is_empty());
+    assert_eq!(large.get(&0, &guard), None);
+}
+
+#[test]
+fn merge_from() {
+    let ours = HashMap::<usize, usize>::new();
+    let theirs = HashMap::<usize, usize>::new();
+    let guard = ours.guard();
+    for i in 0..10 {
+        ours.insert(i, i * 10, &guard);
+    }
+    for i in 5..15 {
+        theirs.insert(i, 150 - i * 10, &guard);
+    }
+
+    ours.merge_from(
+        &theirs,
+        |&existing, &incoming| existing.max(incoming),
+        &guard,
+    );
+    assert_eq!(ours.len(), 15);
+    for i in 0..5 {
+        assert_eq!(ours.get(&i, &guard), Some(&(i * 10)));
+    }
+    // the larger value wins, whichever map it came from
+    for (i, v) in [(5, 100), (6, 90), (7, 80), (8, 80), (9, 90)] {
+        assert_eq!(ours.get(&i, &guard), Some(&v));
+    }
+    for i in 10..15 {
+        assert_eq!(ours.get(&i, &guard), Some(&(150 - i * 10)));
+    }
+    // the other map is left as it was
+    assert_eq!(theirs.len(), 10);
+    assert_eq!(theirs.get(&5, &guard), Some(&100));
+}
+
+#[test]
+fn move_matching() {
+    let live = HashMap::<usize, u64>::new();
+    let archive = HashMap::<usize, u64>::new();
+    let guard = live.guard();
+    // entries map an id to the time it was last touched
+    for i in 0..100 {
+        live.insert(i, (i as u64 * 7) % 100, &guard);
+    }
+    archive.insert(1000, 0, &guard);
+
+    let moved = live.move_matching(&archive, |_, &touched| touched < 30, &guard);
+    assert_eq!(moved, 30);
+    assert_eq!(live.len(), 70);
+    assert_eq!(archive.len(), 31);
+    for i in 0..100 {
+        let touched = (i as u64 * 7) % 100;
+        let (from, to) = if touched < 30 {
+            (&live, &archive)
+        } else {
+            (&archive, &live)
+        };
+        assert_eq!(to.get(&i, &guard), Some(&touched));
+        assert!(!from.contains_key(&i, &guard));
+    }
+    assert_eq!(live.move_matching(&archive, |_, &t| t < 30, &guard), 0);
+}
+
+#[test]
+fn move_matching_restores_dest_on_conflict() {
+    // a value that runs a hook when it is cloned, so that we can write to the source map right
+    // as move_matching copies the entry into the destination.
+    #[derive(Debug, PartialEq)]
+    struct Hooked(usize);
+    thread_local! {
+        static ON_CLONE: std::cell::RefCell<Option<Box<dyn FnOnce()>>> = Default::default();
+    }
+    impl Clone for Hooked {
+        fn clone(&self) -> Self {
+            if let Some(hook) = ON_CLONE.with(|hook| hook.borrow_mut().take()) {
+                hook();
+            }
+            Hooked(self.0)
+        }
+    }
+
+    let live = Arc::new(HashMap::<usize, Hooked>::new());
+    let archive = HashMap::<usize, Hooked>::new();
+    let guard = live.guard();
+    live.insert(1, Hooked(1), &guard);
+    archive.insert(1, Hooked(0), &guard);
+
+    let writer = live.clone();
+    ON_CLONE.with(|hook| {
+        *hook.borrow_mut() = Some(Box::new(move || {
+            writer.pin().insert(1, Hooked(2));
+        }))
+    });
+    assert_eq!(live.move_matching(&archive, |_, _| true, &guard), 0);
+    // the concurrent write wins, and the archive keeps the value it had before.
+    assert_eq!(live.get(&1, &guard), Some(&Hooked(2)));
+    assert_eq!(archive.get(&1, &guard), Some(&Hooked(0)));
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn concurrent_move_matching() {
+    let live = Arc::new(HashMap::<usize, usize>::new());
+    let archive = HashMap::<usize, usize>::new();
+    let guard = live.guard();
+    for i in 0..1000 {
+        live.insert(i, 0, &guard);
+    }
+
+    let toucher = {
+        let live = live.clone();
+        std::thread::spawn(move || {
+            let guard = live.guard();
+            for i in 0..1000 {
+                live.compute_if_present(&i, |_, &v| Some(v + 1), &guard);
+            }
+        })
+    };
+    let moved = live.move_matching(&archive, |&k, _| k % 2 == 0, &guard);
+    toucher.join().unwrap();
+
+    // an entry that was touched while it was being moved stays where it was, so every key ends
+    // up in exactly one of the maps
+    assert_eq!(archive.len(), moved);
+    assert_eq!(live.len() + archive.len(), 1000);
+    for i in 0..1000 {
+        assert_ne!(
+            live.contains_key(&i, &guard),
+            archive.contains_key(&i, &guard)
+        );
+    }
+}
+
+#[test]
+fn prefault() {
+    let map = HashMap::<usize, usize>::with_capacity(1 << 20);
+    let guard = map.guard();
+    // the table is allocated by the prefault, not by the first insert
+    map.prefault(&guard);
+    let capacity = map.capacity(&guard);
+    assert!(capacity >= 1 << 20);
+    assert!(map.is_empty());
+
+    for i in 0..10_000 {
+        map.insert(i, i, &guard);
+    }
+    // prefaulting a map whose table is already allocated leaves it alone
+    map.prefault(&guard);
+    assert_eq!(map.len(), 10_000);
+    for i in 0..10_000 {
+        assert_eq!(map.get(&i, &guard), Some(&i));
+    }
+    assert_eq!(map.capacity(&guard), capacity);
+}
+
+#[test]
+fn await_resize() {
+    const ENTRIES: usize = 1 << 16;
+
+    let map = Arc::new(HashMap::<usize, usize>::new());
+    {
+        let guard = map.guard();
+        for i in 0..ENTRIES {
+            map.insert(i, i, &guard);
+        }
+    }
+    let capacity = map.pin().capacity();
+
+    // grow the table by exactly one doubling, so that no second resize follows the first.
+    let done = Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let resizer = {
+        let map = map.clone();
+        let done = done.clone();
+        std::thread::spawn(move || {
+            let guard = map.guard();
+            let threshold = map.capacity(&guard) * 3 / 4;
+            map.reserve_exact(threshold - map.len(), &guard);
+            done.store(true, std::sync::atomic::Ordering::SeqCst);
+        })
+    };
+
+    let guard = map.guard();
+    while !map.is_resizing(&guard) && !done.load(std::sync::atomic::Ordering::SeqCst) {
+        std::hint::spin_loop();
+    }
+    // whether or not we caught the resize in progress, it has fully replaced the table once
+    // await_resize returns, so the new capacity must show without waiting for the resizer.
+    map.await_resize(&guard);
+    assert!(!map.is_resizing(&guard));
+    assert_eq!(map.capacity(&guard), capacity * 2);
+    resizer.join().unwrap();
+    assert_eq!(map.len(), ENTRIES);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn stable_for_each_while_growing() {
+    const ENTRIES: usize = 1000;
+
+    let map = Arc::new(HashMap::<usize, usize>::new());
+    {
+        let guard = map.guard();
+        for i in 0..ENTRIES {
+            map.insert(i, i, &guard);
+        }
+    }
+
+    let done = Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let grower = {
+        let map = map.clone();
+        let done = done.clone();
+        std::thread::spawn(move || {
+            let guard = map.guard();
+            for i in ENTRIES..ENTRIES * 64 {
+                map.insert(i, i, &guard);
+            }
+            done.store(true, std::sync::atomic::Ordering::SeqCst);
+        })
+    };
+
+    let mut scans = 0;
+    while !done.load(std::sync::atomic::Ordering::SeqCst) || scans == 0 {
+        let mut seen = vec![0; ENTRIES];
+        map.pin().stable_for_each(|&k, &v| {
+            assert_eq!(k, v);
+            if k < ENTRIES {
+                seen[k] += 1;
+            }
+        });
+        assert!(seen.iter().all(|&n| n == 1));
+        scans += 1;
+    }
+    grower.join().unwrap();
+}
+
+#[test]
+fn nonblocking_on_held_bin_lock() {
+    let map = Arc::new(HashMap::<usize, usize>::new());
+    {
+        let guard = map.guard();
+        for i in 0..10 {
+            map.insert(i, i, &guard);
+        }
+    }
+    let guard = map.guard();
+    let same = (10..).find(|k| map.same_bin(&3, k, &guard)).unwrap();
+    let other = (10..).find(|k| !map.same_bin(&3, k, &guard)).unwrap();
+
+    let (locked_tx, locked_rx) = std::sync::mpsc::channel();
+    let (release_tx, release_rx) = std::sync::mpsc::channel::<()>();
+    // hold on to the lock of the bin of 3 until told to let go
+    let holder = {
+        let map = map.clone();
+        std::thread::spawn(move || {
+            let guard = map.guard();
+            map.compute_if_present(
+                &3,
+                |_, v| {
+                    locked_tx.send(()).unwrap();
+                    release_rx.recv().unwrap();
+                    Some(v + 1)
+                },
+                &guard,
+            );
+        })
+    };
+    locked_rx.recv().unwrap();
+
+    assert_eq!(
+        map.try_insert_nonblocking(same, 0, &guard),
+        Err(WouldBlock((same, 0)))
+    );
+    assert_eq!(map.try_remove_nonblocking(&3, &guard), Err(WouldBlock(())));
+    assert!(map
+        .try_compute_if_present_nonblocking(&3, |_, _| unreachable!(), &guard)
+        .is_err());
+    assert!(map
+        .try_compute_full_nonblocking(&same, |_| unreachable!(), &guard)
+        .is_err());
+    assert_eq!(map.try_insert_nonblocking(other, 0, &guard), Ok(None));
+    assert_eq!(
+        map.try_compute_if_present_nonblocking(&other, |_, v| Some(v + 1), &guard)
+            .ok(),
+        Some(Some(&1))
+    );
+    assert_eq!(
+        map.try_compute_full_nonblocking(&other, |_| None, &guard)
+            .ok(),
+        Some(Compute::Removed(&1))
+    );
+    assert_eq!(map.try_insert_nonblocking(other, 0, &guard), Ok(None));
+    assert_eq!(map.try_remove_nonblocking(&other, &guard), Ok(Some(&0)));
+
+    release_tx.send(()).unwrap();
+    holder.join().unwrap();
+    assert_eq!(map.try_insert_nonblocking(same, 0, &guard), Ok(None));
+    assert_eq!(map.try_remove_nonblocking(&3, &guard), Ok(Some(&4)));
+}
+
+#[test]
+fn nonblocking_inserts_defer_growth() {
+    let map = HashMap::<usize, usize>::new();
+    let guard = map.guard();
+    for i in 0..100 {
+        assert_eq!(map.try_insert_nonblocking(i, i, &guard), Ok(None));
+    }
+    // nonblocking inserts leave the resize to the next write that may block
+    assert_eq!(map.capacity(&guard), 16);
+    assert_eq!(map.remove(&1000, &guard), None);
+    assert!(map.capacity(&guard) >= 128);
+    for i in 0..100 {
+        assert_eq!(map.get(&i, &guard), Some(&i));
+    }
+}
+
+#[test]
+fn content_hash() {
+    let map = HashMap::<usize, usize>::new();
+    let guard = map.guard();
+    let empty = map.content_hash(&guard);
+    for i in 0..100 {
+        map.insert(i, i, &guard);
+    }
+    let fingerprint = map.content_hash(&guard);
+    assert_ne!(fingerprint, empty);
+    assert_eq!(map.content_hash(&guard), fingerprint);
+
+    map.insert(100, 100, &guard);
+    let inserted = map.content_hash(&guard);
+    assert_ne!(inserted, fingerprint);
+    assert_eq!(map.content_hash(&guard), inserted);
+
+    map.remove(&100, &guard);
+    assert_eq!(map.content_hash(&guard), fingerprint);
+
+    // a changed value changes the fingerprint just like a changed key
+    map.insert(0, 1, &guard);
+    assert_ne!(map.content_hash(&guard), fingerprint);
+    map.clear(&guard);
+    assert_eq!(map.content_hash(&guard), empty);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn approx_distinct_values() {
+    const DISTINCT: u64 = 20_000;
+
+    let map = HashMap::<u64, u64>::new();
+    let guard = map.guard();
+    assert_eq!(map.approx_distinct_values(&guard), 0);
+    for i in 0..3 * DISTINCT {
+        map.insert(i, i % DISTINCT, &guard);
+    }
+
+    // the sketch has a standard error of 1.6%, so allow for four times that.
+    let estimate = map.approx_distinct_values(&guard) as f64;
+    let error = (estimate - DISTINCT as f64).abs() / DISTINCT as f64;
+    assert!(error < 0.065, "estimated {} distinct values", estimate);
+}
+
+#[test]
+fn insert_counted() {
+    let map = HashMap::<usize, usize>::new();
+    let guard = map.guard();
+    for i in 0..1000 {
+        assert_eq!(map.insert_counted(i, i, &guard), (None, 0));
+    }
+    assert_eq!(map.len(), 1000);
+    for i in 0..1000 {
+        assert_eq!(map.insert_counted(i, i + 1, &guard), (Some(&i), 0));
+    }
+    assert_eq!(map.len(), 1000);
+    assert_eq!(map.get(&7, &guard), Some(&8));
+
+    // colliding keys end up in a tree bin, which insert_counted handles just like insert
+    let map = HashMap::<usize, usize, _>::with_hasher(ZeroHashBuilder);
+    let guard = map.guard();
+    for i in 0..100 {
+        assert_eq!(map.insert_counted(i, i, &guard).0, None);
+    }
+    assert_eq!(map.insert_counted(50, 0, &guard).0, Some(&50));
+    assert_eq!(map.len(), 100);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn concurrent_insert_counted() {
+    const THREADS: usize = 4;
+    const KEYS: usize = 1000;
+
+    let map = Arc::new(HashMap::<usize, usize>::new());
+    let threads: Vec<_> = (0..THREADS)
+        .map(|t| {
+            let map = map.clone();
+            std::thread::spawn(move || {
+                let guard = map.guard();
+                // every thread writes every key, so exactly one of them inserts it
+                (0..KEYS)
+                    .filter(|&i| map.insert_counted(i, t, &guard).0.is_none())
+                    .count()
+            })
+        })
+        .collect();
+    let inserted: usize = threads.into_iter().map(|t| t.join().unwrap()).sum();
+    assert_eq!(inserted, KEYS);
+    assert_eq!(map.len(), KEYS);
+}
+
+// needs the lock wait counter to tell when the insert is stuck behind the held lock.
+#[cfg(feature = "metrics")]
+#[test]
+#[cfg_attr(miri, ignore)]
+fn insert_counted_hot_key() {
+    let map = Arc::new(HashMap::<usize, usize>::new());
+    map.pin().insert(0, 0);
+
+    let (locked_tx, locked_rx) = std::sync::mpsc::channel();
+    let (release_tx, release_rx) = std::sync::mpsc::channel::<()>();
+    // hold on to the lock of the bin of 0 until told to let go, so that the insert has to wait.
+    let holder = {
+        let map = map.clone();
+        std::thread::spawn(move || {
+            let guard = map.guard();
+            map.compute_if_present(
+                &0,
+                |_, v| {
+                    locked_tx.send(()).unwrap();
+                    release_rx.recv().unwrap();
+                    Some(v + 1)
+                },
+                &guard,
+            );
+        })
+    };
+    locked_rx.recv().unwrap();
+
+    let inserter = {
+        let map = map.clone();
+        std::thread::spawn(move || {
+            let guard = map.guard();
+            let (old, retries) = map.insert_counted(0, 2, &guard);
+            (old.copied(), retries)
+        })
+    };
+    while map.stats().lock_waits == 0 {
+        std::thread::yield_now();
+    }
+    release_tx.send(()).unwrap();
+    holder.join().unwrap();
+
+    let (old, retries) = inserter.join().unwrap();
+    assert_eq!(old, Some(1));
+    assert!(retries > 0, "the insert into the held bin never retried");
+    assert_eq!(map.len(), 1);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn lock_strategies() {
+    const THREADS: usize = 8;
+    const ROUNDS: usize = 2_000;
+    const KEYS: usize = 4;
+
+    for &strategy in &[
+        LockStrategy::Spin,
+        LockStrategy::Park,
+        LockStrategy::SpinThenPark { spins: 64 },
+    ] {
+        let map = Arc::new(HashMap::<usize, usize>::new());
+        map.set_lock_strategy(strategy);
+        {
+            let guard = map.guard();
+            for k in 0..KEYS {
+                map.insert(k, 0, &guard);
+            }
+        }
+        let threads: Vec<_> = (0..THREADS)
+            .map(|t| {
+                let map = map.clone();
+                std::thread::spawn(move || {
+                    let guard = map.guard();
+                    for i in 0..ROUNDS {
+                        map.compute_if_present(&((t + i) % KEYS), |_, v| Some(v + 1), &guard);
+                    }
+                })
+            })
+            .collect();
+        for t in threads {
+            t.join().unwrap();
+        }
+
+        let guard = map.guard();
+        let total: usize = (0..KEYS).map(|k| *map.get(&k, &guard).unwrap()).sum();
+        assert_eq!(total, THREADS * ROUNDS, "lost updates with {:?}", strategy);
+    }
+}
+
+#[test]
+fn compare_exchange_increments() {
+    const THREADS: usize = 8;
+    const ROUNDS: usize = 1_000;
+
+    let map = Arc::new(HashMap::<usize, usize>::new());
+    map.pin().insert(0, 0);
+    let threads: Vec<_> = (0..THREADS)
+        .map(|_| {
+            let map = map.clone();
+            std::thread::spawn(move || {
+                let guard = map.guard();
+                for _ in 0..ROUNDS {
+                    let mut current = *map.get(&0, &guard).unwrap();
+                    while let Err(actual) = map.compare_exchange(&0, &current, current + 1, &guard)
+                    {
+                        current = *actual.unwrap();
+                    }
+                }
+            })
+        })
+        .collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+    assert_eq!(map.pin().get(&0), Some(&(THREADS * ROUNDS)));
+    assert_eq!(map.pin().compare_exchange(&1, &0, 1), Err(None));
+}
+
+#[test]
+fn replace_if_with() {
+    let map = HashMap::<usize, usize>::new();
+    let guard = map.guard();
+    map.insert(1, 10, &guard);
+
+    // the predicate holds, so the value is replaced, and the old one returned
+    let outcome = map.replace_if_with(&1, |&v| v % 2 == 0, 11, &guard);
+    assert_eq!(outcome, ReplaceIf::Replaced(&10));
+    assert_eq!(map.get(&1, &guard), Some(&11));
+
+    // the predicate fails, so the value stays in place
+    let outcome = map.replace_if_with(&1, |&v| v % 2 == 0, 12, &guard);
+    assert_eq!(outcome, ReplaceIf::Rejected(&11));
+    assert_eq!(map.get(&1, &guard), Some(&11));
+
+    // the predicate is not even asked about absent keys
+    let outcome = map.replace_if_with(&2, |_| unreachable!(), 12, &guard);
+    assert_eq!(outcome, ReplaceIf::Absent);
+    assert!(!map.contains_key(&2, &guard));
+    assert_eq!(map.len(), 1);
+}
+
+#[test]
+fn get_migrating() {
+    #[derive(Clone, Debug, PartialEq)]
+    struct Record {
+        version: u32,
+        name: String,
+    }
+
+    let map = HashMap::<usize, Record>::new();
+    let guard = map.guard();
+    for i in 0..10 {
+        let name = i.to_string();
+        map.insert(i, Record { version: 1, name }, &guard);
+    }
+
+    let migrations = std::cell::Cell::new(0);
+    let upgrade = |record: &Record| {
+        if record.version >= 2 {
+            return None;
+        }
+        migrations.set(migrations.get() + 1);
+        Some(Record {
+            version: 2,
+            name: record.name.to_uppercase(),
+        })
+    };
+
+    let upgraded = map.get_migrating(&3, upgrade, &guard).unwrap();
+    assert_eq!(upgraded.version, 2);
+    assert_eq!(map.get(&3, &guard), Some(upgraded));
+    assert_eq!(migrations.get(), 1);
+
+    // the stored value is up to date now, so reading it again does not migrate it
+    let again = map.get_migrating(&3, upgrade, &guard).unwrap();
+    assert!(std::ptr::eq(again, upgraded));
+    assert_eq!(migrations.get(), 1);
+
+    // other entries are untouched until they are read
+    assert_eq!(map.get(&4, &guard).unwrap().version, 1);
+    assert_eq!(map.get_migrating(&42, upgrade, &guard), None);
+}
+
+#[test]
+fn repair() {
+    let map = HashMap::<usize, usize>::new();
+    let guard = map.guard();
+    for i in 0..100 {
+        // every tenth value is out of range
+        let value = if i % 10 == 0 { 1000 + i } else { i };
+        map.insert(i, value, &guard);
+    }
+
+    let clamp = |_: &usize, &v: &usize| (v >= 100).then(|| 99);
+    assert_eq!(map.repair(clamp, &guard), 10);
+    for i in 0..100 {
+        let expected = if i % 10 == 0 { 99 } else { i };
+        assert_eq!(map.get(&i, &guard), Some(&expected));
+    }
+    assert_eq!(map.len(), 100);
+
+    // everything is in range now
+    assert_eq!(map.repair(clamp, &guard), 0);
+}
+
+#[test]
+fn wait_until_len() {
+    use std::time::Duration;
+
+    let map = Arc::new(HashMap::<usize, usize>::new());
+    let producer = {
+        let map = map.clone();
+        std::thread::spawn(move || {
+            let guard = map.guard();
+            for i in 0..1000 {
+                map.insert(i, i, &guard);
+            }
+        })
+    };
+    // a timeout this long is no timeout at all
+    assert!(map.wait_until_len(1000, Duration::MAX));
+    assert_eq!(map.len(), 1000);
+    producer.join().unwrap();
+
+    // no one inserts any more, so this times out
+    assert!(!map.wait_until_len(1001, Duration::from_millis(10)));
+    assert!(map.wait_until_len(0, Duration::from_secs(0)));
+    assert!(map.wait_until_len(1000, Duration::MAX));
+}
+
+#[test]
+fn iter_with_lock_state() {
+    let map = Arc::new(HashMap::<usize, usize>::new());
+    {
+        let guard = map.guard();
+        for i in 0..10 {
+            map.insert(i, i, &guard);
+        }
+    }
+
+    let (locked_tx, locked_rx) = std::sync::mpsc::channel();
+    let (release_tx, release_rx) = std::sync::mpsc::channel::<()>();
+    // hold on to the lock of the bin of 3 until told to let go
+    let holder = {
+        let map = map.clone();
+        std::thread::spawn(move || {
+            let guard = map.guard();
+            map.compute_if_present(
+                &3,
+                |_, v| {
+                    locked_tx.send(()).unwrap();
+                    release_rx.recv().unwrap();
+                    Some(*v)
+                },
+                &guard,
+            );
+        })
+    };
+    locked_rx.recv().unwrap();
+
+    let guard = map.guard();
+    let mut seen = 0;
+    for (&k, _, held) in map.iter_with_lock_state(&guard) {
+        assert_eq!(held, map.same_bin(&3, &k, &guard), "lock state of {}", k);
+        seen += 1;
+    }
+    assert_eq!(seen, 10);
+
+    release_tx.send(()).unwrap();
+    holder.join().unwrap();
+    assert!(map.pin().iter_with_lock_state().all(|(_, _, held)| !held));
+}
@@ -522,3 +522,44 @@ fn concurrent_compute_if_present() {
         assert!(map.get(&i).is_none());
     }
 }
+
+#[test]
+fn index() {
+    let map = HashMap::<usize, usize>::new();
+    let map = map.pin();
+    map.insert(42, 0);
+    assert_eq!(map[&42], 0);
+}
+
+#[test]
+#[should_panic(expected = "no entry found for key")]
+fn index_missing() {
+    let map = HashMap::<usize, usize>::new();
+    let map = map.pin();
+    map.insert(42, 0);
+    let _ = map[&0];
+}
+
+#[test]
+fn pin_batched() {
+    let map = HashMap::<usize, usize>::new();
+    let removed = map.pin_batched(|map| {
+        for i in 0..10_000 {
+            map.insert(i, i);
+        }
+        for i in 0..10_000 {
+            assert_eq!(map.get(&i), Some(&i));
+        }
+        (0..10_000)
+            .filter(|i| i % 2 == 0)
+            .filter_map(|i| map.remove(&i))
+            .count()
+    });
+    assert_eq!(removed, 5_000);
+
+    let map = map.pin();
+    assert_eq!(map.len(), 5_000);
+    for i in 0..10_000 {
+        assert_eq!(map.get(&i).is_some(), i % 2 == 1);
+    }
+}
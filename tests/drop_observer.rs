@@ -0,0 +1,61 @@
+// the held pin of manual-gc keeps removed entries from ever being reclaimed.
+#![cfg(all(feature = "drop-observer", not(feature = "manual-gc"), not(miri)))]
+
+use flurry::{epoch, HashMap};
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+mod hasher;
+use hasher::ZeroHashBuilder;
+
+/// Pins and unpins the epoch until `observed` has seen `n` keys, and returns them in order.
+fn wait_for_reclaimed(observed: &Mutex<Vec<usize>>, n: usize) -> Vec<usize> {
+    for _ in 0..100_000 {
+        if observed.lock().len() >= n {
+            break;
+        }
+        epoch::pin().flush();
+    }
+    let mut keys = observed.lock().clone();
+    keys.sort_unstable();
+    keys
+}
+
+#[test]
+fn observes_reclaimed_entries() {
+    let observed = Arc::new(Mutex::new(Vec::new()));
+    // all keys share a bin, so that the bin is turned into a tree.
+    let map = HashMap::<usize, usize, _>::with_hasher(ZeroHashBuilder);
+    let keys = observed.clone();
+    map.set_drop_observer(move |&key: &usize, &value: &usize| {
+        assert_eq!(key, value);
+        keys.lock().push(key);
+    });
+
+    {
+        let guard = map.guard();
+        for i in 0..32 {
+            map.insert(i, i, &guard);
+        }
+        // replaced values are not reported.
+        map.insert(0, 0, &guard);
+        for i in 0..8 {
+            map.remove(&i, &guard);
+        }
+        for i in 8..16 {
+            map.compute_if_present(&i, |_, _| None, &guard);
+        }
+        // our guard may still be reading the removed entries.
+        assert!(observed.lock().is_empty());
+    }
+    assert_eq!(
+        wait_for_reclaimed(&observed, 16),
+        (0..16).collect::<Vec<_>>()
+    );
+
+    map.pin().clear();
+    assert_eq!(
+        wait_for_reclaimed(&observed, 32),
+        (0..32).collect::<Vec<_>>()
+    );
+}
@@ -1,10 +1,12 @@
 use crossbeam_epoch as epoch;
-use flurry::{DefaultHashBuilder, HashMap};
-use std::hash::{BuildHasher, BuildHasherDefault, Hasher};
+use flurry::{DefaultHashBuilder, HashMap, HashSet};
+use std::hash::{BuildHasher, BuildHasherDefault, Hash, Hasher};
+use std::sync::Arc;
 
 #[derive(Default)]
 pub struct ZeroHasher;
 
+#[derive(Clone)]
 pub struct ZeroHashBuilder;
 
 impl Hasher for ZeroHasher {
@@ -22,6 +24,48 @@ impl BuildHasher for ZeroHashBuilder {
     }
 }
 
+/// Hashes integer keys with the function it holds, so that tests can choose which bins keys
+/// land in.
+#[derive(Clone, Copy)]
+pub struct FnHashBuilder(pub fn(u64) -> u64);
+
+pub struct FnHasher {
+    f: fn(u64) -> u64,
+    key: u64,
+}
+
+impl Hasher for FnHasher {
+    fn finish(&self) -> u64 {
+        (self.f)(self.key)
+    }
+    fn write(&mut self, _: &[u8]) {
+        unimplemented!("only integer keys are hashed")
+    }
+    fn write_u64(&mut self, n: u64) {
+        self.key = n;
+    }
+    fn write_usize(&mut self, n: usize) {
+        self.key = n as u64;
+    }
+}
+
+impl BuildHasher for FnHashBuilder {
+    type Hasher = FnHasher;
+
+    fn build_hasher(&self) -> FnHasher {
+        FnHasher { f: self.0, key: 0 }
+    }
+}
+
+/// Hashes every key to itself.
+pub const IDENTITY: FnHashBuilder = FnHashBuilder(|n| n);
+
+/// Puts every key in the first bin of the table.
+pub const FIRST_BIN: FnHashBuilder = FnHashBuilder(|n| n << 32);
+
+/// Hashes each key to its value divided by 100, so that each hundred keys share a bin.
+pub const HUNDREDS: FnHashBuilder = FnHashBuilder(|n| n / 100);
+
 fn check<S: BuildHasher + Default>() {
     let range = if cfg!(miri) { 0..16 } else { 0..1000 };
     let guard = epoch::pin();
@@ -63,3 +107,470 @@ fn test_max_hasher() {
 
     check::<BuildHasherDefault<MaxHasher>>();
 }
+
+#[test]
+fn rehash_with_fixed_seed() {
+    let map = HashMap::<i32, i32>::new();
+    let guard = map.guard();
+    for i in 0..1000 {
+        map.insert(i, i * 2, &guard);
+    }
+
+    let rehashed = map.rehash_with(DefaultHashBuilder::with_seeds(1, 2), &guard);
+    let rehashed_guard = rehashed.guard();
+    assert_eq!(rehashed.len(), 1000);
+    for i in 0..1000 {
+        assert_eq!(rehashed.get(&i, &rehashed_guard), Some(&(i * 2)));
+    }
+
+    let before = map.bin_stats(&guard);
+    let after = rehashed.bin_stats(&rehashed_guard);
+    assert_eq!(after.bin_lengths.iter().sum::<usize>(), 1000);
+    assert_ne!(before.bin_lengths, after.bin_lengths);
+}
+
+#[test]
+fn rehash_away_from_weak_hasher() {
+    let map = HashMap::<i32, i32, _>::with_hasher(ZeroHashBuilder);
+    let guard = map.guard();
+    for i in 0..100 {
+        map.insert(i, i, &guard);
+    }
+    let before = map.bin_stats(&guard);
+    assert_eq!(before.longest(), 100);
+    assert_eq!(before.occupied(), 1);
+    assert_eq!(before.tree_bins, 1);
+
+    let rehashed = map.rehash_with(DefaultHashBuilder::default(), &guard);
+    let after = rehashed.pin().bin_stats();
+    assert!(after.longest() < 100);
+    assert!(after.occupied() > 1);
+    assert_eq!(after.histogram().iter().sum::<usize>(), after.bins());
+}
+
+#[test]
+fn builder_with_hasher_and_load_factor() {
+    let map = HashMap::<i32, i32>::builder()
+        .hasher(DefaultHashBuilder::with_seeds(1, 2))
+        .capacity(10)
+        .load_factor(0.5)
+        .build();
+    let guard = map.guard();
+    // 1 + 10 / 0.5 = 21 entries, rounded up to the next power of two
+    assert_eq!(map.capacity(&guard), 32);
+
+    // at a load factor of ½, 32 bins hold up to 15 entries before the table is resized
+    for i in 0..15 {
+        map.insert(i, i, &guard);
+    }
+    assert_eq!(map.capacity(&guard), 32);
+    map.insert(15, 15, &guard);
+    assert_eq!(map.capacity(&guard), 64);
+    for i in 16..100 {
+        map.insert(i, i, &guard);
+    }
+    assert_eq!(map.capacity(&guard), 256);
+    for i in 0..100 {
+        assert_eq!(map.get(&i, &guard), Some(&i));
+    }
+}
+
+#[test]
+fn default_load_factor_reserve() {
+    let map = HashMap::<i32, i32>::new();
+    let guard = map.guard();
+    map.insert(0, 0, &guard);
+    assert_eq!(map.capacity(&guard), 16);
+
+    // like in Java, reserving for 20 entries asks for 32 bins, and then grows until those 32
+    // fit below the resize threshold, which takes 64 bins.
+    map.reserve(19, &guard);
+    assert_eq!(map.capacity(&guard), 64);
+}
+
+#[test]
+fn builder_load_factor_reserve() {
+    let map = HashMap::<i32, i32>::builder().load_factor(0.5).build();
+    let guard = map.guard();
+    map.insert(0, 0, &guard);
+    assert_eq!(map.capacity(&guard), 16);
+
+    // 1 + 20 / 0.5 = 41 entries, rounded up to the next power of two, just like when
+    // the capacity is given up front
+    map.reserve(19, &guard);
+    assert_eq!(map.capacity(&guard), 64);
+    for i in 1..20 {
+        map.insert(i, i, &guard);
+    }
+    assert_eq!(map.capacity(&guard), 64);
+}
+
+#[test]
+fn builder_min_capacity() {
+    let map = HashMap::<i32, i32>::builder().min_capacity(100).build();
+    let guard = map.guard();
+    // the table is only allocated on the first insert
+    assert_eq!(map.capacity(&guard), 0);
+    map.insert(1, 1, &guard);
+    assert_eq!(map.capacity(&guard), 128);
+
+    // a capacity that needs fewer bins does not go below the minimum
+    let map = HashMap::<i32, i32>::builder()
+        .min_capacity(100)
+        .capacity(10)
+        .build();
+    assert_eq!(map.pin().capacity(), 128);
+}
+
+#[test]
+fn builder_without_treeification() {
+    let map = HashMap::<i32, i32>::builder()
+        .hasher(ZeroHashBuilder)
+        .treeification(false)
+        .build();
+    let guard = map.guard();
+    // every key collides, so with treeification this bin would have become a tree.
+    for i in 0..100 {
+        map.insert(i, i, &guard);
+    }
+    assert!(!map.would_treeify(&0, &guard));
+    let stats = map.bin_stats(&guard);
+    assert_eq!(stats.tree_bins, 0);
+    assert_eq!(stats.longest(), 100);
+    assert!(map.capacity(&guard) >= 64);
+    for i in 0..100 {
+        assert_eq!(map.get(&i, &guard), Some(&i));
+    }
+    assert_eq!(map.remove(&50, &guard), Some(&50));
+    assert_eq!(map.get(&50, &guard), None);
+    assert_eq!(map.len(), 99);
+
+    // the clone keeps the setting.
+    let cloned = map.clone();
+    cloned.insert(100, 100, &guard);
+    assert_eq!(cloned.bin_stats(&guard).tree_bins, 0);
+}
+
+#[test]
+fn default_hasher_is_randomly_seeded() {
+    // sequential integers are what a fixed, weak seed would collide on the most predictably
+    let fill = |hasher: DefaultHashBuilder| {
+        let map = HashMap::<u64, u64, _>::with_capacity_and_hasher(1000, hasher);
+        let guard = map.guard();
+        for i in 0..1000 {
+            map.insert(i << 32, i, &guard);
+        }
+        map.bin_stats(&guard)
+    };
+
+    let a = fill(DefaultHashBuilder::default());
+    let b = fill(DefaultHashBuilder::default());
+    assert_eq!(a.bins(), b.bins());
+    let moved = a
+        .bin_lengths
+        .iter()
+        .zip(&b.bin_lengths)
+        .filter(|(a, b)| a != b)
+        .count();
+    // with ~0.5 entries per bin, independently seeded maps typically differ in about half of
+    // their bins. the hashers of some pairs of seeds are much more alike than that, though, so
+    // only check for a clear difference.
+    assert!(
+        moved > a.bins() / 100,
+        "{} of {} bins differ",
+        moved,
+        a.bins()
+    );
+
+    // a fixed seed places the keys the same way every time
+    assert_eq!(
+        fill(DefaultHashBuilder::with_seed(7)),
+        fill(DefaultHashBuilder::with_seed(7))
+    );
+    assert_ne!(
+        fill(DefaultHashBuilder::with_seed(7)),
+        fill(DefaultHashBuilder::with_seed(8))
+    );
+}
+
+#[test]
+fn default_hasher_from_ahash() {
+    let from = DefaultHashBuilder::from(ahash::RandomState::with_seeds(1, 2));
+    let seeded = DefaultHashBuilder::with_seeds(1, 2);
+    for i in 0..100u64 {
+        let mut a = from.build_hasher();
+        a.write_u64(i);
+        let mut b = seeded.build_hasher();
+        b.write_u64(i);
+        assert_eq!(a.finish(), b.finish());
+    }
+}
+
+#[test]
+fn same_bin() {
+    let map = HashMap::<u64, u64, _>::with_hasher(IDENTITY);
+    let guard = map.guard();
+    map.insert(0, 0, &guard);
+    assert_eq!(map.capacity(&guard), 16);
+    // 1 and 17 only differ above the bits used for 16 bins
+    assert!(map.same_bin(&1, &17, &guard));
+    assert!(!map.same_bin(&1, &2, &guard));
+
+    for i in 1..20 {
+        map.insert(i, i, &guard);
+    }
+    assert_eq!(map.capacity(&guard), 32);
+    assert!(!map.same_bin(&1, &17, &guard));
+    assert!(map.same_bin(&1, &33, &guard));
+}
+
+/// Hashes `key` the way a map that uses `build_hasher` does.
+fn hash_one<S: BuildHasher, T: ?Sized + Hash>(build_hasher: &S, key: &T) -> u64 {
+    let mut hasher = build_hasher.build_hasher();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[test]
+fn observe_hasher() {
+    let map = HashMap::<String, usize>::with_hasher(DefaultHashBuilder::with_seed(42));
+    let guard = map.guard();
+    for i in 0..100 {
+        map.insert(i.to_string(), i, &guard);
+    }
+
+    // hashing a key with the map's hasher tells which bin the key lands in
+    let capacity = map.capacity(&guard);
+    for i in 0..100 {
+        let key = i.to_string();
+        let bin = hash_one(map.hasher(), &key) as usize & (capacity - 1);
+        assert_eq!(bin, map.bin_index(&key, &guard));
+    }
+    assert_eq!(
+        hash_one(map.pin().hasher(), "key"),
+        hash_one(&DefaultHashBuilder::with_seed(42), "key")
+    );
+
+    let set = HashSet::<usize>::with_hasher(DefaultHashBuilder::with_seed(42));
+    assert_eq!(
+        hash_one(set.hasher(), &7),
+        hash_one(&DefaultHashBuilder::with_seed(42), &7)
+    );
+    assert_eq!(hash_one(set.pin().hasher(), &7), hash_one(set.hasher(), &7));
+}
+
+#[test]
+#[cfg(feature = "debug-dump")]
+fn dump_structure() {
+    // a fixed seed, so that keys always land in the same bins
+    let map = HashMap::<u64, (), _>::with_hasher(FnHashBuilder(|n| {
+        (n ^ 0x5eed).wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 32
+    }));
+    let guard = map.guard();
+    for key in [3, 14, 15, 92, 65, 35, 89, 79, 32, 38] {
+        map.insert(key, (), &guard);
+    }
+    assert_eq!(
+        map.dump_structure(&guard),
+        concat!(
+            "0: 0 []\n",
+            "1: 2 [3, 35]\n",
+            "2: 0 []\n",
+            "3: 0 []\n",
+            "4: 0 []\n",
+            "5: 1 [38]\n",
+            "6: 0 []\n",
+            "7: 0 []\n",
+            "8: 1 [32]\n",
+            "9: 1 [14]\n",
+            "10: 1 [89]\n",
+            "11: 0 []\n",
+            "12: 0 []\n",
+            "13: 0 []\n",
+            "14: 2 [92, 65]\n",
+            "15: 2 [15, 79]\n",
+        )
+    );
+}
+
+#[test]
+fn nonblocking_insert_treeifies() {
+    let map = HashMap::<u64, u64, _>::with_capacity_and_hasher(64, FIRST_BIN);
+    let guard = map.guard();
+    for i in 0..9 {
+        assert_eq!(map.try_insert_nonblocking(i, i, &guard), Ok(None));
+    }
+    assert_eq!(map.bin_stats(&guard).tree_bins, 1);
+}
+
+#[test]
+fn would_treeify() {
+    // large enough that a long bin is turned into a tree rather than making the map resize
+    let map = HashMap::<u64, u64, _>::with_capacity_and_hasher(64, FIRST_BIN);
+    let guard = map.guard();
+    assert!(!map.would_treeify(&0, &guard));
+    for i in 0..8 {
+        assert!(!map.would_treeify(&0, &guard), "at {} entries", i);
+        map.insert(i, i, &guard);
+    }
+    // the bin now holds 8 entries, and the next one turns it into a tree
+    assert!(map.would_treeify(&0, &guard));
+    assert!(map.would_treeify(&1000, &guard));
+    assert_eq!(map.bin_stats(&guard).tree_bins, 0);
+
+    map.insert(8, 8, &guard);
+    assert_eq!(map.bin_stats(&guard).tree_bins, 1);
+    // a tree has no further to degrade
+    assert!(!map.would_treeify(&0, &guard));
+}
+
+#[test]
+fn maybe_untreeify() {
+    // large enough that a long bin is turned into a tree rather than making the map resize
+    let map = HashMap::<u64, u64, _>::with_capacity_and_hasher(64, FIRST_BIN);
+    let guard = map.guard();
+    for i in 0..32 {
+        map.insert(i, i, &guard);
+    }
+    assert_eq!(map.bin_stats(&guard).tree_bins, 1);
+    assert_eq!(map.maybe_untreeify(&guard), 0);
+
+    // removals leave the tree in place while it is not yet tiny
+    for i in 6..32 {
+        map.remove(&i, &guard);
+    }
+    assert_eq!(map.bin_stats(&guard).tree_bins, 1);
+
+    assert_eq!(map.maybe_untreeify(&guard), 1);
+    let stats = map.bin_stats(&guard);
+    assert_eq!(stats.tree_bins, 0);
+    assert_eq!(stats.longest(), 6);
+    for i in 0..6 {
+        assert_eq!(map.get(&i, &guard), Some(&i));
+    }
+    // the list still takes inserts and removals
+    map.insert(6, 6, &guard);
+    map.remove(&0, &guard);
+    assert_eq!(map.len(), 6);
+}
+
+#[test]
+fn worst_bins() {
+    let map = HashMap::<u64, u64>::new();
+    let guard = map.guard();
+    map.insert(0, 0, &guard);
+    assert_eq!(map.capacity(&guard), 16);
+
+    // four keys in the bin of 0, three in another bin, and one more in a third
+    let bins: Vec<usize> = (0..1000).map(|k| map.bin_index(&k, &guard)).collect();
+    let bins = &bins;
+    let longest = bins[0];
+    let second = *bins.iter().find(|&&bin| bin != longest).unwrap();
+    let third = *bins
+        .iter()
+        .find(|&&bin| bin != longest && bin != second)
+        .unwrap();
+    let keys_in = |bin, n| {
+        (0..1000u64)
+            .filter(move |&k| bins[k as usize] == bin)
+            .take(n)
+    };
+    for k in keys_in(longest, 4)
+        .chain(keys_in(second, 3))
+        .chain(keys_in(third, 1))
+    {
+        map.insert(k, k, &guard);
+    }
+    assert_eq!(map.capacity(&guard), 16);
+
+    assert_eq!(map.worst_bins(2, &guard), [(longest, 4), (second, 3)]);
+    assert_eq!(map.pin().worst_bins(3)[2], (third, 1));
+    assert_eq!(map.worst_bins(10, &guard).len(), 3);
+    assert!(map.worst_bins(0, &guard).is_empty());
+    assert_eq!(map.worst_bins(usize::MAX, &guard).len(), 3);
+}
+
+#[test]
+fn avg_chain_length() {
+    let map = HashMap::<u64, u64>::new();
+    let guard = map.guard();
+    for i in 0..10_000 {
+        map.insert(i, i, &guard);
+    }
+    // the load of the table is at most ¾, so well-spread keys rarely share a bin
+    let avg = map.avg_chain_length(&guard);
+    assert!((1.0..1.5).contains(&avg), "{}", avg);
+
+    let colliding = HashMap::<u64, u64, _>::with_hasher(ZeroHashBuilder);
+    let guard = colliding.guard();
+    for i in 0..100 {
+        colliding.insert(i, i, &guard);
+    }
+    assert_eq!(colliding.pin().avg_chain_length(), 100.0);
+}
+
+#[test]
+fn reduce_bin_consistent() {
+    const KEYS: usize = 4;
+    const TOTAL: usize = 100;
+    let rounds = if cfg!(miri) { 20 } else { 2000 };
+
+    let map = Arc::new(HashMap::<usize, usize, _>::with_hasher(HUNDREDS));
+    {
+        let guard = map.guard();
+        assert_eq!(map.reduce_bin(&0, 0, |n, _, _| n + 1, &guard), 0);
+        // keys 0 to 3 share a bin, and their values always add up to TOTAL.
+        map.insert(0, TOTAL, &guard);
+        for k in 1..KEYS {
+            map.insert(k, 0, &guard);
+        }
+    }
+
+    let mover = {
+        let map = map.clone();
+        std::thread::spawn(move || {
+            let guard = map.guard();
+            for i in 0..rounds {
+                let (from, to) = (i % KEYS, (i + 1) % KEYS);
+                map.compute_many(
+                    [&from, &to],
+                    |[a, b]| {
+                        let (a, b) = (*a.unwrap(), *b.unwrap());
+                        [Some(0), Some(a + b)]
+                    },
+                    &guard,
+                );
+            }
+        })
+    };
+    let grower = {
+        let map = map.clone();
+        std::thread::spawn(move || {
+            let guard = map.guard();
+            // these keys go to other bins, and make the table grow.
+            for i in 1..rounds {
+                map.insert(i * 100, i, &guard);
+            }
+        })
+    };
+
+    let guard = map.guard();
+    for _ in 0..rounds {
+        let (keys, sum) = map.reduce_bin(
+            &0,
+            (0, 0),
+            |(keys, sum), &k, &v| {
+                if k < KEYS {
+                    (keys + 1, sum + v)
+                } else {
+                    (keys, sum)
+                }
+            },
+            &guard,
+        );
+        assert_eq!(keys, KEYS);
+        assert_eq!(sum, TOTAL);
+    }
+    mover.join().unwrap();
+    grower.join().unwrap();
+}
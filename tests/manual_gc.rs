@@ -0,0 +1,40 @@
+#![cfg(feature = "manual-gc")]
+
+use flurry::HashMap;
+use std::sync::Arc;
+
+#[test]
+fn destructors_only_run_on_collect() {
+    let replaced = Arc::new(0);
+    let removed = Arc::new(1);
+
+    let map = HashMap::<usize, Arc<usize>>::new();
+    map.insert(42, replaced.clone(), &map.guard());
+    map.insert(42, removed.clone(), &map.guard());
+    map.remove(&42, &map.guard());
+    for i in 0..100 {
+        map.insert(i, Arc::new(i), &map.guard());
+        map.remove(&i, &map.guard());
+    }
+
+    // no amount of pinning and flushing reclaims the garbage
+    for _ in 0..1000 {
+        map.guard().flush();
+    }
+    assert_eq!(Arc::strong_count(&replaced), 2);
+    assert_eq!(Arc::strong_count(&removed), 2);
+
+    map.collect();
+    assert_eq!(Arc::strong_count(&replaced), 1);
+    assert_eq!(Arc::strong_count(&removed), 1);
+
+    // garbage produced after a collect is held back again
+    map.insert(42, replaced.clone(), &map.guard());
+    map.remove(&42, &map.guard());
+    for _ in 0..1000 {
+        map.guard().flush();
+    }
+    assert_eq!(Arc::strong_count(&replaced), 2);
+    map.collect();
+    assert_eq!(Arc::strong_count(&replaced), 1);
+}
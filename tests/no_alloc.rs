@@ -0,0 +1,50 @@
+#![cfg(not(miri))]
+
+use flurry::{epoch, HashMap};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Counts every allocation made by the process.
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+// this is the only test in this file, so that no other test allocates while it counts.
+#[test]
+fn byte_slice_lookups() {
+    let map = HashMap::<Vec<u8>, usize>::new();
+    for (i, key) in [&b"GET"[..], b"PUT", b"DELETE"].iter().enumerate() {
+        map.insert(key.to_vec(), i, &epoch::pin());
+    }
+    let query: &[u8] = b"PUT /index.html";
+    let (method, missing) = (&query[..3], &query[4..]);
+    // unlike map.guard(), this does not register a new participant, which allocates.
+    let guard = epoch::pin();
+
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let found = map.get(method, &guard).copied();
+    let contains = map.contains_key(method, &guard);
+    let not_found = map.get(missing, &guard).copied();
+    let removed = map.remove(missing, &guard).copied();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+
+    assert_eq!(found, Some(1));
+    assert!(contains);
+    assert_eq!(not_found, None);
+    assert_eq!(removed, None);
+    assert_eq!(allocations, 0, "byte slice lookups allocated");
+}
@@ -0,0 +1,57 @@
+// the held pin of manual-gc keeps removed nodes from ever being reclaimed.
+#![cfg(all(feature = "node-pool", not(feature = "manual-gc"), not(miri)))]
+
+use flurry::{epoch, HashMap};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Counts every allocation made by the process.
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+// this is the only test in this file, so that no other test allocates while it counts.
+#[test]
+fn churn_reuses_nodes() {
+    const ROUNDS: usize = 10_000;
+
+    // values of type () are never allocated, so each insert allocates its node, unless it can
+    // reuse one from the pool. every insert also flushes the garbage of the thread, which
+    // allocates a bag for the garbage if there is any. without the pool, the churn thus makes two
+    // allocations per round, and with it, just the one for the bag.
+    let map = HashMap::<usize, ()>::new();
+    let churn = |rounds| {
+        for i in 0..rounds {
+            // unlike map.guard(), this does not register a new participant, which allocates.
+            let guard = epoch::pin();
+            map.insert(i % 64, (), &guard);
+            map.remove(&(i % 64), &guard);
+        }
+    };
+    // warm up the pool and the epoch machinery.
+    churn(ROUNDS);
+
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    churn(ROUNDS);
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    assert!(
+        allocations < ROUNDS * 3 / 2,
+        "{} allocations for {} inserts",
+        allocations,
+        ROUNDS
+    );
+}
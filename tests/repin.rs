@@ -0,0 +1,44 @@
+//! This test lives in its own binary, since it relies on no other thread keeping the global
+//! epoch from advancing. With the `manual-gc` feature, nothing is freed outside of
+//! `HashMap::collect`, so it does not apply.
+#![cfg(not(feature = "manual-gc"))]
+
+use flurry::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+struct Counted;
+
+impl Drop for Counted {
+    fn drop(&mut self) {
+        DROPPED.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+fn scan_and_remove(every: usize) -> usize {
+    let map = HashMap::new();
+    {
+        let guard = map.guard();
+        for i in 0..10_000 {
+            map.insert(i, Counted, &guard);
+        }
+    }
+
+    let before = DROPPED.load(Ordering::SeqCst);
+    let mut dropped_during_scan = 0;
+    let mut guard = map.guard();
+    map.for_each_repinning(every, &mut guard, |k, _| {
+        map.remove(k, &map.guard());
+        dropped_during_scan = DROPPED.load(Ordering::SeqCst) - before;
+    });
+    assert!(map.is_empty());
+    dropped_during_scan
+}
+
+#[test]
+fn repin_frees_removed_entries_during_scan() {
+    // without repinning, the scan keeps the epoch pinned, so nothing removed during it is freed
+    assert_eq!(scan_and_remove(usize::MAX), 0);
+    assert!(scan_and_remove(100) > 0);
+}
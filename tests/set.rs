@@ -1,4 +1,4 @@
-use flurry::HashSet;
+use flurry::{DefaultHashBuilder, HashSet};
 use std::sync::Arc;
 
 #[test]
@@ -296,6 +296,21 @@ fn extend_ref() {
     assert_eq!(entries, collected);
 }
 
+#[test]
+fn extend_from_other_set() {
+    let source: HashSet<usize> = (0..100).collect();
+    let set: HashSet<usize> = HashSet::new();
+
+    let source_guard = source.guard();
+    (&set).extend(source.iter(&source_guard));
+
+    let guard = set.guard();
+    assert_eq!(set.len(), 100);
+    for i in 0..100 {
+        assert!(set.contains(&i, &guard));
+    }
+}
+
 #[test]
 fn from_iter_ref() {
     use std::iter::FromIterator;
@@ -320,3 +335,276 @@ fn from_iter_empty() {
 
     assert_eq!(set.len(), 0)
 }
+
+#[test]
+fn extract_if() {
+    let set: HashSet<usize> = (0..1000).collect();
+    let guard = set.guard();
+
+    let mut extracted: Vec<_> = set.extract_if(|&e| e >= 600, &guard).copied().collect();
+    extracted.sort();
+
+    assert_eq!(extracted, (600..1000).collect::<Vec<_>>());
+    assert_eq!(set.len(), 600);
+    for i in 0..600 {
+        assert!(set.contains(&i, &guard));
+    }
+    for i in 600..1000 {
+        assert!(!set.contains(&i, &guard));
+    }
+}
+
+#[test]
+fn extract_if_lazy() {
+    let set: HashSet<usize> = (0..10).collect();
+    let set_ref = set.pin();
+
+    let mut extract = set_ref.extract_if(|_| true);
+    assert!(extract.next().is_some());
+    drop(extract);
+
+    assert_eq!(set_ref.len(), 9);
+}
+
+#[test]
+fn builder() {
+    let set = HashSet::<usize>::builder()
+        .hasher(DefaultHashBuilder::with_seeds(1, 2))
+        .capacity(10)
+        .load_factor(0.5)
+        .build();
+    let guard = set.guard();
+    for i in 0..100 {
+        assert!(set.insert(i, &guard));
+    }
+    for i in 0..100 {
+        assert!(set.contains(&i, &guard));
+    }
+    assert_eq!(set.len(), 100);
+}
+
+#[test]
+fn pop() {
+    let set: HashSet<usize> = (0..3).collect();
+    let guard = set.guard();
+    let mut popped: Vec<_> = std::iter::from_fn(|| set.pop(&guard)).copied().collect();
+    popped.sort();
+    assert_eq!(popped, vec![0, 1, 2]);
+    assert!(set.is_empty());
+    assert_eq!(set.pop(&guard), None);
+}
+
+#[test]
+fn concurrent_pop() {
+    const ELEMENTS: usize = 10_000;
+    const THREADS: usize = 8;
+
+    let set: Arc<HashSet<usize>> = Arc::new((0..ELEMENTS).collect());
+    let workers: Vec<_> = (0..THREADS)
+        .map(|_| {
+            let set = set.clone();
+            std::thread::spawn(move || {
+                let guard = set.guard();
+                let mut popped = Vec::new();
+                while let Some(&e) = set.pop(&guard) {
+                    popped.push(e);
+                }
+                popped
+            })
+        })
+        .collect();
+
+    let mut popped: Vec<_> = workers
+        .into_iter()
+        .flat_map(|worker| worker.join().unwrap())
+        .collect();
+    popped.sort_unstable();
+    // every element was popped by exactly one thread
+    assert_eq!(popped, (0..ELEMENTS).collect::<Vec<_>>());
+    assert!(set.is_empty());
+}
+
+#[test]
+fn extend_from() {
+    let a: HashSet<usize> = (0..100).collect();
+    let b: HashSet<usize> = (50..150).collect();
+    a.extend_from(&b, &a.guard(), &b.guard());
+
+    let guard = a.guard();
+    let mut union: Vec<_> = a.iter(&guard).copied().collect();
+    union.sort_unstable();
+    assert_eq!(union, (0..150).collect::<Vec<_>>());
+    assert_eq!(b.len(), 100);
+}
+
+#[test]
+fn retain_intersection() {
+    let a: HashSet<usize> = (0..100).collect();
+    let b: HashSet<usize> = (50..150).collect();
+    a.retain_intersection(&b, &a.guard(), &b.guard());
+
+    let guard = a.guard();
+    let mut intersection: Vec<_> = a.iter(&guard).copied().collect();
+    intersection.sort_unstable();
+    assert_eq!(intersection, (50..100).collect::<Vec<_>>());
+
+    let guard = b.guard();
+    let mut other: Vec<_> = b.iter(&guard).copied().collect();
+    other.sort_unstable();
+    assert_eq!(other, (50..150).collect::<Vec<_>>());
+}
+
+#[test]
+fn subtract() {
+    // exercise both the case where self is the smaller set and where other is
+    for (ours, theirs) in [(0..100, 50..1000), (0..1000, 50..100)] {
+        let a: HashSet<usize> = ours.clone().collect();
+        let b: HashSet<usize> = theirs.clone().collect();
+        a.subtract(&b, &a.guard(), &b.guard());
+
+        let guard = a.guard();
+        let mut difference: Vec<_> = a.iter(&guard).copied().collect();
+        difference.sort_unstable();
+        let expected: Vec<_> = ours.filter(|i| !theirs.contains(i)).collect();
+        assert_eq!(difference, expected);
+        assert_eq!(b.len(), theirs.len());
+    }
+}
+
+#[test]
+fn equal_to_std_set() {
+    let set: HashSet<usize> = (0..10).collect();
+    let mut expected: std::collections::HashSet<usize> = (0..10).collect();
+    assert_eq!(set, expected);
+
+    expected.remove(&0);
+    expected.insert(10);
+    assert_ne!(set, expected);
+
+    expected.remove(&10);
+    assert_ne!(set, expected);
+}
+
+#[test]
+fn as_map() {
+    let set = HashSet::<usize>::new();
+    let guard = set.guard();
+    for i in 0..100 {
+        set.insert(i, &guard);
+    }
+
+    let map = set.as_map();
+    let stats = map.bin_stats(&guard);
+    assert_eq!(stats.bin_lengths.iter().sum::<usize>(), 100);
+    assert_eq!(stats.bin_lengths.len(), map.capacity(&guard));
+    assert_eq!(map.len(), set.len());
+    assert_eq!(map.probe_length(&42, &guard), Some(1));
+    assert_eq!(map.probe_length(&100, &guard), None);
+
+    // the view follows changes made through the set
+    set.insert(100, &guard);
+    assert_eq!(map.len(), 101);
+    assert_eq!(set.pin().as_map().probe_length(&100, &guard), Some(1));
+}
+
+#[test]
+fn jaccard() {
+    let a = HashSet::<usize>::new();
+    let b = HashSet::<usize>::new();
+    let (a_guard, b_guard) = (a.guard(), b.guard());
+    assert_eq!(a.jaccard(&b, &a_guard, &b_guard), 1.0);
+
+    for i in 0..100 {
+        a.insert(i, &a_guard);
+        b.insert(i, &b_guard);
+    }
+    assert_eq!(a.jaccard(&b, &a_guard, &b_guard), 1.0);
+
+    let c = HashSet::<usize>::new();
+    let c_guard = c.guard();
+    for i in 100..200 {
+        c.insert(i, &c_guard);
+    }
+    assert_eq!(a.jaccard(&c, &a_guard, &c_guard), 0.0);
+    assert_eq!(a.jaccard(&HashSet::new(), &a_guard, &c_guard), 0.0);
+
+    // 50 shared elements out of 150
+    let d = HashSet::<usize>::new();
+    let d_guard = d.guard();
+    for i in 50..150 {
+        d.insert(i, &d_guard);
+    }
+    assert!((a.jaccard(&d, &a_guard, &d_guard) - 1.0 / 3.0).abs() < 1e-9);
+    assert_eq!(a.pin().jaccard(&d.pin()), d.jaccard(&a, &d_guard, &a_guard));
+}
+
+#[test]
+fn proper_subset() {
+    let small = HashSet::<usize>::new();
+    let large = HashSet::<usize>::new();
+    let (small_guard, large_guard) = (small.guard(), large.guard());
+    for i in 0..10 {
+        small.insert(i, &small_guard);
+        large.insert(i, &large_guard);
+    }
+
+    // equal sets are subsets of each other, but not proper ones
+    assert!(small.is_subset(&large, &small_guard, &large_guard));
+    assert!(!small.is_proper_subset(&large, &small_guard, &large_guard));
+    assert!(!small.is_proper_superset(&large, &small_guard, &large_guard));
+    assert!(!large.is_proper_subset(&small, &large_guard, &small_guard));
+    assert!(!large.is_proper_superset(&small, &large_guard, &small_guard));
+
+    large.insert(10, &large_guard);
+    assert!(small.is_proper_subset(&large, &small_guard, &large_guard));
+    assert!(!small.is_proper_superset(&large, &small_guard, &large_guard));
+    assert!(large.is_proper_superset(&small, &large_guard, &small_guard));
+    assert!(!large.is_proper_subset(&small, &large_guard, &small_guard));
+
+    // smaller, but not contained
+    small.remove(&0, &small_guard);
+    small.insert(100, &small_guard);
+    small.remove(&1, &small_guard);
+    assert!(!small.pin().is_proper_subset(&large.pin()));
+    assert!(!large.pin().is_proper_superset(&small.pin()));
+}
+
+#[test]
+fn iter_sorted() {
+    let set = HashSet::<usize>::new();
+    let guard = set.guard();
+    for i in [7, 3, 9, 0, 5, 1, 8, 2, 6, 4] {
+        set.insert(i, &guard);
+    }
+
+    let sorted: Vec<_> = set.iter_sorted(&guard).copied().collect();
+    assert_eq!(sorted, (0..10).collect::<Vec<_>>());
+    let descending: Vec<_> = set.pin().iter_sorted_by(|a, b| b.cmp(a)).copied().collect();
+    assert_eq!(descending, (0..10).rev().collect::<Vec<_>>());
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn concurrent_toggle() {
+    const THREADS: usize = 4;
+    const TOGGLES: usize = 1001;
+
+    let set = Arc::new(HashSet::<usize>::new());
+    let threads: Vec<_> = (0..THREADS)
+        .map(|_| {
+            let set = set.clone();
+            std::thread::spawn(move || {
+                let guard = set.guard();
+                (0..TOGGLES).filter(|_| set.toggle(7, &guard)).count()
+            })
+        })
+        .collect();
+    let added: usize = threads.into_iter().map(|t| t.join().unwrap()).sum();
+
+    // the toggles alternate between adding and removing, so with an even total, as many were
+    // added as removed, and the set is back to where it started.
+    assert_eq!(added, THREADS * TOGGLES / 2);
+    assert!(!set.pin().contains(&7));
+    assert!(set.pin().toggle(7));
+    assert!(set.pin().contains(&7));
+}